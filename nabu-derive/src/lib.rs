@@ -0,0 +1,535 @@
+//! Derive macros converting structs and enums to and from nabu's `XffValue`, without pulling in
+//! serde.
+//!
+//! Please use them through nabu's `derive` feature, they are re-exported as `nabu::IntoXff` and
+//! `nabu::FromXff`. All generated code refers to `::nabu`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    ext::IdentExt, parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Fields,
+    GenericArgument, Generics, LitStr, PathArguments, Type,
+};
+
+/// Derives `From<T> for XffValue`
+///
+/// Structs become `Object`s with one key per field, tuple structs `Array`s, newtype structs the
+/// value of their only field and unit structs `Null`.
+/// Enums become `Object`s with the name of the variant as their only key, holding `Null` for unit
+/// variants, the value for newtype variants, an `Array` for tuple variants and an `Object` for
+/// struct variants.
+///
+/// `Option` fields are written as `Null` when `None`, `Vec<u8>` fields as `Data` and any other
+/// `Vec` as an `Array`.
+/// All other field types need a `From<FieldType> for XffValue` implementation.
+///
+/// Keys can be renamed with `#[xff(rename = "key")]` on fields and variants.
+#[proc_macro_derive(IntoXff, attributes(xff))]
+pub fn derive_into_xff(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    into_xff(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `TryFrom<XffValue> for T`, with `NabuError` as its error
+///
+/// The inverse of `IntoXff`, see there for how types are mapped.
+///
+/// `Option` fields are `None` if their key is missing or holds `Null`, unknown keys are ignored.
+/// Integers are accepted from any integer `Number` that fits into the field, floats from any
+/// `Number`.
+/// Unit variants are also accepted from a `String` holding their name.
+/// All other field types need a `TryFrom<XffValue, Error = NabuError>` implementation.
+#[proc_macro_derive(FromXff, attributes(xff))]
+pub fn derive_from_xff(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_xff(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+// -----------------------------------------------------------
+//                         IntoXff
+// -----------------------------------------------------------
+
+fn into_xff(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let generics = with_bound(&input.generics, parse_quote!(::nabu::XffValue: ::core::convert::From<__T>));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => {
+                let mut inserts = Vec::new();
+                for field in fields.named.iter() {
+                    let ident = field.ident.as_ref().expect("named field");
+                    let key = xff_key(&field.attrs, ident.unraw().to_string())?;
+                    let conv = into_value(&field.ty, quote!(value.#ident));
+                    inserts.push(quote! { object.insert(#key, #conv); });
+                }
+                quote! {
+                    let mut object = ::nabu::Object::new();
+                    #(#inserts)*
+                    ::nabu::XffValue::Object(object)
+                }
+            }
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                into_value(&fields.unnamed[0].ty, quote!(value.0))
+            }
+            Fields::Unnamed(fields) => {
+                let convs = fields.unnamed.iter().enumerate().map(|(n, field)| {
+                    let index = syn::Index::from(n);
+                    into_value(&field.ty, quote!(value.#index))
+                });
+                quote! {
+                    <::nabu::XffValue as ::core::convert::From<::std::vec::Vec<::nabu::XffValue>>>::from(::std::vec![#(#convs),*])
+                }
+            }
+            Fields::Unit => quote! { ::nabu::XffValue::Null },
+        },
+        Data::Enum(data) => {
+            let mut arms = Vec::new();
+            for variant in data.variants.iter() {
+                let ident = &variant.ident;
+                let key = xff_key(&variant.attrs, ident.unraw().to_string())?;
+                let (pattern, content) = match &variant.fields {
+                    Fields::Named(fields) => {
+                        let idents: Vec<_> = fields
+                            .named
+                            .iter()
+                            .map(|field| field.ident.as_ref().expect("named field"))
+                            .collect();
+                        let mut inserts = Vec::new();
+                        for field in fields.named.iter() {
+                            let ident = field.ident.as_ref().expect("named field");
+                            let field_key = xff_key(&field.attrs, ident.unraw().to_string())?;
+                            let conv = into_value(&field.ty, quote!(#ident));
+                            inserts.push(quote! { fields.insert(#field_key, #conv); });
+                        }
+                        (
+                            quote! { #name::#ident { #(#idents),* } },
+                            quote! {
+                                {
+                                    let mut fields = ::nabu::Object::new();
+                                    #(#inserts)*
+                                    ::nabu::XffValue::Object(fields)
+                                }
+                            },
+                        )
+                    }
+                    Fields::Unnamed(fields) => {
+                        let idents: Vec<_> = (0..fields.unnamed.len())
+                            .map(|n| format_ident!("field_{}", n))
+                            .collect();
+                        let convs: Vec<_> = fields
+                            .unnamed
+                            .iter()
+                            .zip(idents.iter())
+                            .map(|(field, ident)| into_value(&field.ty, quote!(#ident)))
+                            .collect();
+                        let pattern = quote! { #name::#ident(#(#idents),*) };
+                        if convs.len() == 1 {
+                            (pattern, quote! { #(#convs)* })
+                        } else {
+                            (pattern, quote! { <::nabu::XffValue as ::core::convert::From<::std::vec::Vec<::nabu::XffValue>>>::from(::std::vec![#(#convs),*]) })
+                        }
+                    }
+                    Fields::Unit => (quote! { #name::#ident }, quote! { ::nabu::XffValue::Null }),
+                };
+                arms.push(quote! { #pattern => (#key, #content), });
+            }
+            if arms.is_empty() {
+                quote! { match value {} }
+            } else {
+                quote! {
+                    let (key, content) = match value {
+                        #(#arms)*
+                    };
+                    let mut object = ::nabu::Object::new();
+                    object.insert(key, content);
+                    ::nabu::XffValue::Object(object)
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "IntoXff can not be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::core::convert::From<#name #ty_generics> for ::nabu::XffValue #where_clause {
+            fn from(value: #name #ty_generics) -> Self {
+                #body
+            }
+        }
+    })
+}
+
+/// Converts the expression of the type into a XffValue
+fn into_value(ty: &Type, expr: TokenStream2) -> TokenStream2 {
+    match kind(ty) {
+        Kind::Option(inner) => {
+            let conv = into_value(inner, quote!(value));
+            quote! {
+                match #expr {
+                    ::core::option::Option::Some(value) => #conv,
+                    ::core::option::Option::None => ::nabu::XffValue::Null,
+                }
+            }
+        }
+        Kind::Vec(inner) => {
+            let conv = into_value(inner, quote!(value));
+            quote! {
+                <::nabu::XffValue as ::core::convert::From<::std::vec::Vec<::nabu::XffValue>>>::from(
+                    #expr
+                        .into_iter()
+                        .map(|value| #conv)
+                        .collect::<::std::vec::Vec<::nabu::XffValue>>(),
+                )
+            }
+        }
+        _ => quote! { <::nabu::XffValue as ::core::convert::From<#ty>>::from(#expr) },
+    }
+}
+
+// -----------------------------------------------------------
+//                         FromXff
+// -----------------------------------------------------------
+
+fn from_xff(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let name_str = name.unraw().to_string();
+    let generics = with_bound(
+        &input.generics,
+        parse_quote!(__T: ::core::convert::TryFrom<::nabu::XffValue, Error = ::nabu::NabuError>),
+    );
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => from_fields(&data.fields, quote!(Self), &name_str)?,
+        Data::Enum(data) => {
+            let mut arms = Vec::new();
+            for variant in data.variants.iter() {
+                let ident = &variant.ident;
+                let key = xff_key(&variant.attrs, ident.unraw().to_string())?;
+                let ctx = format!("{}::{}", name_str, key);
+                let build = from_fields(&variant.fields, quote!(Self::#ident), &ctx)?;
+                arms.push(quote! { #key => { #build } });
+            }
+            let err_type = conversion_error(&name_str, "an Object with exactly one key or a String");
+            let err_variant = quote! {
+                ::nabu::NabuError::ConversionError(::std::format!(
+                    "{}: unknown variant {:?}",
+                    #name_str,
+                    other
+                ))
+            };
+            quote! {
+                let (variant, value) = match value {
+                    ::nabu::XffValue::Object(object) if object.len() == 1 => object
+                        .map
+                        .into_iter()
+                        .next()
+                        .expect("length checked above"),
+                    // unit variants
+                    ::nabu::XffValue::String(variant) => (variant, ::nabu::XffValue::Null),
+                    value => return ::core::result::Result::Err(#err_type),
+                };
+                match variant.as_str() {
+                    #(#arms)*
+                    other => ::core::result::Result::Err(#err_variant),
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "FromXff can not be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::core::convert::TryFrom<::nabu::XffValue> for #name #ty_generics #where_clause {
+            type Error = ::nabu::NabuError;
+
+            fn try_from(value: ::nabu::XffValue) -> ::core::result::Result<Self, ::nabu::NabuError> {
+                #body
+            }
+        }
+    })
+}
+
+/// Builds `constructor` out of `value`, returning from the surrounding function
+fn from_fields(fields: &Fields, constructor: TokenStream2, ctx: &str) -> syn::Result<TokenStream2> {
+    Ok(match fields {
+        Fields::Named(fields) => {
+            let mut builds = Vec::new();
+            for field in fields.named.iter() {
+                let ident = field.ident.as_ref().expect("named field");
+                let key = xff_key(&field.attrs, ident.unraw().to_string())?;
+                let field_ctx = format!("{}.{}", ctx, key);
+                let build = match kind(&field.ty) {
+                    Kind::Option(inner) => {
+                        let conv = from_value(inner, &field_ctx);
+                        quote! {
+                            match object.remove(#key) {
+                                ::core::option::Option::None
+                                | ::core::option::Option::Some(::nabu::XffValue::Null) => ::core::option::Option::None,
+                                ::core::option::Option::Some(value) => ::core::option::Option::Some(#conv),
+                            }
+                        }
+                    }
+                    _ => {
+                        let conv = from_value(&field.ty, &field_ctx);
+                        let err = quote! {
+                            ::nabu::NabuError::ConversionError(::std::format!("{}: missing key", #field_ctx))
+                        };
+                        quote! {
+                            match object.remove(#key) {
+                                ::core::option::Option::Some(value) => #conv,
+                                ::core::option::Option::None => return ::core::result::Result::Err(#err),
+                            }
+                        }
+                    }
+                };
+                builds.push(quote! { #ident: #build, });
+            }
+            let err = conversion_error(ctx, "an Object");
+            quote! {
+                let mut object = match value {
+                    ::nabu::XffValue::Object(object) => object,
+                    value => return ::core::result::Result::Err(#err),
+                };
+                ::core::result::Result::Ok(#constructor { #(#builds)* })
+            }
+        }
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+            let conv = from_value(&fields.unnamed[0].ty, ctx);
+            quote! {
+                ::core::result::Result::Ok(#constructor(#conv))
+            }
+        }
+        Fields::Unnamed(fields) => {
+            let len = fields.unnamed.len();
+            let convs = fields.unnamed.iter().enumerate().map(|(n, field)| {
+                let conv = from_value(&field.ty, &format!("{}.{}", ctx, n));
+                quote! {
+                    {
+                        let value = values.next().expect("length checked above");
+                        #conv
+                    }
+                }
+            });
+            let err = conversion_error(ctx, &format!("an Array of {} elements", len));
+            quote! {
+                let mut values = match value {
+                    ::nabu::XffValue::Array(array) if array.len() == #len => array.into_iter(),
+                    value => return ::core::result::Result::Err(#err),
+                };
+                ::core::result::Result::Ok(#constructor(#(#convs),*))
+            }
+        }
+        Fields::Unit => {
+            let err = conversion_error(ctx, "Null");
+            quote! {
+                match value {
+                    ::nabu::XffValue::Null => ::core::result::Result::Ok(#constructor),
+                    value => ::core::result::Result::Err(#err),
+                }
+            }
+        }
+    })
+}
+
+/// Converts `value`, a XffValue, into the type, returning from the surrounding function on errors
+fn from_value(ty: &Type, ctx: &str) -> TokenStream2 {
+    match kind(ty) {
+        Kind::Option(inner) => {
+            let conv = from_value(inner, ctx);
+            quote! {
+                match value {
+                    ::nabu::XffValue::Null => ::core::option::Option::None,
+                    value => ::core::option::Option::Some(#conv),
+                }
+            }
+        }
+        Kind::Bytes => {
+            let err = conversion_error(ctx, "Data");
+            quote! {
+                match value {
+                    ::nabu::XffValue::Data(data) => data.into_vec(),
+                    value => return ::core::result::Result::Err(#err),
+                }
+            }
+        }
+        Kind::Vec(inner) => {
+            let conv = from_value(inner, &format!("{}[]", ctx));
+            let err = conversion_error(ctx, "an Array");
+            quote! {
+                match value {
+                    ::nabu::XffValue::Array(array) => {
+                        let mut out = ::std::vec::Vec::with_capacity(array.len());
+                        for value in array {
+                            out.push(#conv);
+                        }
+                        out
+                    }
+                    value => return ::core::result::Result::Err(#err),
+                }
+            }
+        }
+        Kind::String => {
+            let err = conversion_error(ctx, "a String");
+            quote! {
+                match value {
+                    ::nabu::XffValue::String(string) => string,
+                    value => return ::core::result::Result::Err(#err),
+                }
+            }
+        }
+        Kind::Bool => {
+            let err = conversion_error(ctx, "a Boolean");
+            quote! {
+                match &value {
+                    ::nabu::XffValue::Boolean(boolean) => *boolean,
+                    _ => return ::core::result::Result::Err(#err),
+                }
+            }
+        }
+        Kind::Int => {
+            let err = conversion_error(ctx, &format!("a Number fitting into {}", quote!(#ty)));
+            quote! {
+                match match value.as_number() {
+                    ::core::option::Option::Some(&::nabu::Number::Unsigned(u)) => <#ty as ::core::convert::TryFrom<usize>>::try_from(u).ok(),
+                    ::core::option::Option::Some(&::nabu::Number::Integer(i)) => <#ty as ::core::convert::TryFrom<isize>>::try_from(i).ok(),
+                    ::core::option::Option::Some(&::nabu::Number::BigUnsigned(u)) => <#ty as ::core::convert::TryFrom<u128>>::try_from(u).ok(),
+                    ::core::option::Option::Some(&::nabu::Number::BigInteger(i)) => <#ty as ::core::convert::TryFrom<i128>>::try_from(i).ok(),
+                    _ => ::core::option::Option::None,
+                } {
+                    ::core::option::Option::Some(num) => num,
+                    ::core::option::Option::None => return ::core::result::Result::Err(#err),
+                }
+            }
+        }
+        Kind::Float => {
+            let err = conversion_error(ctx, "a Number");
+            quote! {
+                match value.as_number() {
+                    ::core::option::Option::Some(&::nabu::Number::Float(f)) => f as #ty,
+                    ::core::option::Option::Some(&::nabu::Number::Unsigned(u)) => u as #ty,
+                    ::core::option::Option::Some(&::nabu::Number::Integer(i)) => i as #ty,
+                    ::core::option::Option::Some(&::nabu::Number::BigUnsigned(u)) => u as #ty,
+                    ::core::option::Option::Some(&::nabu::Number::BigInteger(i)) => i as #ty,
+                    ::core::option::Option::Some(::nabu::Number::Decimal(d)) => d.to_f64() as #ty,
+                    ::core::option::Option::None => return ::core::result::Result::Err(#err),
+                }
+            }
+        }
+        Kind::Value => quote! { value },
+        Kind::Other => quote! {
+            <#ty as ::core::convert::TryFrom<::nabu::XffValue>>::try_from(value)?
+        },
+    }
+}
+
+/// Error for a `value` that does not match the expected type
+fn conversion_error(ctx: &str, expected: &str) -> TokenStream2 {
+    quote! {
+        ::nabu::NabuError::ConversionError(::std::format!(
+            "{}: expected {}, found {}",
+            #ctx,
+            #expected,
+            value
+        ))
+    }
+}
+
+// -----------------------------------------------------------
+//                         Helpers
+// -----------------------------------------------------------
+
+enum Kind<'a> {
+    Option(&'a Type),
+    /// `Vec<u8>`
+    Bytes,
+    Vec(&'a Type),
+    String,
+    Bool,
+    Int,
+    Float,
+    /// `XffValue` itself
+    Value,
+    Other,
+}
+
+fn kind(ty: &Type) -> Kind<'_> {
+    let segment = match ty {
+        Type::Path(path) if path.qself.is_none() => match path.path.segments.last() {
+            Some(segment) => segment,
+            None => return Kind::Other,
+        },
+        _ => return Kind::Other,
+    };
+    let generic = match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    };
+    match (segment.ident.to_string().as_str(), generic) {
+        ("Option", Some(inner)) => Kind::Option(inner),
+        ("Vec", Some(inner)) => match inner {
+            Type::Path(path) if path.path.is_ident("u8") => Kind::Bytes,
+            _ => Kind::Vec(inner),
+        },
+        ("String", None) => Kind::String,
+        ("bool", None) => Kind::Bool,
+        (
+            "u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16" | "i32" | "i64" | "i128"
+            | "isize",
+            None,
+        ) => Kind::Int,
+        ("f32" | "f64", None) => Kind::Float,
+        ("XffValue", None) => Kind::Value,
+        _ => Kind::Other,
+    }
+}
+
+/// Returns the key of a field or variant, `#[xff(rename = "key")]` overrides the default
+fn xff_key(attrs: &[Attribute], default: String) -> syn::Result<String> {
+    let mut key = default;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("xff")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                key = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else {
+                Err(meta.error("unsupported xff attribute, expected `rename`"))
+            }
+        })?;
+    }
+    Ok(key)
+}
+
+/// Adds the bound to every type parameter, with `__T` standing in for the parameter
+fn with_bound(generics: &Generics, bound: syn::WherePredicate) -> Generics {
+    let mut generics = generics.clone();
+    let params: Vec<_> = generics.type_params().map(|param| param.ident.clone()).collect();
+    let where_clause = generics.make_where_clause();
+    for param in params {
+        let predicate = quote!(#bound).to_string().replace("__T", &param.to_string());
+        where_clause
+            .predicates
+            .push(syn::parse_str(&predicate).expect("valid where predicate"));
+    }
+    generics
+}
@@ -0,0 +1,184 @@
+use core::fmt;
+
+use crate::error::NabuError;
+
+/// Bytes shown on either side of the failing byte in an excerpt
+const EXCERPT_CONTEXT: usize = 8;
+
+/// A `NabuError` encountered while decoding, together with where it was encountered
+///
+/// Returned by `from_bytes_diagnosed`. Besides the error itself, it carries the logical path of
+/// the value that failed to decode, like `root.users[3].name`, and a hex excerpt of the bytes
+/// around the position of the error.
+///
+/// # Example
+/// ```rust
+/// use nabu::serde::{from_bytes_diagnosed, to_bytes, DecodeOptions};
+/// use nabu::{NabuError, XffValue};
+///
+/// let value = XffValue::from(vec![("users", XffValue::from(vec![XffValue::from(true)]))]);
+/// let mut bytes = to_bytes(value).unwrap();
+/// // TRU is replaced by an invalid byte
+/// let pos = bytes.iter().position(|b| *b == 16).unwrap();
+/// bytes[pos] = 99;
+///
+/// let diagnostic = from_bytes_diagnosed(&bytes, &DecodeOptions::default()).unwrap_err();
+/// assert!(matches!(diagnostic.error(), NabuError::InvalidXFFByte(99, _, 2)));
+/// assert_eq!(diagnostic.code(), 502);
+/// assert_eq!(diagnostic.path(), "root.users[0]");
+/// assert!(diagnostic.excerpt().unwrap().contains("63"));
+/// ```
+#[derive(Debug)]
+pub struct Diagnostic {
+    error: NabuError,
+    path: String,
+    excerpt: Option<String>,
+}
+
+impl Diagnostic {
+    /// Creates the diagnostic of an error encountered while decoding `bytes`
+    pub(crate) fn new(error: NabuError, trace: Trace, bytes: &[u8]) -> Self {
+        let excerpt = match error.position() {
+            Some(pos) if !trace.decompressed && pos <= bytes.len() => Some(excerpt(bytes, pos)),
+            _ => None,
+        };
+        Diagnostic {
+            error,
+            path: trace.path(),
+            excerpt,
+        }
+    }
+
+    /// Returns the error
+    pub fn error(&self) -> &NabuError {
+        &self.error
+    }
+
+    /// Returns the error, discarding the diagnostic
+    pub fn into_error(self) -> NabuError {
+        self.error
+    }
+
+    /// Returns the stable code of the error, see `NabuError::code`
+    pub fn code(&self) -> u16 {
+        self.error.code()
+    }
+
+    /// Returns the byte position of the error, see `NabuError::position`
+    pub fn position(&self) -> Option<usize> {
+        self.error.position()
+    }
+
+    /// Returns the logical path of the value the error was encountered in
+    ///
+    /// The path starts with `root`, the decoded value itself, followed by `.key` or `["key"]`
+    /// for object keys and `[index]` for array elements.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns up to 8 bytes on either side of the position of the error as hex, the byte at the
+    /// position in square brackets
+    ///
+    /// Like the position itself, the bracketed byte is the one the error was noticed at, which
+    /// may be right after the offending byte, or `[end]` if the bytes ended early.
+    ///
+    /// `None` if the error has no byte position, or its position is inside of a decompressed value.
+    pub fn excerpt(&self) -> Option<&str> {
+        self.excerpt.as_deref()
+    }
+}
+
+impl From<Diagnostic> for NabuError {
+    fn from(diagnostic: Diagnostic) -> Self {
+        diagnostic.error
+    }
+}
+
+impl std::error::Error for Diagnostic {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (error {}, at {}", self.error, self.code(), self.path)?;
+        if let Some(excerpt) = &self.excerpt {
+            write!(f, ", bytes: {}", excerpt)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Renders the bytes around `pos` as hex, `pos` may be the end of the bytes
+fn excerpt(bytes: &[u8], pos: usize) -> String {
+    let start = pos.saturating_sub(EXCERPT_CONTEXT);
+    let end = bytes.len().min(pos + EXCERPT_CONTEXT + 1);
+    let mut out: Vec<String> = Vec::with_capacity(end - start + 1);
+    for (i, byte) in bytes.iter().enumerate().take(end).skip(start) {
+        if i == pos {
+            out.push(format!("[{:02x}]", byte));
+        } else {
+            out.push(format!("{:02x}", byte));
+        }
+    }
+    if pos == bytes.len() {
+        out.push("[end]".to_string());
+    }
+    out.join(" ")
+}
+
+/// One step of the path to a value
+#[derive(Debug)]
+pub(crate) enum PathSegment {
+    Index(usize),
+    Key(String),
+}
+
+/// The path to the value a decoder failed in, collected while the error is returned
+#[derive(Debug, Default)]
+pub(crate) struct Trace {
+    /// Innermost segment first
+    segments: Vec<PathSegment>,
+    /// `true` if the error was encountered inside of a decompressed value
+    pub(crate) decompressed: bool,
+}
+
+impl Trace {
+    /// Adds the segment enclosing all segments added before
+    pub(crate) fn push(&mut self, segment: PathSegment) {
+        self.segments.push(segment);
+    }
+
+    /// Adds the segments of a nested trace, enclosed by all segments added later
+    pub(crate) fn extend(&mut self, inner: Trace) {
+        self.segments.extend(inner.segments);
+        self.decompressed |= inner.decompressed;
+    }
+
+    fn path(&self) -> String {
+        path_string(self.segments.iter().rev())
+    }
+}
+
+/// Renders the segments, outermost first, as a path starting with `root`
+pub(crate) fn path_string<'a, I>(segments: I) -> String
+where
+    I: Iterator<Item = &'a PathSegment>,
+{
+    let mut out = String::from("root");
+    for segment in segments {
+        match segment {
+            PathSegment::Index(i) => out.push_str(&format!("[{}]", i)),
+            PathSegment::Key(key)
+                if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_') =>
+            {
+                out.push('.');
+                out.push_str(key);
+            }
+            PathSegment::Key(key) => out.push_str(&format!("[{:?}]", key)),
+        }
+    }
+    out
+}
@@ -3,6 +3,10 @@ use std::usize;
 
 use crate::xff::value::XffValue;
 
+mod diagnostic;
+pub use diagnostic::Diagnostic;
+pub(crate) use diagnostic::{path_string, PathSegment, Trace};
+
 #[derive(Debug)]
 pub enum NabuError {
     // -----------------------------------------------
@@ -10,6 +14,12 @@ pub enum NabuError {
     // -----------------------------------------------
     /// Wrapper for any and all std::io::Errors
     IoError(std::io::Error),
+    /// Custom error message of a `serde::Serialize` or `serde::Deserialize` implementation, or of
+    /// a type that cannot be mapped to or from a XffValue
+    ///
+    /// # Parameters
+    /// * `msg` - The error message
+    SerdeError(String),
 
     // -----------------------------------------------
     //                   Xff v0 errors
@@ -98,6 +108,36 @@ pub enum NabuError {
     /// * `len` - The length of the value
     InvalidXFFValueLength(usize),
 
+    // -----------------------------------------------
+    //                   Xff v2 errors
+    // -----------------------------------------------
+    /// The string is not valid UTF-8, the first invalid byte is at the wrapped position
+    ///
+    /// # Parameters
+    /// * `pos` - The position in the file of the first invalid byte of the string
+    InvalidUTF8String(usize),
+
+    /// The compressed value was compressed with a method unknown to this implementation, or
+    /// the `compression` feature is disabled
+    ///
+    /// # Parameters
+    /// * `method` - The unknown method
+    /// * `pos` - The position in the file of the content of the compressed value
+    UnknownCompressionMethod(u8, usize),
+
+    /// The compressed value is malformed, or does not decompress to exactly one value
+    ///
+    /// # Parameters
+    /// * `pos` - The position in the file of the content of the compressed value
+    InvalidCompressedValue(usize),
+
+    /// A compressed value was encountered while parsing into borrowed values, it has to be
+    /// decompressed into owned values instead
+    ///
+    /// # Parameters
+    /// * `pos` - The position in the file of the compressed value
+    BorrowedCompressedValue(usize),
+
     // -----------------------------------------------
     //             Xff general serde errors
     // -----------------------------------------------
@@ -129,6 +169,20 @@ pub enum NabuError {
     /// * `value` - The invalid value
     /// * `version` - The XFF version
     InvalidXFFValueForVersion(XffValue, u8),
+    /// The wrapped character cannot be represented in a string of the wrapped XFF version
+    /// Nothing is truncated or replaced, the value is not written at all
+    ///
+    /// # Parameters
+    /// * `char` - The unrepresentable character
+    /// * `version` - The XFF version
+    UnrepresentableCharacter(char, u8),
+    /// Arrays and objects are nested deeper than `MAX_DEPTH`, decoding the value with the
+    /// default options would fail
+    /// Nothing is written at all
+    ///
+    /// # Parameters
+    /// * `max` - The deepest nesting that can be written
+    NestingTooDeep(usize),
 
     // -----------------------------------------------
     //                Xff v0 serde errors
@@ -164,12 +218,103 @@ pub enum NabuError {
     /// * `version` - The unknown version
     UnknownXFFVersion(u8),
 
+    /// Unknown XFF version announced by an extended version header
+    ///
+    /// # Parameters
+    /// * `version` - The unknown version
+    UnknownExtendedXFFVersion(u16),
+
     /// Invalid XFF version, the value is not for the correct version
     ///
     /// # Parameters
     /// * `value` - The invalid value
     /// * `version` - The invalid version
     InvalidXFFVersion(XffValue, u8),
+
+    /// The integrity trailer of the file does not match its content
+    ///
+    /// # Parameters
+    /// * `pos` - The position of the first block of bytes not matching its checksum, or of the
+    ///   trailer itself if it is malformed
+    ChecksumMismatch(usize),
+
+    /// The file is encrypted, and has to be read with `read_encrypted`
+    EncryptedXFF,
+
+    /// The file is encrypted with a scheme unknown to this implementation
+    ///
+    /// # Parameters
+    /// * `scheme` - The unknown scheme
+    UnknownEncryptionScheme(u8),
+
+    /// The passphrase does not match the one the file was encrypted with
+    WrongPassphrase,
+
+    /// The encrypted file is malformed, or was altered after it was encrypted
+    CorruptedEncryptedXFF,
+
+    // -----------------------------------------------
+    //               Xff decode limit errors
+    // -----------------------------------------------
+    /// Arrays and objects are nested deeper than `DecodeOptions::max_depth` allows
+    ///
+    /// # Parameters
+    /// * `max` - The deepest nesting allowed
+    /// * `pos` - The position in the file of the array or object exceeding it
+    DepthLimitExceeded(usize, usize),
+
+    /// A value is longer than `DecodeOptions::max_value_len` allows
+    ///
+    /// # Parameters
+    /// * `len` - The length of the value
+    /// * `pos` - The position in the file of the value
+    ValueLengthLimitExceeded(usize, usize),
+
+    /// An object has more keys than `DecodeOptions::max_keys` allows
+    ///
+    /// # Parameters
+    /// * `max` - The most keys allowed
+    /// * `pos` - The position in the file of the first key exceeding it
+    KeyLimitExceeded(usize, usize),
+
+    /// Decoding would allocate more bytes than `DecodeOptions::max_total_bytes` allows
+    ///
+    /// # Parameters
+    /// * `max` - The most bytes allowed
+    /// * `pos` - The position in the file of the value exceeding it
+    AllocationLimitExceeded(usize, usize),
+
+    // -----------------------------------------------
+    //                  Xff value errors
+    // -----------------------------------------------
+    /// A XffValue does not match the type it is converted into with `FromXff`
+    ///
+    /// # Parameters
+    /// * `msg` - The error message, starting with the path to the mismatched value
+    ConversionError(String),
+    /// The query is not valid, the first invalid character is at the wrapped position
+    ///
+    /// # Parameters
+    /// * `pos` - The position of the first invalid character, counted in characters
+    /// * `query` - The invalid query
+    InvalidQuery(usize, String),
+    /// The patch is malformed, or cannot be applied to the value
+    ///
+    /// # Parameters
+    /// * `msg` - The error message, starting with the path of the failed operation if applying it
+    ///   failed
+    InvalidPatch(String),
+    /// Merging two values with `MergeStrategy::ErrorOnConflict` found a conflict
+    ///
+    /// # Parameters
+    /// * `path` - The JSON Pointer of the first conflicting value
+    MergeConflict(String),
+    /// The schema or one of its patterns is malformed
+    ///
+    /// # Parameters
+    /// * `msg` - The error message, starting with the path of the offending key if converting
+    ///   a schema stored as `XffValue` failed
+    InvalidSchema(String),
 }
 
 pub type Result<T> = std::result::Result<T, NabuError>;
@@ -180,11 +325,167 @@ impl From<std::io::Error> for NabuError {
     }
 }
 
+impl std::error::Error for NabuError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NabuError::IoError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl NabuError {
+    /// Returns the stable code of the error, to classify errors without matching on them
+    ///
+    /// A code never changes once assigned, and is never reused for another error. The hundreds
+    /// give the group of the error:
+    ///
+    /// * `1xx` - External errors, like IO and serde errors
+    /// * `2xx` - Xff v0 errors
+    /// * `3xx` - Xff v1 errors
+    /// * `4xx` - Xff v2 errors
+    /// * `5xx` - Xff general serde errors
+    /// * `6xx` - Xff v0 serde errors
+    /// * `7xx` - Xff file errors, like checksum and encryption errors
+    /// * `8xx` - Xff decode limit errors
+    /// * `9xx` - Xff value errors, like failed conversions and invalid queries, patches and schemas
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::from_bytes;
+    ///
+    /// let err = from_bytes(&[2, 16]).unwrap_err();
+    /// assert_eq!(err.code(), 702);
+    /// assert_eq!(err.code() / 100, 7);
+    /// ```
+    pub fn code(&self) -> u16 {
+        match self {
+            // external errors
+            NabuError::IoError(_) => 100,
+            NabuError::SerdeError(_) => 101,
+
+            // Xff v0 errors
+            NabuError::MissingETX(_) => 200,
+            NabuError::MissingDLE(_) => 201,
+            NabuError::MissingESC(_) => 202,
+            NabuError::MissingCommandCharacter => 203,
+
+            // Xff v1 errors
+            NabuError::MissingTXT(_) => 300,
+            NabuError::MissingNUM(_) => 301,
+            NabuError::MissingARY(_) => 302,
+            NabuError::MissingOBJ(_) => 303,
+            NabuError::MissingDAT(_) => 304,
+            NabuError::MissingEV(_) => 305,
+            NabuError::InvalidNumber(_, _) => 306,
+            NabuError::InvalidArray(_, _) => 307,
+            NabuError::InvalidObject(_, _) => 308,
+            NabuError::InvalidKey(_, _) => 309,
+            NabuError::InvalidXFFValueLength(_) => 310,
+
+            // Xff v2 errors
+            NabuError::InvalidUTF8String(_) => 400,
+            NabuError::UnknownCompressionMethod(_, _) => 401,
+            NabuError::InvalidCompressedValue(_) => 402,
+            NabuError::BorrowedCompressedValue(_) => 403,
+
+            // Xff general serde errors
+            NabuError::InvalidASCIIString(_, _, _) => 500,
+            NabuError::InvalidXFFExtension(_, _) => 501,
+            NabuError::InvalidXFFByte(_, _, _) => 502,
+            NabuError::InvalidXFFValueForVersion(_, _) => 503,
+            NabuError::UnrepresentableCharacter(_, _) => 504,
+            NabuError::NestingTooDeep(_) => 505,
+
+            // Xff v0 serde errors
+            NabuError::InvalidASCIICommandCharacter(_, _) => 600,
+
+            // Xff file errors
+            NabuError::MissingEM(_) => 700,
+            NabuError::EmpthyXFF => 701,
+            NabuError::TruncatedXFF(_) => 702,
+            NabuError::UnknownXFFVersion(_) => 703,
+            NabuError::UnknownExtendedXFFVersion(_) => 704,
+            NabuError::InvalidXFFVersion(_, _) => 705,
+            NabuError::ChecksumMismatch(_) => 706,
+            NabuError::EncryptedXFF => 707,
+            NabuError::UnknownEncryptionScheme(_) => 708,
+            NabuError::WrongPassphrase => 709,
+            NabuError::CorruptedEncryptedXFF => 710,
+
+            // Xff decode limit errors
+            NabuError::DepthLimitExceeded(_, _) => 800,
+            NabuError::ValueLengthLimitExceeded(_, _) => 801,
+            NabuError::KeyLimitExceeded(_, _) => 802,
+            NabuError::AllocationLimitExceeded(_, _) => 803,
+
+            // Xff value errors
+            NabuError::ConversionError(_) => 900,
+            NabuError::InvalidQuery(_, _) => 901,
+            NabuError::InvalidPatch(_) => 902,
+            NabuError::MergeConflict(_) => 903,
+            NabuError::InvalidSchema(_) => 904,
+        }
+    }
+
+    /// Returns the position in the byte stream the error was encountered at, counted in bytes
+    /// from its start
+    ///
+    /// `None` for errors not encountered while decoding a byte stream.
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            NabuError::MissingETX(pos)
+            | NabuError::MissingDLE(pos)
+            | NabuError::MissingESC(pos)
+            | NabuError::MissingTXT(pos)
+            | NabuError::MissingNUM(pos)
+            | NabuError::MissingARY(pos)
+            | NabuError::MissingOBJ(pos)
+            | NabuError::MissingDAT(pos)
+            | NabuError::MissingEV(pos)
+            | NabuError::InvalidNumber(pos, _)
+            | NabuError::InvalidArray(pos, _)
+            | NabuError::InvalidObject(pos, _)
+            | NabuError::InvalidKey(pos, _)
+            | NabuError::InvalidUTF8String(pos)
+            | NabuError::UnknownCompressionMethod(_, pos)
+            | NabuError::InvalidCompressedValue(pos)
+            | NabuError::BorrowedCompressedValue(pos)
+            | NabuError::InvalidASCIIString(_, pos, _)
+            | NabuError::InvalidXFFByte(_, pos, _)
+            | NabuError::InvalidASCIICommandCharacter(_, pos)
+            | NabuError::MissingEM(pos)
+            | NabuError::TruncatedXFF(pos)
+            | NabuError::ChecksumMismatch(pos)
+            | NabuError::DepthLimitExceeded(_, pos)
+            | NabuError::ValueLengthLimitExceeded(_, pos)
+            | NabuError::KeyLimitExceeded(_, pos)
+            | NabuError::AllocationLimitExceeded(_, pos) => Some(*pos),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::ser::Error for NabuError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        NabuError::SerdeError(msg.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for NabuError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        NabuError::SerdeError(msg.to_string())
+    }
+}
+
 impl fmt::Display for NabuError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             // external errors
             NabuError::IoError(err) => err.fmt(f),
+            NabuError::SerdeError(msg) => write!(f, "Serde error: {}", msg),
 
             // Xff v0 errors
             NabuError::MissingETX(u) => write!(f, "Missing ETX at byte position {}", u),
@@ -200,16 +501,24 @@ impl fmt::Display for NabuError {
             NabuError::MissingDAT(u) => write!(f, "Missing DAT at byte position {}", u),
             NabuError::MissingEV(u) => write!(f, "Missing EV at byte position {}", u),
             NabuError::InvalidNumber(i, n) => write!(f, "Invalid number: {} at byte position {}", n, i),
-            NabuError::InvalidArray(a, i) => write!(f, "Invalid array structure byte: {} at byte position {}. Expected an array separator", a, i),
-            NabuError::InvalidObject(o, i) => write!(f, "Invalid object structure byte: {} at byte position {}. Expected an object separator", o, i),
+            NabuError::InvalidArray(i, a) => write!(f, "Invalid array structure byte: {} at byte position {}. Expected an array separator", a, i),
+            NabuError::InvalidObject(i, o) => write!(f, "Invalid object structure byte: {} at byte position {}. Expected an object separator", o, i),
             NabuError::InvalidKey(p, v) => write!(f, "Invalid non string key: {} at byte position {}", v, p),
             NabuError::InvalidXFFValueLength(len) => write!(f, "Invalid XFF value length: {} (max: 8 bytes / 18.446.744.073.709.551.615)", len),
 
+            // Xff v2 errors
+            NabuError::InvalidUTF8String(u) => write!(f, "Invalid UTF-8 string at byte position {}", u),
+            NabuError::UnknownCompressionMethod(m, u) => write!(f, "Unknown compression method: {} at byte position {}", m, u),
+            NabuError::InvalidCompressedValue(u) => write!(f, "Invalid compressed value at byte position {}", u),
+            NabuError::BorrowedCompressedValue(u) => write!(f, "Compressed value at byte position {} can not be borrowed", u),
+
             // Xff general serde errors
-            NabuError::InvalidASCIIString(b, i, v) => write!(f, "Invalid ASCII character (according to xff specification version: {}): {} at byte position {}", b, v, i),
+            NabuError::InvalidASCIIString(b, i, v) => write!(f, "Invalid ASCII character (according to xff specification version: {}): {} at byte position {}", v, b, i),
             NabuError::InvalidXFFExtension(ext, err) => write!(f, "Invalid {} extension, {}", ext, err),
             NabuError::InvalidXFFByte(b, i, v) => write!(f, "Invalid XFF byte: {} for Xff Version {} at byte position {}", b, v, i),
             NabuError::InvalidXFFValueForVersion(value, ver) => write!(f, "Invalid XffValue for xff specification version {}: {:?}", ver, value),
+            NabuError::UnrepresentableCharacter(c, ver) => write!(f, "Character {:?} (U+{:04X}) can not be represented in a string of xff specification version {}", c, *c as u32, ver),
+            NabuError::NestingTooDeep(max) => write!(f, "Arrays and objects nested deeper than {} levels can not be written", max),
 
             // Xff v0 serde errors
             NabuError::InvalidASCIICommandCharacter(b, i) => write!(f, "Invalid ASCII command character: {} at byte position {}", b, i),
@@ -219,7 +528,26 @@ impl fmt::Display for NabuError {
             NabuError::EmpthyXFF => write!(f, "Empthy XFF"),
             NabuError::TruncatedXFF(u) => write!(f, "Truncated XFF at byte position {}", u),
             NabuError::UnknownXFFVersion(ver) => write!(f, "Unknown XFF version: {}", ver),
+            NabuError::UnknownExtendedXFFVersion(ver) => write!(f, "Unknown extended XFF version: {}", ver),
             NabuError::InvalidXFFVersion(val, ver) => write!(f, "Invalid XffValue for XFF version. Value {}; Version {}", val, ver),
+            NabuError::ChecksumMismatch(u) => write!(f, "Checksum mismatch in the block starting at byte position {}", u),
+            NabuError::EncryptedXFF => write!(f, "Encrypted XFF, a passphrase is needed to read it"),
+            NabuError::UnknownEncryptionScheme(s) => write!(f, "Unknown encryption scheme: {}", s),
+            NabuError::WrongPassphrase => write!(f, "Wrong passphrase"),
+            NabuError::CorruptedEncryptedXFF => write!(f, "Corrupted encrypted XFF"),
+
+            // Xff decode limit errors
+            NabuError::DepthLimitExceeded(max, u) => write!(f, "Nesting deeper than the limit of {} at byte position {}", max, u),
+            NabuError::ValueLengthLimitExceeded(len, u) => write!(f, "Value length {} exceeds the limit at byte position {}", len, u),
+            NabuError::KeyLimitExceeded(max, u) => write!(f, "More object keys than the limit of {} at byte position {}", max, u),
+            NabuError::AllocationLimitExceeded(max, u) => write!(f, "Allocating more than the limit of {} bytes at byte position {}", max, u),
+
+            // Xff value errors
+            NabuError::ConversionError(msg) => write!(f, "Conversion error: {}", msg),
+            NabuError::InvalidQuery(pos, query) => write!(f, "Invalid query: {} at character position {}", query, pos),
+            NabuError::InvalidPatch(msg) => write!(f, "Invalid patch: {}", msg),
+            NabuError::MergeConflict(path) => write!(f, "Merge conflict at '{}'", path),
+            NabuError::InvalidSchema(msg) => write!(f, "Invalid schema: {}", msg),
         }
     }
 }
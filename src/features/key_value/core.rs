@@ -3,9 +3,15 @@ use std::{collections::BTreeMap, path::Path};
 use crate::{
     error::NabuError,
     key_value_core::new_core_store,
-    serde::{read, write_legacy},
+    serde::{read, read_exact, write_legacy, write_legacy_exact},
     XffValue,
 };
+#[cfg(feature = "encryption")]
+use crate::xff::{
+    deserializer::deserialize_xff_bytes,
+    encryption::{decrypt, encrypt},
+    serializer::{serialize_xff, write_bytes_to_file},
+};
 
 /// LEGACY (v0) - Please consider using the inbuilt `OBJECT` type instead
 ///
@@ -25,7 +31,40 @@ use crate::{
 /// assert!(data.is_ok());
 /// ```
 pub fn read_core(path: &Path) -> Result<BTreeMap<String, XffValue>, NabuError> {
-    let content = read(path)?.into_array().unwrap();
+    core_from_value(read(path)?)
+}
+
+/// LEGACY (v0) - Please consider using the inbuilt `OBJECT` type instead
+///
+/// Reads the content of a XFF file at exactly the given path, whatever its extension, and
+/// returns a BTreeMap
+/// Works like `read_core`, but the path is not changed to end in `.xff`
+///
+/// # Arguments
+/// * `path` - The path to the file to read
+pub fn read_core_exact(path: &Path) -> Result<BTreeMap<String, XffValue>, NabuError> {
+    core_from_value(read_exact(path)?)
+}
+
+/// LEGACY (v0) - Please consider using the inbuilt `OBJECT` type instead
+///
+/// Reads the content of an encrypted XFF file and returns a BTreeMap
+/// Please note that only XFF files written by the `write_core_encrypted` function are supported
+///
+/// # Arguments
+/// * `path` - The path to the file to read
+/// * `passphrase` - The passphrase the file was encrypted with
+#[cfg(feature = "encryption")]
+pub fn read_core_encrypted(
+    path: &Path,
+    passphrase: &[u8],
+) -> Result<BTreeMap<String, XffValue>, NabuError> {
+    let bytes = std::fs::read(path)?;
+    core_from_value(deserialize_xff_bytes(&decrypt(&bytes, passphrase)?)?)
+}
+
+fn core_from_value(value: XffValue) -> Result<BTreeMap<String, XffValue>, NabuError> {
+    let content = value.into_array().unwrap();
     let mut out = new_core_store();
     let mut key: String = Default::default();
     for (index, entry) in content.iter().enumerate() {
@@ -79,10 +118,44 @@ pub fn read_core(path: &Path) -> Result<BTreeMap<String, XffValue>, NabuError> {
 /// assert!(write.is_ok());
 /// ```
 pub fn write_core(path: &Path, data: BTreeMap<String, XffValue>) -> Result<(), NabuError> {
+    write_legacy(path, core_to_values(data), 0)
+}
+
+/// LEGACY (v0) - Please consider using the inbuilt `OBJECT` type instead
+///
+/// Writes a BTreeMap to a XFF file at exactly the given path, whatever its extension
+/// Works like `write_core`, but the path is not changed to end in `.xff`
+///
+/// # Arguments
+/// * `path` - The path to the file to write
+/// * `data` - The BTreeMap to write
+pub fn write_core_exact(path: &Path, data: BTreeMap<String, XffValue>) -> Result<(), NabuError> {
+    write_legacy_exact(path, core_to_values(data), 0)
+}
+
+/// LEGACY (v0) - Please consider using the inbuilt `OBJECT` type instead
+///
+/// Writes a BTreeMap to an encrypted XFF file
+///
+/// # Arguments
+/// * `path` - The path to the file to write
+/// * `data` - The BTreeMap to write
+/// * `passphrase` - The passphrase to derive the key from
+#[cfg(feature = "encryption")]
+pub fn write_core_encrypted(
+    path: &Path,
+    data: BTreeMap<String, XffValue>,
+    passphrase: &[u8],
+) -> Result<(), NabuError> {
+    let bytes = serialize_xff(core_to_values(data), 0)?;
+    write_bytes_to_file(path, encrypt(&bytes, passphrase)?, true)
+}
+
+pub(crate) fn core_to_values(data: BTreeMap<String, XffValue>) -> Vec<XffValue> {
     let mut out: Vec<XffValue> = Default::default();
     for (key, value) in data.iter() {
         out.push(XffValue::String(key.to_string()));
         out.push(value.clone());
     }
-    write_legacy(path, out, 0)
+    out
 }
@@ -2,9 +2,16 @@ use std::{collections::BTreeMap, path::PathBuf};
 
 use crate::{
     error::NabuError,
-    key_value_core::{read, write},
-    xff::value::XffValue,
+    features::key_value::core::{core_to_values, read_core_exact},
+    xff::{
+        serializer::{serialize_xff, write_bytes_to_file},
+        value::XffValue,
+    },
 };
+#[cfg(feature = "encryption")]
+use crate::{features::key_value::core::read_core_encrypted, xff::encryption::encrypt};
+#[cfg(feature = "encryption")]
+use zeroize::Zeroizing;
 
 #[derive(Debug)]
 /// LEGACY (v0) - Please consider using the inbuilt `OBJECT` type instead
@@ -97,6 +104,21 @@ pub struct NabuDB {
     path: std::path::PathBuf,
     length: usize,
     auto_save: bool,
+    atomic_save: bool,
+    /// Passphrase to encrypt the file with, if any
+    #[cfg(feature = "encryption")]
+    passphrase: Option<Passphrase>,
+}
+
+/// A passphrase, never printed by `Debug` and zeroed when dropped
+#[cfg(feature = "encryption")]
+struct Passphrase(Zeroizing<Vec<u8>>);
+
+#[cfg(feature = "encryption")]
+impl std::fmt::Debug for Passphrase {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("Passphrase(..)")
+    }
 }
 
 impl NabuDB {
@@ -124,12 +146,15 @@ impl NabuDB {
     /// Also returns Nabu errors when issues with the XFF format occur
     pub fn new(path: PathBuf) -> Result<Self, NabuError> {
         if path.exists() {
-            let core: BTreeMap<String, XffValue> = read(path.as_path())?;
+            let core: BTreeMap<String, XffValue> = read_core_exact(path.as_path())?;
             Ok(NabuDB {
                 length: core.len(),
                 core,
                 path: path.to_path_buf(),
                 auto_save: false,
+                atomic_save: true,
+                #[cfg(feature = "encryption")]
+                passphrase: None,
             })
         } else {
             Ok(NabuDB {
@@ -137,10 +162,56 @@ impl NabuDB {
                 core: BTreeMap::new(),
                 path,
                 auto_save: false,
+                atomic_save: true,
+                #[cfg(feature = "encryption")]
+                passphrase: None,
             })
         }
     }
 
+    /// LEGACY (v0)
+    ///
+    /// Creates a new encrypted `NabuDB` from a path
+    /// If the path does not exist, a new `NabuDB` will be created,
+    /// otherwise the `NabuDB` will be loaded from the file, decrypting it with the passphrase
+    ///
+    /// Every `save` encrypts the file with a key derived from the passphrase, see
+    /// `serde::write_encrypted`.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to load
+    /// * `passphrase` - The passphrase to derive the key from
+    ///
+    /// # Example
+    /// ```ignore
+    /// use nabu::key_value_store::new_encrypted_nabudb;
+    ///
+    /// let db = new_encrypted_nabudb("xff-example-data/nabuDB_encrypted.xff", "passphrase");
+    /// assert!(db.is_ok());
+    /// ```
+    ///
+    /// # Errors
+    /// Returns `NabuError::WrongPassphrase` if the passphrase does not match the existing file
+    /// Returns IO errors when issues with reading the file from disk occur
+    /// Also returns Nabu errors when issues with the XFF format occur
+    #[cfg(feature = "encryption")]
+    pub fn new_encrypted<K: AsRef<[u8]>>(path: PathBuf, passphrase: K) -> Result<Self, NabuError> {
+        let passphrase = Zeroizing::new(passphrase.as_ref().to_vec());
+        let core: BTreeMap<String, XffValue> = if path.exists() {
+            read_core_encrypted(path.as_path(), &passphrase)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(NabuDB {
+            length: core.len(),
+            core,
+            path,
+            auto_save: false,
+            atomic_save: true,
+            passphrase: Some(Passphrase(passphrase)),
+        })
+    }
+
     /// LEGACY (v0)
     ///
     /// Saves the `NabuDB` to disk
@@ -158,7 +229,13 @@ impl NabuDB {
     /// db.save();
     /// ```
     pub fn save(&mut self) -> Result<(), NabuError> {
-        write(&self.path, self.core.clone())
+        let bytes = serialize_xff(core_to_values(self.core.clone()), 0)?;
+        #[cfg(feature = "encryption")]
+        let bytes = match &self.passphrase {
+            Some(Passphrase(passphrase)) => encrypt(&bytes, passphrase)?,
+            None => bytes,
+        };
+        write_bytes_to_file(&self.path, bytes, self.atomic_save)
     }
 
     /// LEGACY (v0)
@@ -183,6 +260,29 @@ impl NabuDB {
         self.auto_save = auto_save;
     }
 
+    /// LEGACY (v0)
+    ///
+    /// Enables or disables atomic saving
+    /// By default, every save is atomic
+    ///
+    /// An atomic save writes a temporary file next to the `NabuDB` and renames it over the
+    /// `NabuDB` once it is completely on disk, so a crash while saving never loses the
+    /// previously saved entries. Disabling it overwrites the file in place.
+    ///
+    /// # Arguments
+    /// * `atomic_save` - boolean to set atomic saving to on or off
+    ///
+    /// # Example
+    /// ```ignore
+    /// use nabu::key_value_store::new_nabudb;
+    ///
+    /// let mut db = new_nabudb("xff-example-data/nabuDB_v0.xff").unwrap();
+    /// db.set_atomic_save(false);
+    /// ```
+    pub fn set_atomic_save(&mut self, atomic_save: bool) {
+        self.atomic_save = atomic_save;
+    }
+
     /// LEGACY (v0)
     ///
     /// Helper function to call everywhere to save the `NabuDB`
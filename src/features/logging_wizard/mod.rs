@@ -50,6 +50,8 @@ use std::collections::BTreeMap;
 pub struct LoggingWizard {
     /// This stores if the logs are to be appended or not
     append: bool,
+    /// This stores if the file is saved atomically or overwritten in place
+    atomic_save: bool,
     /// This stores the path of the file where the logs are written, this file would only be
     /// crated if not existent, otherwise a simple append would be used
     path: std::path::PathBuf,
@@ -107,12 +109,34 @@ impl LoggingWizard {
     where
         P: AsRef<std::path::Path>,
     {
-        let path = path.as_ref().to_path_buf().with_extension("xff");
+        LoggingWizard::from_file_exact(path.as_ref().with_extension("xff"))
+    }
+
+    /// LEGACY (v0)
+    /// Creates a new LoggingWizard from disk, at exactly the given path, whatever its extension
+    /// Works like `from_file`, but the path is not changed to end in `.xff`
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to read
+    ///
+    /// # Example
+    /// ```ignore
+    /// use nabu::logging_wizard::LoggingWizard;
+    ///
+    /// let wizard = LoggingWizard::from_file_exact("xff-example-data/logging_wizard.log");
+    /// assert!(wizard.is_ok());
+    /// ```
+    pub fn from_file_exact<P>(path: P) -> Result<LoggingWizard, NabuError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let path = path.as_ref().to_path_buf();
         if path.exists() {
             read_log_wizard(path, false)
         } else {
             Ok(LoggingWizard {
                 append: false,
+                atomic_save: true,
                 path,
                 logs: Vec::new(),
                 logs_len: 0,
@@ -137,9 +161,31 @@ impl LoggingWizard {
     where
         P: AsRef<std::path::Path>,
     {
-        let path = path.as_ref().to_path_buf().with_extension("xff");
+        LoggingWizard::new_exact(path.as_ref().with_extension("xff"))
+    }
+
+    /// LEGACY (v0)
+    /// Creates a new LoggingWizard at exactly the given path, whatever its extension, without
+    /// reading the file from disk to memory, appends all new logs to the end
+    /// Works like `new`, but the path is not changed to end in `.xff`
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to write
+    ///
+    /// # Example
+    /// ```ignore
+    /// use nabu::logging_wizard::LoggingWizard;
+    ///
+    /// let wizard = LoggingWizard::new_exact("xff-example-data/logging_wizard.log");
+    /// ```
+    pub fn new_exact<P>(path: P) -> LoggingWizard
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let path = path.as_ref().to_path_buf();
         LoggingWizard {
             append: true,
+            atomic_save: true,
             path,
             logs: Vec::new(),
             logs_len: 0,
@@ -162,7 +208,7 @@ impl LoggingWizard {
     /// ```
     pub fn save(&mut self) -> Result<(), NabuError> {
         if self.append {
-            match append_to_log_wizard(&self.path, &self.logs) {
+            match append_to_log_wizard(&self.path, &self.logs, self.atomic_save) {
                 Ok(_) => {
                     self.logs.clear();
                     self.logs_len = 0;
@@ -171,10 +217,31 @@ impl LoggingWizard {
                 Err(e) => Err(e),
             }
         } else {
-            write_log_wizard(&self.path, &self.logs)
+            write_log_wizard(&self.path, &self.logs, self.atomic_save)
         }
     }
 
+    /// LEGACY (v0)
+    /// Enables or disables atomic saving, by default every save is atomic
+    ///
+    /// An atomic save writes a temporary file next to the log file and renames it over the log
+    /// file once it is completely on disk, so a crash while saving never loses the previously
+    /// saved logs. Disabling it overwrites the file in place.
+    ///
+    /// # Arguments
+    /// * `atomic_save` - boolean to set atomic saving to on or off
+    ///
+    /// # Example
+    /// ```ignore
+    /// use nabu::logging_wizard::LoggingWizard;
+    ///
+    /// let mut wizard = LoggingWizard::new("xff-example-data/logging_wizard.xff");
+    /// wizard.set_atomic_save(false);
+    /// ```
+    pub fn set_atomic_save(&mut self, atomic_save: bool) {
+        self.atomic_save = atomic_save;
+    }
+
     /// LEGACY (v0)
     /// Adds a new log to the LoggingWizard
     ///
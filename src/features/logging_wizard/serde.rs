@@ -1,7 +1,7 @@
 use crate::{
     error::NabuError,
     features::logging_wizard::{Log, LogData, LoggingWizard},
-    serde::read,
+    serde::read_exact,
     xff::serializer::{serialize_xff, write_bytes_to_file},
     XffValue,
     CommandCharacter,
@@ -19,9 +19,10 @@ use std::{
 /// # Arguments
 /// * `path` - The path to the file to write
 /// * `data` - The data to write
-pub fn write_log_wizard(path: &Path, data: &Vec<Log>) -> Result<(), NabuError> {
+/// * `atomic` - If the file is written atomically
+pub fn write_log_wizard(path: &Path, data: &Vec<Log>, atomic: bool) -> Result<(), NabuError> {
     let byte_data = logs_to_bytes(data)?;
-    write_bytes_to_file(path, byte_data)
+    write_bytes_to_file(path, byte_data, atomic)
 }
 
 /// LEGACY (v0)
@@ -31,11 +32,11 @@ pub fn write_log_wizard(path: &Path, data: &Vec<Log>) -> Result<(), NabuError> {
 /// # Arguments
 /// * `path` - The path to the file to write
 /// * `data` - The data to write
-pub fn append_to_log_wizard(path: &Path, data: &Vec<Log>) -> Result<(), NabuError> {
+/// * `atomic` - If the file is written atomically
+pub fn append_to_log_wizard(path: &Path, data: &Vec<Log>, atomic: bool) -> Result<(), NabuError> {
     let mut byte_data = logs_to_bytes(data)?;
     if !path.exists() {
-        std::fs::write(path, byte_data)?;
-        Ok(())
+        write_bytes_to_file(path, byte_data, atomic)
     } else {
         let mut file_as_bytes: Vec<u8> = std::fs::read(path)?;
         // Dropping the last byte, EM byte
@@ -51,8 +52,7 @@ pub fn append_to_log_wizard(path: &Path, data: &Vec<Log>) -> Result<(), NabuErro
         // appending data to file, this should move the data instead of copying it into the vector.
         // I hope at least.
         file_as_bytes.extend(byte_data);
-        std::fs::write(path, file_as_bytes)?;
-        Ok(())
+        write_bytes_to_file(path, file_as_bytes, atomic)
     }
 }
 
@@ -113,7 +113,7 @@ where
 {
     let mut value_pos: usize = 1;
     // creating the Token array
-    let mut data: VecDeque<XffValue> = read(path.as_ref())?.into_array().unwrap().into_iter().collect();
+    let mut data: VecDeque<XffValue> = read_exact(path.as_ref())?.into_array().unwrap().into_iter().collect();
     let mut logs: Vec<Log> = Vec::new();
     while data.len() > 0 {
         match data[0] {
@@ -148,7 +148,8 @@ where
         logs_len: logs.len(),
         logs,
         append,
-        path: path.as_ref().to_path_buf().with_extension("xff"),
+        atomic_save: true,
+        path: path.as_ref().to_path_buf(),
     })
 }
 
@@ -10,7 +10,7 @@ The overarching goal of this project is to create a rust library that can be use
 
 As with all my projects, this documentation contains everything you never wanted to know about `.xff` files or Nabu.
 
-This README documents the usage of the most recent version of `.xff`: Version 1.
+This README documents the usage of the most recent version of `.xff`: Version 2.
 
 If you would like to use version 0, please refer to the [releases page](https://github.com/Xqhare/nabu/releases/tag/v.0.6.2). 
 There you can find the documentation and code for version 0. 
@@ -52,15 +52,24 @@ The only candidate is 'Nabu's Archival Binary Utility' as of now. I don't really
     - [A Hello World of sorts](#a-hello-world-of-sorts)
     - [Serde](#serde)
         - [Usage of serde](#usage-of-serde)
+        - [The serde crate](#the-serde-crate)
+        - [Derive macros](#derive-macros)
     - [XffValue](#xffvalue)
         - [From](#from)
         - [Associated Functions](#associated-functions)
+        - [Pointers and queries](#pointers-and-queries)
+        - [Diff and patch](#diff-and-patch)
+        - [Merging](#merging)
+        - [Schemas](#schemas)
+        - [Canonical encoding and hashing](#canonical-encoding-and-hashing)
         - [Notes on value types](#notes-on-value-types)
             - [Object](#object)
             - [Array](#array)
 - [Errors](#errors)
     - [IO Errors](#ioerror)
     - [InternalError](#internalerror)
+    - [Error codes and diagnostics](#error-codes-and-diagnostics)
+    - [Recovering damaged files](#recovering-damaged-files)
 - [Testing](#testing)
     
 ## Roadmap
@@ -75,6 +84,12 @@ The only candidate is 'Nabu's Archival Binary Utility' as of now. I don't really
 - Performant
     - 100MB are read in approximately 3 seconds
 - Meaningful errors
+- Crash-safe, atomic writing of files
+- Optional integrity checksums, detecting corrupted data down to the block of 4096 bytes
+- Optional compression of `Data` values or whole files, without any dependencies
+- Optional authenticated encryption of files with a passphrase
+- Optional integration with the `serde` crate
+- Optional derive macros for conversions to and from `XffValue`
 - Fully documented
 - High test coverage
 
@@ -85,8 +100,15 @@ All specifications are in the `specifications` directory.
 
 - [V0](specifications/v0.md).
 - [V1](specifications/v1.md).
+- [V2](specifications/v2.md).
 
-V2 is not yet finalized, but my musings about it can be found [here](specifications/v2.md).
+Encrypted files wrap a `.xff` byte stream of any version, see [encryption](specifications/encryption.md).
+
+V2 fixes the pain points of v1: strings are encoded in UTF-8, numbers are stored in binary and the version header can be extended to two more bytes.
+V1 can still be written by passing `1` to `write_legacy` or `to_bytes_legacy`.
+
+V1 strings are limited to the [`.xff` byte encoding](specifications/xff-byte-encoding.md), any character outside of it is rejected with `NabuError::UnrepresentableCharacter` instead of being silently truncated.
+V1 numbers follow the number grammar of the specification: numbers like `+1`, `1e5` or `007` are rejected with `NabuError::InvalidNumber` when read, `NaN` and infinite floats are refused when written.
 
 ## Usage
 
@@ -105,9 +127,23 @@ A quick overview:
 ```rust
 // All functionality needed to read, parse and write `.xff` files
 use nabu::serde::{read, write, remove_file};
+// Encoding and decoding of `.xff` byte streams held in memory or behind any reader or writer, and random access to them
+use nabu::serde::{from_bytes, from_bytes_ref, to_bytes, XffDocument, XffReader, XffWriter};
+// Writing with options, like an integrity checksum
+use nabu::serde::{to_bytes_with_options, write_with_options, WriteOptions};
 // All types needed to store and manipulate entries stored in `.xff` files
 use nabu::XffValue;
 use nabu::{Array, Object, Data, Number};
+// A parsed query selecting values nested inside of a `XffValue`
+use nabu::Query;
+// Structural diff and patch of `XffValue`s
+use nabu::diff::{diff, Patch};
+// Deep merge of `Object`s and `XffValue`s
+use nabu::MergeStrategy;
+// Schemas describing the shape of `XffValue`s, validated with the path of every violation
+use nabu::schema::{Schema, Violation};
+// A value borrowing its data from a `.xff` byte stream
+use nabu::XffValueRef;
 ```
 
 ### A Hello World of sorts
@@ -174,6 +210,257 @@ assert_eq!(ok, data);
 # remove_file(path_2).unwrap();
 ```
 
+To read or write a file under any other name, e.g. a backup called `data.v2.backup` or a file without an extension, every function taking a path has a variant ending in `_exact`, which uses the path exactly as given.
+
+```rust
+use nabu::serde::{read_exact, remove_file_exact, write_exact};
+use nabu::XffValue;
+let path = "xff-example-data/serde-example.v2.backup";
+
+let data = XffValue::from("hello mom");
+write_exact(path, data.clone()).unwrap();
+assert_eq!(read_exact(path).unwrap(), data);
+remove_file_exact(path).unwrap();
+```
+
+Should the `.xff` content not live in a file, for example when it is sent over a socket or embedded in another container, `from_bytes` and `to_bytes` work on byte streams held in memory instead.
+
+```rust
+use nabu::serde::{from_bytes, to_bytes};
+use nabu::XffValue;
+
+let data = XffValue::from(vec![XffValue::from("hello mom"), XffValue::from(true)]);
+
+let bytes = to_bytes(data.clone());
+assert!(bytes.is_ok());
+let decoded = from_bytes(&bytes.unwrap());
+assert!(decoded.is_ok());
+assert_eq!(decoded.unwrap(), data);
+```
+
+Byte streams from untrusted sources can declare values far larger than themselves, or nest arrays deep enough to overflow the stack.
+`read_with_options`, `from_bytes_with_options` and the `with_options` variants of every other decoder take `DecodeOptions` limiting the nesting depth, the length of any single value, the keys of any single object and the total bytes allocated while decoding.
+Anything beyond a limit is rejected with an error before it is allocated.
+The default options, used by `read`, `from_bytes` and every decoder without options, limit the nesting to `MAX_DEPTH` (128 levels) and nothing else.
+Deeper limits are honoured, as long as the stack of the decoding thread is large enough: the decoders recurse once for every level.
+The writers never write values nested deeper than `MAX_DEPTH`, so everything written can be read back with the default options.
+
+```rust
+use nabu::serde::{from_bytes_with_options, to_bytes, DecodeOptions};
+use nabu::{NabuError, XffValue};
+
+let options = DecodeOptions::new()
+    .max_depth(32)
+    .max_value_len(1024)
+    .max_keys(256)
+    .max_total_bytes(1 << 20);
+
+let bytes = to_bytes(XffValue::from("hello mom")).unwrap();
+assert_eq!(from_bytes_with_options(&bytes, &options).unwrap(), XffValue::from("hello mom"));
+
+let bytes = to_bytes(XffValue::from(vec![0_u8; 4096])).unwrap();
+assert!(matches!(
+    from_bytes_with_options(&bytes, &options),
+    Err(NabuError::ValueLengthLimitExceeded(4096, _))
+));
+```
+
+For large files, `XffReader` and `XffWriter` decode and encode v1 and v2 incrementally over any `std::io::Read` or `std::io::Write`, without holding the complete byte stream in memory.
+
+```rust
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use nabu::serde::{XffReader, XffWriter};
+use nabu::XffValue;
+
+let path = "xff-example-data/serde-stream-example.xff";
+let data = XffValue::from(vec![XffValue::from("hello mom"), XffValue::from(vec![0, 1, 2, 3])]);
+
+let mut writer = XffWriter::new(BufWriter::new(File::create(path).unwrap()));
+assert!(writer.write(&data).is_ok());
+
+let mut reader = XffReader::new(BufReader::new(File::open(path).unwrap()));
+let read = reader.read();
+assert!(read.is_ok());
+assert_eq!(read.unwrap(), data);
+# std::fs::remove_file(path).unwrap();
+```
+
+To read large files full of data without copying it, `from_bytes_ref` parses a v1 or v2 byte stream held in memory, for example a memory-mapped file, into a `XffValueRef`.
+All `Data` is borrowed from the byte stream and `String`s are only decoded once accessed, `to_owned` converts it into a `XffValue`.
+
+```rust
+use nabu::serde::{from_bytes_ref, to_bytes};
+use nabu::{Object, XffValue, XffValueRef};
+
+let mut object = Object::new();
+object.insert("name", "picture.png");
+object.insert("content", vec![137_u8, 80, 78, 71]);
+let bytes = to_bytes(XffValue::from(object.clone())).unwrap();
+
+let value_ref: XffValueRef = from_bytes_ref(&bytes).unwrap();
+assert_eq!(value_ref.get("content").unwrap().as_data(), Some(&[137_u8, 80, 78, 71][..]));
+assert_eq!(value_ref.get("name").unwrap().as_string().unwrap().to_str().unwrap(), "picture.png");
+assert_eq!(value_ref.to_owned().unwrap(), XffValue::from(object));
+```
+
+To read only a few values out of a large file, `XffDocument` navigates a v1 or v2 byte stream over any `std::io::Read` and `std::io::Seek`.
+Arrays and objects are navigated with `get` and `index`, jumping over all preceding siblings using their length prefixes, and only the values passed to `read` are decoded.
+
+```rust
+use std::fs::File;
+use std::io::BufReader;
+use nabu::serde::{write, XffDocument};
+use nabu::{Object, XffValue};
+
+let path = "xff-example-data/serde-document-example.xff";
+let mut object = Object::new();
+object.insert("images", vec![XffValue::from("a.png"), XffValue::from("b.png")]);
+object.insert("name", "album");
+assert!(write(path, XffValue::from(object)).is_ok());
+
+let mut document = XffDocument::new(BufReader::new(File::open(path).unwrap())).unwrap();
+let root = document.root();
+let images = document.get(&root, "images").unwrap().unwrap();
+let image = document.index(&images, 1).unwrap().unwrap();
+assert_eq!(document.read(&image).unwrap(), XffValue::from("b.png"));
+# std::fs::remove_file(path).unwrap();
+```
+
+Structural errors like a missing `EV` are always detected, but a flipped bit inside of a string or data is read as a different value.
+All files are written atomically: the bytes are written to a temporary file next to the target, flushed to disk and renamed over the target, so a crash or power loss never leaves a truncated file behind.
+This includes `NabuDB::save` and `LoggingWizard::save`, both of which can opt out with `set_atomic_save(false)`; `write_with_options` opts out with `WriteOptions::atomic(false)`.
+
+`write_with_options` and `to_bytes_with_options` can append an integrity trailer of CRC-32 checksums, one for each block of 4096 bytes, by setting `WriteOptions::checksum`.
+The trailer is announced right after the version byte, and `read`, `from_bytes`, `from_bytes_ref` and `from_xff_bytes` verify it before decoding anything, returning `NabuError::ChecksumMismatch` with the position of the corrupted block.
+Readers unaware of the trailer reject the byte stream instead of reading it unverified.
+
+```rust
+use nabu::serde::{from_bytes, to_bytes_with_options, WriteOptions};
+use nabu::{NabuError, XffValue};
+
+let options = WriteOptions::new().checksum(true);
+let mut bytes = to_bytes_with_options(XffValue::from(vec![7_u8; 10_000]), &options).unwrap();
+assert_eq!(from_bytes(&bytes).unwrap(), XffValue::from(vec![7_u8; 10_000]));
+
+bytes[5_000] = 8;
+assert!(matches!(from_bytes(&bytes), Err(NabuError::ChecksumMismatch(4096))));
+```
+
+With the opt-in `compression` feature, `WriteOptions::compression` stores values compressed with a dependency-free LZ77 codec.
+`Compression::Data` compresses every `Data` value on its own, leaving everything else navigable by `XffDocument`, while `Compression::File` compresses the whole value.
+Values are only stored compressed if that makes them smaller.
+
+```toml
+[dependencies]
+nabu = { git = "https://github.com/Xqhare/nabu", features = ["compression"] }
+```
+
+`read`, `from_bytes`, `from_xff_bytes`, `XffReader` and `XffDocument::read` decompress values transparently.
+The compression method is stored alongside every compressed value, an unknown method, or any compressed value read without the feature, is rejected with `NabuError::UnknownCompressionMethod`.
+`from_bytes_ref` cannot borrow decompressed bytes and returns `NabuError::BorrowedCompressedValue` instead.
+
+```ignore
+use nabu::serde::{from_bytes, to_bytes, to_bytes_with_options, Compression, WriteOptions};
+use nabu::XffValue;
+
+let value = XffValue::from(vec![0_u8; 100_000]);
+let options = WriteOptions::new().compression(Compression::File);
+let bytes = to_bytes_with_options(value.clone(), &options).unwrap();
+assert!(bytes.len() < to_bytes(value.clone()).unwrap().len() / 100);
+assert_eq!(from_bytes(&bytes).unwrap(), value);
+```
+
+With the opt-in `encryption` feature, `write_encrypted` and `read_encrypted` store files encrypted with XChaCha20-Poly1305, using a key derived from a passphrase with Argon2id.
+A header identifying the scheme precedes the encrypted bytes, `read` rejects encrypted files with `NabuError::EncryptedXFF`.
+A wrong passphrase is reported as `NabuError::WrongPassphrase`, a file altered after it was encrypted as `NabuError::CorruptedEncryptedXFF`.
+`new_encrypted_nabudb` opens a `NabuDB` encrypted the same way.
+
+```toml
+[dependencies]
+nabu = { git = "https://github.com/Xqhare/nabu", features = ["encryption"] }
+```
+
+```ignore
+use nabu::serde::{read_encrypted, write_encrypted};
+use nabu::{NabuError, XffValue};
+
+let path = "xff-example-data/secrets.xff";
+write_encrypted(path, XffValue::from("hunter2"), "correct horse battery staple").unwrap();
+assert_eq!(read_encrypted(path, "correct horse battery staple").unwrap(), XffValue::from("hunter2"));
+assert!(matches!(read_encrypted(path, "hunter2"), Err(NabuError::WrongPassphrase)));
+```
+
+#### The serde crate
+Not to be confused with the `serde` module above: with the opt-in `serde` feature, any type implementing `Serialize` or `Deserialize` of the [serde](https://serde.rs) crate can be converted to and from a `XffValue` or a `.xff` byte stream, no hand-written conversions needed.
+
+```toml
+[dependencies]
+nabu = { git = "https://github.com/Xqhare/nabu", features = ["serde"] }
+```
+
+Structs and maps become `Object`s, sequences and tuples `Array`s, bytes (e.g. through `serde_bytes`) `Data` and unit or `None` `Null`.
+Enum variants are stored externally tagged: unit variants as a `String` holding their name, all other variants as an `Object` with their name as its only key.
+`XffValue` itself implements `Serialize` and `Deserialize` as well.
+
+`from_xff_bytes` decodes v1 and v2 byte streams directly into the type, without building a `XffValue` first, except for the content of compressed values.
+
+```ignore
+use nabu::{from_xff, from_xff_bytes, to_xff, to_xff_bytes, XffValue};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Person {
+    name: String,
+    age: u8,
+    nickname: Option<String>,
+}
+
+let person = Person { name: "Jörg".to_string(), age: 42, nickname: None };
+
+let value: XffValue = to_xff(&person).unwrap();
+assert!(value.is_object());
+assert_eq!(from_xff::<Person>(value).unwrap(), person);
+
+let bytes: Vec<u8> = to_xff_bytes(&person).unwrap();
+assert_eq!(from_xff_bytes::<Person>(&bytes).unwrap(), person);
+```
+
+#### Derive macros
+Without pulling in serde, the opt-in `derive` feature provides `#[derive(IntoXff, FromXff)]`, implementing `From<T> for XffValue` and `TryFrom<XffValue> for T` with `NabuError` as its error.
+
+```toml
+[dependencies]
+nabu = { git = "https://github.com/Xqhare/nabu", features = ["derive"] }
+```
+
+Structs become `Object`s, tuple structs `Array`s, newtype structs the value of their only field and unit structs `Null`.
+Enums are stored as tagged objects: an `Object` with the name of the variant as its only key, holding `Null`, the value, an `Array` or an `Object` depending on the variant.
+`Option` fields are written as `Null`, and read as `None` if their key is missing or holds `Null`.
+`Vec<u8>` fields are stored as `Data`, all other `Vec`s as `Array`s.
+Keys of fields and variants can be renamed with `#[xff(rename = "key")]`.
+
+Any other field type needs its own `From` and `TryFrom` implementations, e.g. by deriving them as well.
+
+```ignore
+use nabu::{FromXff, IntoXff, XffValue};
+
+#[derive(Debug, Clone, PartialEq, IntoXff, FromXff)]
+struct Person {
+    #[xff(rename = "full_name")]
+    name: String,
+    age: u8,
+    nickname: Option<String>,
+    picture: Vec<u8>,
+}
+
+let person = Person { name: "Jörg".to_string(), age: 42, nickname: None, picture: vec![0, 1, 2] };
+
+let value = XffValue::from(person.clone());
+assert!(value.as_object().unwrap().contains_key("full_name"));
+assert_eq!(Person::try_from(value).unwrap(), person);
+```
+
 ### XffValue
 A XffValue is the type used by Nabu to store and manipulate data.
 There are basic types such as `String`, `Number`, `Boolean`, `Null` and `Data`, along with the `Array` and `Object` types.
@@ -256,6 +543,9 @@ There are also several implementations of the `From` trait for the different typ
     - `usize`, `u8`, `u16`, `u32`, `u64` -> `Number::Unsigned`
     - `isize`, `i8`, `i16`, `i32`, `i64` -> `Number::Integer`
     - `f32`, `f64` -> `Number::Float`
+    - `u128` -> `Number::Unsigned` if it fits, `Number::BigUnsigned` otherwise
+    - `i128` -> `Number::Integer` if it fits, `Number::BigInteger` otherwise
+    - `Decimal` -> `Number::Decimal`
 
 - `Data`
     - `Vec<D>` where `D` can be converted to `u8` -> `Data`
@@ -270,11 +560,21 @@ There are also several implementations of the `From` trait for the different typ
 
 `XffValue` has several associated functions:
 - `into_{type}`
-    - Returns an option if the value is of the requested type.
+    - Consumes the value and returns an option if the value is of the requested type.
+- `as_{type}` and `as_{type}_mut`
+    - Return an option with a reference to the value if it is of the requested type, without cloning it.
+    - `as_str`, `as_number`, `as_array`, `as_object`, `as_data` and `as_boolean`, as well as `as_string_mut`, `as_number_mut`, `as_array_mut`, `as_object_mut`, `as_data_mut` and `as_boolean_mut`
+    - `as_boolean` returns a copy of the `bool` instead of a reference.
 - `is_{type}`
     - Returns a bool if the value is of the requested type.
 - `is_true`, `is_false` and `is_null`
     - Return true if the assertion is true
+- `pointer` and `pointer_mut`
+    - Return a reference to the value at a JSON Pointer, like `/users/3/name`
+- `query`
+    - Returns references to all values selected by a `Query`, like `$.users[?(@.age >= 18)].name`
+- `merge`
+    - Merges another value into this one, resolving conflicts by a `MergeStrategy`
 
 A quick example using `Number`, but it is applicable to any type:
 ```rust
@@ -285,17 +585,25 @@ let number = XffValue::Number(Number::from(42));
 assert!(number.is_number());
 assert!(!number.is_string());
 
-let inner_number = number.into_number().unwrap();
-assert_eq!(inner_number, Number::from(42));
-let inner_value_wrong_type = number.into_data();
+let inner_number = number.as_number().unwrap();
+assert_eq!(inner_number, &Number::from(42));
+let inner_value_wrong_type = number.as_data();
 assert_eq!(inner_value_wrong_type, None);
+
+let mut number = number;
+*number.as_number_mut().unwrap() = Number::from(-42);
+let inner_number = number.into_number().unwrap();
+assert_eq!(inner_number, Number::from(-42));
 ```
 
 `Number` has the associated functions:
 
-- `into_usize`, `into_isize`, `into_f64`
-- `is_unsigned`, `is_integer`, `is_float`
+- `into_usize`, `into_isize`, `into_f64`, `into_u128`, `into_i128`, `into_decimal`
+- `is_unsigned`, `is_integer`, `is_float`, `is_big_unsigned`, `is_big_integer`, `is_decimal`
 - `as_string` -> This converts any number into a string
+
+Numbers are read without losing precision: integers too large for `Unsigned` or `Integer` are stored as `BigUnsigned` or `BigInteger`, and any other number that cannot be stored in a float exactly, like `0.1000000000000000055`, is kept as its decimal text in a `Decimal`.
+Number text can be parsed the same way with `str::parse::<Number>()`.
 - `as_u8()` -> converts the number into an ASCII encoded byte-stream
 
 `Object` has the associated functions:
@@ -309,8 +617,9 @@ The underlying data can be interacted with directly by using:
 - `clear`
 - `insert`
 - `remove`
-- `get`
+- `get`, `get_mut`
 - `contains_key`
+- `merge`
 - `iter`
 - `len`
 
@@ -325,7 +634,7 @@ The underlying data can be interacted with directly by using:
 - `clear`
 - `push`
 - `pop`
-- `get`
+- `get`, `get_mut`
 - `contains`
 - `iter`
 - `len`
@@ -339,6 +648,120 @@ The underlying data can be interacted with directly by using:
 - `len`
 - `into_vec`
 
+#### Pointers and queries
+Nested values can be reached without cloning anything in between.
+`pointer` and `pointer_mut` follow a JSON Pointer, `query` runs a `Query`, a subset of JSONPath supporting wildcards, array slices and filters on field values.
+See the documentation of `Query` for its full syntax.
+
+```rust
+use nabu::XffValue;
+
+let users = XffValue::from(vec![
+    XffValue::from(vec![("name", XffValue::from("Alice")), ("age", XffValue::from(31))]),
+    XffValue::from(vec![("name", XffValue::from("Bob")), ("age", XffValue::from(17))]),
+]);
+let mut data = XffValue::from(vec![("users", users)]);
+
+assert_eq!(data.pointer("/users/1/name"), Some(&XffValue::from("Bob")));
+*data.pointer_mut("/users/1/age").unwrap() = XffValue::from(18);
+
+let adults = data.query("$.users[?(@.age >= 18)].name").unwrap();
+assert_eq!(adults, vec![&XffValue::from("Alice"), &XffValue::from("Bob")]);
+let all = data.query("$.users[*].name").unwrap();
+assert_eq!(all.len(), 2);
+```
+
+#### Diff and patch
+`nabu::diff::diff` computes the changes between two values as a `Patch` of added, removed and replaced values, each addressed by a JSON Pointer.
+Applying the patch to the older value reproduces the newer one, and a patch can be stored as a `XffValue` itself.
+
+```rust
+use nabu::diff::{diff, Patch};
+use nabu::XffValue;
+
+let old = XffValue::from(vec![("hosts", XffValue::from(vec![XffValue::from("a"), XffValue::from("c")])), ("port", XffValue::from(80))]);
+let new = XffValue::from(vec![("hosts", XffValue::from(vec![XffValue::from("a"), XffValue::from("b"), XffValue::from("c")])), ("port", XffValue::from(8080))]);
+
+let patch = diff(&old, &new);
+assert_eq!(patch.to_string(), "+ /hosts/1: b\n~ /port: 80 -> 8080");
+
+let stored = XffValue::from(patch);
+let mut value = old.clone();
+Patch::try_from(stored).unwrap().apply(&mut value).unwrap();
+assert_eq!(value, new);
+```
+
+#### Merging
+`Object::merge` and `XffValue::merge` merge layered values, like defaults, a site configuration and user overrides, recursing into objects present on both sides.
+Values stored at the same path that differ are resolved by a `MergeStrategy`: the merged in value wins (`RightWins`, the default), the existing value wins (`LeftWins`), arrays are concatenated (`ConcatArrays`) or the merge fails without changing anything (`ErrorOnConflict`).
+
+```rust
+use nabu::{MergeStrategy, XffValue};
+
+let mut config = XffValue::from(vec![
+    ("server", XffValue::from(vec![("port", XffValue::from(8080)), ("hosts", XffValue::from(vec![XffValue::from("a")]))])),
+]);
+let user = XffValue::from(vec![
+    ("server", XffValue::from(vec![("hosts", XffValue::from(vec![XffValue::from("b")]))])),
+]);
+
+let mut strict = config.clone();
+assert!(strict.merge(user.clone(), MergeStrategy::ErrorOnConflict).is_err());
+
+config.merge(user, MergeStrategy::ConcatArrays).unwrap();
+assert_eq!(config.pointer("/server/port"), Some(&XffValue::from(8080)));
+assert_eq!(config.pointer("/server/hosts/1"), Some(&XffValue::from("b")));
+```
+
+#### Schemas
+A `nabu::schema::Schema` describes the shape of a value: its allowed types, required and unknown keys of objects, ranges of numbers, patterns of strings, lengths of strings, data, arrays and objects and the schemas of array elements and object values.
+`validate` returns every `Violation`, each with the JSON Pointer of the offending value.
+Schemas can be stored as `XffValue` objects themselves, so they can be shipped in `.xff` files alongside the data they describe.
+Patterns are a subset of regular expressions, see the documentation of `nabu::schema::Pattern`.
+
+```rust
+use nabu::schema::Schema;
+use nabu::XffValue;
+
+let schema = Schema::try_from(XffValue::from(vec![
+    ("types", XffValue::from("object")),
+    ("required", XffValue::from(vec![XffValue::from("port")])),
+    ("properties", XffValue::from(vec![
+        ("port", XffValue::from(vec![("types", XffValue::from("number")), ("maximum", XffValue::from(65535))])),
+        ("host", XffValue::from(vec![("types", XffValue::from("string")), ("pattern", XffValue::from("^[a-z.]+$"))])),
+    ])),
+])).unwrap();
+
+let config = XffValue::from(vec![("port", XffValue::from(80)), ("host", XffValue::from("example.com"))]);
+assert!(schema.is_valid(&config));
+
+let config = XffValue::from(vec![("host", XffValue::from(42))]);
+let violations = schema.validate(&config);
+assert_eq!(violations[0].to_string(), "'/port': missing required key");
+assert_eq!(violations[1].to_string(), "'/host': expected string, found number");
+```
+
+#### Canonical encoding and hashing
+The same value can be stored as different bytes, depending on the `.xff` version and the number variants it was built from.
+`nabu::serde::serialize_canonical` encodes a value into its canonical byte stream instead, the same for every value equal by content, on every machine.
+It is a v2 byte stream with minimal length prefixes, numbers stored in the first binary type holding their value exactly and no trailing `RS` separators.
+Numbers equal by value are encoded the same, no matter if they are a `usize`, an `f64` or a `Decimal`.
+
+`XffValue::content_hash` returns the SHA-256 digest of the canonical byte stream, to deduplicate values or detect changes across machines.
+
+```rust
+use nabu::serde::{from_bytes, to_bytes_legacy};
+use nabu::XffValue;
+
+let value = XffValue::from(vec![("id", XffValue::from(1)), ("price", XffValue::from(2.5))]);
+let hash = value.content_hash().unwrap();
+
+// v1 reads the id back as a `usize`, but the hash is the same
+let read = from_bytes(&to_bytes_legacy(vec![value.clone()], 1).unwrap()).unwrap();
+assert_ne!(read, value);
+assert_eq!(read.content_hash().unwrap(), hash);
+```
+
 #### Notes on value types
 All types are printable.
 The default returned by `XffValue::default()` is `XffValue::Null`.
@@ -389,6 +812,64 @@ These errors are generally not recoverable as they point to a malformed file.
 
 In error messages that contain a position value, the position is given in bytes from the start of the `.xff` file.
 
+#### Error codes and diagnostics
+`NabuError` implements `std::error::Error`, with the wrapped `std::io::Error` as the `source` of an `IoError`, so it can be returned with `?` from functions returning `Box<dyn Error>`.
+
+Every error has a stable numeric code returned by `NabuError::code`, which never changes once assigned.
+The hundreds give the group of the error: `1xx` external errors like `IoError`, `2xx` to `6xx` errors of the `.xff` versions and their serde, `7xx` file errors like checksums and encryption, `8xx` decode limit errors, and `9xx` value errors like failed conversions and invalid queries, patches and schemas.
+`NabuError::position` returns the byte position of errors encountered while decoding.
+
+`from_bytes_diagnosed` decodes like `from_bytes_with_options`, but returns a `Diagnostic` on error.
+It carries the error, the logical path of the value it was encountered in, and a hex excerpt of the bytes around its position.
+
+```rust
+use nabu::serde::{from_bytes_diagnosed, to_bytes, DecodeOptions};
+use nabu::XffValue;
+
+let value = XffValue::from(vec![("users", XffValue::from(vec![XffValue::from("hello mom")]))]);
+let mut bytes = to_bytes(value).unwrap();
+// not valid UTF-8
+let pos = bytes.iter().position(|b| *b == b'h').unwrap();
+bytes[pos] = 255;
+
+let diagnostic = from_bytes_diagnosed(&bytes, &DecodeOptions::default()).unwrap_err();
+assert_eq!(diagnostic.code(), 400);
+assert_eq!(diagnostic.position(), Some(pos));
+assert_eq!(diagnostic.path(), "root.users[0]");
+assert!(diagnostic.excerpt().unwrap().contains("[ff] 65 6c 6c 6f"));
+```
+
+#### Recovering damaged files
+`recover`, `recover_exact` and `recover_bytes` decode as much of a damaged `.xff` v0, v1 or v2 file as they can, instead of failing on the first error.
+A damaged value inside of an array or object is skipped up to the next readable value, every other value is kept.
+V0 files, including `NabuDB` files, are a flat list of values: a damaged one is skipped up to the next `ETX` followed by a readable value, and recovered as `XffValue::Null` to keep the keys and values of a `NabuDB` file paired.
+If the integrity trailer of a v2 file does not match, the value is recovered from all bytes and the trailer is skipped with a `NabuError::ChecksumMismatch`.
+The returned `Recovered` holds the recovered value and a `SkippedBytes` for every skipped range of bytes, with the path of the value and the error that made it unreadable.
+
+Encrypted files are decoded as strictly as by `read`.
+
+```rust
+use nabu::serde::{recover_bytes, to_bytes_legacy};
+use nabu::XffValue;
+
+let value = XffValue::from(vec![("users", XffValue::from(vec![
+    XffValue::from("hello mom"),
+    XffValue::from("hello dad"),
+]))]);
+let mut bytes = to_bytes_legacy(vec![value], 1).unwrap();
+// not a valid v1 character
+let pos = bytes.iter().position(|b| *b == b'm').unwrap();
+bytes[pos] = 0;
+
+let recovered = recover_bytes(&bytes).unwrap();
+assert_eq!(recovered.skipped().len(), 1);
+assert_eq!(recovered.skipped()[0].path(), "root.users[0]");
+assert_eq!(
+    recovered.value(),
+    &XffValue::from(vec![("users", XffValue::from(vec![XffValue::from("hello dad")]))])
+);
+```
+
 ### Testing
 Nabu can be tested with the following commands:
 ```bash
@@ -408,10 +889,21 @@ mod error;
 mod xff;
 
 pub use crate::xff::value::XffValue;
-pub use crate::xff::value::{Array, CommandCharacter, Data, Number, Object};
+pub use crate::xff::value::{Array, CommandCharacter, Data, Decimal, MergeStrategy, Number, Object, Query};
+pub use crate::xff::value::{XffStr, XffValueRef};
+
+pub use crate::error::{Diagnostic, NabuError};
+
+#[cfg(feature = "serde")]
+pub use crate::xff::serde_integration::{
+    from_xff, from_xff_bytes, from_xff_bytes_with_options, to_xff, to_xff_bytes,
+};
+
+#[cfg(feature = "derive")]
+pub use nabu_derive::{FromXff, IntoXff};
 
 /// Most recent finalised version of XFF specification
-const XFF_VERSION: u8 = 1;
+const XFF_VERSION: u8 = 2;
 
 /// Module to serialize and deserialize XFF files
 ///
@@ -420,6 +912,7 @@ const XFF_VERSION: u8 = 1;
 /// use nabu::serde::{read, write, remove_file};
 /// use nabu::{CommandCharacter, Data, Number, XffValue};
 /// // No matter what the extension of the path you provide, it will be converted to .xff
+/// // To keep it, use the `_exact` variants like `read_exact` and `write_exact`
 /// let path = "xff-example-data/serde-main-example.txt";
 /// let path_2 = "xff-example-data/serde-main-example.xff";
 ///
@@ -435,15 +928,29 @@ const XFF_VERSION: u8 = 1;
 /// remove_file(path_2).unwrap();
 /// ```
 pub mod serde {
-    use crate::error::NabuError;
-    use crate::xff::deserializer::deserialize_xff;
-    use crate::xff::serializer::{serialize_xff, write_bytes_to_file};
-    use crate::xff::value::XffValue;
+    use crate::error::{Diagnostic, NabuError};
+    use crate::xff::deserializer::{
+        deserialize_xff, deserialize_xff_borrowed, deserialize_xff_borrowed_with_options,
+        deserialize_xff_bytes, deserialize_xff_bytes_diagnosed, deserialize_xff_bytes_with_options,
+        recover_xff_bytes, recover_xff_bytes_with_options,
+    };
+    use crate::xff::serializer::{serialize_xff, serialize_xff_with_options, write_bytes_to_file};
+    use crate::xff::serializer::canonical::serialize_xff_canonical;
+    use crate::xff::value::{XffValue, XffValueRef};
     use crate::XFF_VERSION;
+    #[cfg(feature = "encryption")]
+    use crate::xff::encryption::{decrypt, encrypt};
+
+    pub use crate::xff::deserializer::{
+        DecodeOptions, Recovered, SkippedBytes, XffDocument, XffNode, XffReader, MAX_DEPTH,
+    };
+    pub use crate::xff::serializer::{WriteOptions, XffWriter};
+    #[cfg(feature = "compression")]
+    pub use crate::xff::compression::Compression;
 
     /// Reads the content of a XFF file and returns a Vec of XffValues
     ///
-    /// Because of the way v0 is implemented, it always returns a vector, for v1 it only has one element
+    /// Because of the way v0 is implemented, it always returns a vector, for v1 and v2 it only has one element
     ///
     /// # Arguments
     /// * `path` - The path to the file to read
@@ -465,14 +972,167 @@ pub mod serde {
     where
         P: AsRef<std::path::Path>,
     {
-        let path_with_xff_extension = path.as_ref().with_extension("xff");
-        deserialize_xff(&path_with_xff_extension)
+        read_exact(path.as_ref().with_extension("xff"))
+    }
+
+    /// Reads the content of a XFF file at exactly the given path, whatever its extension
+    ///
+    /// Works like `read`, but the path is not changed to end in `.xff`.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to read
+    ///
+    /// # Error
+    /// Errors if the file is not a valid XFF file or if an IO error occurs
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::{read_exact, write_exact};
+    /// use nabu::XffValue;
+    ///
+    /// let path = "xff-example-data/read_exact.v2.backup";
+    /// write_exact(path, XffValue::from("hello mom")).unwrap();
+    /// assert_eq!(read_exact(path).unwrap(), XffValue::from("hello mom"));
+    /// assert!(read_exact("xff-example-data/read_exact.v2.xff").is_err());
+    /// # std::fs::remove_file(path).unwrap();
+    /// ```
+    pub fn read_exact<P>(path: P) -> Result<XffValue, NabuError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        deserialize_xff(path.as_ref())
+    }
+
+    /// Reads the content of a XFF file within the limits of the `DecodeOptions`
+    ///
+    /// Use it to read files from untrusted sources, a crafted file cannot exhaust memory or
+    /// stack beyond the limits.
+    /// The path is changed to end in `.xff`, use `read_with_options_exact` to keep it as is.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to read
+    /// * `options` - The limits to decode within
+    ///
+    /// # Error
+    /// Errors like `read`, and with `NabuError::DepthLimitExceeded`,
+    /// `NabuError::ValueLengthLimitExceeded`, `NabuError::KeyLimitExceeded` or
+    /// `NabuError::AllocationLimitExceeded` once a limit is hit
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::{read_with_options, write, DecodeOptions};
+    /// use nabu::{NabuError, XffValue};
+    ///
+    /// let path = "xff-example-data/read_with_options.xff";
+    /// write(path, XffValue::from(vec![0_u8; 10_000])).unwrap();
+    /// let options = DecodeOptions::new().max_value_len(1_000);
+    /// assert!(matches!(
+    ///     read_with_options(path, &options),
+    ///     Err(NabuError::ValueLengthLimitExceeded(10_000, _))
+    /// ));
+    /// # std::fs::remove_file(path).unwrap();
+    /// ```
+    pub fn read_with_options<P>(path: P, options: &DecodeOptions) -> Result<XffValue, NabuError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        read_with_options_exact(path.as_ref().with_extension("xff"), options)
+    }
+
+    /// Reads the content of a XFF file at exactly the given path within the limits of the
+    /// `DecodeOptions`, whatever its extension
+    ///
+    /// Works like `read_with_options`, but the path is not changed to end in `.xff`.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to read
+    /// * `options` - The limits to decode within
+    ///
+    /// # Error
+    /// Errors like `read_with_options`
+    pub fn read_with_options_exact<P>(
+        path: P,
+        options: &DecodeOptions,
+    ) -> Result<XffValue, NabuError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let path = path.as_ref();
+        let max = match options.get_max_total_bytes() {
+            Some(max) => max,
+            None => return deserialize_xff_bytes_with_options(&std::fs::read(path)?, options),
+        };
+        // the file itself is the first allocation
+        let file_len = usize::try_from(std::fs::metadata(path)?.len()).unwrap_or(usize::MAX);
+        if file_len > max {
+            return Err(NabuError::AllocationLimitExceeded(max, 0));
+        }
+        let options = options.max_total_bytes(max - file_len);
+        deserialize_xff_bytes_with_options(&std::fs::read(path)?, &options).map_err(|e| match e {
+            NabuError::AllocationLimitExceeded(_, pos) => NabuError::AllocationLimitExceeded(max, pos),
+            e => e,
+        })
+    }
+
+    /// Recovers every readable value of a damaged XFF file
+    ///
+    /// Instead of failing on the first error like `read`, damaged values inside of arrays and
+    /// objects are skipped, and decoding resumes at the next value that can be read again.
+    /// Every skipped range of bytes is returned alongside the recovered value.
+    ///
+    /// v0 files, like those of `NabuDB`, v1 and v2 files are recovered, encrypted files are read
+    /// like `read`.
+    /// The path is changed to end in `.xff`, use `recover_exact` to keep it as is.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to recover
+    ///
+    /// # Error
+    /// Errors if an IO error occurs, or like `read` for encrypted files and unknown versions
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::{recover, write_legacy};
+    /// use nabu::XffValue;
+    ///
+    /// let path = "xff-example-data/recover.xff";
+    /// write_legacy(path, vec![XffValue::from(vec![XffValue::from("hello mom")])], 1).unwrap();
+    /// let recovered = recover(path).unwrap();
+    /// assert!(recovered.is_complete());
+    /// assert_eq!(recovered.into_value(), XffValue::from(vec![XffValue::from("hello mom")]));
+    /// # std::fs::remove_file(path).unwrap();
+    /// ```
+    pub fn recover<P>(path: P) -> Result<Recovered, NabuError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        recover_exact(path.as_ref().with_extension("xff"))
+    }
+
+    /// Recovers every readable value of a damaged XFF file at exactly the given path, whatever
+    /// its extension
+    ///
+    /// Works like `recover`, but the path is not changed to end in `.xff`.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to recover
+    ///
+    /// # Error
+    /// Errors if an IO error occurs, or like `read` for encrypted files and unknown versions
+    pub fn recover_exact<P>(path: P) -> Result<Recovered, NabuError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        recover_xff_bytes(&std::fs::read(path)?)
     }
 
     /// Writes XffValues to a XFF file
     ///
     /// Supports the most up to date version of the XFF specification.
-    /// To write v1, please supply only one element.
+    /// To write v1 or v2, please supply only one element.
+    ///
+    /// The file is written atomically, a crash never leaves a truncated file behind.
+    /// To overwrite the file in place instead, please refer to `write_with_options`.
     ///
     /// To write legacy versions, please refer to `write_legacy`.
     ///
@@ -501,56 +1161,613 @@ pub mod serde {
         P: AsRef<std::path::Path>,
         D: Into<Vec<XffValue>>,
     {
-        let path_with_xff_extension = path.as_ref().with_extension("xff");
-        let byte_data = serialize_xff(data.into(), XFF_VERSION)?;
-        write_bytes_to_file(&path_with_xff_extension, byte_data)
+        write_exact(path.as_ref().with_extension("xff"), data)
     }
 
-    /// Writes a Vec of XffValues to a XFF file with a specific XFF version
-    /// Provided for backwards compatibility and convenience
+    /// Writes XffValues to a XFF file at exactly the given path, whatever its extension
+    ///
+    /// Works like `write`, but the path is not changed to end in `.xff`.
     ///
     /// # Arguments
     /// * `path` - The path to the file to write
-    /// * `data` - The data to write
-    /// * `xff_version` - The XFF version to use
+    /// * `data` - The XffValue to write
     ///
     /// # Error
     /// Only errors if an IO error occurs
     ///
     /// # Example
     /// ```rust
-    /// use nabu::serde::write_legacy;
+    /// use nabu::serde::{read_exact, write_exact};
     /// use nabu::XffValue;
     ///
-    /// let data = {
-    ///     vec![
-    ///         XffValue::String("hello mom".to_string()),
-    ///     ]
-    /// };
-    /// let tmp = write_legacy("xff-example-data/v0.xff", data.clone(), 0);
-    /// assert!(tmp.is_ok());
+    /// let path = "xff-example-data/write_exact";
+    /// write_exact(path, XffValue::from(42)).unwrap();
+    /// assert_eq!(read_exact(path).unwrap(), XffValue::from(42));
+    /// # std::fs::remove_file(path).unwrap();
     /// ```
-    pub fn write_legacy<P>(path: P, data: Vec<XffValue>, xff_version: u8) -> Result<(), NabuError>
+    pub fn write_exact<P, D>(path: P, data: D) -> Result<(), NabuError>
     where
         P: AsRef<std::path::Path>,
+        D: Into<Vec<XffValue>>,
     {
-        let path_with_xff_extension = path.as_ref().with_extension("xff");
-        let byte_data = serialize_xff(data, xff_version)?;
-        write_bytes_to_file(&path_with_xff_extension, byte_data)
+        let byte_data = serialize_xff(data.into(), XFF_VERSION)?;
+        write_bytes_to_file(path.as_ref(), byte_data, true)
     }
 
-    /// A convenience function to delete any XFF file from disk
-    /// The file will be gone, take care!
+    /// Writes XffValues to a XFF file, as configured by the `WriteOptions`
+    ///
+    /// Supports the most up to date version of the XFF specification.
+    /// To write v1 or v2, please supply only one element.
+    ///
+    /// The path is changed to end in `.xff`, use `write_with_options_exact` to write to
+    /// exactly the given path.
     ///
     /// # Arguments
-    /// * `path` - The path to the file to remove
+    /// * `path` - The path to the file to write
+    /// * `data` - The XffValue to write
+    /// * `options` - The options to write with
     ///
     /// # Error
     /// Only errors if an IO error occurs
     ///
     /// # Example
     /// ```rust
-    /// # use nabu::serde::write;
+    /// use nabu::serde::{read, write_with_options, WriteOptions};
+    /// use nabu::XffValue;
+    ///
+    /// let data = XffValue::from(vec![0_u8; 10_000]);
+    /// let options = WriteOptions::new().checksum(true);
+    /// let tmp = write_with_options("xff-example-data/checksum.xff", data.clone(), &options);
+    /// assert!(tmp.is_ok());
+    /// assert_eq!(read("xff-example-data/checksum.xff").unwrap(), data);
+    /// # std::fs::remove_file("xff-example-data/checksum.xff").unwrap();
+    /// ```
+    pub fn write_with_options<P, D>(path: P, data: D, options: &WriteOptions) -> Result<(), NabuError>
+    where
+        P: AsRef<std::path::Path>,
+        D: Into<Vec<XffValue>>,
+    {
+        write_with_options_exact(path.as_ref().with_extension("xff"), data, options)
+    }
+
+    /// Writes XffValues to exactly the given path, as configured by the `WriteOptions`
+    ///
+    /// Works like `write_with_options`, but the path is not changed to end in `.xff`.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to write
+    /// * `data` - The XffValue to write
+    /// * `options` - The options to write with
+    ///
+    /// # Error
+    /// Errors like `write_with_options`
+    pub fn write_with_options_exact<P, D>(
+        path: P,
+        data: D,
+        options: &WriteOptions,
+    ) -> Result<(), NabuError>
+    where
+        P: AsRef<std::path::Path>,
+        D: Into<Vec<XffValue>>,
+    {
+        let byte_data = serialize_xff_with_options(data.into(), options)?;
+        write_bytes_to_file(path.as_ref(), byte_data, options.is_atomic())
+    }
+
+    /// Writes a Vec of XffValues to a XFF file with a specific XFF version
+    /// Provided for backwards compatibility and convenience
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to write
+    /// * `data` - The data to write
+    /// * `xff_version` - The XFF version to use
+    ///
+    /// # Error
+    /// Only errors if an IO error occurs
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::write_legacy;
+    /// use nabu::XffValue;
+    ///
+    /// let data = {
+    ///     vec![
+    ///         XffValue::String("hello mom".to_string()),
+    ///     ]
+    /// };
+    /// let tmp = write_legacy("xff-example-data/v0.xff", data.clone(), 0);
+    /// assert!(tmp.is_ok());
+    /// ```
+    pub fn write_legacy<P>(path: P, data: Vec<XffValue>, xff_version: u8) -> Result<(), NabuError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        write_legacy_exact(path.as_ref().with_extension("xff"), data, xff_version)
+    }
+
+    /// Writes a Vec of XffValues to a XFF file at exactly the given path with a specific XFF
+    /// version
+    ///
+    /// Works like `write_legacy`, but the path is not changed to end in `.xff`.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to write
+    /// * `data` - The data to write
+    /// * `xff_version` - The XFF version to use
+    ///
+    /// # Error
+    /// Only errors if an IO error occurs
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::{read_exact, write_legacy_exact};
+    /// use nabu::XffValue;
+    ///
+    /// let path = "xff-example-data/write_legacy_exact.v0";
+    /// write_legacy_exact(path, vec![XffValue::from("hello mom")], 0).unwrap();
+    /// assert!(read_exact(path).is_ok());
+    /// # std::fs::remove_file(path).unwrap();
+    /// ```
+    pub fn write_legacy_exact<P>(path: P, data: Vec<XffValue>, xff_version: u8) -> Result<(), NabuError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let byte_data = serialize_xff(data, xff_version)?;
+        write_bytes_to_file(path.as_ref(), byte_data, true)
+    }
+
+    /// Writes XffValues to an encrypted XFF file
+    ///
+    /// The XFF byte stream is encrypted with XChaCha20-Poly1305, using a key derived from the
+    /// passphrase with Argon2id. A header identifying the scheme precedes the encrypted bytes.
+    /// Only `read_encrypted` with the same passphrase can read the file again.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to write
+    /// * `data` - The XffValue to write
+    /// * `passphrase` - The passphrase to derive the key from
+    ///
+    /// # Error
+    /// Errors if an IO error occurs, or if the operating system cannot provide random bytes
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::{read, read_encrypted, write_encrypted};
+    /// use nabu::{NabuError, XffValue};
+    ///
+    /// let path = "xff-example-data/encrypted.xff";
+    /// let data = XffValue::from("hello mom");
+    /// write_encrypted(path, data.clone(), "correct horse battery staple").unwrap();
+    /// assert_eq!(read_encrypted(path, "correct horse battery staple").unwrap(), data);
+    /// assert!(matches!(read_encrypted(path, "hunter2"), Err(NabuError::WrongPassphrase)));
+    /// assert!(matches!(read(path), Err(NabuError::EncryptedXFF)));
+    /// # std::fs::remove_file(path).unwrap();
+    /// ```
+    #[cfg(feature = "encryption")]
+    pub fn write_encrypted<P, D, K>(path: P, data: D, passphrase: K) -> Result<(), NabuError>
+    where
+        P: AsRef<std::path::Path>,
+        D: Into<Vec<XffValue>>,
+        K: AsRef<[u8]>,
+    {
+        write_encrypted_exact(path.as_ref().with_extension("xff"), data, passphrase)
+    }
+
+    /// Writes XffValues to an encrypted XFF file at exactly the given path, whatever its
+    /// extension
+    ///
+    /// Works like `write_encrypted`, but the path is not changed to end in `.xff`.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to write
+    /// * `data` - The XffValue to write
+    /// * `passphrase` - The passphrase to derive the key from
+    ///
+    /// # Error
+    /// Errors if an IO error occurs, or if the operating system cannot provide random bytes
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::{read_encrypted_exact, write_encrypted_exact};
+    /// use nabu::XffValue;
+    ///
+    /// let path = "xff-example-data/secrets.bin";
+    /// write_encrypted_exact(path, XffValue::from(42), "passphrase").unwrap();
+    /// assert_eq!(read_encrypted_exact(path, "passphrase").unwrap(), XffValue::from(42));
+    /// # std::fs::remove_file(path).unwrap();
+    /// ```
+    #[cfg(feature = "encryption")]
+    pub fn write_encrypted_exact<P, D, K>(path: P, data: D, passphrase: K) -> Result<(), NabuError>
+    where
+        P: AsRef<std::path::Path>,
+        D: Into<Vec<XffValue>>,
+        K: AsRef<[u8]>,
+    {
+        let byte_data = serialize_xff(data.into(), XFF_VERSION)?;
+        write_bytes_to_file(
+            path.as_ref(),
+            encrypt(&byte_data, passphrase.as_ref())?,
+            true,
+        )
+    }
+
+    /// Reads the content of an encrypted XFF file written by `write_encrypted`
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to read
+    /// * `passphrase` - The passphrase the file was encrypted with
+    ///
+    /// # Error
+    /// Errors with `NabuError::WrongPassphrase` if the passphrase does not match, and with
+    /// `NabuError::CorruptedEncryptedXFF` if the file is not encrypted or was altered
+    /// Also errors if the decrypted content is not a valid XFF byte stream or if an IO error occurs
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::{read_encrypted, write_encrypted};
+    /// use nabu::XffValue;
+    ///
+    /// let path = "xff-example-data/read_encrypted.xff";
+    /// write_encrypted(path, XffValue::from(42), "passphrase").unwrap();
+    /// assert_eq!(read_encrypted(path, "passphrase").unwrap(), XffValue::from(42));
+    /// # std::fs::remove_file(path).unwrap();
+    /// ```
+    #[cfg(feature = "encryption")]
+    pub fn read_encrypted<P, K>(path: P, passphrase: K) -> Result<XffValue, NabuError>
+    where
+        P: AsRef<std::path::Path>,
+        K: AsRef<[u8]>,
+    {
+        read_encrypted_exact(path.as_ref().with_extension("xff"), passphrase)
+    }
+
+    /// Reads the content of an encrypted XFF file at exactly the given path, whatever its
+    /// extension
+    ///
+    /// Works like `read_encrypted`, but the path is not changed to end in `.xff`.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to read
+    /// * `passphrase` - The passphrase the file was encrypted with
+    ///
+    /// # Error
+    /// Errors like `read_encrypted`
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::{read_encrypted_exact, write_encrypted_exact};
+    /// use nabu::XffValue;
+    ///
+    /// let path = "xff-example-data/read_encrypted_exact";
+    /// write_encrypted_exact(path, XffValue::from("hello mom"), "passphrase").unwrap();
+    /// assert_eq!(
+    ///     read_encrypted_exact(path, "passphrase").unwrap(),
+    ///     XffValue::from("hello mom")
+    /// );
+    /// # std::fs::remove_file(path).unwrap();
+    /// ```
+    #[cfg(feature = "encryption")]
+    pub fn read_encrypted_exact<P, K>(path: P, passphrase: K) -> Result<XffValue, NabuError>
+    where
+        P: AsRef<std::path::Path>,
+        K: AsRef<[u8]>,
+    {
+        let bytes = std::fs::read(path)?;
+        deserialize_xff_bytes(&decrypt(&bytes, passphrase.as_ref())?)
+    }
+
+    /// Decodes a XFF byte stream held in memory and returns the contained XffValue
+    ///
+    /// The version byte is read and dispatched on in the same way as `read`, the only difference
+    /// being that no file is touched.
+    ///
+    /// # Arguments
+    /// * `bytes` - The complete XFF byte stream, starting with the version byte and ending with `EM`
+    ///
+    /// # Error
+    /// Errors if the bytes are not a valid XFF byte stream
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::{from_bytes, to_bytes};
+    /// use nabu::XffValue;
+    ///
+    /// let data = XffValue::from("hello mom");
+    /// let bytes: Vec<u8> = to_bytes(data.clone()).unwrap();
+    /// let read = from_bytes(&bytes);
+    /// assert!(read.is_ok());
+    /// assert_eq!(read.unwrap(), data);
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Result<XffValue, NabuError> {
+        deserialize_xff_bytes(bytes)
+    }
+
+    /// Decodes a XFF byte stream held in memory within the limits of the `DecodeOptions`
+    ///
+    /// # Arguments
+    /// * `bytes` - The complete XFF byte stream, starting with the version byte and ending with `EM`
+    /// * `options` - The limits to decode within
+    ///
+    /// # Error
+    /// Errors like `from_bytes`, and with `NabuError::DepthLimitExceeded`,
+    /// `NabuError::ValueLengthLimitExceeded`, `NabuError::KeyLimitExceeded` or
+    /// `NabuError::AllocationLimitExceeded` once a limit is hit
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::{from_bytes_with_options, to_bytes, DecodeOptions};
+    /// use nabu::{NabuError, Object, XffValue};
+    ///
+    /// let mut object = Object::new();
+    /// object.insert("one", 1);
+    /// object.insert("two", 2);
+    /// let bytes = to_bytes(XffValue::from(object)).unwrap();
+    /// let options = DecodeOptions::new().max_keys(1);
+    /// assert!(matches!(
+    ///     from_bytes_with_options(&bytes, &options),
+    ///     Err(NabuError::KeyLimitExceeded(1, _))
+    /// ));
+    /// ```
+    pub fn from_bytes_with_options(
+        bytes: &[u8],
+        options: &DecodeOptions,
+    ) -> Result<XffValue, NabuError> {
+        deserialize_xff_bytes_with_options(bytes, options)
+    }
+
+    /// Decodes a XFF byte stream held in memory within the limits of the `DecodeOptions`,
+    /// describing any error with a `Diagnostic`
+    ///
+    /// The `Diagnostic` carries the error alongside the logical path of the value it was
+    /// encountered in and a hex excerpt of the bytes around it.
+    ///
+    /// # Arguments
+    /// * `bytes` - The complete XFF byte stream, starting with the version byte and ending with `EM`
+    /// * `options` - The limits to decode within
+    ///
+    /// # Error
+    /// Errors like `from_bytes_with_options`, wrapped in a `Diagnostic`
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::{from_bytes_diagnosed, to_bytes, DecodeOptions};
+    /// use nabu::{NabuError, XffValue};
+    ///
+    /// let user = XffValue::from(vec![("name", "hello mom"), ("mood", "happy")]);
+    /// let bytes = to_bytes(XffValue::from(vec![("user", user)])).unwrap();
+    /// let options = DecodeOptions::new().max_keys(1);
+    /// let diagnostic = from_bytes_diagnosed(&bytes, &options).unwrap_err();
+    /// assert!(matches!(diagnostic.error(), NabuError::KeyLimitExceeded(1, _)));
+    /// assert_eq!(diagnostic.path(), "root.user");
+    /// ```
+    pub fn from_bytes_diagnosed(
+        bytes: &[u8],
+        options: &DecodeOptions,
+    ) -> Result<XffValue, Diagnostic> {
+        deserialize_xff_bytes_diagnosed(bytes, options)
+    }
+
+    /// Recovers every readable value of a damaged XFF byte stream held in memory
+    ///
+    /// Works like `recover`, for bytes already held in memory.
+    ///
+    /// # Arguments
+    /// * `bytes` - The complete XFF byte stream, starting with the version byte
+    ///
+    /// # Error
+    /// Errors like `from_bytes` for encrypted byte streams and unknown versions
+    pub fn recover_bytes(bytes: &[u8]) -> Result<Recovered, NabuError> {
+        recover_xff_bytes(bytes)
+    }
+
+    /// Recovers every readable value of a damaged XFF byte stream held in memory, within the
+    /// limits of the `DecodeOptions`
+    ///
+    /// Works like `recover_bytes`, values exceeding a limit are skipped like damaged ones.
+    ///
+    /// # Arguments
+    /// * `bytes` - The complete XFF byte stream, starting with the version byte
+    /// * `options` - The limits to decode within
+    ///
+    /// # Error
+    /// Errors like `from_bytes_with_options` for encrypted byte streams and unknown versions
+    pub fn recover_bytes_with_options(
+        bytes: &[u8],
+        options: &DecodeOptions,
+    ) -> Result<Recovered, NabuError> {
+        recover_xff_bytes_with_options(bytes, options)
+    }
+
+    /// Parses a v1 or v2 XFF byte stream held in memory without copying any string or data
+    ///
+    /// The returned `XffValueRef` borrows all data from the byte stream, strings are only decoded
+    /// once they are accessed.
+    /// This makes reading large files full of data, e.g. memory-mapped, a lot cheaper than
+    /// `from_bytes`.
+    /// Use `XffValueRef::to_owned` to convert it into a `XffValue`.
+    ///
+    /// # Arguments
+    /// * `bytes` - The complete XFF byte stream, starting with the version byte and ending with `EM`
+    ///
+    /// # Error
+    /// Errors if the bytes are not a valid v1 or v2 XFF byte stream, v0 is not supported
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::{from_bytes_ref, to_bytes};
+    /// use nabu::XffValue;
+    ///
+    /// let data = XffValue::from(vec![0_u8, 1, 2, 3]);
+    /// let bytes: Vec<u8> = to_bytes(data.clone()).unwrap();
+    /// let read = from_bytes_ref(&bytes).unwrap();
+    /// assert_eq!(read.as_data(), Some(&bytes[4..8]));
+    /// assert_eq!(read.to_owned().unwrap(), data);
+    /// ```
+    pub fn from_bytes_ref(bytes: &[u8]) -> Result<XffValueRef<'_>, NabuError> {
+        deserialize_xff_borrowed(bytes)
+    }
+
+    /// Parses a v1 or v2 XFF byte stream held in memory without copying any string or data,
+    /// within the limits of the `DecodeOptions`
+    ///
+    /// Works like `from_bytes_ref`, borrowed strings and data only count towards
+    /// `DecodeOptions::max_value_len`.
+    ///
+    /// # Arguments
+    /// * `bytes` - The complete XFF byte stream, starting with the version byte and ending with `EM`
+    /// * `options` - The limits to decode within
+    ///
+    /// # Error
+    /// Errors like `from_bytes_ref`, and with the error of any limit exceeded
+    pub fn from_bytes_ref_with_options<'a>(
+        bytes: &'a [u8],
+        options: &DecodeOptions,
+    ) -> Result<XffValueRef<'a>, NabuError> {
+        deserialize_xff_borrowed_with_options(bytes, options)
+    }
+
+    /// Encodes XffValues into a XFF byte stream held in memory
+    ///
+    /// Supports the most up to date version of the XFF specification, the output is identical to
+    /// the content of a file written by `write`.
+    /// To encode v1 or v2, please supply only one element.
+    ///
+    /// # Arguments
+    /// * `data` - The XffValue to encode
+    ///
+    /// # Error
+    /// Errors if the data cannot be encoded in the current XFF version
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::to_bytes;
+    /// use nabu::XffValue;
+    ///
+    /// let bytes = to_bytes(XffValue::Null);
+    /// assert!(bytes.is_ok());
+    /// // Version, NUL, EM
+    /// assert_eq!(bytes.unwrap(), vec![2, 0, 25]);
+    /// ```
+    pub fn to_bytes<D>(data: D) -> Result<Vec<u8>, NabuError>
+    where
+        D: Into<Vec<XffValue>>,
+    {
+        serialize_xff(data.into(), XFF_VERSION)
+    }
+
+    /// Encodes XffValues into a XFF byte stream held in memory, as configured by the `WriteOptions`
+    ///
+    /// The output is identical to the content of a file written by `write_with_options`.
+    ///
+    /// # Arguments
+    /// * `data` - The XffValue to encode
+    /// * `options` - The options to encode with
+    ///
+    /// # Error
+    /// Errors if the data cannot be encoded in the current XFF version
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::{from_bytes, to_bytes_with_options, WriteOptions};
+    /// use nabu::{NabuError, XffValue};
+    ///
+    /// let options = WriteOptions::new().checksum(true);
+    /// let mut bytes = to_bytes_with_options(XffValue::from("hello mom"), &options).unwrap();
+    /// // Version, ETB, TXT, length, "hello mom", EV, EM, checksum, length of the checksummed
+    /// // bytes, ETB
+    /// assert_eq!(bytes.len(), 16 + 4 + 8 + 1);
+    ///
+    /// bytes[5] = b'j';
+    /// assert!(matches!(from_bytes(&bytes), Err(NabuError::ChecksumMismatch(0))));
+    /// ```
+    pub fn to_bytes_with_options<D>(data: D, options: &WriteOptions) -> Result<Vec<u8>, NabuError>
+    where
+        D: Into<Vec<XffValue>>,
+    {
+        serialize_xff_with_options(data.into(), options)
+    }
+
+    /// Encodes a Vec of XffValues into a XFF byte stream with a specific XFF version
+    /// Provided for backwards compatibility and convenience
+    ///
+    /// # Arguments
+    /// * `data` - The data to encode
+    /// * `xff_version` - The XFF version to use
+    ///
+    /// # Error
+    /// Errors if the data cannot be encoded in the supplied XFF version
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::to_bytes_legacy;
+    /// use nabu::XffValue;
+    ///
+    /// let bytes = to_bytes_legacy(vec![XffValue::Null], 1);
+    /// assert!(bytes.is_ok());
+    /// // Version, NUL, EM
+    /// assert_eq!(bytes.unwrap(), vec![1, 0, 25]);
+    /// ```
+    pub fn to_bytes_legacy(data: Vec<XffValue>, xff_version: u8) -> Result<Vec<u8>, NabuError> {
+        serialize_xff(data, xff_version)
+    }
+
+    /// Encodes a XffValue into its canonical XFF byte stream
+    ///
+    /// Every value has exactly one canonical byte stream, and values equal by content, numbers
+    /// equal by value no matter their variant, have the same one on every machine.
+    /// It is a v2 byte stream that:
+    /// - stores every length attribute in as few bytes as hold the length, but at least one
+    /// - stores every integer as the first of `u64`, `i64`, `u128` and `i128` that holds it,
+    ///   every other number as `f64` if that holds it exactly, as decimal text otherwise
+    /// - separates elements of arrays and objects by `RS`, without a trailing `RS`
+    /// - orders object entries by their keys, compared bytewise
+    /// - compresses nothing and has no integrity trailer
+    ///
+    /// The byte stream can be decoded by `from_bytes`, decoding every number to a value equal to
+    /// the encoded one, though not necessarily of the same variant.
+    /// `XffValue::content_hash` hashes it.
+    ///
+    /// # Arguments
+    /// * `data` - The XffValue to encode
+    ///
+    /// # Error
+    /// Errors for `NaN` and infinite floats, and for the deprecated `CommandCharacter` and
+    /// `ArrayCmdChar`
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::serialize_canonical;
+    /// use nabu::{Decimal, Number, XffValue};
+    ///
+    /// let decimal: Decimal = "1,50".parse().unwrap();
+    /// let a = vec![XffValue::from(1.5), XffValue::from(2)];
+    /// let b = vec![XffValue::Number(Number::Decimal(decimal)), XffValue::from(2_u8)];
+    /// assert_eq!(
+    ///     serialize_canonical(&XffValue::from(a)).unwrap(),
+    ///     serialize_canonical(&XffValue::from(b)).unwrap()
+    /// );
+    ///
+    /// let bytes = serialize_canonical(&XffValue::from(vec![XffValue::from(2)])).unwrap();
+    /// // Version, ARY, length, NUM, length, u64, 2, EV, EV, EM
+    /// assert_eq!(bytes[..7], [2, 3, 1, 13, 2, 1, 9]);
+    /// assert_eq!(bytes[7..], [1, 2, 0, 0, 0, 0, 0, 0, 0, 24, 24, 25]);
+    /// ```
+    pub fn serialize_canonical(data: &XffValue) -> Result<Vec<u8>, NabuError> {
+        serialize_xff_canonical(data)
+    }
+
+    /// A convenience function to delete any XFF file from disk
+    /// The file will be gone, take care!
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to remove
+    ///
+    /// # Error
+    /// Only errors if an IO error occurs
+    ///
+    /// # Example
+    /// ```rust
+    /// # use nabu::serde::write;
     /// # use nabu::XffValue;
     ///
     /// # let data = {vec![XffValue::String("hello mom".to_string())]};
@@ -564,11 +1781,49 @@ pub mod serde {
     where
         P: AsRef<std::path::Path>,
     {
-        let path_with_xff_extension = path.as_ref().with_extension("xff");
-        Ok(std::fs::remove_file(path_with_xff_extension)?)
+        remove_file_exact(path.as_ref().with_extension("xff"))
+    }
+
+    /// A convenience function to delete a file at exactly the given path, whatever its extension
+    /// The file will be gone, take care!
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to remove
+    ///
+    /// # Error
+    /// Only errors if an IO error occurs
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::{remove_file_exact, write_exact};
+    /// use nabu::XffValue;
+    ///
+    /// let path = "xff-example-data/remove_exact.v2.backup";
+    /// write_exact(path, XffValue::from("hello mom")).unwrap();
+    /// assert!(remove_file_exact(path).is_ok());
+    /// assert!(!std::path::Path::new(path).exists());
+    /// ```
+    pub fn remove_file_exact<P>(path: P) -> Result<(), NabuError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        Ok(std::fs::remove_file(path)?)
     }
 }
 
+/// Structural diff and patch of `XffValue`s
+///
+/// `diff` computes the changes between two values as a `Patch`, which can be applied to the older
+/// value to reproduce the newer one, and stored as a `XffValue` itself.
+pub mod diff {
+    pub use crate::xff::diff::{diff, Operation, Patch};
+}
+
+/// Schemas describing the shape of `XffValue`s, and their validation
+pub mod schema {
+    pub use crate::xff::schema::{Pattern, Schema, ValueType, Violation};
+}
+
 // -------------------------------------------------
 //                      LEGACY 
 // -------------------------------------------------
@@ -610,7 +1865,7 @@ pub mod key_value_core {
 
     use crate::{
         error::NabuError,
-        features::key_value::core::{read_core, write_core},
+        features::key_value::core::{read_core, read_core_exact, write_core, write_core_exact},
         XffValue,
     };
 
@@ -637,8 +1892,29 @@ pub mod key_value_core {
     where
         P: AsRef<std::path::Path>,
     {
-        let path_with_xff_extension = path.as_ref().with_extension("xff");
-        read_core(&path_with_xff_extension)
+        read_core(path.as_ref())
+    }
+
+    /// LEGACY (v0) - Please consider using the inbuilt `OBJECT` type instead
+    ///
+    /// Reads the content of a XFF file at exactly the given path, whatever its extension, and
+    /// returns a BTreeMap
+    /// Works like `read`, but the path is not changed to end in `.xff`
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to read
+    ///
+    /// # Example
+    /// ```ignore
+    /// use nabu::key_value_core::read_exact;
+    ///
+    /// let data = read_exact("xff-example-data/key_value_core.v0");
+    /// ```
+    pub fn read_exact<P>(path: P) -> Result<BTreeMap<String, XffValue>, NabuError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        read_core_exact(path.as_ref())
     }
 
     /// LEGACY (v0) - Please consider using the inbuilt `OBJECT` type instead
@@ -665,8 +1941,30 @@ pub mod key_value_core {
     where
         P: AsRef<std::path::Path>,
     {
-        let path_with_xff_extension = path.as_ref().with_extension("xff");
-        write_core(&path_with_xff_extension, data)
+        write_core(path.as_ref(), data)
+    }
+
+    /// LEGACY (v0) - Please consider using the inbuilt `OBJECT` type instead
+    ///
+    /// Writes a BTreeMap to a XFF file at exactly the given path, whatever its extension
+    /// Works like `write`, but the path is not changed to end in `.xff`
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to write
+    /// * `data` - The BTreeMap to write
+    ///
+    /// # Example
+    /// ```ignore
+    /// use nabu::key_value_core::{write_exact, new_core_store};
+    ///
+    /// let tmp = write_exact("xff-example-data/key_value_core.v0", new_core_store());
+    /// assert!(tmp.is_ok());
+    /// ```
+    pub fn write_exact<P>(path: P, data: BTreeMap<String, XffValue>) -> Result<(), NabuError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        write_core_exact(path.as_ref(), data)
     }
 
     /// LEGACY (v0) - Please consider using the inbuilt `OBJECT` type instead
@@ -728,4 +2026,88 @@ pub mod key_value_store {
     {
         NabuDB::new(path.as_ref().with_extension("xff"))
     }
+
+    /// LEGACY (v0) - Please consider using the inbuilt `OBJECT` type instead
+    ///
+    /// Creates a new key-value database for in place operations, stored at exactly the given
+    /// path, whatever its extension
+    ///
+    /// Works like `new_nabudb`, but the path is not changed to end in `.xff`.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to write
+    ///
+    /// # Example
+    /// ```ignore
+    /// use nabu::key_value_store::new_nabudb_exact;
+    ///
+    /// let db = new_nabudb_exact("xff-example-data/nabuDB.db");
+    /// assert!(db.is_ok());
+    /// ```
+    pub fn new_nabudb_exact<P>(path: P) -> Result<NabuDB, NabuError>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        NabuDB::new(path.as_ref().to_path_buf())
+    }
+
+    /// LEGACY (v0) - Please consider using the inbuilt `OBJECT` type instead
+    ///
+    /// Creates a new encrypted key-value database for in place operations
+    ///
+    /// Works exactly like `new_nabudb`, but the file is decrypted with the passphrase when read
+    /// and encrypted with it on every save, see `serde::write_encrypted`.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to write
+    /// * `passphrase` - The passphrase to derive the key from
+    ///
+    /// # Example
+    /// ```ignore
+    /// use nabu::key_value_store::new_encrypted_nabudb;
+    /// use nabu::XffValue;
+    ///
+    /// let path = "xff-example-data/nabuDB_encrypted_example.xff";
+    /// let mut db = new_encrypted_nabudb(path, "passphrase").unwrap();
+    /// db.insert("password".to_string(), XffValue::from("hunter2"));
+    /// db.save().unwrap();
+    /// let read = new_encrypted_nabudb(path, "passphrase").unwrap();
+    /// assert_eq!(read.get("password").unwrap(), db.get("password").unwrap());
+    /// assert!(new_encrypted_nabudb(path, "wrong").is_err());
+    /// ```
+    #[cfg(feature = "encryption")]
+    pub fn new_encrypted_nabudb<P, K>(path: P, passphrase: K) -> Result<NabuDB, NabuError>
+    where
+        P: AsRef<std::path::Path>,
+        K: AsRef<[u8]>,
+    {
+        NabuDB::new_encrypted(path.as_ref().with_extension("xff"), passphrase)
+    }
+
+    /// LEGACY (v0) - Please consider using the inbuilt `OBJECT` type instead
+    ///
+    /// Creates a new encrypted key-value database for in place operations, stored at exactly the
+    /// given path, whatever its extension
+    ///
+    /// Works like `new_encrypted_nabudb`, but the path is not changed to end in `.xff`.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the file to write
+    /// * `passphrase` - The passphrase to derive the key from
+    ///
+    /// # Example
+    /// ```ignore
+    /// use nabu::key_value_store::new_encrypted_nabudb_exact;
+    ///
+    /// let db = new_encrypted_nabudb_exact("xff-example-data/secrets.db", "passphrase");
+    /// assert!(db.is_ok());
+    /// ```
+    #[cfg(feature = "encryption")]
+    pub fn new_encrypted_nabudb_exact<P, K>(path: P, passphrase: K) -> Result<NabuDB, NabuError>
+    where
+        P: AsRef<std::path::Path>,
+        K: AsRef<[u8]>,
+    {
+        NabuDB::new_encrypted(path.as_ref().to_path_buf(), passphrase)
+    }
 }
@@ -0,0 +1,175 @@
+use crate::error::{NabuError, Result};
+
+/// The amount of bytes covered by one checksum of the integrity trailer
+pub const BLOCK_LEN: usize = 4096;
+
+/// `ETB`, announcing an integrity trailer directly after the version byte, and the last byte of
+/// the trailer itself
+pub const ETB: u8 = 23;
+
+/// Length of the fixed part of the trailer, the length of the protected bytes and `ETB`
+const FOOTER_LEN: usize = 9;
+
+/// Lookup table of the CRC-32 (IEEE 802.3) of every byte
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Continues the CRC-32 (IEEE 802.3) over the bytes, as used by zip, gzip and png
+///
+/// The CRC-32 of some bytes is `!crc32_update(!0, bytes)`.
+fn crc32_update(crc: u32, bytes: &[u8]) -> u32 {
+    bytes.iter().fold(crc, |crc, byte| {
+        CRC_TABLE[((crc ^ u32::from(*byte)) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Computes the integrity trailer of a byte stream fed to it in parts of any length
+#[derive(Debug, Clone)]
+pub struct TrailerBuilder {
+    checksums: Vec<u8>,
+    /// The CRC-32 of the current block, not yet inverted
+    crc: u32,
+    /// Amount of bytes in the current block
+    block_len: usize,
+    protected_len: usize,
+}
+
+impl TrailerBuilder {
+    pub fn new() -> Self {
+        TrailerBuilder {
+            checksums: Vec::new(),
+            crc: !0,
+            block_len: 0,
+            protected_len: 0,
+        }
+    }
+
+    /// Feeds the next protected bytes
+    pub fn update(&mut self, mut bytes: &[u8]) {
+        self.protected_len += bytes.len();
+        while !bytes.is_empty() {
+            let (block, rest) = bytes.split_at(bytes.len().min(BLOCK_LEN - self.block_len));
+            self.crc = crc32_update(self.crc, block);
+            self.block_len += block.len();
+            if self.block_len == BLOCK_LEN {
+                self.checksums.extend((!self.crc).to_le_bytes());
+                self.crc = !0;
+                self.block_len = 0;
+            }
+            bytes = rest;
+        }
+    }
+
+    /// Returns the length of the trailer protecting all bytes fed so far
+    pub fn trailer_len(&self) -> usize {
+        self.protected_len.div_ceil(BLOCK_LEN) * 4 + FOOTER_LEN
+    }
+
+    /// Returns the trailer protecting all bytes fed
+    ///
+    /// The trailer is the CRC-32 of every block of `BLOCK_LEN` bytes, the last block may be
+    /// shorter, followed by the amount of protected bytes and `ETB`. All numbers are
+    /// Little-Endian.
+    pub fn finish(mut self) -> Vec<u8> {
+        if self.block_len > 0 {
+            self.checksums.extend((!self.crc).to_le_bytes());
+        }
+        self.checksums
+            .extend((self.protected_len as u64).to_le_bytes());
+        self.checksums.push(ETB);
+        self.checksums
+    }
+
+    /// Compares the trailer read after the protected bytes with the one expected
+    ///
+    /// # Errors
+    /// Returns `NabuError::ChecksumMismatch` with the position of the first block not matching
+    /// its checksum, or the position of the amount of protected bytes if the rest of the trailer
+    /// does not match
+    pub fn verify(self, trailer: &[u8]) -> Result<()> {
+        let protected_len = self.protected_len;
+        let expected = self.finish();
+        let checksums_len = expected.len() - FOOTER_LEN;
+        if trailer.len() != expected.len() || trailer[checksums_len..] != expected[checksums_len..]
+        {
+            return Err(NabuError::ChecksumMismatch(protected_len + checksums_len));
+        }
+        match trailer[..checksums_len]
+            .chunks_exact(4)
+            .zip(expected.chunks_exact(4))
+            .position(|(read, computed)| read != computed)
+        {
+            Some(block) => Err(NabuError::ChecksumMismatch(block * BLOCK_LEN)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for TrailerBuilder {
+    fn default() -> Self {
+        TrailerBuilder::new()
+    }
+}
+
+/// Returns `true` if the byte stream announces an integrity trailer, with `ETB` directly after
+/// its version byte
+pub fn has_trailer(bytes: &[u8]) -> bool {
+    matches!(bytes, [2, ETB, ..])
+}
+
+/// Announces and appends the integrity trailer protecting all bytes of a v2 byte stream
+///
+/// `ETB` is inserted directly after the version byte, and the trailer appended after the `EM`.
+pub fn append_trailer(bytes: &mut Vec<u8>) {
+    bytes.insert(1, ETB);
+    let mut trailer = TrailerBuilder::new();
+    trailer.update(bytes);
+    bytes.extend(trailer.finish());
+}
+
+/// Verifies the integrity trailer, if the byte stream announces one
+///
+/// Returns the protected bytes without the trailer, or all bytes if there is no trailer, and the
+/// position of the value following the version byte and `ETB`.
+///
+/// # Errors
+/// Returns `NabuError::ChecksumMismatch` with the position of the first block not matching its
+/// checksum, or the position of the trailer itself if it is malformed
+pub fn verify_trailer(bytes: &[u8]) -> Result<(&[u8], usize)> {
+    if !has_trailer(bytes) {
+        return Ok((bytes, 1));
+    }
+    let footer_pos = bytes
+        .len()
+        .checked_sub(FOOTER_LEN)
+        .filter(|_| bytes.last() == Some(&ETB))
+        .ok_or(NabuError::ChecksumMismatch(bytes.len()))?;
+    let mut len_bytes = [0u8; 8];
+    len_bytes.copy_from_slice(&bytes[footer_pos..bytes.len() - 1]);
+    let protected_len = usize::try_from(u64::from_le_bytes(len_bytes))
+        .ok()
+        .filter(|len| len.checked_add(len.div_ceil(BLOCK_LEN) * 4) == Some(footer_pos))
+        .ok_or(NabuError::ChecksumMismatch(footer_pos))?;
+    let (protected, trailer) = bytes.split_at(protected_len);
+    let mut expected = TrailerBuilder::new();
+    expected.update(protected);
+    expected.verify(trailer)?;
+    Ok((protected, 2))
+}
@@ -0,0 +1,250 @@
+use crate::error::{NabuError, Result};
+#[cfg(feature = "compression")]
+use crate::xff::serializer::v1::encode_length;
+
+/// `CMP`, the type byte of a compressed value
+pub const CMP: u8 = 6;
+
+/// The LZ77 compression method, using the block format of LZ4
+#[cfg(feature = "compression")]
+const LZ77: u8 = 1;
+
+/// The shortest match worth encoding
+#[cfg(feature = "compression")]
+const MIN_MATCH: usize = 4;
+
+/// The farthest a match can point back
+#[cfg(feature = "compression")]
+const MAX_OFFSET: usize = u16::MAX as usize;
+
+/// The amount of bytes at the end of the input that are always literals
+#[cfg(feature = "compression")]
+const LAST_LITERALS: usize = 5;
+
+/// No match starts within this amount of bytes before the end of the input
+#[cfg(feature = "compression")]
+const MF_LIMIT: usize = 12;
+
+#[cfg(feature = "compression")]
+const HASH_BITS: u32 = 16;
+
+/// What `WriteOptions::compression` compresses
+///
+/// Compressed values are stored with the method they were compressed with, reading them back
+/// requires the `compression` feature.
+#[cfg(feature = "compression")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// Nothing is compressed
+    #[default]
+    None,
+    /// Every `Data` value is compressed on its own, if that makes it smaller.
+    /// All other values stay as they are, and can still be navigated by `XffDocument`.
+    Data,
+    /// The complete value is compressed as a whole, if that makes it smaller
+    File,
+}
+
+/// Compresses the complete encoding of a single value, from its type byte up to and including
+/// its `EV`, and wraps it into a compressed value
+///
+/// Returns `None` if the compressed value would not be smaller than the encoding.
+#[cfg(feature = "compression")]
+pub fn compress_value(encoded: &[u8]) -> Option<Vec<u8>> {
+    let mut content = vec![LZ77];
+    content.extend(encode_length(encoded.len()));
+    lz77_compress(encoded, &mut content);
+    let mut out = vec![CMP];
+    out.extend(encode_length(content.len()));
+    out.extend(content);
+    // EV
+    out.push(24);
+    (out.len() < encoded.len()).then_some(out)
+}
+
+/// Decompresses the content of a compressed value, the method byte, the decompressed length and
+/// the compressed bytes
+///
+/// # Arguments
+/// * `content` - The content of the compressed value, without `CMP`, length and `EV`
+/// * `byte_pos` - The position of the content, reported in errors
+///
+/// # Errors
+/// Returns `NabuError::UnknownCompressionMethod` for methods unknown to this implementation, all
+/// methods are unknown without the `compression` feature
+/// Returns `NabuError::InvalidCompressedValue` if the compressed bytes are malformed or do not
+/// decompress to the stored length
+pub fn decompress_value(content: &[u8], byte_pos: usize) -> Result<Vec<u8>> {
+    let method = *content
+        .first()
+        .ok_or(NabuError::InvalidCompressedValue(byte_pos))?;
+    match method {
+        #[cfg(feature = "compression")]
+        LZ77 => {
+            let (len, compressed) = lz77_header(content, byte_pos)?;
+            lz77_decompress(compressed, len).ok_or(NabuError::InvalidCompressedValue(byte_pos))
+        }
+        _ => Err(NabuError::UnknownCompressionMethod(method, byte_pos)),
+    }
+}
+
+/// Returns the length the content of a compressed value decompresses to, without
+/// decompressing it
+///
+/// # Arguments
+/// * `content` - The content of the compressed value, without `CMP`, length and `EV`
+/// * `byte_pos` - The position of the content, reported in errors
+///
+/// # Errors
+/// Returns the same errors as `decompress_value` for a malformed method or length
+pub fn decompressed_len(content: &[u8], byte_pos: usize) -> Result<usize> {
+    let method = *content
+        .first()
+        .ok_or(NabuError::InvalidCompressedValue(byte_pos))?;
+    match method {
+        #[cfg(feature = "compression")]
+        LZ77 => lz77_header(content, byte_pos).map(|(len, _)| len),
+        _ => Err(NabuError::UnknownCompressionMethod(method, byte_pos)),
+    }
+}
+
+/// Splits the content of a LZ77 compressed value into its decompressed length and the
+/// compressed bytes
+#[cfg(feature = "compression")]
+fn lz77_header(content: &[u8], byte_pos: usize) -> Result<(usize, &[u8])> {
+    let invalid = NabuError::InvalidCompressedValue(byte_pos);
+    let len_of_len = usize::from(*content.get(1).ok_or(invalid)?);
+    if len_of_len > 8 {
+        return Err(NabuError::InvalidXFFValueLength(len_of_len));
+    }
+    let len_bytes = content
+        .get(2..2 + len_of_len)
+        .ok_or(NabuError::InvalidCompressedValue(byte_pos))?;
+    let mut le_bytes = [0u8; 8];
+    le_bytes[..len_of_len].copy_from_slice(len_bytes);
+    let len = usize::try_from(u64::from_le_bytes(le_bytes))
+        .map_err(|_| NabuError::InvalidXFFValueLength(usize::MAX))?;
+    Ok((len, &content[2 + len_of_len..]))
+}
+
+// -----------------------------------------------------------
+//                          LZ77
+// -----------------------------------------------------------
+
+/// Compresses the input as a sequence of literal runs, each followed by a match copying earlier
+/// output, the last one by nothing
+///
+/// Each sequence starts with a token byte: the high 4 bits hold the amount of literals, the low
+/// 4 bits the length of the match minus `MIN_MATCH`. A nibble of 15 is continued by bytes added
+/// to it, up to and including the first byte that is not 255. The literals follow, then the
+/// offset of the match as 2 bytes in Little-Endian byte-ordering, then the continued match
+/// length.
+///
+/// Like LZ4, the last `LAST_LITERALS` bytes are always literals and no match starts within the
+/// last `MF_LIMIT` bytes, so that any LZ4 decoder accepts the output.
+#[cfg(feature = "compression")]
+fn lz77_compress(input: &[u8], out: &mut Vec<u8>) {
+    let mut table = vec![usize::MAX; 1 << HASH_BITS];
+    let match_end = input.len().saturating_sub(LAST_LITERALS);
+    let mut anchor = 0;
+    let mut pos = 0;
+    while pos + MF_LIMIT <= input.len() {
+        let key = u32::from_le_bytes([input[pos], input[pos + 1], input[pos + 2], input[pos + 3]]);
+        let hash = (key.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize;
+        let candidate = std::mem::replace(&mut table[hash], pos);
+        if candidate != usize::MAX
+            && pos - candidate <= MAX_OFFSET
+            && input[candidate..candidate + MIN_MATCH] == input[pos..pos + MIN_MATCH]
+        {
+            let len = MIN_MATCH
+                + input[pos + MIN_MATCH..match_end]
+                    .iter()
+                    .zip(&input[candidate + MIN_MATCH..])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+            write_sequence(out, &input[anchor..pos], Some((pos - candidate, len)));
+            pos += len;
+            anchor = pos;
+        } else {
+            pos += 1;
+        }
+    }
+    write_sequence(out, &input[anchor..], None);
+}
+
+#[cfg(feature = "compression")]
+fn write_sequence(out: &mut Vec<u8>, literals: &[u8], found: Option<(usize, usize)>) {
+    let match_len = found.map_or(0, |(_, len)| len - MIN_MATCH);
+    out.push(((literals.len().min(15) as u8) << 4) | match_len.min(15) as u8);
+    if literals.len() >= 15 {
+        write_continuation(out, literals.len() - 15);
+    }
+    out.extend_from_slice(literals);
+    if let Some((offset, _)) = found {
+        out.extend((offset as u16).to_le_bytes());
+        if match_len >= 15 {
+            write_continuation(out, match_len - 15);
+        }
+    }
+}
+
+#[cfg(feature = "compression")]
+fn write_continuation(out: &mut Vec<u8>, mut len: usize) {
+    while len >= 255 {
+        out.push(255);
+        len -= 255;
+    }
+    out.push(len as u8);
+}
+
+/// Decompresses the input, returns `None` if it is malformed or does not decompress to exactly
+/// `len` bytes
+#[cfg(feature = "compression")]
+fn lz77_decompress(input: &[u8], len: usize) -> Option<Vec<u8>> {
+    // the length is untrusted, never reserve more than the input can expand to quickly
+    let mut out: Vec<u8> = Vec::with_capacity(len.min(input.len().saturating_mul(4)));
+    let mut pos = 0;
+    while pos < input.len() {
+        let token = input[pos];
+        pos += 1;
+        let literal_len = read_continuation(input, &mut pos, usize::from(token >> 4))?;
+        let literals = input.get(pos..pos.checked_add(literal_len)?)?;
+        pos += literal_len;
+        if out.len() + literal_len > len {
+            return None;
+        }
+        out.extend_from_slice(literals);
+        if pos == input.len() {
+            break;
+        }
+        let offset = usize::from(u16::from_le_bytes([*input.get(pos)?, *input.get(pos + 1)?]));
+        pos += 2;
+        let match_len =
+            read_continuation(input, &mut pos, usize::from(token & 15))?.checked_add(MIN_MATCH)?;
+        if offset == 0 || offset > out.len() || out.len().checked_add(match_len)? > len {
+            return None;
+        }
+        // byte by byte, the match may overlap the bytes it produces
+        let start = out.len() - offset;
+        for i in 0..match_len {
+            out.push(out[start + i]);
+        }
+    }
+    (out.len() == len).then_some(out)
+}
+
+#[cfg(feature = "compression")]
+fn read_continuation(input: &[u8], pos: &mut usize, nibble: usize) -> Option<usize> {
+    let mut len = nibble;
+    if nibble == 15 {
+        loop {
+            let b = *input.get(*pos)?;
+            *pos += 1;
+            len = len.checked_add(usize::from(b))?;
+            if b != 255 {
+                break;
+            }
+        }
+    }
+    Some(len)
+}
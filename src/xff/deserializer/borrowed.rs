@@ -0,0 +1,282 @@
+use std::mem::size_of;
+
+use crate::{
+    error::{NabuError, Result},
+    xff::{
+        checksum::verify_trailer,
+        deserializer::{
+            options::{DecodeLimits, DecodeOptions},
+            v1::decode_xff_v1_number,
+            v2::decode_xff_v2_number,
+        },
+        value::{XffStr, XffValueRef},
+    },
+};
+
+/// Parses a v1 or v2 XFF byte stream into a `XffValueRef` borrowing from it
+///
+/// No string or data is copied, strings are only checked for their length and decoded once
+/// accessed.
+///
+/// # Arguments
+/// * `bytes` - The complete XFF byte stream, starting with the version byte
+///
+/// Limits nesting to `MAX_DEPTH`, use `deserialize_xff_borrowed_with_options` for other limits.
+///
+/// # Errors
+/// Returns `NabuError::EmpthyXFF` for empty input
+/// Returns `NabuError::UnknownXFFVersion` for v0 and any unknown version, and
+/// `NabuError::UnknownExtendedXFFVersion` for any version announced by an extended version header
+/// Returns `NabuError::ChecksumMismatch` if a v2 byte stream ends with an integrity trailer not
+/// matching its content
+/// Returns `NabuError::BorrowedCompressedValue` for compressed values
+/// Also returns any error encountered while parsing the byte stream
+pub fn deserialize_xff_borrowed(bytes: &[u8]) -> Result<XffValueRef<'_>> {
+    deserialize_xff_borrowed_with_options(bytes, &DecodeOptions::default())
+}
+
+/// Parses a v1 or v2 XFF byte stream into a `XffValueRef` like `deserialize_xff_borrowed`, within
+/// the limits of the options
+///
+/// Borrowed strings and data are not allocated, they only count towards `max_value_len`.
+///
+/// # Errors
+/// Errors like `deserialize_xff_borrowed`, and with the error of any limit exceeded
+pub fn deserialize_xff_borrowed_with_options<'a>(
+    bytes: &'a [u8],
+    options: &DecodeOptions,
+) -> Result<XffValueRef<'a>> {
+    let version = match bytes.first() {
+        Some(version @ (1 | 2)) => *version,
+        // extended version header, 2 more version bytes
+        Some(255) => {
+            return match bytes.get(1..3) {
+                Some(ext) => Err(NabuError::UnknownExtendedXFFVersion(u16::from_le_bytes([
+                    ext[0], ext[1],
+                ]))),
+                None => Err(NabuError::TruncatedXFF(bytes.len())),
+            }
+        }
+        Some(version) => return Err(NabuError::UnknownXFFVersion(*version)),
+        None => return Err(NabuError::EmpthyXFF),
+    };
+    if bytes.len() == 1 {
+        return Err(NabuError::MissingEM(2));
+    }
+    let (bytes, value_pos) = if version == 2 {
+        verify_trailer(bytes)?
+    } else {
+        (bytes, 1)
+    };
+    let mut parser = BorrowedParser {
+        input: bytes,
+        byte_pos: value_pos,
+        version,
+        limits: DecodeLimits::new(options),
+    };
+    let out = parser.value()?;
+    match parser.next() {
+        Some(25) => Ok(out),
+        _ => Err(NabuError::TruncatedXFF(parser.byte_pos)),
+    }
+}
+
+struct BorrowedParser<'a> {
+    input: &'a [u8],
+    /// Amount of bytes consumed, also the position of the next byte
+    byte_pos: usize,
+    version: u8,
+    limits: DecodeLimits,
+}
+
+impl<'a> BorrowedParser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.byte_pos).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let out = self.peek();
+        if out.is_some() {
+            self.byte_pos += 1;
+        }
+        out
+    }
+
+    fn expect_byte(&mut self) -> Result<u8> {
+        self.next().ok_or(NabuError::TruncatedXFF(self.byte_pos))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .byte_pos
+            .checked_add(len)
+            .filter(|end| *end <= self.input.len())
+            .ok_or(NabuError::TruncatedXFF(self.input.len()))?;
+        let out = &self.input[self.byte_pos..end];
+        self.byte_pos = end;
+        Ok(out)
+    }
+
+    fn expect_ev(&mut self) -> Result<()> {
+        if self.peek() == Some(24) {
+            self.byte_pos += 1;
+            Ok(())
+        } else {
+            Err(NabuError::MissingEV(self.byte_pos))
+        }
+    }
+
+    /// Consumes a length attribute and checks it against the limits
+    fn length(&mut self) -> Result<usize> {
+        let value_pos = self.byte_pos;
+        let len_of_len = self.expect_byte()?;
+        if len_of_len > 8 {
+            return Err(NabuError::InvalidXFFValueLength(len_of_len.into()));
+        }
+        let len_bytes = self.take(len_of_len as usize)?;
+        let mut le_bytes = [0u8; 8];
+        le_bytes[..len_bytes.len()].copy_from_slice(len_bytes);
+        let len = usize::try_from(u64::from_le_bytes(le_bytes))
+            .map_err(|_| NabuError::InvalidXFFValueLength(usize::MAX))?;
+        self.limits.check_len(len, value_pos)?;
+        Ok(len)
+    }
+
+    /// Reads the length, content and `EV` of a string, the `TXT` byte is already consumed
+    fn string(&mut self) -> Result<XffStr<'a>> {
+        let len = self.length()?;
+        let str_pos = self.byte_pos;
+        let str_bytes = self.take(len)?;
+        self.expect_ev()?;
+        Ok(XffStr::new(str_bytes, self.version, str_pos))
+    }
+
+    /// Consumes the separator after an element of an array or a key-value pair of an object
+    fn separator(&mut self, object: bool) -> Result<()> {
+        match self.peek() {
+            // RS, may be trailing
+            Some(30) => {
+                self.byte_pos += 1;
+                Ok(())
+            }
+            Some(24) => Ok(()),
+            Some(b) if object => Err(NabuError::InvalidObject(self.byte_pos, b)),
+            Some(b) => Err(NabuError::InvalidArray(self.byte_pos, b)),
+            None => Err(NabuError::TruncatedXFF(self.byte_pos)),
+        }
+    }
+
+    /// Checks the length of an array or object against its content and consumes its `EV`
+    fn end_container(&mut self, start_pos: usize, len: usize) -> Result<()> {
+        if self.peek() != Some(24) || self.byte_pos - start_pos != len {
+            return Err(NabuError::MissingEV(self.byte_pos));
+        }
+        self.byte_pos += 1;
+        Ok(())
+    }
+
+    fn expect_structure_byte(&mut self, expected: u8) -> Result<()> {
+        let b = self.expect_byte()?;
+        if b != expected {
+            return Err(NabuError::InvalidObject(self.byte_pos, b));
+        }
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<XffValueRef<'a>> {
+        let type_byte = self.expect_byte()?;
+        match type_byte {
+            0 => Ok(XffValueRef::Null),
+            //TXT
+            1 => Ok(XffValueRef::String(self.string()?)),
+            2 => {
+                //NUM
+                let len = self.length()?;
+                let num_pos = self.byte_pos;
+                let num_bytes = self.take(len)?;
+                self.expect_ev()?;
+                let num = if self.version == 1 {
+                    decode_xff_v1_number(num_bytes.to_vec(), num_pos)?
+                } else {
+                    decode_xff_v2_number(num_bytes, num_pos)?
+                };
+                Ok(XffValueRef::Number(num))
+            }
+            3 => {
+                //ARY
+                self.limits.enter(self.byte_pos - 1)?;
+                let len = self.length()?;
+                let start_pos = self.byte_pos;
+                let mut ary_bind: Vec<XffValueRef<'a>> = Vec::new();
+                loop {
+                    match self.peek() {
+                        Some(24) => break,
+                        Some(_) => {
+                            self.limits
+                                .allocate(size_of::<XffValueRef>(), self.byte_pos)?;
+                            ary_bind.push(self.value()?);
+                            self.separator(false)?;
+                        }
+                        None => return Err(NabuError::TruncatedXFF(self.byte_pos)),
+                    }
+                }
+                self.end_container(start_pos, len)?;
+                self.limits.leave();
+                Ok(XffValueRef::Array(ary_bind))
+            }
+            4 => {
+                //OBJ
+                self.limits.enter(self.byte_pos - 1)?;
+                let len = self.length()?;
+                let start_pos = self.byte_pos;
+                let mut obj_bind: Vec<(XffStr<'a>, XffValueRef<'a>)> = Vec::new();
+                loop {
+                    match self.peek() {
+                        Some(24) => break,
+                        Some(_) => {
+                            self.limits.check_keys(obj_bind.len() + 1, self.byte_pos)?;
+                            self.limits
+                                .allocate(size_of::<(XffStr, XffValueRef)>(), self.byte_pos)?;
+                            // GS
+                            self.expect_structure_byte(29)?;
+                            let key_type = self.expect_byte()?;
+                            if key_type != 1 {
+                                return Err(NabuError::InvalidObject(self.byte_pos, key_type));
+                            }
+                            let key = self.string()?;
+                            // US
+                            self.expect_structure_byte(31)?;
+                            let value = self.value()?;
+                            // Trailing GS
+                            self.expect_structure_byte(29)?;
+                            obj_bind.push((key, value));
+                            self.separator(true)?;
+                        }
+                        None => return Err(NabuError::TruncatedXFF(self.byte_pos)),
+                    }
+                }
+                self.end_container(start_pos, len)?;
+                self.limits.leave();
+                Ok(XffValueRef::Object(obj_bind))
+            }
+            5 => {
+                //DAT
+                let len = self.length()?;
+                let data = self.take(len)?;
+                self.expect_ev()?;
+                Ok(XffValueRef::Data(data))
+            }
+            // CMP, decompressed bytes can not be borrowed from the input
+            6 if self.version == 2 => Err(NabuError::BorrowedCompressedValue(self.byte_pos - 1)),
+            //TRU
+            16 => Ok(XffValueRef::Boolean(true)),
+            //FAL
+            17 => Ok(XffValueRef::Boolean(false)),
+            _ => Err(NabuError::InvalidXFFByte(
+                type_byte,
+                self.byte_pos,
+                self.version,
+            )),
+        }
+    }
+}
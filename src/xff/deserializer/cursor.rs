@@ -0,0 +1,343 @@
+use std::{collections::BTreeMap, mem::size_of};
+
+use crate::{
+    error::{NabuError, PathSegment, Result, Trace},
+    xff::{
+        compression::{decompress_value, decompressed_len, CMP},
+        deserializer::{
+            options::{DecodeLimits, DecodeOptions},
+            v1::{decode_xff_v1_number, decode_xff_v1_string},
+            v2::decode_xff_v2_number,
+        },
+        value::XffValue,
+    },
+};
+
+/// Reads the bytes of a byte stream held in memory, keeping track of the position
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Cursor<'a> {
+    pub(crate) contents: &'a [u8],
+    /// Amount of bytes consumed, also the position of the next byte
+    pub(crate) byte_pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub(crate) fn new(contents: &'a [u8], byte_pos: usize) -> Self {
+        Cursor { contents, byte_pos }
+    }
+
+    pub(crate) fn peek(&self) -> Option<u8> {
+        self.contents.get(self.byte_pos).copied()
+    }
+
+    pub(crate) fn next(&mut self) -> Option<u8> {
+        let out = self.peek();
+        if out.is_some() {
+            self.byte_pos += 1;
+        }
+        out
+    }
+
+    /// Consumes the next byte, erroring if the bytes ended
+    pub(crate) fn expect_byte(&mut self) -> Result<u8> {
+        self.next().ok_or(NabuError::TruncatedXFF(self.byte_pos))
+    }
+
+    /// Consumes the next `len` bytes, erroring if fewer are left
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .byte_pos
+            .checked_add(len)
+            .filter(|end| *end <= self.contents.len())
+            .ok_or(NabuError::TruncatedXFF(self.contents.len()))?;
+        let out = &self.contents[self.byte_pos..end];
+        self.byte_pos = end;
+        Ok(out)
+    }
+
+    pub(crate) fn expect_ev(&mut self) -> Result<()> {
+        if self.peek() == Some(24) {
+            self.byte_pos += 1;
+            Ok(())
+        } else {
+            Err(NabuError::MissingEV(self.byte_pos))
+        }
+    }
+
+    /// Consumes a v1 or v2 length attribute, the byte holding the length of the length followed
+    /// by up to 8 bytes of Little-Endian length
+    pub(crate) fn length(&mut self) -> Result<usize> {
+        let len_of_len = self.expect_byte()?;
+        if len_of_len > 8 {
+            return Err(NabuError::InvalidXFFValueLength(len_of_len.into()));
+        }
+        let len_bytes = self.take(len_of_len as usize)?;
+        let mut le_bytes = [0u8; 8];
+        le_bytes[..len_bytes.len()].copy_from_slice(len_bytes);
+        usize::try_from(u64::from_le_bytes(le_bytes))
+            .map_err(|_| NabuError::InvalidXFFValueLength(usize::MAX))
+    }
+}
+
+/// Deserializes a complete v1 or v2 byte stream, starting with the version byte, within the
+/// limits of the options
+///
+/// The value starts at `byte_pos`, after the version byte and anything announced after it.
+/// Any error is returned alongside the path to the value it was encountered in.
+pub(crate) fn deserialize_stream(
+    contents: &[u8],
+    byte_pos: usize,
+    version: u8,
+    options: &DecodeOptions,
+) -> std::result::Result<XffValue, (NabuError, Trace)> {
+    // version is byte 0; already matched against
+    let mut cursor = ValueCursor::new(contents, byte_pos, version, DecodeLimits::new(options));
+    if cursor.cursor.peek().is_none() {
+        return Err((NabuError::MissingEM(2), cursor.trace));
+    }
+    let out = match cursor.value() {
+        Ok(out) => out,
+        Err(err) => return Err((err, cursor.trace)),
+    };
+    match cursor.cursor.next() {
+        Some(25) => Ok(out),
+        _ => Err((
+            NabuError::TruncatedXFF(cursor.cursor.byte_pos),
+            cursor.trace,
+        )),
+    }
+}
+
+/// Decodes the values of v1 and v2 byte streams held in memory
+///
+/// Both versions share their structure, they only differ in how strings and numbers are
+/// encoded, and v2 adds compressed values.
+pub(crate) struct ValueCursor<'a> {
+    pub(crate) cursor: Cursor<'a>,
+    version: u8,
+    /// `true` inside of the bytes of a decompressed value, which may not be compressed again
+    decompressed: bool,
+    pub(crate) limits: DecodeLimits,
+    /// The path to the value the last error was encountered in
+    pub(crate) trace: Trace,
+}
+
+impl<'a> ValueCursor<'a> {
+    pub(crate) fn new(
+        contents: &'a [u8],
+        byte_pos: usize,
+        version: u8,
+        limits: DecodeLimits,
+    ) -> Self {
+        ValueCursor {
+            cursor: Cursor::new(contents, byte_pos),
+            version,
+            decompressed: false,
+            limits,
+            trace: Trace::default(),
+        }
+    }
+
+    /// Records that the error was encountered in the value at `segment`
+    fn trace(&mut self, segment: PathSegment, err: NabuError) -> NabuError {
+        self.trace.push(segment);
+        err
+    }
+
+    /// Consumes a length attribute and checks it against the limits
+    pub(crate) fn length(&mut self) -> Result<usize> {
+        let value_pos = self.cursor.byte_pos;
+        let len = self.cursor.length()?;
+        self.limits.check_len(len, value_pos)?;
+        Ok(len)
+    }
+
+    /// Reads the length, content and `EV` of a string, the `TXT` byte is already consumed
+    fn string(&mut self) -> Result<String> {
+        let len = self.length()?;
+        let str_pos = self.cursor.byte_pos;
+        self.limits.allocate(len, str_pos)?;
+        let str_bytes = self.cursor.take(len)?;
+        if self.version == 1 {
+            self.cursor.expect_ev()?;
+            return decode_xff_v1_string(str_bytes.to_vec(), str_pos);
+        }
+        let out = std::str::from_utf8(str_bytes)
+            .map_err(|e| NabuError::InvalidUTF8String(str_pos + e.valid_up_to()))?
+            .to_string();
+        self.cursor.expect_ev()?;
+        Ok(out)
+    }
+
+    /// Reads the length, content and `EV` of a number, the `NUM` byte is already consumed
+    fn number(&mut self) -> Result<XffValue> {
+        let len = self.length()?;
+        let num_pos = self.cursor.byte_pos;
+        self.limits.allocate(len, num_pos)?;
+        let num_bytes = self.cursor.take(len)?;
+        self.cursor.expect_ev()?;
+        let num = if self.version == 1 {
+            decode_xff_v1_number(num_bytes.to_vec(), num_pos)?
+        } else {
+            decode_xff_v2_number(num_bytes, num_pos)?
+        };
+        Ok(XffValue::Number(num))
+    }
+
+    pub(crate) fn value(&mut self) -> Result<XffValue> {
+        let type_byte = self.cursor.expect_byte()?;
+        match type_byte {
+            0 => Ok(XffValue::Null),
+            //TXT
+            1 => Ok(XffValue::String(self.string()?)),
+            //NUM
+            2 => self.number(),
+            3 => {
+                //ARY
+                self.limits.enter(self.cursor.byte_pos - 1)?;
+                let len = self.length()?;
+                let start_pos = self.cursor.byte_pos;
+                let mut ary_bind: Vec<XffValue> = Default::default();
+                loop {
+                    match self.cursor.peek() {
+                        Some(24) => break,
+                        Some(_) => {
+                            self.limits
+                                .allocate(size_of::<XffValue>(), self.cursor.byte_pos)?;
+                            let index = ary_bind.len();
+                            let value = self
+                                .value()
+                                .map_err(|err| self.trace(PathSegment::Index(index), err))?;
+                            ary_bind.push(value);
+                        }
+                        None => return Err(NabuError::TruncatedXFF(self.cursor.byte_pos)),
+                    }
+                    match self.cursor.peek() {
+                        // RS, may be trailing
+                        Some(30) => self.cursor.byte_pos += 1,
+                        Some(24) => break,
+                        Some(b) => return Err(NabuError::InvalidArray(self.cursor.byte_pos, b)),
+                        None => return Err(NabuError::TruncatedXFF(self.cursor.byte_pos)),
+                    }
+                }
+                if self.cursor.byte_pos - start_pos != len {
+                    return Err(NabuError::MissingEV(self.cursor.byte_pos));
+                }
+                self.cursor.expect_ev()?;
+                self.limits.leave();
+                Ok(XffValue::from(ary_bind))
+            }
+            4 => {
+                //OBJ
+                self.limits.enter(self.cursor.byte_pos - 1)?;
+                let len = self.length()?;
+                let start_pos = self.cursor.byte_pos;
+                let mut obj_bind: BTreeMap<String, XffValue> = Default::default();
+                loop {
+                    match self.cursor.peek() {
+                        Some(24) => break,
+                        Some(_) => {
+                            self.limits
+                                .check_keys(obj_bind.len() + 1, self.cursor.byte_pos)?;
+                            self.limits
+                                .allocate(size_of::<(String, XffValue)>(), self.cursor.byte_pos)?;
+                            let (key, value) = self.key_value()?;
+                            obj_bind.insert(key, value);
+                        }
+                        None => return Err(NabuError::TruncatedXFF(self.cursor.byte_pos)),
+                    }
+                    match self.cursor.peek() {
+                        // RS, may be trailing
+                        Some(30) => self.cursor.byte_pos += 1,
+                        Some(24) => break,
+                        Some(b) => return Err(NabuError::InvalidObject(self.cursor.byte_pos, b)),
+                        None => return Err(NabuError::TruncatedXFF(self.cursor.byte_pos)),
+                    }
+                }
+                if self.cursor.byte_pos - start_pos != len {
+                    return Err(NabuError::MissingEV(self.cursor.byte_pos));
+                }
+                self.cursor.expect_ev()?;
+                self.limits.leave();
+                Ok(XffValue::from(obj_bind))
+            }
+            5 => {
+                //DAT
+                let len = self.length()?;
+                self.limits.allocate(len, self.cursor.byte_pos)?;
+                let data = self.cursor.take(len)?.to_vec();
+                self.cursor.expect_ev()?;
+                Ok(XffValue::from(data))
+            }
+            // only in v2, and never inside of a decompressed value
+            CMP if self.version == 2 && !self.decompressed => {
+                let len = self.length()?;
+                let content_pos = self.cursor.byte_pos;
+                let content = self.cursor.take(len)?;
+                self.cursor.expect_ev()?;
+                self.compressed(content, content_pos)
+            }
+            //TRU
+            16 => Ok(XffValue::Boolean(true)),
+            //FAL
+            17 => Ok(XffValue::Boolean(false)),
+            _ => Err(NabuError::InvalidXFFByte(
+                type_byte,
+                self.cursor.byte_pos,
+                self.version,
+            )),
+        }
+    }
+
+    fn key_value(&mut self) -> Result<(String, XffValue)> {
+        // GS
+        let gs = self.cursor.expect_byte()?;
+        if gs != 29 {
+            return Err(NabuError::InvalidObject(self.cursor.byte_pos, gs));
+        }
+        // keys are always plain strings, never compressed
+        let key_type = self.cursor.expect_byte()?;
+        if key_type != 1 {
+            return Err(NabuError::InvalidObject(self.cursor.byte_pos, key_type));
+        }
+        let key = self.string()?;
+        // US
+        let us = self.cursor.expect_byte()?;
+        if us != 31 {
+            return Err(NabuError::InvalidObject(self.cursor.byte_pos, us));
+        }
+        let value = self
+            .value()
+            .map_err(|err| self.trace(PathSegment::Key(key.clone()), err))?;
+        // Trailing GS
+        let gs = self.cursor.expect_byte()?;
+        if gs != 29 {
+            return Err(NabuError::InvalidObject(self.cursor.byte_pos, gs));
+        }
+        Ok((key, value))
+    }
+
+    /// Decompresses the content of a compressed value and decodes the value it contains, the
+    /// decompressed bytes count towards the limits
+    pub(crate) fn compressed(&mut self, content: &[u8], byte_pos: usize) -> Result<XffValue> {
+        let len = decompressed_len(content, byte_pos)?;
+        self.limits.check_len(len, byte_pos)?;
+        self.limits.allocate(len, byte_pos)?;
+        let decompressed = decompress_value(content, byte_pos)?;
+        let mut cursor = ValueCursor::new(&decompressed, 0, 2, self.limits);
+        cursor.decompressed = true;
+        let out = match cursor.value() {
+            Ok(out) => out,
+            Err(err) => {
+                self.trace.extend(cursor.trace);
+                self.trace.decompressed = true;
+                return Err(err);
+            }
+        };
+        if cursor.cursor.byte_pos != decompressed.len() {
+            return Err(NabuError::InvalidCompressedValue(byte_pos));
+        }
+        self.limits = cursor.limits;
+        Ok(out)
+    }
+}
@@ -0,0 +1,432 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::{
+    error::{NabuError, Result},
+    xff::{
+        checksum::has_trailer,
+        deserializer::{options::DecodeOptions, reader::XffReader},
+        serializer::v1::encode_xff_v1_string,
+        value::XffValue,
+    },
+};
+
+/// Amount of bytes read at once while navigating
+const CHUNK_LEN: u64 = 8 * 1024;
+
+/// Random-access XFF reader over any `std::io::Read` and `std::io::Seek`
+///
+/// Only the top-level value is parsed when the document is opened. Navigating into arrays and
+/// objects with `get` and `index` jumps over all preceding siblings using their length prefixes,
+/// and only the values passed to `read` are decoded. Reading a single field of a large file
+/// therefore only touches the bytes of the field and the headers of its preceding siblings.
+///
+/// Supports version 1 and 2 byte streams, version 0 has no length prefixes to jump with.
+/// An integrity trailer is not verified, as that requires reading the whole byte stream.
+///
+/// Values are read within the limits of the `DecodeOptions`, set with `with_options`. The depth
+/// of a value read is counted from the node it is read at.
+///
+/// # Example
+/// ```rust
+/// use std::io::Cursor;
+/// use nabu::serde::{XffDocument, to_bytes};
+/// use nabu::{Object, XffValue};
+///
+/// let mut object = Object::new();
+/// object.insert("name", "album");
+/// object.insert("images", vec![XffValue::from("a.png"), XffValue::from("b.png")]);
+/// let bytes = to_bytes(XffValue::from(object)).unwrap();
+///
+/// let mut document = XffDocument::new(Cursor::new(bytes)).unwrap();
+/// let root = document.root();
+/// let images = document.get(&root, "images").unwrap().unwrap();
+/// let image = document.index(&images, 1).unwrap().unwrap();
+/// assert_eq!(document.read(&image).unwrap(), XffValue::from("b.png"));
+/// assert!(document.index(&images, 2).unwrap().is_none());
+/// ```
+#[derive(Debug)]
+pub struct XffDocument<R: Read + Seek> {
+    reader: R,
+    version: u8,
+    root: XffNode,
+    /// Position of the version byte in the reader, all positions are relative to it
+    start: u64,
+    /// Bytes last read from the reader, starting at `buf_pos`
+    buf: Vec<u8>,
+    buf_pos: u64,
+    options: DecodeOptions,
+}
+
+/// The position and type of a single value inside of a `XffDocument`
+///
+/// Nodes are cheap to copy and only valid for the document that returned them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XffNode {
+    type_byte: u8,
+    /// Position of the type byte
+    position: u64,
+    /// Position of the first content byte
+    content_pos: u64,
+    /// Length of the content, 0 for values without content
+    content_len: u64,
+}
+
+impl XffNode {
+    /// Returns the position of the value in the byte stream
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Returns the length of the content of the value in bytes, as stored in its length prefix
+    ///
+    /// Returns 0 for booleans and null values, they have no content.
+    pub fn content_len(&self) -> u64 {
+        self.content_len
+    }
+
+    /// Returns `true` if the value is a `String`
+    pub fn is_string(&self) -> bool {
+        self.type_byte == 1
+    }
+
+    /// Returns `true` if the value is a `Number`
+    pub fn is_number(&self) -> bool {
+        self.type_byte == 2
+    }
+
+    /// Returns `true` if the value is an `Array`
+    pub fn is_array(&self) -> bool {
+        self.type_byte == 3
+    }
+
+    /// Returns `true` if the value is an `Object`
+    pub fn is_object(&self) -> bool {
+        self.type_byte == 4
+    }
+
+    /// Returns `true` if the value is `Data`
+    pub fn is_data(&self) -> bool {
+        self.type_byte == 5
+    }
+
+    /// Returns `true` if the value is a `Boolean`
+    pub fn is_boolean(&self) -> bool {
+        self.type_byte == 16 || self.type_byte == 17
+    }
+
+    /// Returns `true` if the value is `Null`
+    pub fn is_null(&self) -> bool {
+        self.type_byte == 0
+    }
+
+    /// Returns `true` if the value is compressed
+    ///
+    /// Compressed values can not be navigated into, `read` decompresses and decodes them.
+    pub fn is_compressed(&self) -> bool {
+        self.type_byte == 6
+    }
+
+    fn has_content(&self) -> bool {
+        (1..=6).contains(&self.type_byte)
+    }
+
+    fn content_end(&self) -> u64 {
+        self.content_pos + self.content_len
+    }
+
+    /// Returns the position of the first byte after the value
+    fn end(&self) -> u64 {
+        if self.has_content() {
+            // EV
+            self.content_end() + 1
+        } else {
+            self.position + 1
+        }
+    }
+}
+
+impl<R: Read + Seek> XffDocument<R> {
+    /// Opens a XFF byte stream for random access
+    ///
+    /// Reads the version and the header of the top-level value, and checks that the byte stream
+    /// ends with an `EM` after it. The byte stream starts at the current position of the reader.
+    ///
+    /// # Arguments
+    /// * `reader` - The reader containing the XFF byte stream
+    ///
+    /// # Errors
+    /// Returns IO errors of the wrapped reader
+    /// Returns `NabuError::EmpthyXFF` if the reader is empty, `NabuError::UnknownXFFVersion` for
+    /// v0 and any unknown version, and `NabuError::UnknownExtendedXFFVersion` for any version
+    /// announced by an extended version header
+    /// Also returns any error encountered while reading the header of the top-level value
+    pub fn new(reader: R) -> Result<Self> {
+        XffDocument::with_options(reader, &DecodeOptions::default())
+    }
+
+    /// Opens a XFF byte stream for random access like `new`, reading values within the limits of
+    /// the options
+    ///
+    /// # Arguments
+    /// * `reader` - The reader containing the XFF byte stream
+    /// * `options` - The limits to read values within
+    ///
+    /// # Errors
+    /// Errors like `new`
+    pub fn with_options(mut reader: R, options: &DecodeOptions) -> Result<Self> {
+        let start = reader.stream_position()?;
+        let mut document = XffDocument {
+            reader,
+            version: 0,
+            root: XffNode {
+                type_byte: 0,
+                position: 1,
+                content_pos: 1,
+                content_len: 0,
+            },
+            start,
+            buf: Vec::new(),
+            buf_pos: 0,
+            options: *options,
+        };
+        document.fill(0)?;
+        document.version = match document.buf.first() {
+            Some(version @ (1 | 2)) => *version,
+            // extended version header, 2 more version bytes
+            Some(255) => {
+                return match document.buf.get(1..3) {
+                    Some(ext) => Err(NabuError::UnknownExtendedXFFVersion(u16::from_le_bytes([
+                        ext[0], ext[1],
+                    ]))),
+                    None => Err(NabuError::TruncatedXFF(document.buf.len())),
+                }
+            }
+            Some(version) => return Err(NabuError::UnknownXFFVersion(*version)),
+            None => return Err(NabuError::EmpthyXFF),
+        };
+        if document.buf.len() == 1 {
+            return Err(NabuError::MissingEM(2));
+        }
+        // an integrity trailer is announced by `ETB` after the version byte
+        let root = document.node(if has_trailer(&document.buf) { 2 } else { 1 })?;
+        if document.byte_at(root.end())? != 25 {
+            return Err(NabuError::TruncatedXFF(to_usize(root.end())));
+        }
+        document.root = root;
+        Ok(document)
+    }
+
+    /// Returns the version of the byte stream
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns the top-level value of the byte stream
+    pub fn root(&self) -> XffNode {
+        self.root
+    }
+
+    /// Returns the value of the key if the node is an `Object` containing the key
+    /// Returns `None` if the node is not an `Object` or does not contain the key
+    ///
+    /// Only the keys of the object are read, all other values are jumped over.
+    ///
+    /// # Errors
+    /// Returns IO errors of the wrapped reader
+    /// Also returns any error encountered while reading the headers of the key-value pairs
+    pub fn get(&mut self, node: &XffNode, key: &str) -> Result<Option<XffNode>> {
+        if !node.is_object() {
+            return Ok(None);
+        }
+        let key_bytes = if self.version == 1 {
+            match encode_xff_v1_string(key) {
+                Ok(bytes) => bytes,
+                // a key that cannot be encoded cannot be contained either
+                Err(_) => return Ok(None),
+            }
+        } else {
+            key.as_bytes().to_vec()
+        };
+        let mut pos = node.content_pos;
+        loop {
+            if self.byte_at(pos)? == 24 {
+                break;
+            }
+            // GS
+            self.expect_structure_byte(pos, 29)?;
+            let key_node = self.node(pos + 1)?;
+            if !key_node.is_string() {
+                return Err(NabuError::InvalidObject(
+                    to_usize(pos + 2),
+                    key_node.type_byte,
+                ));
+            }
+            // US
+            self.expect_structure_byte(key_node.end(), 31)?;
+            let value = self.node(key_node.end() + 1)?;
+            // Trailing GS
+            self.expect_structure_byte(value.end(), 29)?;
+            if key_node.content_len == key_bytes.len() as u64
+                && self.bytes_at(key_node.content_pos, key_bytes.len())? == key_bytes
+            {
+                return Ok(Some(value));
+            }
+            pos = self.separator(value.end() + 1, true)?;
+        }
+        if pos != node.content_end() {
+            return Err(NabuError::MissingEV(to_usize(pos)));
+        }
+        Ok(None)
+    }
+
+    /// Returns the element at the index if the node is an `Array` of sufficient length
+    /// Returns `None` if the node is not an `Array` or the index is out of bounds
+    ///
+    /// All preceding elements are jumped over without being read.
+    ///
+    /// # Errors
+    /// Returns IO errors of the wrapped reader
+    /// Also returns any error encountered while reading the headers of the preceding elements
+    pub fn index(&mut self, node: &XffNode, index: usize) -> Result<Option<XffNode>> {
+        if !node.is_array() {
+            return Ok(None);
+        }
+        let mut pos = node.content_pos;
+        let mut current: usize = 0;
+        loop {
+            if self.byte_at(pos)? == 24 {
+                break;
+            }
+            let element = self.node(pos)?;
+            if current == index {
+                return Ok(Some(element));
+            }
+            current += 1;
+            pos = self.separator(element.end(), false)?;
+        }
+        if pos != node.content_end() {
+            return Err(NabuError::MissingEV(to_usize(pos)));
+        }
+        Ok(None)
+    }
+
+    /// Decodes the value of the node, including everything it contains
+    ///
+    /// # Errors
+    /// Returns IO errors of the wrapped reader
+    /// Also returns any error encountered while decoding the value
+    pub fn read(&mut self, node: &XffNode) -> Result<XffValue> {
+        self.reader
+            .seek(SeekFrom::Start(self.start + node.position))?;
+        XffReader::at_position(&mut self.reader, to_usize(node.position), &self.options)
+            .read_value(self.version)
+    }
+
+    /// Consumes the `XffDocument` and returns the wrapped reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads the header of the value starting at `pos`, and checks for the `EV` after its content
+    fn node(&mut self, pos: u64) -> Result<XffNode> {
+        let type_byte = self.byte_at(pos)?;
+        match type_byte {
+            0 | 16 | 17 => Ok(XffNode {
+                type_byte,
+                position: pos,
+                content_pos: pos + 1,
+                content_len: 0,
+            }),
+            // CMP only exists in v2
+            1..=6 if type_byte != 6 || self.version == 2 => {
+                let len_of_len = self.byte_at(pos + 1)?;
+                if len_of_len > 8 {
+                    return Err(NabuError::InvalidXFFValueLength(len_of_len.into()));
+                }
+                let len_bytes = self.bytes_at(pos + 2, len_of_len as usize)?;
+                let mut le_bytes = [0u8; 8];
+                le_bytes[..len_bytes.len()].copy_from_slice(&len_bytes);
+                let content_pos = pos + 2 + u64::from(len_of_len);
+                let content_len = u64::from_le_bytes(le_bytes);
+                let node = XffNode {
+                    type_byte,
+                    position: pos,
+                    content_pos,
+                    content_len,
+                };
+                let content_end = content_pos
+                    .checked_add(content_len)
+                    .ok_or(NabuError::InvalidXFFValueLength(usize::MAX))?;
+                if self.byte_at(content_end)? != 24 {
+                    return Err(NabuError::MissingEV(to_usize(content_end)));
+                }
+                Ok(node)
+            }
+            _ => Err(NabuError::InvalidXFFByte(
+                type_byte,
+                to_usize(pos + 1),
+                self.version,
+            )),
+        }
+    }
+
+    /// Checks the separator after an element of an array or a key-value pair of an object at
+    /// `pos` and returns the position of the next element, or of the `EV` of the container
+    fn separator(&mut self, pos: u64, object: bool) -> Result<u64> {
+        match self.byte_at(pos)? {
+            // RS, may be trailing
+            30 => Ok(pos + 1),
+            24 => Ok(pos),
+            b if object => Err(NabuError::InvalidObject(to_usize(pos), b)),
+            b => Err(NabuError::InvalidArray(to_usize(pos), b)),
+        }
+    }
+
+    fn expect_structure_byte(&mut self, pos: u64, expected: u8) -> Result<()> {
+        let b = self.byte_at(pos)?;
+        if b != expected {
+            return Err(NabuError::InvalidObject(to_usize(pos + 1), b));
+        }
+        Ok(())
+    }
+
+    fn byte_at(&mut self, pos: u64) -> Result<u8> {
+        if pos < self.buf_pos || pos >= self.buf_pos + self.buf.len() as u64 {
+            self.fill(pos)?;
+        }
+        self.buf
+            .get((pos - self.buf_pos) as usize)
+            .copied()
+            .ok_or(NabuError::TruncatedXFF(to_usize(pos)))
+    }
+
+    fn bytes_at(&mut self, pos: u64, len: usize) -> Result<Vec<u8>> {
+        let end = pos + len as u64;
+        if pos >= self.buf_pos && end <= self.buf_pos + self.buf.len() as u64 {
+            let start = (pos - self.buf_pos) as usize;
+            return Ok(self.buf[start..start + len].to_vec());
+        }
+        self.reader.seek(SeekFrom::Start(self.start + pos))?;
+        let mut out: Vec<u8> = Vec::new();
+        (&mut self.reader).take(len as u64).read_to_end(&mut out)?;
+        if out.len() != len {
+            return Err(NabuError::TruncatedXFF(to_usize(pos) + out.len()));
+        }
+        Ok(out)
+    }
+
+    /// Replaces the buffer with the bytes starting at `pos`
+    fn fill(&mut self, pos: u64) -> Result<()> {
+        self.reader.seek(SeekFrom::Start(self.start + pos))?;
+        self.buf.clear();
+        self.buf_pos = pos;
+        (&mut self.reader)
+            .take(CHUNK_LEN)
+            .read_to_end(&mut self.buf)?;
+        Ok(())
+    }
+}
+
+fn to_usize(pos: u64) -> usize {
+    usize::try_from(pos).unwrap_or(usize::MAX)
+}
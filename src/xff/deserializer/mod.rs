@@ -1,12 +1,30 @@
-use std::collections::VecDeque;
 use std::path::Path;
 
-use crate::{error::NabuError, xff::value::XffValue};
+use crate::{
+    error::{Diagnostic, NabuError, Trace},
+    xff::{encryption::is_encrypted, value::XffValue},
+};
 
+pub(crate) mod cursor;
 pub mod v0;
-use crate::xff::deserializer::v0::deserialize_xff_v0;
+use crate::xff::deserializer::v0::deserialize_xff_v0_traced;
 pub mod v1;
 use crate::xff::deserializer::v1::deserialize_xff_v1;
+pub mod v2;
+use crate::xff::deserializer::v2::deserialize_xff_v2;
+pub mod reader;
+pub use crate::xff::deserializer::reader::XffReader;
+pub mod borrowed;
+pub use crate::xff::deserializer::borrowed::{
+    deserialize_xff_borrowed, deserialize_xff_borrowed_with_options,
+};
+pub mod document;
+pub use crate::xff::deserializer::document::{XffDocument, XffNode};
+pub mod options;
+pub use crate::xff::deserializer::options::{DecodeOptions, MAX_DEPTH};
+pub mod recover;
+pub use crate::xff::deserializer::recover::{Recovered, SkippedBytes};
+use crate::xff::deserializer::recover::{recover_xff_v0, recover_xff_v1, recover_xff_v2};
 
 /// Reads the content of a XFF file and returns a Vec
 ///
@@ -23,16 +41,127 @@ use crate::xff::deserializer::v1::deserialize_xff_v1;
 /// Also returns `NabuError::UnknownXFFVersion` when the version is higher than the current highest version of the XFF format
 pub fn deserialize_xff(path: &Path) -> Result<XffValue, NabuError> {
     //takes about 200ms for 300mb
-    let mut content: VecDeque<u8> = std::fs::read(path)?.into();
+    deserialize_xff_bytes(&std::fs::read(path)?)
+}
+
+/// Deserializes a XFF byte stream already held in memory
+///
+/// Reads the first byte to determine the version and then calls the appropriate deserializer for the version
+///
+/// # Arguments
+/// * `bytes` - The complete XFF byte stream, starting with the version byte
+///
+/// # Errors
+/// Returns `NabuError::UnknownXFFVersion` when the version is higher than the current highest version of the XFF format
+/// Returns `NabuError::UnknownExtendedXFFVersion` for any version announced by an extended version header
+/// Returns `NabuError::ChecksumMismatch` if a v2 byte stream ends with an integrity trailer not
+/// matching its content
+/// Returns `NabuError::EncryptedXFF` for encrypted byte streams
+/// Also returns any error encountered while decoding the byte stream
+pub fn deserialize_xff_bytes(bytes: &[u8]) -> Result<XffValue, NabuError> {
+    deserialize_xff_bytes_with_options(bytes, &DecodeOptions::default())
+}
+
+/// Deserializes a XFF byte stream already held in memory, within the limits of the options
+///
+/// # Arguments
+/// * `bytes` - The complete XFF byte stream, starting with the version byte
+/// * `options` - The limits to decode within
+///
+/// # Errors
+/// Returns any error of `deserialize_xff_bytes`
+/// Also returns `NabuError::DepthLimitExceeded`, `NabuError::ValueLengthLimitExceeded`,
+/// `NabuError::KeyLimitExceeded` or `NabuError::AllocationLimitExceeded` once a limit is hit
+pub fn deserialize_xff_bytes_with_options(
+    bytes: &[u8],
+    options: &DecodeOptions,
+) -> Result<XffValue, NabuError> {
+    deserialize_xff_traced(bytes, options).map_err(|(err, _)| err)
+}
+
+/// Deserializes a XFF byte stream already held in memory, within the limits of the options,
+/// describing any error with a `Diagnostic`
+///
+/// # Arguments
+/// * `bytes` - The complete XFF byte stream, starting with the version byte
+/// * `options` - The limits to decode within
+///
+/// # Errors
+/// Returns a `Diagnostic` wrapping any error of `deserialize_xff_bytes_with_options`
+pub fn deserialize_xff_bytes_diagnosed(
+    bytes: &[u8],
+    options: &DecodeOptions,
+) -> Result<XffValue, Diagnostic> {
+    deserialize_xff_traced(bytes, options)
+        .map_err(|(err, trace)| Diagnostic::new(err, trace, bytes))
+}
+
+/// Recovers every readable value of a damaged XFF byte stream already held in memory
+///
+/// v0, v1 and v2 byte streams are recovered, encrypted and unknown versions are deserialized like
+/// `deserialize_xff_bytes`.
+///
+/// # Arguments
+/// * `bytes` - The complete XFF byte stream, starting with the version byte
+///
+/// # Errors
+/// Returns any error of `deserialize_xff_bytes` for encrypted and unknown versions, and for byte
+/// streams too short to hold any value
+pub fn recover_xff_bytes(bytes: &[u8]) -> Result<Recovered, NabuError> {
+    recover_xff_bytes_with_options(bytes, &DecodeOptions::default())
+}
+
+/// Recovers every readable value of a damaged XFF byte stream already held in memory, within the
+/// limits of the options
+///
+/// A value exceeding a limit is skipped like a damaged one.
+///
+/// # Arguments
+/// * `bytes` - The complete XFF byte stream, starting with the version byte
+/// * `options` - The limits to decode within
+///
+/// # Errors
+/// Returns any error of `deserialize_xff_bytes_with_options` for encrypted and unknown versions,
+/// and for byte streams too short to hold any value
+pub fn recover_xff_bytes_with_options(
+    bytes: &[u8],
+    options: &DecodeOptions,
+) -> Result<Recovered, NabuError> {
+    match bytes {
+        [0, _, ..] => Ok(recover_xff_v0(bytes, options)),
+        [1, _, ..] => Ok(recover_xff_v1(bytes, options)),
+        [2, _, ..] => Ok(recover_xff_v2(bytes, options)),
+        _ => deserialize_xff_bytes_with_options(bytes, options).map(Recovered::complete),
+    }
+}
+
+/// Reads the version byte and calls the appropriate deserializer for the version, returning the
+/// path to the value any error was encountered in alongside it
+fn deserialize_xff_traced(
+    content: &[u8],
+    options: &DecodeOptions,
+) -> Result<XffValue, (NabuError, Trace)> {
+    let untraced = |err| (err, Trace::default());
     if content.len() == 1 {
-        return Err(NabuError::MissingEM(2));
-    } else if content.len() == 0 {
-        return Err(NabuError::EmpthyXFF);
+        return Err(untraced(NabuError::MissingEM(2)));
+    } else if content.is_empty() {
+        return Err(untraced(NabuError::EmpthyXFF));
+    }
+    if is_encrypted(content) {
+        return Err(untraced(NabuError::EncryptedXFF));
     }
     // check for 2 bytes is done
     match content[0] {
-        0 => deserialize_xff_v0(&mut content),
-        1 => Ok(deserialize_xff_v1(&mut content)?),
-        _ => Err(NabuError::UnknownXFFVersion(content[0])),
+        0 => deserialize_xff_v0_traced(content, options),
+        1 => deserialize_xff_v1(content, options),
+        2 => deserialize_xff_v2(content, options),
+        // extended version header, 2 more version bytes
+        255 => match (content.get(1), content.get(2)) {
+            (Some(low), Some(high)) => Err(untraced(NabuError::UnknownExtendedXFFVersion(
+                u16::from_le_bytes([*low, *high]),
+            ))),
+            _ => Err(untraced(NabuError::TruncatedXFF(content.len()))),
+        },
+        _ => Err(untraced(NabuError::UnknownXFFVersion(content[0]))),
     }
 }
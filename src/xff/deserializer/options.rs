@@ -0,0 +1,181 @@
+use crate::error::{NabuError, Result};
+
+/// The deepest nesting of arrays and objects the default `DecodeOptions` accept, and the writers
+/// write
+///
+/// The decoders recurse once for every array or object, anything nested deeper is rejected
+/// with `NabuError::DepthLimitExceeded` long before the stack could run out. Writing a value
+/// nested deeper fails with `NabuError::NestingTooDeep`, so everything written can be read back.
+pub const MAX_DEPTH: usize = 128;
+
+/// Limits for reading a XFF byte stream, taken by `read_with_options`, `from_bytes_with_options`
+/// and the `with_options` variants of every other decoder
+///
+/// A byte stream can declare lengths far beyond its actual size, or nest values deep enough to
+/// exhaust the stack. Limiting what is decoded makes it safe to read byte streams from untrusted
+/// sources, anything beyond a limit is rejected with an error before it is allocated.
+///
+/// `DecodeOptions::default()` limits nesting to `MAX_DEPTH`, all other limits are off; it is
+/// what `read`, `from_bytes` and every other decoder without options use.
+///
+/// # Example
+/// ```rust
+/// use nabu::serde::{from_bytes_with_options, to_bytes, DecodeOptions};
+/// use nabu::{NabuError, XffValue};
+///
+/// let options = DecodeOptions::new().max_depth(2);
+/// let flat = to_bytes(XffValue::from(vec![XffValue::from(1)])).unwrap();
+/// assert!(from_bytes_with_options(&flat, &options).is_ok());
+///
+/// let deep = XffValue::from(vec![XffValue::from(vec![XffValue::from(vec![XffValue::Null])])]);
+/// let deep = to_bytes(deep).unwrap();
+/// assert!(matches!(
+///     from_bytes_with_options(&deep, &options),
+///     Err(NabuError::DepthLimitExceeded(2, _))
+/// ));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeOptions {
+    max_depth: Option<usize>,
+    max_value_len: Option<usize>,
+    max_keys: Option<usize>,
+    max_total_bytes: Option<usize>,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        DecodeOptions {
+            max_depth: Some(MAX_DEPTH),
+            max_value_len: None,
+            max_keys: None,
+            max_total_bytes: None,
+        }
+    }
+}
+
+impl DecodeOptions {
+    /// Creates the default options, limiting nesting to `MAX_DEPTH` and nothing else
+    pub fn new() -> Self {
+        DecodeOptions::default()
+    }
+
+    /// Limits how deep arrays and objects may be nested
+    ///
+    /// A value that is not an array or object has a depth of 0, an array containing it a depth
+    /// of 1, and so on. Exceeding it is reported as `NabuError::DepthLimitExceeded`.
+    ///
+    /// The decoders recurse once for every level, so a limit above `MAX_DEPTH` needs a
+    /// correspondingly larger stack: about 20 KiB per level in unoptimized builds, and less than
+    /// 1 KiB in optimized ones.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Returns the deepest nesting allowed, if limited
+    pub fn get_max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// Limits the length in bytes of any single value, as stored in its length attribute or
+    /// once decompressed
+    ///
+    /// Exceeding it is reported as `NabuError::ValueLengthLimitExceeded`.
+    pub fn max_value_len(mut self, max_value_len: usize) -> Self {
+        self.max_value_len = Some(max_value_len);
+        self
+    }
+
+    /// Returns the longest value allowed, if limited
+    pub fn get_max_value_len(&self) -> Option<usize> {
+        self.max_value_len
+    }
+
+    /// Limits the amount of keys of any single object
+    ///
+    /// Exceeding it is reported as `NabuError::KeyLimitExceeded`.
+    pub fn max_keys(mut self, max_keys: usize) -> Self {
+        self.max_keys = Some(max_keys);
+        self
+    }
+
+    /// Returns the most keys allowed in an object, if limited
+    pub fn get_max_keys(&self) -> Option<usize> {
+        self.max_keys
+    }
+
+    /// Limits the total amount of bytes allocated while decoding
+    ///
+    /// This includes the file read by `read_with_options`, the content of all strings, numbers,
+    /// keys and data, decompressed bytes, and every element of an array or object.
+    /// Exceeding it is reported as `NabuError::AllocationLimitExceeded`.
+    pub fn max_total_bytes(mut self, max_total_bytes: usize) -> Self {
+        self.max_total_bytes = Some(max_total_bytes);
+        self
+    }
+
+    /// Returns the most bytes allowed to be allocated, if limited
+    pub fn get_max_total_bytes(&self) -> Option<usize> {
+        self.max_total_bytes
+    }
+}
+
+/// Tracks the depth and allocations of a decoder against its `DecodeOptions`
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DecodeLimits {
+    options: DecodeOptions,
+    depth: usize,
+    allocated: usize,
+}
+
+impl DecodeLimits {
+    pub(crate) fn new(options: &DecodeOptions) -> Self {
+        DecodeLimits {
+            options: *options,
+            depth: 0,
+            allocated: 0,
+        }
+    }
+
+    /// Checks the length of a value starting at `byte_pos`
+    pub(crate) fn check_len(&self, len: usize, byte_pos: usize) -> Result<()> {
+        match self.options.max_value_len {
+            Some(max) if len > max => Err(NabuError::ValueLengthLimitExceeded(len, byte_pos)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the amount of keys of an object, after one more key at `byte_pos` was added
+    pub(crate) fn check_keys(&self, keys: usize, byte_pos: usize) -> Result<()> {
+        match self.options.max_keys {
+            Some(max) if keys > max => Err(NabuError::KeyLimitExceeded(max, byte_pos)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Accounts for `bytes` more bytes allocated for the value at `byte_pos`
+    pub(crate) fn allocate(&mut self, bytes: usize, byte_pos: usize) -> Result<()> {
+        self.allocated = self.allocated.saturating_add(bytes);
+        match self.options.max_total_bytes {
+            Some(max) if self.allocated > max => {
+                Err(NabuError::AllocationLimitExceeded(max, byte_pos))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Enters an array or object at `byte_pos`
+    pub(crate) fn enter(&mut self, byte_pos: usize) -> Result<()> {
+        self.depth += 1;
+        let max = self.options.max_depth.unwrap_or(MAX_DEPTH);
+        if self.depth > max {
+            return Err(NabuError::DepthLimitExceeded(max, byte_pos));
+        }
+        Ok(())
+    }
+
+    /// Leaves an array or object
+    pub(crate) fn leave(&mut self) {
+        self.depth -= 1;
+    }
+}
@@ -0,0 +1,427 @@
+use std::{collections::BTreeMap, io::Read, mem::size_of};
+
+use crate::{
+    error::{NabuError, Result},
+    xff::{
+        checksum::{TrailerBuilder, ETB},
+        deserializer::{
+            options::{DecodeLimits, DecodeOptions},
+            v0::deserialize_xff_v0,
+            v1::{decode_xff_v1_number, decode_xff_v1_string},
+            v2::{decode_xff_v2_compressed, decode_xff_v2_number},
+        },
+        value::XffValue,
+    },
+};
+
+/// Incremental XFF reader over any `std::io::Read`
+///
+/// Version 1 and 2 byte streams are decoded while they are read, only the bytes of the value
+/// currently being decoded are held in memory. Version 0 byte streams are read completely before
+/// decoding.
+///
+/// Every call to `read` consumes exactly one complete XFF byte stream, up to and including its
+/// `EM`, so several v1 or v2 streams can be read from the same reader one after the other.
+/// Structural bytes are read one at a time, so wrapping unbuffered readers like a `File` in a
+/// `BufReader` is recommended.
+///
+/// Every byte stream is decoded within the limits of the `DecodeOptions`, set with
+/// `with_options`.
+///
+/// An integrity trailer announced by a v2 byte stream is read and verified after its `EM`, the
+/// checksums are computed while the bytes are read. A corrupted byte stream is therefore
+/// reported as `NabuError::ChecksumMismatch`, unless decoding it already failed before.
+///
+/// # Example
+/// ```rust
+/// use nabu::serde::{XffReader, to_bytes};
+/// use nabu::XffValue;
+///
+/// let data = XffValue::from(vec![XffValue::from("hello mom"), XffValue::from(true)]);
+/// let bytes = to_bytes(data.clone()).unwrap();
+///
+/// let mut reader = XffReader::new(bytes.as_slice());
+/// let read = reader.read();
+/// assert!(read.is_ok());
+/// assert_eq!(read.unwrap(), data);
+/// ```
+#[derive(Debug)]
+pub struct XffReader<R: Read> {
+    reader: R,
+    /// One byte of look-ahead, already taken from the reader
+    peeked: Option<u8>,
+    /// Amount of bytes consumed from the current XFF byte stream
+    byte_pos: usize,
+    options: DecodeOptions,
+    limits: DecodeLimits,
+    /// Computes the integrity trailer of the current byte stream, if it announced one
+    trailer: Option<TrailerBuilder>,
+}
+
+impl<R: Read> XffReader<R> {
+    /// Creates a new `XffReader` reading from the supplied reader
+    ///
+    /// # Arguments
+    /// * `reader` - The reader containing the XFF byte stream
+    pub fn new(reader: R) -> Self {
+        XffReader::with_options(reader, &DecodeOptions::default())
+    }
+
+    /// Creates a new `XffReader` reading from the supplied reader, decoding every byte stream
+    /// within the limits of the options
+    ///
+    /// # Arguments
+    /// * `reader` - The reader containing the XFF byte stream
+    /// * `options` - The limits to decode within
+    pub fn with_options(reader: R, options: &DecodeOptions) -> Self {
+        XffReader {
+            reader,
+            peeked: None,
+            byte_pos: 0,
+            options: *options,
+            limits: DecodeLimits::new(options),
+            trailer: None,
+        }
+    }
+
+    /// Reads one complete XFF byte stream and returns the contained XffValue
+    ///
+    /// # Errors
+    /// Returns IO errors of the wrapped reader
+    /// Returns `NabuError::EmpthyXFF` if the reader is empty and `NabuError::UnknownXFFVersion`
+    /// for unsupported versions
+    /// Returns `NabuError::ChecksumMismatch` if a v2 byte stream does not match its integrity
+    /// trailer
+    /// Also returns any error encountered while decoding the byte stream
+    pub fn read(&mut self) -> Result<XffValue> {
+        self.byte_pos = 0;
+        self.limits = DecodeLimits::new(&self.options);
+        self.trailer = None;
+        let version = match self.next_byte()? {
+            Some(b) => b,
+            None => return Err(NabuError::EmpthyXFF),
+        };
+        match version {
+            0 => {
+                let mut content: Vec<u8> = vec![0];
+                if let Some(b) = self.peeked.take() {
+                    content.push(b);
+                }
+                self.reader.read_to_end(&mut content)?;
+                if content.len() == 1 {
+                    return Err(NabuError::MissingEM(2));
+                }
+                deserialize_xff_v0(&content, &self.options)
+            }
+            1 | 2 => {
+                match self.peek_byte()? {
+                    None => return Err(NabuError::MissingEM(2)),
+                    Some(ETB) if version == 2 => {
+                        // integrity trailer announced, protecting the version byte and `ETB`
+                        self.next_byte()?;
+                        let mut trailer = TrailerBuilder::new();
+                        trailer.update(&[version, ETB]);
+                        self.trailer = Some(trailer);
+                    }
+                    Some(_) => {}
+                }
+                let out = self.read_value(version)?;
+                if self.next_byte()? != Some(25) {
+                    return Err(NabuError::TruncatedXFF(self.byte_pos));
+                }
+                if let Some(trailer) = self.trailer.take() {
+                    let read = self.take_bytes(trailer.trailer_len())?;
+                    trailer.verify(&read)?;
+                }
+                Ok(out)
+            }
+            255 => {
+                // extended version header
+                let ext_version = self.take_bytes(2)?;
+                Err(NabuError::UnknownExtendedXFFVersion(u16::from_le_bytes([
+                    ext_version[0],
+                    ext_version[1],
+                ])))
+            }
+            _ => Err(NabuError::UnknownXFFVersion(version)),
+        }
+    }
+
+    /// Creates a new `XffReader` positioned inside of a byte stream, used to decode single values
+    /// with `read_value`
+    ///
+    /// # Arguments
+    /// * `reader` - The reader, positioned at the type byte of a value
+    /// * `byte_pos` - The position of the type byte in the byte stream, used for errors
+    /// * `options` - The limits to decode within
+    pub(crate) fn at_position(reader: R, byte_pos: usize, options: &DecodeOptions) -> Self {
+        XffReader {
+            reader,
+            peeked: None,
+            byte_pos,
+            options: *options,
+            limits: DecodeLimits::new(options),
+            trailer: None,
+        }
+    }
+
+    /// Returns a reference to the wrapped reader
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Consumes the `XffReader` and returns the wrapped reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn next_byte(&mut self) -> Result<Option<u8>> {
+        let out = match self.peeked.take() {
+            Some(b) => Some(b),
+            None => {
+                let mut buf = [0u8; 1];
+                loop {
+                    match self.reader.read(&mut buf) {
+                        Ok(0) => break None,
+                        Ok(_) => {
+                            if let Some(trailer) = &mut self.trailer {
+                                trailer.update(&buf);
+                            }
+                            break Some(buf[0]);
+                        }
+                        Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+            }
+        };
+        if out.is_some() {
+            self.byte_pos += 1;
+        }
+        Ok(out)
+    }
+
+    fn peek_byte(&mut self) -> Result<Option<u8>> {
+        if self.peeked.is_none() {
+            let next = self.next_byte()?;
+            if next.is_some() {
+                // not consumed yet
+                self.byte_pos -= 1;
+            }
+            self.peeked = next;
+        }
+        Ok(self.peeked)
+    }
+
+    /// Consumes the next byte, erroring if the stream ended
+    fn expect_byte(&mut self) -> Result<u8> {
+        self.next_byte()?
+            .ok_or(NabuError::TruncatedXFF(self.byte_pos))
+    }
+
+    /// Consumes `len` bytes without allocating more than the reader actually delivers
+    fn take_bytes(&mut self, len: usize) -> Result<Vec<u8>> {
+        let mut out: Vec<u8> = Vec::new();
+        let mut remaining = len;
+        if remaining > 0 {
+            if let Some(b) = self.peeked.take() {
+                out.push(b);
+                remaining -= 1;
+            }
+        }
+        let read_from = out.len();
+        (&mut self.reader)
+            .take(remaining as u64)
+            .read_to_end(&mut out)?;
+        if let Some(trailer) = &mut self.trailer {
+            trailer.update(&out[read_from..]);
+        }
+        self.byte_pos += out.len();
+        if out.len() != len {
+            return Err(NabuError::TruncatedXFF(self.byte_pos));
+        }
+        Ok(out)
+    }
+
+    fn expect_ev(&mut self) -> Result<()> {
+        match self.peek_byte()? {
+            Some(24) => {
+                self.peeked = None;
+                self.byte_pos += 1;
+                Ok(())
+            }
+            _ => Err(NabuError::MissingEV(self.byte_pos)),
+        }
+    }
+
+    /// Consumes a length attribute and checks it against the limits
+    fn read_length(&mut self) -> Result<usize> {
+        let value_pos = self.byte_pos;
+        let len_of_len = self.expect_byte()?;
+        if len_of_len > 8 {
+            return Err(NabuError::InvalidXFFValueLength(len_of_len.into()));
+        }
+        let len_bytes = self.take_bytes(len_of_len as usize)?;
+        let mut le_bytes = [0u8; 8];
+        le_bytes[..len_bytes.len()].copy_from_slice(&len_bytes);
+        let len = usize::try_from(u64::from_le_bytes(le_bytes))
+            .map_err(|_| NabuError::InvalidXFFValueLength(usize::MAX))?;
+        self.limits.check_len(len, value_pos)?;
+        Ok(len)
+    }
+
+    /// Reads the length, content and `EV` of a string, the `TXT` byte is already consumed
+    fn read_string(&mut self, version: u8) -> Result<String> {
+        let len = self.read_length()?;
+        let str_pos = self.byte_pos;
+        self.limits.allocate(len, str_pos)?;
+        let str_bytes = self.take_bytes(len)?;
+        let string = if version == 1 {
+            decode_xff_v1_string(str_bytes, str_pos)?
+        } else {
+            String::from_utf8(str_bytes)
+                .map_err(|e| NabuError::InvalidUTF8String(str_pos + e.utf8_error().valid_up_to()))?
+        };
+        self.expect_ev()?;
+        Ok(string)
+    }
+
+    /// Reads one value of a v1 or v2 byte stream, they only differ in how strings and numbers
+    /// are encoded
+    pub(crate) fn read_value(&mut self, version: u8) -> Result<XffValue> {
+        let type_byte = self.expect_byte()?;
+        match type_byte {
+            0 => Ok(XffValue::Null),
+            //TXT
+            1 => Ok(XffValue::from(self.read_string(version)?)),
+            2 => {
+                //NUM
+                let len = self.read_length()?;
+                let num_pos = self.byte_pos;
+                self.limits.allocate(len, num_pos)?;
+                let num_bytes = self.take_bytes(len)?;
+                self.expect_ev()?;
+                let num = if version == 1 {
+                    decode_xff_v1_number(num_bytes, num_pos)?
+                } else {
+                    decode_xff_v2_number(&num_bytes, num_pos)?
+                };
+                Ok(XffValue::Number(num))
+            }
+            3 => {
+                //ARY
+                self.limits.enter(self.byte_pos - 1)?;
+                let len = self.read_length()?;
+                let start_pos = self.byte_pos;
+                let mut ary_bind: Vec<XffValue> = Default::default();
+                loop {
+                    match self.peek_byte()? {
+                        Some(24) => break,
+                        Some(_) => {
+                            self.limits.allocate(size_of::<XffValue>(), self.byte_pos)?;
+                            ary_bind.push(self.read_value(version)?);
+                        }
+                        None => return Err(NabuError::TruncatedXFF(self.byte_pos)),
+                    }
+                    match self.peek_byte()? {
+                        // RS, may be trailing
+                        Some(30) => {
+                            let _ = self.next_byte()?;
+                        }
+                        Some(24) => break,
+                        Some(b) => return Err(NabuError::InvalidArray(self.byte_pos, b)),
+                        None => return Err(NabuError::TruncatedXFF(self.byte_pos)),
+                    }
+                }
+                if self.byte_pos - start_pos != len {
+                    return Err(NabuError::MissingEV(self.byte_pos));
+                }
+                self.expect_ev()?;
+                self.limits.leave();
+                Ok(XffValue::from(ary_bind))
+            }
+            4 => {
+                //OBJ
+                self.limits.enter(self.byte_pos - 1)?;
+                let len = self.read_length()?;
+                let start_pos = self.byte_pos;
+                let mut obj_bind: BTreeMap<String, XffValue> = Default::default();
+                loop {
+                    match self.peek_byte()? {
+                        Some(24) => break,
+                        Some(_) => {
+                            self.limits.check_keys(obj_bind.len() + 1, self.byte_pos)?;
+                            self.limits
+                                .allocate(size_of::<(String, XffValue)>(), self.byte_pos)?;
+                            let (key, value) = self.read_key_value(version)?;
+                            obj_bind.insert(key, value);
+                        }
+                        None => return Err(NabuError::TruncatedXFF(self.byte_pos)),
+                    }
+                    match self.peek_byte()? {
+                        // RS, may be trailing
+                        Some(30) => {
+                            let _ = self.next_byte()?;
+                        }
+                        Some(24) => break,
+                        Some(b) => return Err(NabuError::InvalidObject(self.byte_pos, b)),
+                        None => return Err(NabuError::TruncatedXFF(self.byte_pos)),
+                    }
+                }
+                if self.byte_pos - start_pos != len {
+                    return Err(NabuError::MissingEV(self.byte_pos));
+                }
+                self.expect_ev()?;
+                self.limits.leave();
+                Ok(XffValue::from(obj_bind))
+            }
+            5 => {
+                //DAT
+                let len = self.read_length()?;
+                self.limits.allocate(len, self.byte_pos)?;
+                let data = self.take_bytes(len)?;
+                self.expect_ev()?;
+                Ok(XffValue::from(data))
+            }
+            6 if version == 2 => {
+                //CMP
+                let len = self.read_length()?;
+                let content_pos = self.byte_pos;
+                let content = self.take_bytes(len)?;
+                self.expect_ev()?;
+                decode_xff_v2_compressed(&content, content_pos, &mut self.limits)
+            }
+            //TRU
+            16 => Ok(XffValue::Boolean(true)),
+            //FAL
+            17 => Ok(XffValue::Boolean(false)),
+            _ => Err(NabuError::InvalidXFFByte(type_byte, self.byte_pos, version)),
+        }
+    }
+
+    fn read_key_value(&mut self, version: u8) -> Result<(String, XffValue)> {
+        // GS
+        let gs = self.expect_byte()?;
+        if gs != 29 {
+            return Err(NabuError::InvalidObject(self.byte_pos, gs));
+        }
+        // keys are always plain strings, never compressed
+        let key_type = self.expect_byte()?;
+        if key_type != 1 {
+            return Err(NabuError::InvalidObject(self.byte_pos, key_type));
+        }
+        let key = self.read_string(version)?;
+        // US
+        let us = self.expect_byte()?;
+        if us != 31 {
+            return Err(NabuError::InvalidObject(self.byte_pos, us));
+        }
+        let value = self.read_value(version)?;
+        // Trailing GS
+        let gs = self.expect_byte()?;
+        if gs != 29 {
+            return Err(NabuError::InvalidObject(self.byte_pos, gs));
+        }
+        Ok((key, value))
+    }
+}
@@ -0,0 +1,511 @@
+use std::{collections::BTreeMap, mem::size_of, ops::Range};
+
+use crate::{
+    error::{path_string, NabuError, PathSegment, Result},
+    xff::{
+        checksum::{has_trailer, verify_trailer},
+        compression::CMP,
+        deserializer::{
+            cursor::Cursor,
+            options::{DecodeLimits, DecodeOptions},
+            v0::decode_xff_v0_value,
+            v1::{decode_xff_v1_length, decode_xff_v1_value},
+            v2::decode_xff_v2_value,
+        },
+        value::XffValue,
+    },
+};
+
+/// Decodes the single value starting at a position, returning it alongside the position of the
+/// first byte after it
+type DecodeValue = fn(&[u8], usize, &mut DecodeLimits) -> Result<(XffValue, usize)>;
+
+/// Everything that could be decoded from a damaged XFF byte stream, returned by `recover`
+///
+/// # Example
+/// ```rust
+/// use nabu::serde::{recover_bytes, to_bytes_legacy};
+/// use nabu::XffValue;
+///
+/// let value = XffValue::from(vec![
+///     XffValue::from("hello mom"),
+///     XffValue::from("hello dad"),
+///     XffValue::from("hello world"),
+/// ]);
+/// let mut bytes = to_bytes_legacy(vec![value], 1).unwrap();
+/// // damage the second string
+/// let pos = bytes.windows(3).position(|w| w == b"dad").unwrap();
+/// bytes[pos] = 0;
+///
+/// let recovered = recover_bytes(&bytes).unwrap();
+/// assert!(!recovered.is_complete());
+/// assert_eq!(
+///     recovered.value(),
+///     &XffValue::from(vec![XffValue::from("hello mom"), XffValue::from("hello world")])
+/// );
+/// assert_eq!(recovered.skipped()[0].path(), "root[1]");
+/// ```
+#[derive(Debug)]
+pub struct Recovered {
+    value: XffValue,
+    skipped: Vec<SkippedBytes>,
+}
+
+impl Recovered {
+    /// The value of a byte stream decoded without skipping any bytes
+    pub(crate) fn complete(value: XffValue) -> Self {
+        Recovered {
+            value,
+            skipped: Vec::new(),
+        }
+    }
+
+    /// Returns the recovered value
+    ///
+    /// Arrays and objects contain every element that could be decoded, a root value that could
+    /// not be decoded at all is recovered as `XffValue::Null`.
+    pub fn value(&self) -> &XffValue {
+        &self.value
+    }
+
+    /// Returns the recovered value, discarding the skipped bytes
+    pub fn into_value(self) -> XffValue {
+        self.value
+    }
+
+    /// Returns every range of bytes that had to be skipped, in order
+    pub fn skipped(&self) -> &[SkippedBytes] {
+        &self.skipped
+    }
+
+    /// Returns `true` if no bytes had to be skipped, and the value is exactly the one stored
+    pub fn is_complete(&self) -> bool {
+        self.skipped.is_empty()
+    }
+}
+
+/// A range of bytes skipped while recovering a damaged XFF byte stream
+#[derive(Debug)]
+pub struct SkippedBytes {
+    range: Range<usize>,
+    path: String,
+    error: NabuError,
+}
+
+impl SkippedBytes {
+    /// Returns the skipped bytes, counted in bytes from the start of the byte stream
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// Returns the logical path of the skipped value, like `root.users[3]`
+    ///
+    /// Array indices count the elements as they were encountered in the byte stream, including
+    /// skipped ones. If bytes between values were skipped, it is the path of their array or
+    /// object.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the error that made the bytes unreadable
+    pub fn error(&self) -> &NabuError {
+        &self.error
+    }
+}
+
+/// Recovers a v0 byte stream, starting with the version byte, of at least 2 bytes
+///
+/// A damaged value is skipped up to the next `ETX` followed by a readable value or the `EM`, and
+/// recovered as `XffValue::Null`, so that the values following it keep their index. The keys and
+/// values of a `NabuDB` file stay paired as long as no skipped range spans several values.
+///
+/// Values exceeding a limit of the options are skipped like damaged ones.
+pub(crate) fn recover_xff_v0(contents: &[u8], options: &DecodeOptions) -> Recovered {
+    let mut cursor = Cursor::new(contents, 1);
+    let mut limits = DecodeLimits::new(options);
+    let mut values: Vec<XffValue> = Vec::new();
+    let mut skipped: Vec<SkippedBytes> = Vec::new();
+    loop {
+        let pos = cursor.byte_pos;
+        let error = match decode_xff_v0_value(&mut cursor, &mut limits, &mut values) {
+            Ok(true) => break,
+            Ok(false) => continue,
+            Err(error) => error,
+        };
+        // ETX, followed by a readable value or EM
+        let resume = (pos + 1..contents.len()).find(|next| {
+            contents[next - 1] == 3 && {
+                // looking ahead allocates nothing that is kept
+                let mut limits = limits;
+                decode_xff_v0_value(
+                    &mut Cursor::new(contents, *next),
+                    &mut limits,
+                    &mut Vec::new(),
+                )
+                .is_ok()
+            }
+        });
+        skipped.push(SkippedBytes {
+            range: pos..resume.unwrap_or(contents.len()),
+            path: path_string([PathSegment::Index(values.len())].iter()),
+            error,
+        });
+        match resume {
+            Some(next) => {
+                values.push(XffValue::Null);
+                cursor.byte_pos = next;
+            }
+            None => break,
+        }
+    }
+    Recovered {
+        value: XffValue::from(values),
+        skipped,
+    }
+}
+
+/// Recovers a v1 byte stream, starting with the version byte, of at least 2 bytes
+///
+/// Damaged values inside of arrays and objects are skipped up to the next `RS` followed by a
+/// readable value, or the `EV` ending their array or object.
+/// The length of an array or object is only trusted if its `EV` is found where it announces.
+///
+/// Values exceeding a limit of the options are skipped like damaged ones.
+pub(crate) fn recover_xff_v1(contents: &[u8], options: &DecodeOptions) -> Recovered {
+    let mut recoverer = Recoverer::new(contents, decode_xff_v1_value, options);
+    recoverer.root(1);
+    recoverer.finish()
+}
+
+/// Recovers a v2 byte stream, starting with the version byte, of at least 2 bytes
+///
+/// Works like `recover_xff_v1`, a compressed value is skipped as a whole if it is damaged.
+/// If the integrity trailer does not match, the value is recovered from all bytes anyway and the
+/// trailer is skipped with the `NabuError::ChecksumMismatch`.
+pub(crate) fn recover_xff_v2(contents: &[u8], options: &DecodeOptions) -> Recovered {
+    let value_pos = if has_trailer(contents) { 2 } else { 1 };
+    match verify_trailer(contents) {
+        Ok((protected, value_pos)) => {
+            let mut recoverer = Recoverer::new(protected, decode_xff_v2_value, options);
+            recoverer.root(value_pos);
+            recoverer.finish()
+        }
+        Err(err) => {
+            let mut recoverer = Recoverer::new(contents, decode_xff_v2_value, options);
+            recoverer.root(value_pos);
+            // the trailer follows EM
+            let pos = (recoverer.byte_pos + 1).min(contents.len());
+            recoverer.skip(pos..contents.len(), err);
+            recoverer.finish()
+        }
+    }
+}
+
+struct Recoverer<'a> {
+    contents: &'a [u8],
+    /// Decodes any value but arrays and objects, in the version of the byte stream
+    decode: DecodeValue,
+    /// Amount of bytes consumed, also the position of the next byte
+    byte_pos: usize,
+    /// The path to the current value, outermost first
+    path: Vec<PathSegment>,
+    value: XffValue,
+    skipped: Vec<SkippedBytes>,
+    limits: DecodeLimits,
+}
+
+impl<'a> Recoverer<'a> {
+    fn new(contents: &'a [u8], decode: DecodeValue, options: &DecodeOptions) -> Self {
+        Recoverer {
+            contents,
+            decode,
+            byte_pos: 0,
+            path: Vec::new(),
+            value: XffValue::Null,
+            skipped: Vec::new(),
+            limits: DecodeLimits::new(options),
+        }
+    }
+
+    /// Recovers the root value starting at `value_pos`, and the `EM` following it
+    fn root(&mut self, value_pos: usize) {
+        let bound = self.contents.len();
+        self.byte_pos = value_pos;
+        // EM
+        match self.value(&[25], bound) {
+            Ok(value) => {
+                if self.contents.get(self.byte_pos) != Some(&25) {
+                    let pos = self.byte_pos;
+                    self.skip(pos..bound, NabuError::TruncatedXFF(pos));
+                }
+                self.value = value;
+            }
+            Err(err) => {
+                self.skip(value_pos..bound, err);
+                self.byte_pos = bound;
+            }
+        }
+    }
+
+    fn finish(self) -> Recovered {
+        Recovered {
+            value: self.value,
+            skipped: self.skipped,
+        }
+    }
+
+    fn skip(&mut self, range: Range<usize>, error: NabuError) {
+        self.skipped.push(SkippedBytes {
+            range,
+            path: path_string(self.path.iter()),
+            error,
+        });
+    }
+
+    /// Decodes the value at the current position, recovering what it can of arrays and objects
+    ///
+    /// # Arguments
+    /// * `closing` - The bytes that may follow the value in its array, object or file
+    /// * `bound` - The position the value may not extend past
+    ///
+    /// # Errors
+    /// Returns the error of any value other than an array or object, or of a malformed length
+    /// of an array or object
+    fn value(&mut self, closing: &[u8], bound: usize) -> Result<XffValue> {
+        match self.contents.get(self.byte_pos) {
+            //ARY
+            Some(3) => self.container(false, closing, bound),
+            //OBJ
+            Some(4) => self.container(true, closing, bound),
+            _ => {
+                let (value, end) =
+                    (self.decode)(&self.contents[..bound], self.byte_pos, &mut self.limits)?;
+                self.byte_pos = end;
+                Ok(value)
+            }
+        }
+    }
+
+    fn container(&mut self, object: bool, closing: &[u8], bound: usize) -> Result<XffValue> {
+        // v1 and v2 share their length attributes
+        let (len, content_pos) = decode_xff_v1_length(&self.contents[..bound], self.byte_pos + 1)?;
+        self.limits.check_len(len, self.byte_pos + 1)?;
+        if let Err(err) = self.limits.enter(self.byte_pos) {
+            // the enclosing container carries on after the error
+            self.limits.leave();
+            return Err(err);
+        }
+        // the length is trusted only if EV follows where it announces
+        let end = content_pos
+            .checked_add(len)
+            .filter(|end| *end < bound && self.contents[*end] == 24);
+        let limit = end.unwrap_or(bound);
+        self.byte_pos = content_pos;
+        let mut ary_bind: Vec<XffValue> = Default::default();
+        let mut obj_bind: BTreeMap<String, XffValue> = Default::default();
+        let mut index = 0;
+        loop {
+            let pos = self.byte_pos;
+            if self.is_end(pos, end, closing) {
+                self.byte_pos = pos + 1;
+                break;
+            }
+            if pos >= limit {
+                // EV is missing, the enclosing value reports the bytes following
+                break;
+            }
+            if object {
+                let (key, value_pos) = match self.key(pos, limit) {
+                    Ok(key) => key,
+                    Err(err) => {
+                        self.resync(pos, err, true, end, closing, limit);
+                        continue;
+                    }
+                };
+                self.byte_pos = value_pos;
+                self.path.push(PathSegment::Key(key.clone()));
+                let value = self
+                    .limits
+                    .check_keys(obj_bind.len() + 1, pos)
+                    .and_then(|()| self.limits.allocate(size_of::<(String, XffValue)>(), pos))
+                    .and_then(|()| self.value(&[29], limit))
+                    .map_err(|err| self.resync(pos, err, true, end, closing, limit));
+                self.path.pop();
+                match value {
+                    Ok(value) => {
+                        obj_bind.insert(key, value);
+                    }
+                    Err(()) => continue,
+                }
+                // Trailing GS
+                match self.contents[..limit].get(self.byte_pos) {
+                    Some(29) => self.byte_pos += 1,
+                    Some(b) => {
+                        let err = NabuError::InvalidObject(self.byte_pos, *b);
+                        self.resync(self.byte_pos, err, true, end, closing, limit);
+                        continue;
+                    }
+                    // EV is missing, the enclosing value reports the bytes following
+                    None => continue,
+                }
+            } else {
+                self.path.push(PathSegment::Index(index));
+                index += 1;
+                let value = self
+                    .limits
+                    .allocate(size_of::<XffValue>(), pos)
+                    .and_then(|()| self.value(&[30, 24], limit))
+                    .map_err(|err| self.resync(pos, err, false, end, closing, limit));
+                self.path.pop();
+                match value {
+                    Ok(value) => ary_bind.push(value),
+                    Err(()) => continue,
+                }
+            }
+            if self.is_end(self.byte_pos, end, closing) {
+                continue;
+            }
+            match self.contents[..limit].get(self.byte_pos) {
+                // RS, may be trailing
+                Some(30) => self.byte_pos += 1,
+                Some(b) => {
+                    let err = if object {
+                        NabuError::InvalidObject(self.byte_pos, *b)
+                    } else {
+                        NabuError::InvalidArray(self.byte_pos, *b)
+                    };
+                    self.resync(self.byte_pos, err, object, end, closing, limit);
+                }
+                None => {}
+            }
+        }
+        self.limits.leave();
+        if object {
+            Ok(XffValue::from(obj_bind))
+        } else {
+            Ok(XffValue::from(ary_bind))
+        }
+    }
+
+    /// Decodes the `GS`, key and `US` of an object entry starting at `pos`, returning the key
+    /// and the position of the value
+    fn key(&self, pos: usize, limit: usize) -> Result<(String, usize)> {
+        let contents = &self.contents[..limit];
+        // GS
+        match contents.get(pos) {
+            Some(29) => {}
+            Some(b) => return Err(NabuError::InvalidObject(pos, *b)),
+            None => return Err(NabuError::TruncatedXFF(pos)),
+        }
+        // keys are always plain strings, never compressed
+        if contents.get(pos + 1) == Some(&CMP) {
+            return Err(NabuError::InvalidObject(pos + 1, CMP));
+        }
+        // only checked against the limits, the key is counted with its value
+        let mut limits = self.limits;
+        let (key, us_pos) = match (self.decode)(contents, pos + 1, &mut limits)? {
+            (XffValue::String(key), us_pos) => (key, us_pos),
+            (other, us_pos) => return Err(NabuError::InvalidKey(us_pos, other)),
+        };
+        // US
+        match contents.get(us_pos) {
+            Some(31) => Ok((key, us_pos + 1)),
+            Some(b) => Err(NabuError::InvalidObject(us_pos, *b)),
+            None => Err(NabuError::TruncatedXFF(us_pos)),
+        }
+    }
+
+    /// Skips the bytes from `from` up to the next `RS` followed by a readable element, or the
+    /// end of the array or object
+    fn resync(
+        &mut self,
+        from: usize,
+        err: NabuError,
+        object: bool,
+        end: Option<usize>,
+        closing: &[u8],
+        limit: usize,
+    ) {
+        let mut pos = from + 1;
+        while pos < limit
+            && !self.is_end(pos, end, closing)
+            && !(self.contents[pos] == 30 && self.is_element(pos + 1, object, end, closing, limit))
+        {
+            pos += 1;
+        }
+        let pos = pos.min(limit);
+        self.skip(from..pos, err);
+        if pos < limit && self.contents[pos] == 30 {
+            self.byte_pos = pos + 1;
+        } else {
+            self.byte_pos = pos;
+        }
+    }
+
+    /// Returns `true` if a complete element starts at `pos`, followed by the end or by `RS` and
+    /// a second complete element
+    ///
+    /// A single element is not enough, as the last element of a nested array or object may be
+    /// followed by a trailing `RS` as well.
+    fn is_element(
+        &self,
+        pos: usize,
+        object: bool,
+        end: Option<usize>,
+        closing: &[u8],
+        limit: usize,
+    ) -> bool {
+        let mut pos = pos;
+        for _ in 0..2 {
+            let next = match self.element_end(pos, object, limit) {
+                Some(next) => next,
+                None => return false,
+            };
+            if self.is_end(next, end, closing) {
+                return true;
+            }
+            if self.contents[..limit].get(next) != Some(&30) {
+                return false;
+            }
+            // RS, may be trailing
+            pos = next + 1;
+            if self.is_end(pos, end, closing) {
+                return true;
+            }
+        }
+        true
+    }
+
+    /// Returns the position after the complete element starting at `pos`, if there is one
+    fn element_end(&self, pos: usize, object: bool, limit: usize) -> Option<usize> {
+        let contents = &self.contents[..limit];
+        // looking ahead allocates nothing that is kept
+        let mut limits = self.limits;
+        if object {
+            self.key(pos, limit)
+                .and_then(|(_, value_pos)| (self.decode)(contents, value_pos, &mut limits))
+                .ok()
+                .filter(|(_, gs_pos)| contents.get(*gs_pos) == Some(&29))
+                .map(|(_, gs_pos)| gs_pos + 1)
+        } else {
+            (self.decode)(contents, pos, &mut limits)
+                .ok()
+                .map(|(_, next)| next)
+        }
+    }
+
+    /// Returns `true` if the `EV` ending the array or object is at `pos`
+    fn is_end(&self, pos: usize, end: Option<usize>, closing: &[u8]) -> bool {
+        match end {
+            Some(end) => pos == end,
+            None => self.contents.get(pos) == Some(&24) && self.is_closing(pos + 1, closing),
+        }
+    }
+
+    fn is_closing(&self, pos: usize, closing: &[u8]) -> bool {
+        self.contents
+            .get(pos)
+            .map(|b| closing.contains(b))
+            .unwrap_or(false)
+    }
+}
@@ -1,269 +1,169 @@
-use std::collections::VecDeque;
+use std::mem::size_of;
 
 use crate::{
-    error::NabuError,
-    xff::value::{CommandCharacter, Data, XffValue},
+    error::{NabuError, PathSegment, Result, Trace},
+    xff::{
+        deserializer::{
+            cursor::Cursor,
+            options::{DecodeLimits, DecodeOptions},
+            v1::decode_xff_v1_char,
+        },
+        value::{CommandCharacter, Data, XffValue},
+    },
 };
 
 // ---------------------------------------------------
 //                      LEGACY CODE
 // ---------------------------------------------------
 
-pub fn deserialize_xff_v0(content: &mut VecDeque<u8>) -> Result<XffValue, NabuError> {
-    let xff_ver = 0;
+/// Deserializes a complete v0 byte stream, starting with the version byte, within the limits of
+/// the options
+///
+/// Every byte sequence is either decoded or rejected with an error, no input makes it panic.
+pub fn deserialize_xff_v0(content: &[u8], options: &DecodeOptions) -> Result<XffValue> {
+    deserialize_xff_v0_traced(content, options).map_err(|(err, _)| err)
+}
+
+/// Deserializes a complete v0 byte stream like `deserialize_xff_v0`, returning the path to the
+/// value any error was encountered in alongside it
+pub(crate) fn deserialize_xff_v0_traced(
+    content: &[u8],
+    options: &DecodeOptions,
+) -> std::result::Result<XffValue, (NabuError, Trace)> {
     let mut out: Vec<XffValue> = Default::default();
-    // version is byte 0; already match against and used but not removed, for performance, until now
-    let _ = content.pop_front();
-    let mut byte_pos: usize = 1;
+    match decode_values(content, options, &mut out) {
+        Ok(()) => Ok(out.into()),
+        Err(err) => {
+            // the error was encountered in the value following all values decoded
+            let mut trace = Trace::default();
+            trace.push(PathSegment::Index(out.len()));
+            Err((err, trace))
+        }
+    }
+}
 
-    // debug; put true for debug, use --nocapture
-    // this section takes 50ns
-    let debug = false;
-    let print_details = false;
-    let mut loop_amount = usize::MIN;
-    let mut loop_time_sum: std::time::Duration = std::time::Duration::ZERO;
-    let mut stx_amount = usize::MIN;
-    let mut stx_time_sum: std::time::Duration = std::time::Duration::ZERO;
-    let mut dle_amount = usize::MIN;
-    let mut dle_time_sum: std::time::Duration = std::time::Duration::ZERO;
-    let mut cmd_amount = usize::MIN;
-    let mut cmd_time_sum: std::time::Duration = std::time::Duration::ZERO;
+/// Decodes all values up to EM into `out`
+fn decode_values(content: &[u8], options: &DecodeOptions, out: &mut Vec<XffValue>) -> Result<()> {
+    // version is byte 0; already matched against
+    let mut cursor = Cursor::new(content, 1);
+    let mut limits = DecodeLimits::new(options);
+    while !decode_xff_v0_value(&mut cursor, &mut limits, out)? {}
+    Ok(())
+}
 
-    while content.len() > 0 {
-        let now_main = std::time::Instant::now();
-        if debug {
-            if print_details {
-                // +1 for the remove below, its the position I am interested in
-                println!("Main loop, byte pos is: {}", byte_pos + 1);
-            }
-            loop_amount += 1;
-        }
-        let current_byte = {
-            if let Some(b) = content.pop_front() {
-                byte_pos += 1;
-                b
-            } else {
-                Err(NabuError::TruncatedXFF(byte_pos))?
-            }
-        };
-        byte_pos += 1;
-        match current_byte {
-            2 => {
-                // STX
-                let now = std::time::Instant::now();
-                let mut tmp_string_binding = String::new();
-                while content[0] != 3 {
-                    let current_char = {
-                        if let Some(b) = content.pop_front() {
-                            byte_pos += 1;
-                            b
-                        } else {
-                            Err(NabuError::TruncatedXFF(byte_pos))?
-                        }
-                    };
-                    byte_pos += 1;
-                    if current_char >= 8 && current_char <= 13 {
-                        // command characters
-                        match current_char {
-                            8 => {
-                                // Backspace
-                                tmp_string_binding.push('\x08')
-                            }
-                            9 => {
-                                // Horizontal Tab
-                                tmp_string_binding.push('\t')
-                            }
-                            10 => {
-                                // Line Feed
-                                tmp_string_binding.push('\n')
-                            }
-                            11 => {
-                                // Vertical Tab
-                                tmp_string_binding.push('\x0b')
-                            }
-                            12 => {
-                                // Form Feed
-                                tmp_string_binding.push('\x0c')
-                            }
-                            13 => {
-                                // Carriage Return
-                                tmp_string_binding.push('\r')
-                            }
-                            _ => {
-                                unreachable!()
-                            }
-                        }
-                    }
-                    // All valid ASCII string characters
-                    if current_char >= 32 && current_char <= 126
-                        || current_char == 128
-                        || current_char >= 130 && current_char <= 140
-                        || current_char == 142
-                        || current_char >= 145 && current_char <= 156
-                        || current_char >= 158
-                    {
-                        tmp_string_binding.push(char::from_u32(current_char as u32).unwrap());
-                    } else {
-                        return Err(NabuError::InvalidASCIIString(current_char, byte_pos, 0));
-                    }
-                }
-                if content[0] == 3 {
-                    let _ = content.pop_front();
-                    byte_pos += 1;
-                    out.push(XffValue::from((tmp_string_binding, xff_ver)));
-                } else {
-                    return Err(NabuError::MissingETX(byte_pos));
+/// Decodes the value at the position of the cursor into `out`, a run of escaped command
+/// characters is decoded into one value for each character
+///
+/// Returns `true` once the `EM` ending the byte stream is consumed.
+pub(crate) fn decode_xff_v0_value(
+    cursor: &mut Cursor,
+    limits: &mut DecodeLimits,
+    out: &mut Vec<XffValue>,
+) -> Result<bool> {
+    let xff_ver = 0;
+    let current_byte = cursor
+        .next()
+        // Premature EoF
+        .ok_or(NabuError::TruncatedXFF(cursor.byte_pos))?;
+    match current_byte {
+        2 => {
+            // STX
+            let string_pos = cursor.byte_pos - 1;
+            // the length is only known once ETX is found
+            let len = cursor.contents[cursor.byte_pos..]
+                .iter()
+                .position(|b| *b == 3)
+                .unwrap_or(cursor.contents.len() - cursor.byte_pos);
+            limits.check_len(len, string_pos)?;
+            limits.allocate(size_of::<XffValue>() + len, string_pos)?;
+            let mut tmp_string_binding = String::with_capacity(len);
+            loop {
+                let current_char = cursor
+                    .next()
+                    .ok_or(NabuError::MissingETX(cursor.byte_pos))?;
+                if current_char == 3 {
+                    // ETX
+                    break;
                 }
-                if debug {
-                    let elapsed = now.elapsed();
-                    if print_details {
-                        println!("STX Elapsed: {:.2?}", elapsed);
+                // Command characters 8 through 13 and all valid ASCII string characters
+                match decode_xff_v1_char(current_char) {
+                    Some(c) => tmp_string_binding.push(c),
+                    None => {
+                        return Err(NabuError::InvalidASCIIString(
+                            current_char,
+                            cursor.byte_pos - 1,
+                            0,
+                        ))
                     }
-                    stx_amount += 1;
-                    stx_time_sum += elapsed;
                 }
             }
-            16 => {
-                // DLE
-                let now = std::time::Instant::now();
-                // length, 5 bytes
-                let data_length = u64::from_le_bytes([
-                    content[0], content[1], content[2], content[3], content[4], 0, 0, 0,
-                ]);
-                let _ = content.drain(0..5);
-                let data = content.drain(0..data_length as usize).collect::<Vec<u8>>();
-                byte_pos += data_length as usize + 5;
-
-                if content[0] == 16 {
-                    let _ = content.pop_front();
-                    byte_pos += 1;
-                    out.push(XffValue::Data(Data {
-                        len: data_length as usize,
-                        data,
-                    }));
-                    if debug {
-                        let elapsed = now.elapsed();
-                        if print_details {
-                            println!("DLE Elapsed: {:.2?}", elapsed);
-                        }
-                        dle_amount += 1;
-                        dle_time_sum += elapsed;
-                    }
-                    continue;
-                } else {
-                    return Err(NabuError::MissingDLE(byte_pos));
-                }
+            out.push(XffValue::from((tmp_string_binding, xff_ver)));
+        }
+        16 => {
+            // DLE
+            // length, 5 bytes
+            let len_bytes = cursor.take(5)?;
+            let data_length = u64::from_le_bytes([
+                len_bytes[0],
+                len_bytes[1],
+                len_bytes[2],
+                len_bytes[3],
+                len_bytes[4],
+                0,
+                0,
+                0,
+            ]);
+            let data_length = usize::try_from(data_length)
+                .map_err(|_| NabuError::TruncatedXFF(cursor.contents.len()))?;
+            limits.check_len(data_length, cursor.byte_pos - 6)?;
+            limits.allocate(size_of::<XffValue>() + data_length, cursor.byte_pos - 6)?;
+            let data = cursor.take(data_length)?.to_vec();
+            if cursor.next() != Some(16) {
+                return Err(NabuError::MissingDLE(cursor.byte_pos));
             }
-            25 => {
-                // EM
-                if debug {
-                    let elapsed = now_main.elapsed();
-                    if print_details {
-                        println!("Loop Elapsed: {:.2?}", elapsed);
-                    }
-                    loop_time_sum += elapsed;
-                    if print_details || true {
-                        if stx_amount > 0 {
-                            println!("------------------------------------");
-                            println!("STX Amount: {}", stx_amount);
-                            println!("STX Time Sum: {:.2?}", stx_time_sum);
-                            println!(
-                                "STX Time Average: {:.2?}",
-                                stx_time_sum / stx_amount.try_into().unwrap()
-                            );
-                        }
-                        if dle_amount > 0 {
-                            println!("------------------------------------");
-                            println!("DLE Amount: {}", dle_amount);
-                            println!("DLE Time Sum: {:.2?}", dle_time_sum);
-                            println!(
-                                "DLE Time Average: {:.2?}",
-                                dle_time_sum / dle_amount.try_into().unwrap()
-                            );
-                        }
-                        if cmd_amount > 0 {
-                            println!("------------------------------------");
-                            println!("CMD Amount: {}", cmd_amount);
-                            println!("CMD Time Sum: {:.2?}", cmd_time_sum);
-                            println!(
-                                "CMD Time Average: {:.2?}",
-                                cmd_time_sum / cmd_amount.try_into().unwrap()
-                            );
+            out.push(XffValue::Data(Data {
+                len: data_length,
+                data,
+            }));
+        }
+        25 => {
+            // EM
+            return Ok(true);
+        }
+        27 => {
+            // ESC
+            loop {
+                let current_cmd_char = cursor
+                    .next()
+                    .ok_or(NabuError::TruncatedXFF(cursor.byte_pos))?;
+                // ESC inverse check
+                if current_cmd_char != 27 {
+                    match CommandCharacter::from_u8_checked(current_cmd_char) {
+                        Some(c) => {
+                            limits.allocate(size_of::<XffValue>(), cursor.byte_pos - 1)?;
+                            out.push(XffValue::CommandCharacter(c));
                         }
-                        if loop_amount > 0 {
-                            println!("------------------------------------");
-                            println!("Loop Amount: {}", loop_amount);
-                            println!("Loop Time Sum: {:.2?}", loop_time_sum);
-                            println!(
-                                "Loop Time Average: {:.2?}",
-                                loop_time_sum / loop_amount.try_into().unwrap()
-                            );
-                            println!("------------------------------------");
+                        None => {
+                            return Err(NabuError::InvalidASCIICommandCharacter(
+                                current_cmd_char,
+                                cursor.byte_pos,
+                            ))
                         }
                     }
-                    println!("------------------------------------");
-                    println!("TOTALS:");
-                    let t_time = stx_time_sum + dle_time_sum + cmd_time_sum + loop_time_sum;
-                    let t_amount = stx_amount + dle_amount + cmd_amount + loop_amount;
-                    let t_time_o_val = t_time / t_amount.try_into().unwrap();
-                    println!("Total Time: {:.2?}", t_time);
-                    println!("Total Values: {}", t_amount);
-                    println!("Total Time over Values: {:.2?}", t_time_o_val);
-                    println!("------------------------------------");
-                }
-                return Ok(out.into());
-            }
-            27 => {
-                let now = std::time::Instant::now();
-                // ESC
-                loop {
-                    if let Some(current_cmd_char) = content.pop_front() {
-                        byte_pos += 1;
-                        // ESC inverse check
-                        if current_cmd_char != 27 {
-                            let val = CommandCharacter::from_u8_checked(current_cmd_char);
-                            if val.is_none() {
-                                return Err(NabuError::InvalidASCIICommandCharacter(
-                                    current_cmd_char,
-                                    byte_pos,
-                                ));
-                            }
-                            out.push(XffValue::CommandCharacter(val.unwrap()));
-                            continue;
-                        }
-                        // Ending ESC
-                        if content[0] != 27 {
-                            break;
-                        }
-                        content.pop_front();
-                        byte_pos += 1;
-                        out.push(XffValue::CommandCharacter(CommandCharacter::from(27)));
-                    } else {
-                        // pop front returned None, truncation!
-                        return Err(NabuError::TruncatedXFF(byte_pos));
-                    };
+                    continue;
                 }
-                if debug {
-                    let elapsed = now.elapsed();
-                    if print_details {
-                        println!("ESC Elapsed: {:.2?}", elapsed);
-                    }
-                    cmd_amount += 1;
-                    cmd_time_sum += elapsed;
+                // Ending ESC
+                if cursor.peek() != Some(27) {
+                    break;
                 }
-            }
-            _ => {
-                return Err(NabuError::InvalidXFFByte(current_byte, byte_pos, 0));
+                cursor.byte_pos += 1;
+                limits.allocate(size_of::<XffValue>(), cursor.byte_pos - 1)?;
+                out.push(XffValue::CommandCharacter(CommandCharacter::from(27)));
             }
         }
-        if debug {
-            let elapsed = now_main.elapsed();
-            if print_details {
-                println!("Loop Elapsed: {:.2?}", elapsed);
-            }
-            loop_time_sum += elapsed;
+        _ => {
+            return Err(NabuError::InvalidXFFByte(current_byte, cursor.byte_pos, 0));
         }
     }
-    // Premature EoF
-    Err(NabuError::TruncatedXFF(byte_pos))
+    Ok(false)
 }
@@ -0,0 +1,109 @@
+use crate::{
+    error::{NabuError, Result, Trace},
+    xff::{
+        checksum::verify_trailer,
+        deserializer::{
+            cursor::{deserialize_stream, ValueCursor},
+            options::{DecodeLimits, DecodeOptions},
+        },
+        value::{Decimal, Number, XffValue},
+    },
+};
+
+/// Deserializes a complete v2 byte stream, starting with the version byte, within the limits of
+/// the options
+///
+/// An announced integrity trailer is verified before anything is decoded.
+/// Any error is returned alongside the path to the value it was encountered in.
+pub(crate) fn deserialize_xff_v2(
+    contents: &[u8],
+    options: &DecodeOptions,
+) -> std::result::Result<XffValue, (NabuError, Trace)> {
+    let (protected, value_pos) = verify_trailer(contents).map_err(|err| (err, Trace::default()))?;
+    deserialize_stream(protected, value_pos, 2, options)
+}
+
+/// Decodes the single v2 value starting at `byte_pos`, returning it alongside the position of
+/// the first byte after it
+///
+/// # Arguments
+/// * `contents` - The bytes containing the value, it may not extend past their end
+/// * `byte_pos` - The position of the type byte of the value
+/// * `limits` - The limits of the decoder, the value is accounted for in them
+pub(crate) fn decode_xff_v2_value(
+    contents: &[u8],
+    byte_pos: usize,
+    limits: &mut DecodeLimits,
+) -> Result<(XffValue, usize)> {
+    let mut cursor = ValueCursor::new(contents, byte_pos, 2, *limits);
+    let value = cursor.value()?;
+    *limits = cursor.limits;
+    Ok((value, cursor.cursor.byte_pos))
+}
+
+/// Decompresses the content of a compressed v2 value and decodes the value it contains
+///
+/// Errors inside of the decompressed value report positions relative to its decompressed bytes.
+///
+/// # Arguments
+/// * `content` - The content of the compressed value, without `CMP`, length and `EV`
+/// * `byte_pos` - The position of the content, reported in errors
+/// * `limits` - The limits of the decoder, the decompressed value is accounted for in them
+pub(crate) fn decode_xff_v2_compressed(
+    content: &[u8],
+    byte_pos: usize,
+    limits: &mut DecodeLimits,
+) -> Result<XffValue> {
+    // only the limits of the cursor are used
+    let mut cursor = ValueCursor::new(&[], 0, 2, *limits);
+    let out = cursor.compressed(content, byte_pos)?;
+    *limits = cursor.limits;
+    Ok(out)
+}
+
+/// Decodes the content bytes of a binary v2 `Number`
+///
+/// # Arguments
+/// * `num_bytes` - The content of the number, without `NUM`, length and `EV`
+/// * `byte_pos` - The byte position of the first byte of the content, the number type
+pub(crate) fn decode_xff_v2_number(num_bytes: &[u8], byte_pos: usize) -> Result<Number> {
+    let invalid = || NabuError::InvalidNumber(byte_pos, format!("{:?}", num_bytes));
+    let (num_type, num) = match num_bytes.split_first() {
+        Some((num_type, num)) => (*num_type, num),
+        None => return Err(invalid()),
+    };
+    match num_type {
+        1 => {
+            let u = u64::from_le_bytes(num.try_into().map_err(|_| invalid())?);
+            usize::try_from(u)
+                .map(Number::from)
+                .map_err(|_| NabuError::InvalidNumber(byte_pos, u.to_string()))
+        }
+        2 => {
+            let i = i64::from_le_bytes(num.try_into().map_err(|_| invalid())?);
+            isize::try_from(i)
+                .map(Number::from)
+                .map_err(|_| NabuError::InvalidNumber(byte_pos, i.to_string()))
+        }
+        3 => {
+            let f = f64::from_le_bytes(num.try_into().map_err(|_| invalid())?);
+            if f.is_finite() {
+                Ok(Number::from(f))
+            } else {
+                Err(NabuError::InvalidNumber(byte_pos, f.to_string()))
+            }
+        }
+        4 => Ok(Number::BigUnsigned(u128::from_le_bytes(
+            num.try_into().map_err(|_| invalid())?,
+        ))),
+        5 => Ok(Number::BigInteger(i128::from_le_bytes(
+            num.try_into().map_err(|_| invalid())?,
+        ))),
+        6 => std::str::from_utf8(num)
+            .map_err(|_| invalid())?
+            .parse::<Decimal>()
+            .map(Number::Decimal)
+            .map_err(|_| invalid()),
+        _ => Err(NabuError::InvalidXFFByte(num_type, byte_pos, 2)),
+    }
+}
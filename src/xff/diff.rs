@@ -0,0 +1,520 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    error::NabuError,
+    xff::value::{pointer_child, pointer_index, pointer_tokens, Array, Object, XffValue},
+};
+
+/// Maximum amount of element pairs compared to align the elements of two arrays, longer arrays
+/// are compared element by element
+const ALIGNMENT_LIMIT: usize = 1 << 20;
+
+/// A single change of a `Patch`, addressed by a JSON Pointer
+///
+/// Paths follow the same rules as `XffValue::pointer`, `""` is the value itself.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    /// A key was added to an object, or an element inserted into an array at the index, moving
+    /// all following elements back
+    Add {
+        /// The path of the new key or element
+        path: String,
+        /// The added value
+        value: XffValue,
+    },
+    /// A key was removed from an object, or an element removed from an array, moving all
+    /// following elements forward
+    Remove {
+        /// The path of the removed key or element
+        path: String,
+        /// The removed value
+        value: XffValue,
+    },
+    /// A value was replaced by another value, for example a changed string, number or data blob,
+    /// or a value of a different type
+    Replace {
+        /// The path of the replaced value
+        path: String,
+        /// The value before the change
+        old: XffValue,
+        /// The value after the change
+        new: XffValue,
+    },
+}
+
+impl Operation {
+    /// Returns the path the operation applies to
+    pub fn path(&self) -> &str {
+        match self {
+            Operation::Add { path, .. }
+            | Operation::Remove { path, .. }
+            | Operation::Replace { path, .. } => path,
+        }
+    }
+}
+
+impl std::fmt::Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Operation::Add { path, value } => write!(f, "+ {}: {}", path, value),
+            Operation::Remove { path, value } => write!(f, "- {}: {}", path, value),
+            Operation::Replace { path, old, new } => write!(f, "~ {}: {} -> {}", path, old, new),
+        }
+    }
+}
+
+/// A list of operations turning one `XffValue` into another, created by `diff`
+///
+/// The operations are applied in order, array indices always refer to the array as left by the
+/// preceding operations.
+///
+/// A patch can be converted into a `XffValue` to be stored in a `.xff` file, and back with
+/// `Patch::try_from`. It is an `Array` of `Object`s, each with the keys `op` (`"add"`, `"remove"`
+/// or `"replace"`) and `path`, as well as `value` for `add` and `remove`, or `old` and `new` for
+/// `replace`.
+///
+/// # Example
+/// ```rust
+/// use nabu::diff::{diff, Patch};
+/// use nabu::XffValue;
+///
+/// let old = XffValue::from(vec![("name", XffValue::from("nabu")), ("version", XffValue::from(1))]);
+/// let new = XffValue::from(vec![("name", XffValue::from("nabu")), ("version", XffValue::from(2))]);
+///
+/// let patch = diff(&old, &new);
+/// assert_eq!(patch.to_string(), "~ /version: 1 -> 2");
+///
+/// let stored = XffValue::from(patch);
+/// let mut value = old.clone();
+/// Patch::try_from(stored).unwrap().apply(&mut value).unwrap();
+/// assert_eq!(value, new);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Patch {
+    /// The operations of the patch, in the order they are applied
+    pub operations: Vec<Operation>,
+}
+
+/// Computes the changes turning `old` into `new`
+///
+/// Objects are compared key by key and arrays element by element, descending into nested objects
+/// and arrays. Elements inserted into or removed from an array are found as such, instead of
+/// changing all following elements. All other values are replaced as a whole if they differ.
+///
+/// # Arguments
+/// * `old` - The value before the changes
+/// * `new` - The value after the changes
+///
+/// # Example
+/// ```rust
+/// use nabu::diff::{diff, Operation};
+/// use nabu::XffValue;
+///
+/// let old = XffValue::from(vec![XffValue::from("a"), XffValue::from("c")]);
+/// let new = XffValue::from(vec![XffValue::from("a"), XffValue::from("b"), XffValue::from("c")]);
+///
+/// let patch = diff(&old, &new);
+/// assert_eq!(patch.operations, vec![Operation::Add { path: "/1".to_string(), value: XffValue::from("b") }]);
+/// ```
+pub fn diff(old: &XffValue, new: &XffValue) -> Patch {
+    let mut operations: Vec<Operation> = Vec::new();
+    diff_value(String::new(), old, new, &mut operations);
+    Patch { operations }
+}
+
+fn diff_value(path: String, old: &XffValue, new: &XffValue, out: &mut Vec<Operation>) {
+    if old == new {
+        return;
+    }
+    match (old, new) {
+        (XffValue::Object(old), XffValue::Object(new)) => diff_object(&path, old, new, out),
+        (XffValue::Array(old), XffValue::Array(new)) => {
+            diff_array(&path, &old.values, &new.values, out)
+        }
+        _ => out.push(Operation::Replace {
+            path,
+            old: old.clone(),
+            new: new.clone(),
+        }),
+    }
+}
+
+fn diff_object(path: &str, old: &Object, new: &Object, out: &mut Vec<Operation>) {
+    for (key, old_value) in old.iter() {
+        let key_path = pointer_child(path, key);
+        match new.get(key) {
+            Some(new_value) => diff_value(key_path, old_value, new_value, out),
+            None => out.push(Operation::Remove {
+                path: key_path,
+                value: old_value.clone(),
+            }),
+        }
+    }
+    for (key, new_value) in new.iter() {
+        if !old.contains_key(key) {
+            out.push(Operation::Add {
+                path: pointer_child(path, key),
+                value: new_value.clone(),
+            });
+        }
+    }
+}
+
+/// How an element of the old or new array is used
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Step {
+    Keep,
+    Remove,
+    Insert,
+}
+
+fn diff_array(path: &str, old: &[XffValue], new: &[XffValue], out: &mut Vec<Operation>) {
+    let prefix = old.iter().zip(new).take_while(|(o, n)| o == n).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(o, n)| o == n)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let steps = align(old_mid, new_mid);
+    let mut index = prefix;
+    let (mut old_pos, mut new_pos) = (0, 0);
+    let mut i = 0;
+    while i < steps.len() {
+        if steps[i] == Step::Keep {
+            index += 1;
+            old_pos += 1;
+            new_pos += 1;
+            i += 1;
+            continue;
+        }
+        // a run of removed and inserted elements between two kept ones
+        let run_end = steps[i..]
+            .iter()
+            .position(|s| *s == Step::Keep)
+            .map_or(steps.len(), |p| i + p);
+        let removed = steps[i..run_end]
+            .iter()
+            .filter(|s| **s == Step::Remove)
+            .count();
+        let inserted = run_end - i - removed;
+        // pairs of removed and inserted elements are changed elements
+        let changed = removed.min(inserted);
+        for _ in 0..changed {
+            diff_value(
+                pointer_child(path, &index.to_string()),
+                &old_mid[old_pos],
+                &new_mid[new_pos],
+                out,
+            );
+            index += 1;
+            old_pos += 1;
+            new_pos += 1;
+        }
+        for _ in changed..removed {
+            out.push(Operation::Remove {
+                path: pointer_child(path, &index.to_string()),
+                value: old_mid[old_pos].clone(),
+            });
+            old_pos += 1;
+        }
+        for _ in changed..inserted {
+            out.push(Operation::Add {
+                path: pointer_child(path, &index.to_string()),
+                value: new_mid[new_pos].clone(),
+            });
+            index += 1;
+            new_pos += 1;
+        }
+        i = run_end;
+    }
+}
+
+/// Aligns the elements of two arrays along their longest common subsequence
+///
+/// Falls back to comparing element by element if the arrays are too long to align.
+fn align(old: &[XffValue], new: &[XffValue]) -> Vec<Step> {
+    let (n, m) = (old.len(), new.len());
+    if n.saturating_mul(m) > ALIGNMENT_LIMIT {
+        let mut steps = vec![Step::Remove; n];
+        steps.extend(vec![Step::Insert; m]);
+        return steps;
+    }
+    // lengths of the longest common subsequences of old[i..] and new[j..]
+    let mut lcs = vec![0_u32; (n + 1) * (m + 1)];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i * (m + 1) + j] = if old[i] == new[j] {
+                lcs[(i + 1) * (m + 1) + j + 1] + 1
+            } else {
+                lcs[(i + 1) * (m + 1) + j].max(lcs[i * (m + 1) + j + 1])
+            };
+        }
+    }
+    let mut steps: Vec<Step> = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i] == new[j] {
+            steps.push(Step::Keep);
+            i += 1;
+            j += 1;
+        } else if lcs[(i + 1) * (m + 1) + j] >= lcs[i * (m + 1) + j + 1] {
+            steps.push(Step::Remove);
+            i += 1;
+        } else {
+            steps.push(Step::Insert);
+            j += 1;
+        }
+    }
+    steps.extend(vec![Step::Remove; n - i]);
+    steps.extend(vec![Step::Insert; m - j]);
+    steps
+}
+
+impl Patch {
+    /// Creates a new and empty `Patch`
+    pub fn new() -> Self {
+        Patch {
+            operations: Vec::new(),
+        }
+    }
+
+    /// Returns `true` if the patch contains no operations, meaning both values were equal
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Returns the number of operations in the patch
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    /// Returns an iterator over the operations of the patch
+    pub fn iter(&self) -> std::slice::Iter<'_, Operation> {
+        self.operations.iter()
+    }
+
+    /// Applies all operations of the patch to the value, in order
+    ///
+    /// Removed and replaced values are checked against their recorded value, so a patch is only
+    /// applied to the value it was created from. If an operation fails, the value is left
+    /// unchanged.
+    ///
+    /// # Errors
+    /// Returns `NabuError::InvalidPatch` if a path does not exist, or a removed or replaced value
+    /// differs from the value recorded in the patch
+    pub fn apply(&self, value: &mut XffValue) -> Result<(), NabuError> {
+        let mut patched = value.clone();
+        for operation in &self.operations {
+            apply_operation(&mut patched, operation)?;
+        }
+        *value = patched;
+        Ok(())
+    }
+}
+
+fn apply_operation(value: &mut XffValue, operation: &Operation) -> Result<(), NabuError> {
+    let path = operation.path();
+    let error = |msg: &str| NabuError::InvalidPatch(format!("{}: {}", path, msg));
+    if path.is_empty() {
+        return match operation {
+            Operation::Replace { old, new, .. } if old == value => {
+                *value = new.clone();
+                Ok(())
+            }
+            Operation::Replace { .. } => Err(error("value differs from the patch")),
+            _ => Err(error("only replace can be applied to the value itself")),
+        };
+    }
+    let split = path.rfind('/').ok_or(error("not a valid path"))?;
+    let token = pointer_tokens(&path[split..])
+        .and_then(|mut tokens| tokens.next().flatten())
+        .ok_or(error("not a valid path"))?;
+    let parent = value
+        .pointer_mut(&path[..split])
+        .ok_or(error("path does not exist"))?;
+    match (parent, operation) {
+        (XffValue::Object(object), Operation::Add { value, .. }) => {
+            if object.contains_key(&token) {
+                return Err(error("key already exists"));
+            }
+            object.insert(token, value.clone());
+        }
+        (XffValue::Array(array), Operation::Add { value, .. }) => {
+            match pointer_index(&token).filter(|i| *i <= array.len()) {
+                Some(index) => array.insert(index, value.clone()),
+                None => return Err(error("index out of bounds")),
+            }
+        }
+        (parent, Operation::Remove { value, .. }) => {
+            if child(parent, &token) != Some(value) {
+                return Err(error("value differs from the patch"));
+            }
+            match parent {
+                XffValue::Object(object) => {
+                    object.remove(&token);
+                }
+                XffValue::Array(array) => {
+                    // index checked by `child`
+                    array.remove(pointer_index(&token).unwrap_or_default());
+                }
+                _ => unreachable!("only objects and arrays have children"),
+            }
+        }
+        (parent, Operation::Replace { old, new, .. }) => {
+            if child(parent, &token) != Some(old) {
+                return Err(error("value differs from the patch"));
+            }
+            let target = match parent {
+                XffValue::Object(object) => object.get_mut(&token),
+                XffValue::Array(array) => pointer_index(&token).and_then(|i| array.get_mut(i)),
+                _ => None,
+            };
+            if let Some(target) = target {
+                *target = new.clone();
+            }
+        }
+        _ => return Err(error("path does not exist")),
+    }
+    Ok(())
+}
+
+fn child<'a>(parent: &'a XffValue, token: &str) -> Option<&'a XffValue> {
+    match parent {
+        XffValue::Object(object) => object.get(token),
+        XffValue::Array(array) => array.get(pointer_index(token)?),
+        _ => None,
+    }
+}
+
+// -----------------------------------------------------------
+//                     Conversions
+// -----------------------------------------------------------
+
+impl From<Operation> for XffValue {
+    fn from(operation: Operation) -> Self {
+        let mut object = Object::new();
+        match operation {
+            Operation::Add { path, value } => {
+                object.insert("op", "add");
+                object.insert("path", path);
+                object.insert("value", value);
+            }
+            Operation::Remove { path, value } => {
+                object.insert("op", "remove");
+                object.insert("path", path);
+                object.insert("value", value);
+            }
+            Operation::Replace { path, old, new } => {
+                object.insert("op", "replace");
+                object.insert("path", path);
+                object.insert("old", old);
+                object.insert("new", new);
+            }
+        }
+        XffValue::Object(object)
+    }
+}
+
+impl TryFrom<XffValue> for Operation {
+    type Error = NabuError;
+
+    fn try_from(value: XffValue) -> Result<Self, Self::Error> {
+        let mut map: BTreeMap<String, XffValue> = match value {
+            XffValue::Object(object) => object.into_btree_map(),
+            other => {
+                return Err(NabuError::InvalidPatch(format!(
+                    "expected an operation object, found {}",
+                    other
+                )))
+            }
+        };
+        let mut take = |key: &str| {
+            map.remove(key).ok_or(NabuError::InvalidPatch(format!(
+                "operation is missing `{}`",
+                key
+            )))
+        };
+        let op = take("op")?;
+        let path = match take("path")? {
+            XffValue::String(path) => path,
+            other => {
+                return Err(NabuError::InvalidPatch(format!(
+                    "expected a path string, found {}",
+                    other
+                )))
+            }
+        };
+        match op.as_str() {
+            Some("add") => Ok(Operation::Add {
+                path,
+                value: take("value")?,
+            }),
+            Some("remove") => Ok(Operation::Remove {
+                path,
+                value: take("value")?,
+            }),
+            Some("replace") => Ok(Operation::Replace {
+                path,
+                old: take("old")?,
+                new: take("new")?,
+            }),
+            _ => Err(NabuError::InvalidPatch(format!("unknown operation {}", op))),
+        }
+    }
+}
+
+impl From<Patch> for XffValue {
+    fn from(patch: Patch) -> Self {
+        XffValue::Array(Array::from(
+            patch
+                .operations
+                .into_iter()
+                .map(XffValue::from)
+                .collect::<Vec<XffValue>>(),
+        ))
+    }
+}
+
+impl TryFrom<XffValue> for Patch {
+    type Error = NabuError;
+
+    fn try_from(value: XffValue) -> Result<Self, Self::Error> {
+        match value {
+            XffValue::Array(array) => Ok(Patch {
+                operations: array
+                    .into_iter()
+                    .map(Operation::try_from)
+                    .collect::<Result<Vec<Operation>, NabuError>>()?,
+            }),
+            other => Err(NabuError::InvalidPatch(format!(
+                "expected an array of operations, found {}",
+                other
+            ))),
+        }
+    }
+}
+
+impl IntoIterator for Patch {
+    type Item = Operation;
+    type IntoIter = std::vec::IntoIter<Operation>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.operations.into_iter()
+    }
+}
+
+impl std::fmt::Display for Patch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (i, operation) in self.operations.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", operation)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+#[cfg(feature = "encryption")]
+use crate::error::{NabuError, Result};
+#[cfg(feature = "encryption")]
+use argon2::{Algorithm, Argon2, Params, Version};
+#[cfg(feature = "encryption")]
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    Key, XChaCha20Poly1305, XNonce,
+};
+#[cfg(feature = "encryption")]
+use zeroize::Zeroizing;
+
+/// The first bytes of every encrypted file
+pub const MAGIC: &[u8; 8] = b"XFFCRYPT";
+
+/// Argon2id key derivation with XChaCha20-Poly1305 encryption
+#[cfg(feature = "encryption")]
+const ARGON2ID_XCHACHA20POLY1305: u8 = 1;
+
+#[cfg(feature = "encryption")]
+const SALT_LEN: usize = 16;
+
+#[cfg(feature = "encryption")]
+const KEY_CHECK_LEN: usize = 16;
+
+#[cfg(feature = "encryption")]
+const NONCE_LEN: usize = 24;
+
+/// Length of the header of the scheme: magic, scheme, 3 Argon2 parameters, salt, key check and
+/// nonce
+#[cfg(feature = "encryption")]
+const HEADER_LEN: usize = 8 + 1 + 12 + SALT_LEN + KEY_CHECK_LEN + NONCE_LEN;
+
+/// The largest Argon2 memory cost accepted when decrypting, in KiB
+///
+/// The header is read before anything is authenticated, so the costs are capped a few times
+/// above the ones `encrypt` uses (19 MiB), instead of letting any file demand gigabytes.
+#[cfg(feature = "encryption")]
+const MAX_MEMORY_COST: u32 = 64 * 1024;
+
+/// The largest Argon2 time cost accepted when decrypting, `encrypt` uses 2
+#[cfg(feature = "encryption")]
+const MAX_TIME_COST: u32 = 8;
+
+/// The largest Argon2 parallelism accepted when decrypting, `encrypt` uses 1
+#[cfg(feature = "encryption")]
+const MAX_PARALLELISM: u32 = 8;
+
+/// Returns `true` if the bytes start like an encrypted file
+pub fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Encrypts the bytes with a key derived from the passphrase
+///
+/// A new random salt and nonce are used for every call, encrypting the same bytes twice never
+/// produces the same output.
+///
+/// # Errors
+/// Returns IO errors if the operating system cannot provide random bytes
+#[cfg(feature = "encryption")]
+pub fn encrypt(plain: &[u8], passphrase: &[u8]) -> Result<Vec<u8>> {
+    let params = Params::default();
+    let mut random = [0u8; SALT_LEN + NONCE_LEN];
+    getrandom::getrandom(&mut random).map_err(|e| std::io::Error::other(e.to_string()))?;
+    let (salt, nonce) = random.split_at(SALT_LEN);
+    let derived = derive_key(passphrase, salt, &params)?;
+    let (key, key_check) = derived.split_at(32);
+
+    let mut out: Vec<u8> = Vec::with_capacity(HEADER_LEN + plain.len() + 16);
+    out.extend(MAGIC);
+    out.push(ARGON2ID_XCHACHA20POLY1305);
+    out.extend(params.m_cost().to_le_bytes());
+    out.extend(params.t_cost().to_le_bytes());
+    out.extend(params.p_cost().to_le_bytes());
+    out.extend(salt);
+    out.extend(key_check);
+    out.extend(nonce);
+    // the header is authenticated as well
+    let ciphertext = XChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: plain,
+                aad: &out,
+            },
+        )
+        .map_err(|_| NabuError::CorruptedEncryptedXFF)?;
+    out.extend(ciphertext);
+    Ok(out)
+}
+
+/// Decrypts bytes encrypted by `encrypt`
+///
+/// # Errors
+/// Returns `NabuError::WrongPassphrase` if the passphrase is not the one used to encrypt
+/// Returns `NabuError::CorruptedEncryptedXFF` if the bytes are not encrypted or were altered
+/// after encryption
+/// Returns `NabuError::UnknownEncryptionScheme` for schemes unknown to this implementation
+#[cfg(feature = "encryption")]
+pub fn decrypt(bytes: &[u8], passphrase: &[u8]) -> Result<Vec<u8>> {
+    if !is_encrypted(bytes) {
+        return Err(NabuError::CorruptedEncryptedXFF);
+    }
+    let scheme = *bytes
+        .get(MAGIC.len())
+        .ok_or(NabuError::CorruptedEncryptedXFF)?;
+    if scheme != ARGON2ID_XCHACHA20POLY1305 {
+        return Err(NabuError::UnknownEncryptionScheme(scheme));
+    }
+    if bytes.len() < HEADER_LEN {
+        return Err(NabuError::CorruptedEncryptedXFF);
+    }
+    let (header, ciphertext) = bytes.split_at(HEADER_LEN);
+    let cost = |pos: usize| {
+        u32::from_le_bytes([
+            header[pos],
+            header[pos + 1],
+            header[pos + 2],
+            header[pos + 3],
+        ])
+    };
+    let (m_cost, t_cost, p_cost) = (cost(9), cost(13), cost(17));
+    if m_cost > MAX_MEMORY_COST || t_cost > MAX_TIME_COST || p_cost > MAX_PARALLELISM {
+        return Err(NabuError::CorruptedEncryptedXFF);
+    }
+    let params =
+        Params::new(m_cost, t_cost, p_cost, None).map_err(|_| NabuError::CorruptedEncryptedXFF)?;
+    let salt = &header[21..21 + SALT_LEN];
+    let stored_check = &header[21 + SALT_LEN..21 + SALT_LEN + KEY_CHECK_LEN];
+    let nonce = &header[HEADER_LEN - NONCE_LEN..];
+
+    let derived = derive_key(passphrase, salt, &params)?;
+    let (key, key_check) = derived.split_at(32);
+    // compared in constant time
+    if key_check
+        .iter()
+        .zip(stored_check)
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        != 0
+    {
+        return Err(NabuError::WrongPassphrase);
+    }
+    XChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| NabuError::CorruptedEncryptedXFF)
+}
+
+/// Derives the 32 byte key followed by the key check from the passphrase
+///
+/// The key check is stored in the header to tell a wrong passphrase apart from altered bytes.
+/// Both are zeroed when dropped.
+#[cfg(feature = "encryption")]
+fn derive_key(
+    passphrase: &[u8],
+    salt: &[u8],
+    params: &Params,
+) -> Result<Zeroizing<[u8; 32 + KEY_CHECK_LEN]>> {
+    let mut derived = Zeroizing::new([0u8; 32 + KEY_CHECK_LEN]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params.clone())
+        .hash_password_into(passphrase, salt, derived.as_mut())
+        .map_err(|_| NabuError::CorruptedEncryptedXFF)?;
+    Ok(derived)
+}
@@ -0,0 +1,148 @@
+use std::io::Write;
+
+/// Length in bytes of a SHA-256 digest
+pub const DIGEST_LEN: usize = 32;
+
+/// Length in bytes of one block of SHA-256
+const BLOCK_LEN: usize = 64;
+
+/// First 32 bits of the fractional parts of the cube roots of the first 64 primes
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// First 32 bits of the fractional parts of the square roots of the first 8 primes
+const INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// An incremental SHA-256 (FIPS 180-4) hasher
+///
+/// Bytes can be fed in any number of pieces, by `update` or as a `std::io::Write`, the digest
+/// only depends on their concatenation.
+#[derive(Debug, Clone)]
+pub struct Sha256 {
+    state: [u32; 8],
+    /// Bytes not yet processed, less than one block
+    buffer: Vec<u8>,
+    /// Amount of bytes fed in total
+    len: u64,
+}
+
+impl Sha256 {
+    pub fn new() -> Self {
+        Sha256 {
+            state: INITIAL_STATE,
+            buffer: Vec::with_capacity(BLOCK_LEN),
+            len: 0,
+        }
+    }
+
+    /// Feeds the bytes into the hasher
+    pub fn update(&mut self, mut bytes: &[u8]) {
+        self.len = self.len.wrapping_add(bytes.len() as u64);
+        if !self.buffer.is_empty() {
+            let missing = (BLOCK_LEN - self.buffer.len()).min(bytes.len());
+            self.buffer.extend_from_slice(&bytes[..missing]);
+            bytes = &bytes[missing..];
+            if self.buffer.len() < BLOCK_LEN {
+                return;
+            }
+            let mut block = [0u8; BLOCK_LEN];
+            block.copy_from_slice(&self.buffer);
+            self.buffer.clear();
+            self.compress(&block);
+        }
+        let mut blocks = bytes.chunks_exact(BLOCK_LEN);
+        for block in blocks.by_ref() {
+            self.compress(block);
+        }
+        self.buffer.extend_from_slice(blocks.remainder());
+    }
+
+    /// Pads the fed bytes and returns their digest
+    pub fn finalize(mut self) -> [u8; DIGEST_LEN] {
+        let bit_len = self.len.wrapping_mul(8);
+        // a single 1 bit, zeros up to 8 bytes short of a full block, and the length in bits
+        let zeros = (2 * BLOCK_LEN - 8 - (self.buffer.len() + 1)) % BLOCK_LEN;
+        let mut padding = vec![0x80];
+        padding.resize(1 + zeros, 0);
+        padding.extend(bit_len.to_be_bytes());
+        self.update(&padding);
+
+        let mut out = [0u8; DIGEST_LEN];
+        for (chunk, word) in out.chunks_exact_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        out
+    }
+
+    /// Processes one block of `BLOCK_LEN` bytes
+    fn compress(&mut self, block: &[u8]) {
+        let mut schedule = [0u32; 64];
+        for (word, bytes) in schedule.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for i in 16..64 {
+            let s0 = schedule[i - 15].rotate_right(7)
+                ^ schedule[i - 15].rotate_right(18)
+                ^ (schedule[i - 15] >> 3);
+            let s1 = schedule[i - 2].rotate_right(17)
+                ^ schedule[i - 2].rotate_right(19)
+                ^ (schedule[i - 2] >> 10);
+            schedule[i] = schedule[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(schedule[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for (constant, word) in ROUND_CONSTANTS.iter().zip(schedule) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let temp1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(*constant)
+                .wrapping_add(word);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(majority);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+        }
+        for (state, word) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *state = state.wrapping_add(word);
+        }
+    }
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Write for Sha256 {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -1,3 +1,11 @@
+pub mod checksum;
+pub mod compression;
 pub mod deserializer;
+pub mod diff;
+pub mod encryption;
+pub mod hash;
+pub mod schema;
 pub mod serializer;
+#[cfg(feature = "serde")]
+pub mod serde_integration;
 pub mod value;
@@ -0,0 +1,460 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::{
+    error::NabuError,
+    xff::value::{num::compare_numbers, pointer_child, Array, Number, Object, XffValue},
+};
+
+pub use pattern::Pattern;
+
+pub mod pattern;
+
+/// The type of a `XffValue`, as allowed by a `Schema`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    /// `XffValue::String`
+    String,
+    /// `XffValue::Number`, of any variant
+    Number,
+    /// `XffValue::Data`
+    Data,
+    /// `XffValue::Array`
+    Array,
+    /// `XffValue::Object`
+    Object,
+    /// `XffValue::Boolean`
+    Boolean,
+    /// `XffValue::Null`
+    Null,
+    /// `XffValue::CommandCharacter` and `XffValue::ArrayCmdChar`, only used by the legacy v0
+    /// format
+    CommandCharacter,
+}
+
+impl ValueType {
+    /// Returns the type of the value
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::XffValue;
+    /// use nabu::schema::ValueType;
+    ///
+    /// assert_eq!(ValueType::of(&XffValue::from(42)), ValueType::Number);
+    /// assert_eq!(ValueType::of(&XffValue::Null), ValueType::Null);
+    /// ```
+    pub fn of(value: &XffValue) -> ValueType {
+        match value {
+            XffValue::String(_) => ValueType::String,
+            XffValue::Number(_) => ValueType::Number,
+            XffValue::Data(_) => ValueType::Data,
+            XffValue::Array(_) => ValueType::Array,
+            XffValue::Object(_) => ValueType::Object,
+            XffValue::Boolean(_) => ValueType::Boolean,
+            XffValue::Null => ValueType::Null,
+            XffValue::CommandCharacter(_) | XffValue::ArrayCmdChar(_) => {
+                ValueType::CommandCharacter
+            }
+        }
+    }
+
+    /// Returns the name of the type, as used in a schema stored as `XffValue`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValueType::String => "string",
+            ValueType::Number => "number",
+            ValueType::Data => "data",
+            ValueType::Array => "array",
+            ValueType::Object => "object",
+            ValueType::Boolean => "boolean",
+            ValueType::Null => "null",
+            ValueType::CommandCharacter => "command_character",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<ValueType> {
+        [
+            ValueType::String,
+            ValueType::Number,
+            ValueType::Data,
+            ValueType::Array,
+            ValueType::Object,
+            ValueType::Boolean,
+            ValueType::Null,
+            ValueType::CommandCharacter,
+        ]
+        .into_iter()
+        .find(|value_type| value_type.as_str() == name)
+    }
+}
+
+impl std::fmt::Display for ValueType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A value not matching a `Schema`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// The JSON Pointer of the value, the empty string for the validated value itself
+    pub path: String,
+    /// What is wrong with the value
+    pub message: String,
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "'{}': {}", self.path, self.message)
+    }
+}
+
+/// Describes the shape of a `XffValue`
+///
+/// Every constraint is optional, a `Schema::default()` accepts any value. Constraints only apply
+/// to values of the types they are meaningful for, `minimum` to numbers, `items` to arrays and so
+/// on.
+///
+/// A schema can be stored as a `XffValue` object itself and converted with `Schema::try_from`.
+/// Its keys are named like the fields below, with `types` either a single type name or an array
+/// of them, `pattern` a string and `items` and the values of `properties` schemas themselves:
+///
+/// ```text
+/// { "types": "object", "required": ["name"], "deny_unknown_keys": true,
+///   "properties": { "name": { "types": "string", "pattern": "^[a-z]+$" },
+///                   "age": { "types": ["number", "null"], "minimum": 0 } } }
+/// ```
+///
+/// # Example
+/// ```rust
+/// use nabu::XffValue;
+/// use nabu::schema::{Schema, ValueType};
+///
+/// let name = XffValue::from(vec![
+///     ("types", XffValue::from("string")),
+///     ("pattern", XffValue::from("^[a-z]+$")),
+/// ]);
+/// let age = XffValue::from(vec![("types", XffValue::from("number")), ("minimum", XffValue::from(0))]);
+/// let schema = Schema::try_from(XffValue::from(vec![
+///     ("types", XffValue::from("object")),
+///     ("required", XffValue::from(vec![XffValue::from("name"), XffValue::from("age")])),
+///     ("properties", XffValue::from(vec![("name", name), ("age", age)])),
+/// ])).unwrap();
+/// assert_eq!(schema.types, vec![ValueType::Object]);
+///
+/// let valid = XffValue::from(vec![("name", XffValue::from("alice")), ("age", XffValue::from(31))]);
+/// assert!(schema.is_valid(&valid));
+///
+/// let invalid = XffValue::from(vec![("name", XffValue::from("Alice")), ("age", XffValue::from(-1))]);
+/// let violations = schema.validate(&invalid);
+/// assert_eq!(violations.len(), 2);
+/// assert_eq!(violations[0].path, "/age");
+/// assert_eq!(violations[1].to_string(), "'/name': does not match the pattern ^[a-z]+$");
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Schema {
+    /// The allowed types, any type if empty
+    pub types: Vec<ValueType>,
+    /// The smallest allowed number, inclusive
+    pub minimum: Option<Number>,
+    /// The largest allowed number, inclusive
+    pub maximum: Option<Number>,
+    /// A pattern strings have to match
+    pub pattern: Option<Pattern>,
+    /// The smallest allowed length, counted in characters of a string, bytes of data, elements
+    /// of an array or keys of an object
+    pub min_length: Option<usize>,
+    /// The largest allowed length, counted like `min_length`
+    pub max_length: Option<usize>,
+    /// The schema of every element of an array
+    pub items: Option<Box<Schema>>,
+    /// The schemas of the values of keys in an object, the keys are not required to be present
+    pub properties: BTreeMap<String, Schema>,
+    /// Keys an object has to contain
+    pub required: Vec<String>,
+    /// Forbids keys in an object not listed in `properties`
+    pub deny_unknown_keys: bool,
+}
+
+impl Schema {
+    /// Creates a schema accepting any value
+    pub fn new() -> Self {
+        Schema::default()
+    }
+
+    /// Validates the value against the schema
+    ///
+    /// Returns every violation found, with the path of the offending value, or an empty vector if
+    /// the value is valid.
+    pub fn validate(&self, value: &XffValue) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.validate_at(value, String::new(), &mut violations);
+        violations
+    }
+
+    /// Returns `true` if the value matches the schema
+    pub fn is_valid(&self, value: &XffValue) -> bool {
+        self.validate(value).is_empty()
+    }
+
+    fn validate_at(&self, value: &XffValue, path: String, violations: &mut Vec<Violation>) {
+        let value_type = ValueType::of(value);
+        if !self.types.is_empty() && !self.types.contains(&value_type) {
+            let expected: Vec<&str> = self.types.iter().map(ValueType::as_str).collect();
+            violation(
+                violations,
+                &path,
+                format!("expected {}, found {}", expected.join(" or "), value_type),
+            );
+            return;
+        }
+        let len = match value {
+            XffValue::String(s) => Some(s.chars().count()),
+            XffValue::Data(d) => Some(d.len()),
+            XffValue::Array(a) => Some(a.len()),
+            XffValue::Object(o) => Some(o.len()),
+            _ => None,
+        };
+        if let Some(len) = len {
+            if let Some(min) = self.min_length.filter(|min| len < *min) {
+                violation(
+                    violations,
+                    &path,
+                    format!("length {} is less than {}", len, min),
+                );
+            }
+            if let Some(max) = self.max_length.filter(|max| len > *max) {
+                violation(
+                    violations,
+                    &path,
+                    format!("length {} is more than {}", len, max),
+                );
+            }
+        }
+        match value {
+            XffValue::Number(num) => {
+                if let Some(min) = &self.minimum {
+                    if !matches!(
+                        compare_numbers(num, min),
+                        Some(Ordering::Greater | Ordering::Equal)
+                    ) {
+                        violation(violations, &path, format!("{} is less than {}", num, min));
+                    }
+                }
+                if let Some(max) = &self.maximum {
+                    if !matches!(
+                        compare_numbers(num, max),
+                        Some(Ordering::Less | Ordering::Equal)
+                    ) {
+                        violation(violations, &path, format!("{} is more than {}", num, max));
+                    }
+                }
+            }
+            XffValue::String(s) => {
+                if let Some(pattern) = self.pattern.as_ref().filter(|p| !p.is_match(s)) {
+                    violation(
+                        violations,
+                        &path,
+                        format!("does not match the pattern {}", pattern),
+                    );
+                }
+            }
+            XffValue::Array(array) => {
+                if let Some(items) = &self.items {
+                    for (i, element) in array.iter().enumerate() {
+                        items.validate_at(element, format!("{}/{}", path, i), violations);
+                    }
+                }
+            }
+            XffValue::Object(object) => {
+                // required keys in their order, all present keys in the order of the object
+                for key in self.required.iter().filter(|key| !object.contains_key(key)) {
+                    violation(
+                        violations,
+                        &pointer_child(&path, key),
+                        "missing required key".to_string(),
+                    );
+                }
+                for (key, value) in object.iter() {
+                    match self.properties.get(key) {
+                        Some(schema) => {
+                            schema.validate_at(value, pointer_child(&path, key), violations)
+                        }
+                        None if self.deny_unknown_keys => violation(
+                            violations,
+                            &pointer_child(&path, key),
+                            "unknown key".to_string(),
+                        ),
+                        None => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn violation(violations: &mut Vec<Violation>, path: &str, message: String) {
+    violations.push(Violation {
+        path: path.to_string(),
+        message,
+    });
+}
+
+// -----------------------------------------------------------
+//                     Conversions
+// -----------------------------------------------------------
+
+impl From<Schema> for XffValue {
+    fn from(schema: Schema) -> Self {
+        let mut object = Object::new();
+        match schema.types.len() {
+            0 => {}
+            1 => object.insert("types", schema.types[0].as_str()),
+            _ => object.insert(
+                "types",
+                schema
+                    .types
+                    .iter()
+                    .map(|t| XffValue::from(t.as_str()))
+                    .collect::<Vec<XffValue>>(),
+            ),
+        }
+        if let Some(min) = schema.minimum {
+            object.insert("minimum", XffValue::Number(min));
+        }
+        if let Some(max) = schema.maximum {
+            object.insert("maximum", XffValue::Number(max));
+        }
+        if let Some(pattern) = schema.pattern {
+            object.insert("pattern", pattern.as_str());
+        }
+        if let Some(min) = schema.min_length {
+            object.insert("min_length", min);
+        }
+        if let Some(max) = schema.max_length {
+            object.insert("max_length", max);
+        }
+        if let Some(items) = schema.items {
+            object.insert("items", *items);
+        }
+        if !schema.properties.is_empty() {
+            let properties: Object = schema
+                .properties
+                .into_iter()
+                .map(|(key, schema)| (key, XffValue::from(schema)))
+                .collect::<BTreeMap<String, XffValue>>()
+                .into();
+            object.insert("properties", properties);
+        }
+        if !schema.required.is_empty() {
+            object.insert(
+                "required",
+                schema
+                    .required
+                    .into_iter()
+                    .map(XffValue::from)
+                    .collect::<Vec<XffValue>>(),
+            );
+        }
+        if schema.deny_unknown_keys {
+            object.insert("deny_unknown_keys", true);
+        }
+        XffValue::from(object)
+    }
+}
+
+impl TryFrom<XffValue> for Schema {
+    type Error = NabuError;
+
+    /// Converts a schema stored as `XffValue`
+    ///
+    /// # Errors
+    /// Returns `NabuError::InvalidSchema` starting with the path of the offending key, if the
+    /// value is not an object, has unknown keys or a key holds a value of the wrong type
+    fn try_from(value: XffValue) -> Result<Self, Self::Error> {
+        schema_from_value(value, "")
+    }
+}
+
+fn schema_from_value(value: XffValue, path: &str) -> Result<Schema, NabuError> {
+    let error = |path: &str, msg: &str| NabuError::InvalidSchema(format!("'{}': {}", path, msg));
+    let object = value
+        .into_object()
+        .ok_or_else(|| error(path, "expected an object"))?;
+    let mut schema = Schema::default();
+    for (key, value) in object.map {
+        let path = &pointer_child(path, &key);
+        let length = |value: XffValue| {
+            value
+                .into_number()
+                .and_then(|num| match num {
+                    Number::Unsigned(u) => Some(u),
+                    Number::Integer(i) => usize::try_from(i).ok(),
+                    _ => None,
+                })
+                .ok_or_else(|| error(path, "expected an unsigned integer"))
+        };
+        let strings = |value: XffValue| -> Result<Vec<String>, NabuError> {
+            let values = match value {
+                XffValue::String(s) => vec![XffValue::String(s)],
+                XffValue::Array(Array { values }) => values,
+                _ => return Err(error(path, "expected a string or an array of strings")),
+            };
+            values
+                .into_iter()
+                .map(|v| {
+                    v.into_string()
+                        .ok_or_else(|| error(path, "expected a string or an array of strings"))
+                })
+                .collect()
+        };
+        match key.as_str() {
+            "types" => {
+                schema.types = strings(value)?
+                    .iter()
+                    .map(|name| {
+                        ValueType::from_name(name)
+                            .ok_or_else(|| error(path, &format!("unknown type '{}'", name)))
+                    })
+                    .collect::<Result<_, _>>()?
+            }
+            "minimum" | "maximum" => {
+                let num = value
+                    .into_number()
+                    .ok_or_else(|| error(path, "expected a number"))?;
+                if key == "minimum" {
+                    schema.minimum = Some(num);
+                } else {
+                    schema.maximum = Some(num);
+                }
+            }
+            "pattern" => {
+                let pattern = value
+                    .into_string()
+                    .ok_or_else(|| error(path, "expected a string"))?;
+                schema.pattern = Some(Pattern::parse(&pattern)?);
+            }
+            "min_length" => schema.min_length = Some(length(value)?),
+            "max_length" => schema.max_length = Some(length(value)?),
+            "items" => schema.items = Some(Box::new(schema_from_value(value, path)?)),
+            "properties" => {
+                let properties = value
+                    .into_object()
+                    .ok_or_else(|| error(path, "expected an object"))?;
+                for (key, value) in properties.map {
+                    let property = schema_from_value(value, &pointer_child(path, &key))?;
+                    schema.properties.insert(key, property);
+                }
+            }
+            "required" => schema.required = strings(value)?,
+            "deny_unknown_keys" => {
+                schema.deny_unknown_keys = value
+                    .into_boolean()
+                    .ok_or_else(|| error(path, "expected a boolean"))?
+            }
+            _ => return Err(error(path, "unknown key")),
+        }
+    }
+    Ok(schema)
+}
@@ -0,0 +1,509 @@
+use std::str::FromStr;
+
+use crate::error::NabuError;
+
+/// The maximum number of instructions a pattern compiles to, bounding counted repetitions
+const PROGRAM_LIMIT: usize = 10_000;
+
+/// The maximum nesting depth of groups, bounding the recursion of the parser and compiler
+const NESTING_LIMIT: usize = 64;
+
+/// A compiled string pattern, a subset of regular expressions
+///
+/// A pattern matches a string if it matches any part of it, use `^` and `$` to anchor it to the
+/// start and end of the string. Supported are:
+///
+/// * `a` - The character itself, special characters are escaped with `\`
+/// * `.` - Any character
+/// * `[a-z_]`, `[^0-9]` - Any character in, or not in, the set of characters and ranges
+/// * `\d`, `\w`, `\s` - Any digit, word character or whitespace, `\D`, `\W` and `\S` for any other
+/// * `^`, `$` - The start and end of the string
+/// * `(a|b)` - A group of alternatives, `|` works outside of groups as well
+/// * `*`, `+`, `?`, `{2}`, `{2,}`, `{2,5}` - Repeats the preceding item
+///
+/// Matching takes time linear in the length of the string, no matter the pattern. Patterns
+/// compiling to more than 10 000 instructions, repeating anything more than 10 000 times or
+/// nesting groups more than 64 deep are rejected, so that parsing a pattern from an untrusted
+/// schema is bounded as well.
+///
+/// # Example
+/// ```rust
+/// use nabu::schema::Pattern;
+///
+/// let pattern = Pattern::parse(r"^[a-z]+@(example\.com|example\.org)$").unwrap();
+/// assert!(pattern.is_match("alice@example.com"));
+/// assert!(!pattern.is_match("alice@example.net"));
+/// assert!(!pattern.is_match("Alice@example.org"));
+///
+/// assert!(Pattern::parse("(a|b").is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    source: String,
+    program: Vec<Inst>,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    /// Alternatives, each a sequence of nodes
+    Group(Vec<Vec<Node>>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Class {
+    items: Vec<ClassItem>,
+    negated: bool,
+}
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Range(char, char),
+    /// A shorthand like `\d`, negated for `\D`
+    Shorthand(Shorthand, bool),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Shorthand {
+    Digit,
+    Word,
+    Space,
+}
+
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+impl Pattern {
+    /// Parses and compiles the pattern
+    ///
+    /// # Errors
+    /// Returns `NabuError::InvalidSchema` if the pattern is malformed or too large
+    pub fn parse(pattern: &str) -> Result<Pattern, NabuError> {
+        let mut parser = Parser {
+            pattern,
+            chars: pattern.chars().collect(),
+            pos: 0,
+            depth: 0,
+        };
+        let alternatives = parser.alternatives()?;
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error("unmatched ')'"));
+        }
+        let mut program = Vec::new();
+        emit(&Node::Group(alternatives), &mut program)
+            .map_err(|_| NabuError::InvalidSchema(format!("pattern '{}' is too large", pattern)))?;
+        program.push(Inst::Match);
+        Ok(Pattern {
+            source: pattern.to_string(),
+            program,
+        })
+    }
+
+    /// Returns the pattern as it was written
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns `true` if the pattern matches any part of the text
+    pub fn is_match(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        let mut current = Vec::new();
+        let mut next = Vec::new();
+        // the position each instruction was last added at, so every one runs once per position
+        let mut seen = vec![usize::MAX; self.program.len()];
+        for pos in 0..=chars.len() {
+            if self.add_thread(&mut current, &mut seen, 0, pos, chars.len()) {
+                return true;
+            }
+            for &pc in &current {
+                let matched = chars.get(pos).is_some_and(|&c| match &self.program[pc] {
+                    Inst::Char(expected) => c == *expected,
+                    Inst::Any => true,
+                    Inst::Class(class) => class.matches(c),
+                    _ => false,
+                });
+                if matched && self.add_thread(&mut next, &mut seen, pc + 1, pos + 1, chars.len()) {
+                    return true;
+                }
+            }
+            std::mem::swap(&mut current, &mut next);
+            next.clear();
+        }
+        false
+    }
+
+    /// Follows all jumps and assertions from the instruction, adding the instructions consuming
+    /// a character to the list
+    ///
+    /// Returns `true` if the pattern matched
+    fn add_thread(
+        &self,
+        list: &mut Vec<usize>,
+        seen: &mut [usize],
+        pc: usize,
+        pos: usize,
+        len: usize,
+    ) -> bool {
+        let mut stack = vec![pc];
+        while let Some(pc) = stack.pop() {
+            if seen[pc] == pos {
+                continue;
+            }
+            seen[pc] = pos;
+            match self.program[pc] {
+                Inst::Jump(to) => stack.push(to),
+                Inst::Split(first, second) => {
+                    stack.push(second);
+                    stack.push(first);
+                }
+                Inst::Start if pos == 0 => stack.push(pc + 1),
+                Inst::End if pos == len => stack.push(pc + 1),
+                Inst::Start | Inst::End => {}
+                Inst::Match => return true,
+                _ => list.push(pc),
+            }
+        }
+        false
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = NabuError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Pattern::parse(s)
+    }
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Class {
+    fn matches(&self, c: char) -> bool {
+        let found = self.items.iter().any(|item| match item {
+            ClassItem::Range(from, to) => (*from..=*to).contains(&c),
+            ClassItem::Shorthand(shorthand, negated) => shorthand.matches(c) != *negated,
+        });
+        found != self.negated
+    }
+}
+
+impl Shorthand {
+    fn matches(self, c: char) -> bool {
+        match self {
+            Shorthand::Digit => c.is_ascii_digit(),
+            Shorthand::Word => c.is_ascii_alphanumeric() || c == '_',
+            Shorthand::Space => c.is_whitespace(),
+        }
+    }
+}
+
+// -----------------------------------------------------------
+//                          Compiler
+// -----------------------------------------------------------
+
+fn emit(node: &Node, program: &mut Vec<Inst>) -> Result<(), ()> {
+    if program.len() > PROGRAM_LIMIT {
+        return Err(());
+    }
+    match node {
+        Node::Char(c) => program.push(Inst::Char(*c)),
+        Node::Any => program.push(Inst::Any),
+        Node::Class(class) => program.push(Inst::Class(class.clone())),
+        Node::Start => program.push(Inst::Start),
+        Node::End => program.push(Inst::End),
+        Node::Group(alternatives) => {
+            let mut jumps = Vec::new();
+            for (i, alternative) in alternatives.iter().enumerate() {
+                let split = (i + 1 < alternatives.len()).then(|| {
+                    program.push(Inst::Split(0, 0));
+                    program.len() - 1
+                });
+                let start = program.len();
+                for node in alternative {
+                    emit(node, program)?;
+                }
+                if let Some(split) = split {
+                    program.push(Inst::Jump(0));
+                    jumps.push(program.len() - 1);
+                    program[split] = Inst::Split(start, program.len());
+                }
+            }
+            for jump in jumps {
+                program[jump] = Inst::Jump(program.len());
+            }
+        }
+        Node::Repeat { node, min, max } => {
+            // repeating nothing is nothing, no matter how often
+            if emits_nothing(node) {
+                return Ok(());
+            }
+            for _ in 0..*min {
+                emit(node, program)?;
+            }
+            match max {
+                None => {
+                    let split = program.len();
+                    program.push(Inst::Split(0, 0));
+                    emit(node, program)?;
+                    program.push(Inst::Jump(split));
+                    program[split] = Inst::Split(split + 1, program.len());
+                }
+                Some(max) => {
+                    let mut splits = Vec::new();
+                    for _ in *min..*max {
+                        splits.push(program.len());
+                        program.push(Inst::Split(0, 0));
+                        emit(node, program)?;
+                    }
+                    for split in splits {
+                        program[split] = Inst::Split(split + 1, program.len());
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns `true` if the node compiles to no instructions at all, like an empty group
+fn emits_nothing(node: &Node) -> bool {
+    match node {
+        Node::Group(alternatives) => {
+            alternatives.len() == 1 && alternatives[0].iter().all(emits_nothing)
+        }
+        Node::Repeat { node, .. } => emits_nothing(node),
+        _ => false,
+    }
+}
+
+// -----------------------------------------------------------
+//                          Parser
+// -----------------------------------------------------------
+
+struct Parser<'a> {
+    pattern: &'a str,
+    chars: Vec<char>,
+    /// Position of the next character
+    pos: usize,
+    /// Amount of groups the next character is in
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, msg: &str) -> NabuError {
+        NabuError::InvalidSchema(format!(
+            "pattern '{}': {} at character position {}",
+            self.pattern, msg, self.pos
+        ))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        if c.is_some() {
+            self.pos += 1;
+        }
+        c
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn alternatives(&mut self) -> Result<Vec<Vec<Node>>, NabuError> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.eat('|') {
+            alternatives.push(self.sequence()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Node>, NabuError> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let node = self.atom()?;
+            nodes.push(self.quantified(node)?);
+        }
+        Ok(nodes)
+    }
+
+    fn atom(&mut self) -> Result<Node, NabuError> {
+        match self.next() {
+            Some('(') => {
+                if self.depth == NESTING_LIMIT {
+                    return Err(self.error("groups nested too deeply"));
+                }
+                self.depth += 1;
+                let alternatives = self.alternatives()?;
+                if !self.eat(')') {
+                    return Err(self.error("expected ')'"));
+                }
+                self.depth -= 1;
+                Ok(Node::Group(alternatives))
+            }
+            Some('[') => self.class(),
+            Some('.') => Ok(Node::Any),
+            Some('^') => Ok(Node::Start),
+            Some('$') => Ok(Node::End),
+            Some('\\') => match self.escape()? {
+                ClassItem::Range(c, _) => Ok(Node::Char(c)),
+                item => Ok(Node::Class(Class {
+                    items: vec![item],
+                    negated: false,
+                })),
+            },
+            Some('*' | '+' | '?' | '{') => {
+                self.pos -= 1;
+                Err(self.error("nothing to repeat"))
+            }
+            Some(c) => Ok(Node::Char(c)),
+            None => Err(self.error("unexpected end")),
+        }
+    }
+
+    /// Parses an escaped character after the `\`, a shorthand or a single character
+    fn escape(&mut self) -> Result<ClassItem, NabuError> {
+        let item = match self.next() {
+            Some('d') => ClassItem::Shorthand(Shorthand::Digit, false),
+            Some('D') => ClassItem::Shorthand(Shorthand::Digit, true),
+            Some('w') => ClassItem::Shorthand(Shorthand::Word, false),
+            Some('W') => ClassItem::Shorthand(Shorthand::Word, true),
+            Some('s') => ClassItem::Shorthand(Shorthand::Space, false),
+            Some('S') => ClassItem::Shorthand(Shorthand::Space, true),
+            Some('n') => ClassItem::Range('\n', '\n'),
+            Some('t') => ClassItem::Range('\t', '\t'),
+            Some(c) if !c.is_ascii_alphanumeric() => ClassItem::Range(c, c),
+            Some(_) => {
+                self.pos -= 1;
+                return Err(self.error("unknown escape"));
+            }
+            None => return Err(self.error("unexpected end")),
+        };
+        Ok(item)
+    }
+
+    /// Parses a character class after the `[`
+    fn class(&mut self) -> Result<Node, NabuError> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        loop {
+            let from = match self.next() {
+                Some(']') if !items.is_empty() => break,
+                Some('\\') => match self.escape()? {
+                    ClassItem::Range(c, _) => c,
+                    item => {
+                        items.push(item);
+                        continue;
+                    }
+                },
+                Some(c) => c,
+                None => return Err(self.error("expected ']'")),
+            };
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                self.pos += 1;
+                let to = match self.next() {
+                    Some('\\') => match self.escape()? {
+                        ClassItem::Range(c, _) => c,
+                        _ => return Err(self.error("invalid range")),
+                    },
+                    Some(c) => c,
+                    None => return Err(self.error("expected ']'")),
+                };
+                if to < from {
+                    return Err(self.error("invalid range"));
+                }
+                items.push(ClassItem::Range(from, to));
+            } else {
+                items.push(ClassItem::Range(from, from));
+            }
+        }
+        Ok(Node::Class(Class { items, negated }))
+    }
+
+    fn quantified(&mut self, node: Node) -> Result<Node, NabuError> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.pos += 1;
+                let min = self.count()?;
+                let max = if self.eat(',') {
+                    if self.peek() == Some('}') {
+                        None
+                    } else {
+                        Some(self.count()?)
+                    }
+                } else {
+                    Some(min)
+                };
+                if self.peek() != Some('}') || max.is_some_and(|max| max < min) {
+                    return Err(self.error("invalid repetition"));
+                }
+                // every repetition compiles to at least one instruction
+                if max.unwrap_or(min) > PROGRAM_LIMIT {
+                    return Err(self.error("repetition too large"));
+                }
+                (min, max)
+            }
+            _ => return Ok(node),
+        };
+        if matches!(node, Node::Start | Node::End) {
+            return Err(self.error("nothing to repeat"));
+        }
+        self.pos += 1;
+        Ok(Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+        })
+    }
+
+    fn count(&mut self) -> Result<usize, NabuError> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse().map_err(|_| self.error("invalid repetition"))
+    }
+}
@@ -0,0 +1,457 @@
+use serde::de::{
+    self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess,
+    Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use crate::{
+    error::{NabuError, Result},
+    xff::{
+        checksum::verify_trailer,
+        compression::CMP,
+        deserializer::{
+            cursor::ValueCursor,
+            options::{DecodeLimits, DecodeOptions},
+            v1::{decode_xff_v1_number, decode_xff_v1_string},
+            v2::decode_xff_v2_number,
+        },
+        serde_integration::de::{visit_number, KeyDeserializer, XffValueDeserializer},
+    },
+};
+
+/// Deserializes any `Deserialize` type directly out of a v1 or v2 XFF byte stream, without
+/// decoding it into a XffValue first
+///
+/// `Data` and, in v2, `String`s are borrowed from the byte stream where the type allows it.
+pub struct XffBytesDeserializer<'de> {
+    input: &'de [u8],
+    /// Amount of bytes consumed, also the position of the next byte
+    byte_pos: usize,
+    version: u8,
+    limits: DecodeLimits,
+}
+
+impl<'de> XffBytesDeserializer<'de> {
+    /// Creates a new deserializer over a complete v1 or v2 byte stream, starting with the version
+    /// byte
+    ///
+    /// An announced integrity trailer is verified before anything is decoded.
+    ///
+    /// # Errors
+    /// Returns `NabuError::EmpthyXFF` for empty input and `NabuError::UnknownXFFVersion` for any
+    /// other version
+    /// Returns `NabuError::ChecksumMismatch` if a v2 byte stream does not match its integrity
+    /// trailer
+    pub fn new(input: &'de [u8]) -> Result<Self> {
+        XffBytesDeserializer::with_options(input, &DecodeOptions::default())
+    }
+
+    /// Creates a new deserializer like `new`, decoding within the limits of the options
+    ///
+    /// The lengths of all values, the keys of all objects and the depth are checked, and the
+    /// content of every string, number and data counts towards `max_total_bytes`. What the type
+    /// allocates for itself is not counted.
+    ///
+    /// # Errors
+    /// Errors like `new`
+    pub fn with_options(input: &'de [u8], options: &DecodeOptions) -> Result<Self> {
+        match input.first() {
+            Some(1) => Ok(XffBytesDeserializer {
+                input,
+                byte_pos: 1,
+                version: 1,
+                limits: DecodeLimits::new(options),
+            }),
+            Some(2) => {
+                let (input, value_pos) = verify_trailer(input)?;
+                Ok(XffBytesDeserializer {
+                    input,
+                    byte_pos: value_pos,
+                    version: 2,
+                    limits: DecodeLimits::new(options),
+                })
+            }
+            Some(version) => Err(NabuError::UnknownXFFVersion(*version)),
+            None => Err(NabuError::EmpthyXFF),
+        }
+    }
+
+    /// Checks that the complete value was consumed and the byte stream ends with `EM`
+    pub fn end(&mut self) -> Result<()> {
+        match self.next() {
+            Some(25) => Ok(()),
+            _ => Err(NabuError::TruncatedXFF(self.byte_pos)),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.byte_pos).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let out = self.peek();
+        if out.is_some() {
+            self.byte_pos += 1;
+        }
+        out
+    }
+
+    fn expect_byte(&mut self) -> Result<u8> {
+        self.next().ok_or(NabuError::TruncatedXFF(self.byte_pos))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'de [u8]> {
+        let end = self
+            .byte_pos
+            .checked_add(len)
+            .filter(|end| *end <= self.input.len())
+            .ok_or(NabuError::TruncatedXFF(self.input.len()))?;
+        let out = &self.input[self.byte_pos..end];
+        self.byte_pos = end;
+        Ok(out)
+    }
+
+    fn expect_ev(&mut self) -> Result<()> {
+        if self.peek() == Some(24) {
+            self.byte_pos += 1;
+            Ok(())
+        } else {
+            Err(NabuError::MissingEV(self.byte_pos))
+        }
+    }
+
+    /// Consumes a length attribute and checks it against the limits
+    fn length(&mut self) -> Result<usize> {
+        let value_pos = self.byte_pos;
+        let len_of_len = self.expect_byte()?;
+        if len_of_len > 8 {
+            return Err(NabuError::InvalidXFFValueLength(len_of_len.into()));
+        }
+        let len_bytes = self.take(len_of_len as usize)?;
+        let mut le_bytes = [0u8; 8];
+        le_bytes[..len_bytes.len()].copy_from_slice(len_bytes);
+        let len = usize::try_from(u64::from_le_bytes(le_bytes))
+            .map_err(|_| NabuError::InvalidXFFValueLength(usize::MAX))?;
+        self.limits.check_len(len, value_pos)?;
+        Ok(len)
+    }
+
+    /// Consumes the content of a string, number or data, accounting for it in the limits
+    fn content(&mut self, len: usize) -> Result<&'de [u8]> {
+        self.limits.allocate(len, self.byte_pos)?;
+        self.take(len)
+    }
+
+    /// Reads the length, content and `EV` of a string, the `TXT` byte is already consumed
+    fn string<V: Visitor<'de>>(&mut self, visitor: V) -> Result<V::Value> {
+        let len = self.length()?;
+        let str_pos = self.byte_pos;
+        let str_bytes = self.content(len)?;
+        self.expect_ev()?;
+        if self.version == 1 {
+            visitor.visit_string(decode_xff_v1_string(str_bytes.to_vec(), str_pos)?)
+        } else {
+            let s = std::str::from_utf8(str_bytes)
+                .map_err(|e| NabuError::InvalidUTF8String(str_pos + e.valid_up_to()))?;
+            visitor.visit_borrowed_str(s)
+        }
+    }
+
+    /// Reads a string into an owned `String`, the `TXT` byte is already consumed
+    fn owned_string(&mut self) -> Result<String> {
+        let len = self.length()?;
+        let str_pos = self.byte_pos;
+        let str_bytes = self.content(len)?;
+        self.expect_ev()?;
+        if self.version == 1 {
+            decode_xff_v1_string(str_bytes.to_vec(), str_pos)
+        } else {
+            String::from_utf8(str_bytes.to_vec())
+                .map_err(|e| NabuError::InvalidUTF8String(str_pos + e.utf8_error().valid_up_to()))
+        }
+    }
+
+    /// Reads the length, content and `EV` of a compressed value and decodes the value it
+    /// contains, the `CMP` byte is already consumed
+    ///
+    /// The decompressed bytes count towards the limits like they do for `from_bytes`. The value
+    /// is decoded into a XffValue first, nothing is borrowed from the decompressed bytes.
+    fn compressed(&mut self) -> Result<XffValueDeserializer> {
+        let len = self.length()?;
+        let content_pos = self.byte_pos;
+        let content = self.take(len)?;
+        self.expect_ev()?;
+        let mut cursor = ValueCursor::new(&[], 0, 2, self.limits);
+        let value = cursor.compressed(content, content_pos)?;
+        self.limits = cursor.limits;
+        Ok(XffValueDeserializer::new(value))
+    }
+
+    /// Consumes the separator after an element of an array or a key-value pair of an object
+    fn separator(&mut self, object: bool) -> Result<()> {
+        match self.peek() {
+            // RS, may be trailing
+            Some(30) => {
+                self.byte_pos += 1;
+                Ok(())
+            }
+            Some(24) => Ok(()),
+            Some(b) if object => Err(NabuError::InvalidObject(self.byte_pos, b)),
+            Some(b) => Err(NabuError::InvalidArray(self.byte_pos, b)),
+            None => Err(NabuError::TruncatedXFF(self.byte_pos)),
+        }
+    }
+
+    /// Checks the length of an array or object against its content and consumes its `EV`
+    fn end_container(&mut self, start_pos: usize, len: usize) -> Result<()> {
+        if self.peek() != Some(24) || self.byte_pos - start_pos != len {
+            return Err(NabuError::MissingEV(self.byte_pos));
+        }
+        self.byte_pos += 1;
+        Ok(())
+    }
+
+    fn expect_structure_byte(&mut self, expected: u8) -> Result<()> {
+        let b = self.expect_byte()?;
+        if b != expected {
+            return Err(NabuError::InvalidObject(self.byte_pos, b));
+        }
+        Ok(())
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut XffBytesDeserializer<'de> {
+    type Error = NabuError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let type_byte = self.expect_byte()?;
+        match type_byte {
+            0 => visitor.visit_unit(),
+            //TXT
+            1 => self.string(visitor),
+            2 => {
+                //NUM
+                let len = self.length()?;
+                let num_pos = self.byte_pos;
+                let num_bytes = self.content(len)?;
+                self.expect_ev()?;
+                let num = if self.version == 1 {
+                    decode_xff_v1_number(num_bytes.to_vec(), num_pos)?
+                } else {
+                    decode_xff_v2_number(num_bytes, num_pos)?
+                };
+                visit_number(num, visitor)
+            }
+            3 => {
+                //ARY
+                self.limits.enter(self.byte_pos - 1)?;
+                let len = self.length()?;
+                let start_pos = self.byte_pos;
+                let out = visitor.visit_seq(BytesArrayAccess { de: &mut *self })?;
+                self.end_container(start_pos, len)?;
+                self.limits.leave();
+                Ok(out)
+            }
+            4 => {
+                //OBJ
+                self.limits.enter(self.byte_pos - 1)?;
+                let len = self.length()?;
+                let start_pos = self.byte_pos;
+                let out = visitor.visit_map(BytesObjectAccess {
+                    de: &mut *self,
+                    keys: 0,
+                })?;
+                self.end_container(start_pos, len)?;
+                self.limits.leave();
+                Ok(out)
+            }
+            5 => {
+                //DAT
+                let len = self.length()?;
+                let data = self.content(len)?;
+                self.expect_ev()?;
+                visitor.visit_borrowed_bytes(data)
+            }
+            // only in v2
+            CMP if self.version == 2 => self.compressed()?.deserialize_any(visitor),
+            //TRU
+            16 => visitor.visit_bool(true),
+            //FAL
+            17 => visitor.visit_bool(false),
+            _ => Err(NabuError::InvalidXFFByte(
+                type_byte,
+                self.byte_pos,
+                self.version,
+            )),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.peek() == Some(0) {
+            self.byte_pos += 1;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let type_byte = self.expect_byte()?;
+        match type_byte {
+            //TXT, unit variant
+            1 => {
+                let variant = self.owned_string()?;
+                visitor.visit_enum(IntoDeserializer::<NabuError>::into_deserializer(variant))
+            }
+            //OBJ, with the variant as its only key
+            4 => {
+                self.limits.enter(self.byte_pos - 1)?;
+                let len = self.length()?;
+                let start_pos = self.byte_pos;
+                // GS
+                self.expect_structure_byte(29)?;
+                let key_type = self.expect_byte()?;
+                if key_type != 1 {
+                    return Err(NabuError::InvalidObject(self.byte_pos, key_type));
+                }
+                let variant = self.owned_string()?;
+                // US
+                self.expect_structure_byte(31)?;
+                let out = visitor.visit_enum(BytesVariantAccess {
+                    de: &mut *self,
+                    variant,
+                })?;
+                // Trailing GS
+                self.expect_structure_byte(29)?;
+                self.separator(true)?;
+                self.end_container(start_pos, len)?;
+                self.limits.leave();
+                Ok(out)
+            }
+            CMP if self.version == 2 => {
+                self.compressed()?
+                    .deserialize_enum(name, variants, visitor)
+            }
+            _ => Err(NabuError::SerdeError(format!(
+                "expected a String or an Object for an enum, found type byte {} at byte position {}",
+                type_byte, self.byte_pos
+            ))),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct BytesArrayAccess<'a, 'de> {
+    de: &'a mut XffBytesDeserializer<'de>,
+}
+
+impl<'de> SeqAccess<'de> for BytesArrayAccess<'_, 'de> {
+    type Error = NabuError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match self.de.peek() {
+            Some(24) => Ok(None),
+            Some(_) => {
+                let out = seed.deserialize(&mut *self.de)?;
+                self.de.separator(false)?;
+                Ok(Some(out))
+            }
+            None => Err(NabuError::TruncatedXFF(self.de.byte_pos)),
+        }
+    }
+}
+
+struct BytesObjectAccess<'a, 'de> {
+    de: &'a mut XffBytesDeserializer<'de>,
+    /// Amount of keys read so far
+    keys: usize,
+}
+
+impl<'de> MapAccess<'de> for BytesObjectAccess<'_, 'de> {
+    type Error = NabuError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.de.peek() {
+            Some(24) => Ok(None),
+            Some(_) => {
+                self.keys += 1;
+                self.de.limits.check_keys(self.keys, self.de.byte_pos)?;
+                // GS
+                self.de.expect_structure_byte(29)?;
+                let key_type = self.de.expect_byte()?;
+                if key_type != 1 {
+                    return Err(NabuError::InvalidObject(self.de.byte_pos, key_type));
+                }
+                let key = self.de.owned_string()?;
+                // US
+                self.de.expect_structure_byte(31)?;
+                seed.deserialize(KeyDeserializer::new(key)).map(Some)
+            }
+            None => Err(NabuError::TruncatedXFF(self.de.byte_pos)),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let out = seed.deserialize(&mut *self.de)?;
+        // Trailing GS
+        self.de.expect_structure_byte(29)?;
+        self.de.separator(true)?;
+        Ok(out)
+    }
+}
+
+struct BytesVariantAccess<'a, 'de> {
+    de: &'a mut XffBytesDeserializer<'de>,
+    variant: String,
+}
+
+impl<'de> EnumAccess<'de> for BytesVariantAccess<'_, 'de> {
+    type Error = NabuError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let variant = seed.deserialize(KeyDeserializer::new(self.variant.clone()))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for BytesVariantAccess<'_, 'de> {
+    type Error = NabuError;
+
+    fn unit_variant(self) -> Result<()> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(self.de, visitor)
+    }
+}
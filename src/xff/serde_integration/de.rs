@@ -0,0 +1,419 @@
+use std::collections::btree_map;
+
+use serde::de::{
+    self, Deserialize, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use crate::{
+    error::{NabuError, Result},
+    xff::value::{Array, Data, Number, Object, XffValue},
+};
+
+impl<'de> Deserialize<'de> for XffValue {
+    fn deserialize<D: de::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<XffValue, D::Error> {
+        deserializer.deserialize_any(XffValueVisitor)
+    }
+}
+
+struct XffValueVisitor;
+
+impl<'de> Visitor<'de> for XffValueVisitor {
+    type Value = XffValue;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "any value representable by a XffValue")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> std::result::Result<XffValue, E> {
+        Ok(XffValue::Boolean(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> std::result::Result<XffValue, E> {
+        Ok(XffValue::Number(Number::from(v as i128)))
+    }
+
+    fn visit_i128<E: de::Error>(self, v: i128) -> std::result::Result<XffValue, E> {
+        Ok(XffValue::Number(Number::from(v)))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> std::result::Result<XffValue, E> {
+        Ok(XffValue::Number(Number::from(v as u128)))
+    }
+
+    fn visit_u128<E: de::Error>(self, v: u128) -> std::result::Result<XffValue, E> {
+        Ok(XffValue::Number(Number::from(v)))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> std::result::Result<XffValue, E> {
+        Ok(XffValue::Number(Number::Float(v)))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<XffValue, E> {
+        Ok(XffValue::String(v.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> std::result::Result<XffValue, E> {
+        Ok(XffValue::String(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> std::result::Result<XffValue, E> {
+        Ok(XffValue::Data(Data::from(v.to_vec())))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> std::result::Result<XffValue, E> {
+        Ok(XffValue::Data(Data::from(v)))
+    }
+
+    fn visit_none<E: de::Error>(self) -> std::result::Result<XffValue, E> {
+        Ok(XffValue::Null)
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<XffValue, D::Error> {
+        Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<XffValue, E> {
+        Ok(XffValue::Null)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<XffValue, A::Error> {
+        let mut values: Vec<XffValue> = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(XffValue::Array(Array::from(values)))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<XffValue, A::Error> {
+        let mut object = Object::new();
+        while let Some((key, value)) = map.next_entry::<String, XffValue>()? {
+            object.insert(key, value);
+        }
+        Ok(XffValue::Object(object))
+    }
+}
+
+/// Visits a `Number` with the visitor method of its variant
+///
+/// serde has no decimal type, a `Decimal` is visited as the closest float.
+pub(crate) fn visit_number<'de, V: Visitor<'de>>(num: Number, visitor: V) -> Result<V::Value> {
+    match num {
+        Number::Unsigned(u) => visitor.visit_u64(u as u64),
+        Number::Integer(i) => visitor.visit_i64(i as i64),
+        Number::Float(f) => visitor.visit_f64(f),
+        Number::BigUnsigned(u) => visitor.visit_u128(u),
+        Number::BigInteger(i) => visitor.visit_i128(i),
+        Number::Decimal(d) => visitor.visit_f64(d.to_f64()),
+    }
+}
+
+/// Deserializes any `Deserialize` type out of a XffValue, taking ownership of it
+///
+/// The inverse of `XffValueSerializer`, see there for how types are mapped.
+pub struct XffValueDeserializer {
+    value: XffValue,
+}
+
+impl XffValueDeserializer {
+    pub fn new(value: XffValue) -> Self {
+        XffValueDeserializer { value }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, NabuError> for XffValue {
+    type Deserializer = XffValueDeserializer;
+
+    fn into_deserializer(self) -> XffValueDeserializer {
+        XffValueDeserializer::new(self)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for XffValueDeserializer {
+    type Error = NabuError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            XffValue::String(s) => visitor.visit_string(s),
+            XffValue::Number(n) => visit_number(n, visitor),
+            XffValue::Array(a) => {
+                let len = a.values.len();
+                let mut seq = ArrayAccess {
+                    iter: a.values.into_iter(),
+                };
+                let out = visitor.visit_seq(&mut seq)?;
+                if seq.iter.len() != 0 {
+                    return Err(de::Error::invalid_length(len, &"fewer elements in array"));
+                }
+                Ok(out)
+            }
+            XffValue::Object(o) => visitor.visit_map(ObjectAccess {
+                iter: o.map.into_iter(),
+                value: None,
+            }),
+            XffValue::Data(d) => visitor.visit_byte_buf(d.data),
+            XffValue::Boolean(b) => visitor.visit_bool(b),
+            XffValue::Null => visitor.visit_unit(),
+            XffValue::CommandCharacter(_) | XffValue::ArrayCmdChar(_) => Err(NabuError::SerdeError(
+                "command characters are only used in v0 and cannot be deserialized".to_string(),
+            )),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            XffValue::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            XffValue::Null => visitor.visit_unit(),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.value {
+            XffValue::String(variant) => visitor.visit_enum(VariantValueAccess {
+                variant,
+                value: None,
+            }),
+            XffValue::Object(o) if o.map.len() == 1 => {
+                let (variant, value) = o.map.into_iter().next().expect("length checked above");
+                visitor.visit_enum(VariantValueAccess {
+                    variant,
+                    value: Some(value),
+                })
+            }
+            other => Err(NabuError::SerdeError(format!(
+                "expected a String or an Object with exactly one key for an enum, found {}",
+                other
+            ))),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct ArrayAccess {
+    iter: std::vec::IntoIter<XffValue>,
+}
+
+impl<'de> SeqAccess<'de> for ArrayAccess {
+    type Error = NabuError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match self.iter.next() {
+            Some(value) => seed.deserialize(XffValueDeserializer::new(value)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct ObjectAccess {
+    iter: btree_map::IntoIter<String, XffValue>,
+    value: Option<XffValue>,
+}
+
+impl<'de> MapAccess<'de> for ObjectAccess {
+    type Error = NabuError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(KeyDeserializer::new(key)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        match self.value.take() {
+            Some(value) => seed.deserialize(XffValueDeserializer::new(value)),
+            None => Err(NabuError::SerdeError("object value without a key".to_string())),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct VariantValueAccess {
+    variant: String,
+    value: Option<XffValue>,
+}
+
+impl<'de> EnumAccess<'de> for VariantValueAccess {
+    type Error = NabuError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let variant = seed.deserialize(KeyDeserializer::new(self.variant.clone()))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for VariantValueAccess {
+    type Error = NabuError;
+
+    fn unit_variant(self) -> Result<()> {
+        match self.value {
+            None | Some(XffValue::Null) => Ok(()),
+            Some(other) => Err(NabuError::SerdeError(format!(
+                "expected no value for unit variant {}, found {}",
+                self.variant, other
+            ))),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        match self.value {
+            Some(value) => seed.deserialize(XffValueDeserializer::new(value)),
+            None => Err(NabuError::SerdeError(format!(
+                "expected a value for newtype variant {}",
+                self.variant
+            ))),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Some(value @ XffValue::Array(_)) => {
+                de::Deserializer::deserialize_any(XffValueDeserializer::new(value), visitor)
+            }
+            _ => Err(NabuError::SerdeError(format!(
+                "expected an Array for tuple variant {}",
+                self.variant
+            ))),
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.value {
+            Some(value @ XffValue::Object(_)) => {
+                de::Deserializer::deserialize_any(XffValueDeserializer::new(value), visitor)
+            }
+            _ => Err(NabuError::SerdeError(format!(
+                "expected an Object for struct variant {}",
+                self.variant
+            ))),
+        }
+    }
+}
+
+/// Deserializer for the keys of an `Object`
+///
+/// Keys are always strings, numbers and booleans are parsed back from their string form should
+/// the key type ask for them.
+pub(crate) struct KeyDeserializer {
+    key: String,
+}
+
+impl KeyDeserializer {
+    pub(crate) fn new(key: String) -> Self {
+        KeyDeserializer { key }
+    }
+}
+
+macro_rules! deserialize_parsed_key {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                match self.key.parse() {
+                    Ok(v) => visitor.$visit(v),
+                    Err(_) => visitor.visit_string(self.key),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer {
+    type Error = NabuError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(self.key)
+    }
+
+    deserialize_parsed_key! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_enum(IntoDeserializer::<NabuError>::into_deserializer(self.key))
+    }
+
+    forward_to_deserialize_any! {
+        f32 f64 char str string bytes byte_buf unit unit_struct seq tuple tuple_struct map
+        struct identifier ignored_any
+    }
+}
@@ -0,0 +1,134 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    error::Result,
+    xff::{
+        deserializer::{deserialize_xff_bytes_with_options, DecodeOptions},
+        serializer::serialize_xff,
+        value::XffValue,
+    },
+    XFF_VERSION,
+};
+
+pub mod bytes;
+use crate::xff::serde_integration::bytes::XffBytesDeserializer;
+pub mod de;
+use crate::xff::serde_integration::de::XffValueDeserializer;
+pub mod ser;
+use crate::xff::serde_integration::ser::XffValueSerializer;
+
+/// Converts any `Serialize` type into a XffValue
+///
+/// Structs and maps become `Object`s, sequences and tuples `Array`s, bytes (e.g. through
+/// `serde_bytes`) `Data` and unit or `None` `Null`.
+/// Enum variants are stored externally tagged: unit variants as a `String` holding their name,
+/// all other variants as an `Object` with their name as its only key.
+///
+/// # Arguments
+/// * `value` - The value to convert
+///
+/// # Errors
+/// Returns `NabuError::SerdeError` if the `Serialize` implementation errors or a map key is not a
+/// string, number, char or boolean
+///
+/// # Example
+/// ```rust
+/// use nabu::{to_xff, XffValue};
+/// use std::collections::BTreeMap;
+///
+/// let map = BTreeMap::from([("key", vec![Some(42), None])]);
+/// let value = to_xff(&map).unwrap();
+/// assert_eq!(value.into_object().unwrap()["key"], XffValue::from(vec![XffValue::from(42), XffValue::Null]));
+/// ```
+pub fn to_xff<T: ?Sized + Serialize>(value: &T) -> Result<XffValue> {
+    value.serialize(XffValueSerializer)
+}
+
+/// Converts a XffValue into any `Deserialize` type
+///
+/// The inverse of `to_xff`, see there for how types are mapped.
+///
+/// # Arguments
+/// * `value` - The XffValue to convert
+///
+/// # Errors
+/// Returns `NabuError::SerdeError` if the XffValue does not match the type
+///
+/// # Example
+/// ```rust
+/// use nabu::{from_xff, XffValue};
+///
+/// let value = XffValue::from(vec![XffValue::from("hello mom"), XffValue::from("hello dad")]);
+/// let strings: Vec<String> = from_xff(value).unwrap();
+/// assert_eq!(strings, vec!["hello mom", "hello dad"]);
+/// ```
+pub fn from_xff<T: DeserializeOwned>(value: XffValue) -> Result<T> {
+    T::deserialize(XffValueDeserializer::new(value))
+}
+
+/// Encodes any `Serialize` type into a XFF byte stream of the most recent version
+///
+/// The lengths of all arrays and objects have to be known before they can be written, so the
+/// value is converted into a XffValue first and then encoded.
+///
+/// # Arguments
+/// * `value` - The value to encode
+///
+/// # Errors
+/// Returns the errors of `to_xff` and any error encountered while encoding
+pub fn to_xff_bytes<T: ?Sized + Serialize>(value: &T) -> Result<Vec<u8>> {
+    serialize_xff(vec![to_xff(value)?], XFF_VERSION)
+}
+
+/// Decodes any `Deserialize` type out of a XFF byte stream
+///
+/// v1 and v2 byte streams are decoded directly into the type, without building a XffValue first.
+/// Types may borrow `&[u8]` and, for v2, `&str` from the byte stream.
+/// v0 byte streams are decoded into a XffValue first.
+///
+/// # Arguments
+/// * `bytes` - The complete XFF byte stream, starting with the version byte
+///
+/// # Errors
+/// Returns `NabuError::SerdeError` if the byte stream does not match the type
+/// Also returns any error encountered while decoding the byte stream
+///
+/// # Example
+/// ```rust
+/// use nabu::{from_xff_bytes, to_xff_bytes};
+///
+/// let bytes = to_xff_bytes(&("hello mom", 42, true)).unwrap();
+/// let decoded: (&str, u8, bool) = from_xff_bytes(&bytes).unwrap();
+/// assert_eq!(decoded, ("hello mom", 42, true));
+/// ```
+pub fn from_xff_bytes<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> Result<T> {
+    from_xff_bytes_with_options(bytes, &DecodeOptions::default())
+}
+
+/// Decodes any `Deserialize` type out of a XFF byte stream, within the limits of the options
+///
+/// # Arguments
+/// * `bytes` - The complete XFF byte stream, starting with the version byte
+/// * `options` - The limits to decode within
+///
+/// # Errors
+/// Returns any error of `from_xff_bytes`
+/// Also returns `NabuError::DepthLimitExceeded`, `NabuError::ValueLengthLimitExceeded`,
+/// `NabuError::KeyLimitExceeded` or `NabuError::AllocationLimitExceeded` once a limit is hit
+pub fn from_xff_bytes_with_options<'de, T: Deserialize<'de>>(
+    bytes: &'de [u8],
+    options: &DecodeOptions,
+) -> Result<T> {
+    match bytes.first() {
+        Some(1 | 2) => {
+            let mut deserializer = XffBytesDeserializer::with_options(bytes, options)?;
+            let out = T::deserialize(&mut deserializer)?;
+            deserializer.end()?;
+            Ok(out)
+        }
+        // v0, and errors for everything else
+        _ => T::deserialize(XffValueDeserializer::new(
+            deserialize_xff_bytes_with_options(bytes, options)?,
+        )),
+    }
+}
@@ -0,0 +1,527 @@
+use std::collections::BTreeMap;
+
+use serde::ser::{self, Serialize};
+
+use crate::{
+    error::{NabuError, Result},
+    xff::value::{Array, Data, Number, Object, XffValue},
+};
+
+/// Serializes any `Serialize` type into a XffValue
+///
+/// Structs and maps become `Object`s, sequences and tuples `Array`s, bytes `Data` and unit or
+/// `None` `Null`.
+/// Enum variants are stored externally tagged: unit variants as a `String` of their name, all
+/// other variants as an `Object` with the name of the variant as its only key.
+pub struct XffValueSerializer;
+
+impl Serialize for XffValue {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            XffValue::String(s) => serializer.serialize_str(s),
+            XffValue::Number(Number::Unsigned(u)) => serializer.serialize_u64(*u as u64),
+            XffValue::Number(Number::Integer(i)) => serializer.serialize_i64(*i as i64),
+            XffValue::Number(Number::Float(f)) => serializer.serialize_f64(*f),
+            XffValue::Number(Number::BigUnsigned(u)) => serializer.serialize_u128(*u),
+            XffValue::Number(Number::BigInteger(i)) => serializer.serialize_i128(*i),
+            XffValue::Number(Number::Decimal(d)) => serializer.serialize_f64(d.to_f64()),
+            XffValue::Array(a) => serializer.collect_seq(a.values.iter()),
+            XffValue::Object(o) => serializer.collect_map(o.map.iter()),
+            XffValue::Data(d) => serializer.serialize_bytes(&d.data),
+            XffValue::Boolean(b) => serializer.serialize_bool(*b),
+            XffValue::Null => serializer.serialize_unit(),
+            XffValue::CommandCharacter(_) | XffValue::ArrayCmdChar(_) => Err(ser::Error::custom(
+                "command characters are only used in v0 and cannot be serialized",
+            )),
+        }
+    }
+}
+
+impl ser::Serializer for XffValueSerializer {
+    type Ok = XffValue;
+    type Error = NabuError;
+
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeArrayVariant;
+    type SerializeMap = SerializeObject;
+    type SerializeStruct = SerializeObject;
+    type SerializeStructVariant = SerializeObjectVariant;
+
+    fn serialize_bool(self, v: bool) -> Result<XffValue> {
+        Ok(XffValue::Boolean(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<XffValue> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<XffValue> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<XffValue> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<XffValue> {
+        Ok(XffValue::Number(Number::from(v as i128)))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<XffValue> {
+        Ok(XffValue::Number(Number::from(v)))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<XffValue> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<XffValue> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<XffValue> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<XffValue> {
+        Ok(XffValue::Number(Number::from(v as u128)))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<XffValue> {
+        Ok(XffValue::Number(Number::from(v)))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<XffValue> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<XffValue> {
+        Ok(XffValue::Number(Number::Float(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<XffValue> {
+        Ok(XffValue::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<XffValue> {
+        Ok(XffValue::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<XffValue> {
+        Ok(XffValue::Data(Data::from(v.to_vec())))
+    }
+
+    fn serialize_none(self) -> Result<XffValue> {
+        Ok(XffValue::Null)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<XffValue> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<XffValue> {
+        Ok(XffValue::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<XffValue> {
+        Ok(XffValue::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<XffValue> {
+        Ok(XffValue::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<XffValue> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<XffValue> {
+        let mut map = BTreeMap::new();
+        map.insert(variant.to_string(), value.serialize(XffValueSerializer)?);
+        Ok(XffValue::from(map))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray> {
+        Ok(SerializeArray {
+            values: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeArray> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeArrayVariant> {
+        Ok(SerializeArrayVariant {
+            variant,
+            values: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeObject> {
+        Ok(SerializeObject {
+            map: BTreeMap::new(),
+            next_key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeObject> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SerializeObjectVariant> {
+        Ok(SerializeObjectVariant {
+            variant,
+            map: BTreeMap::new(),
+        })
+    }
+}
+
+pub struct SerializeArray {
+    values: Vec<XffValue>,
+}
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = XffValue;
+    type Error = NabuError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.values.push(value.serialize(XffValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<XffValue> {
+        Ok(XffValue::Array(Array::from(self.values)))
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = XffValue;
+    type Error = NabuError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<XffValue> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = XffValue;
+    type Error = NabuError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<XffValue> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+pub struct SerializeArrayVariant {
+    variant: &'static str,
+    values: Vec<XffValue>,
+}
+
+impl ser::SerializeTupleVariant for SerializeArrayVariant {
+    type Ok = XffValue;
+    type Error = NabuError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.values.push(value.serialize(XffValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<XffValue> {
+        let mut map = BTreeMap::new();
+        map.insert(
+            self.variant.to_string(),
+            XffValue::Array(Array::from(self.values)),
+        );
+        Ok(XffValue::from(map))
+    }
+}
+
+pub struct SerializeObject {
+    map: BTreeMap<String, XffValue>,
+    next_key: Option<String>,
+}
+
+impl ser::SerializeMap for SerializeObject {
+    type Ok = XffValue;
+    type Error = NabuError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        self.next_key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let key = self
+            .next_key
+            .take()
+            .ok_or(NabuError::SerdeError("map value without a key".to_string()))?;
+        self.map.insert(key, value.serialize(XffValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<XffValue> {
+        Ok(XffValue::Object(Object { map: self.map }))
+    }
+}
+
+impl ser::SerializeStruct for SerializeObject {
+    type Ok = XffValue;
+    type Error = NabuError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.map
+            .insert(key.to_string(), value.serialize(XffValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<XffValue> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+pub struct SerializeObjectVariant {
+    variant: &'static str,
+    map: BTreeMap<String, XffValue>,
+}
+
+impl ser::SerializeStructVariant for SerializeObjectVariant {
+    type Ok = XffValue;
+    type Error = NabuError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.map
+            .insert(key.to_string(), value.serialize(XffValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<XffValue> {
+        let mut map = BTreeMap::new();
+        map.insert(
+            self.variant.to_string(),
+            XffValue::Object(Object { map: self.map }),
+        );
+        Ok(XffValue::from(map))
+    }
+}
+
+/// Keys of an `Object` are always strings, numbers, chars, booleans and unit variants are stored
+/// in their string form
+struct KeySerializer;
+
+fn key_must_be_a_string() -> NabuError {
+    NabuError::SerdeError("object keys must be strings".to_string())
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = NabuError;
+
+    type SerializeSeq = ser::Impossible<String, NabuError>;
+    type SerializeTuple = ser::Impossible<String, NabuError>;
+    type SerializeTupleStruct = ser::Impossible<String, NabuError>;
+    type SerializeTupleVariant = ser::Impossible<String, NabuError>;
+    type SerializeMap = ser::Impossible<String, NabuError>;
+    type SerializeStruct = ser::Impossible<String, NabuError>;
+    type SerializeStructVariant = ser::Impossible<String, NabuError>;
+
+    fn serialize_bool(self, v: bool) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_none(self) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit(self) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(key_must_be_a_string())
+    }
+}
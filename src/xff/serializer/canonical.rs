@@ -0,0 +1,71 @@
+use std::io::Write;
+
+use crate::{
+    error::{NabuError, Result},
+    xff::{
+        serializer::writer::{serialize_stream, write_stream, Encoding},
+        value::{num::canonical_number_text, Number, XffValue},
+    },
+};
+
+/// Serializes the value into its canonical byte stream
+///
+/// The canonical byte stream is a v2 byte stream with exactly one encoding for every value:
+/// - every length attribute has as few bytes as hold the length, but at least one
+/// - every number is stored in the type `encode_canonical_number` chooses by its canonical text
+/// - `RS` only separates the elements of arrays and objects, there is no trailing `RS`
+/// - object entries are ordered by their keys, compared bytewise
+/// - nothing is compressed, and there is no integrity trailer
+///
+/// Values equal by content, numbers equal by value no matter their variant, serialize into
+/// identical bytes.
+pub fn serialize_xff_canonical(data: &XffValue) -> Result<Vec<u8>> {
+    serialize_stream(data, Encoding::Canonical)
+}
+
+/// Writes the canonical byte stream of the value, version byte and `EM` included, into any
+/// writer
+pub fn write_xff_canonical<W: Write>(data: &XffValue, out: &mut W) -> Result<()> {
+    write_stream(data, Encoding::Canonical, out)
+}
+
+/// Encodes a number into its canonical v2 form, chosen by the canonical text of the number
+///
+/// The text is stored in the first type of number that holds it exactly:
+/// - integers as the first of `u64`, `i64`, `u128` and `i128` they fit into
+/// - other numbers as `f64` if the float has the same canonical text
+/// - anything else as decimal text
+///
+/// As the type only depends on the canonical text, numbers equal by value are encoded the same.
+///
+/// # Errors
+/// Returns `NabuError::InvalidXFFValueForVersion` for `NaN` and infinite floats
+pub(crate) fn encode_canonical_number(data: &XffValue, n: &Number) -> Result<Vec<u8>> {
+    let text = canonical_number_text(n)
+        .ok_or_else(|| NabuError::InvalidXFFValueForVersion(data.clone(), 2))?;
+    let mut out: Vec<u8> = Vec::with_capacity(17);
+    if let Ok(u) = text.parse::<u64>() {
+        out.push(1);
+        out.extend(u.to_le_bytes());
+    } else if let Ok(i) = text.parse::<i64>() {
+        out.push(2);
+        out.extend(i.to_le_bytes());
+    } else if let Ok(u) = text.parse::<u128>() {
+        out.push(4);
+        out.extend(u.to_le_bytes());
+    } else if let Ok(i) = text.parse::<i128>() {
+        out.push(5);
+        out.extend(i.to_le_bytes());
+    } else if let Some(f) = text
+        .parse::<f64>()
+        .ok()
+        .filter(|f| canonical_number_text(&Number::Float(*f)).as_ref() == Some(&text))
+    {
+        out.push(3);
+        out.extend(f.to_le_bytes());
+    } else {
+        out.push(6);
+        out.extend(text.into_bytes());
+    }
+    Ok(out)
+}
@@ -1,14 +1,29 @@
-use std::path::Path;
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use crate::{
     error::{NabuError, Result},
-    xff::value::XffValue,
+    xff::{checksum::append_trailer, value::XffValue},
+    XFF_VERSION,
 };
 
 pub mod v0;
 use crate::xff::serializer::v0::serialize_xff_v0;
 pub mod v1;
 use crate::xff::serializer::v1::serialize_xff_v1;
+pub mod v2;
+use crate::xff::serializer::v2::serialize_xff_v2;
+#[cfg(feature = "compression")]
+use crate::xff::serializer::v2::serialize_xff_v2_compressed;
+pub mod writer;
+pub use crate::xff::serializer::writer::XffWriter;
+pub mod options;
+pub use crate::xff::serializer::options::WriteOptions;
+pub mod canonical;
 
 /// Takes in a Vec of XffValues and serializes it into a byte vector
 ///
@@ -25,6 +40,7 @@ use crate::xff::serializer::v1::serialize_xff_v1;
 ///
 /// # Errors
 /// Returns IO errors when issues with reading the file from disk occur
+/// Returns `NabuError::NestingTooDeep` for v1 and v2 values nested deeper than `MAX_DEPTH`
 pub fn serialize_xff(data: Vec<XffValue>, ver: u8) -> Result<Vec<u8>> {
     match ver {
         0 => serialize_xff_v0(data),
@@ -34,19 +50,98 @@ pub fn serialize_xff(data: Vec<XffValue>, ver: u8) -> Result<Vec<u8>> {
             }
             serialize_xff_v1(data)
         }
+        2 => {
+            if data.len() != 1 {
+                return Err(NabuError::InvalidXFFVersion(data.into(), 2));
+            }
+            serialize_xff_v2(data)
+        }
         _ => Err(NabuError::UnknownXFFVersion(ver)),
     }
 }
 
+/// Serializes the data in the most recent XFF version, as configured by the options
+///
+/// # Arguments
+/// * `data` - The Vec of XffValues to write, only one element is permissable
+/// * `options` - The options to apply
+///
+/// # Errors
+/// Returns any error of `serialize_xff`
+pub fn serialize_xff_with_options(data: Vec<XffValue>, options: &WriteOptions) -> Result<Vec<u8>> {
+    #[cfg(feature = "compression")]
+    let mut bytes = match data.as_slice() {
+        [value] => serialize_xff_v2_compressed(value, options.get_compression())?,
+        _ => return Err(NabuError::InvalidXFFVersion(data.into(), XFF_VERSION)),
+    };
+    #[cfg(not(feature = "compression"))]
+    let mut bytes = serialize_xff(data, XFF_VERSION)?;
+    if options.has_checksum() {
+        append_trailer(&mut bytes);
+    }
+    Ok(bytes)
+}
+
+/// Amount of temporary files created by this process, keeps their names unique
+static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
 /// Writes a vector of bytes to a file
 ///
+/// An atomic write first writes a temporary file next to the target and flushes it to disk,
+/// then renames it over the target. A crash or power loss at any point leaves either the old
+/// or the new file behind, never a truncated one. The permissions of an existing file are
+/// kept, a symbolic link at the path is replaced by the file.
+///
+/// A non-atomic write overwrites the file in place.
+///
 /// # Arguments
 /// * `path` - The path to the file to write
 /// * `data` - The vector of bytes to write
+/// * `atomic` - If the file is written atomically
 ///
 /// # Errors
 /// Returns IO errors should issues with writing the file to disk arise
-pub fn write_bytes_to_file(path: &Path, data: Vec<u8>) -> Result<()> {
-    std::fs::write(path, data)?;
+pub fn write_bytes_to_file(path: &Path, data: Vec<u8>, atomic: bool) -> Result<()> {
+    if !atomic {
+        std::fs::write(path, data)?;
+        return Ok(());
+    }
+    let temp_path = temp_sibling(path);
+    let written = write_synced(&temp_path, &data, path)
+        .and_then(|_| std::fs::rename(&temp_path, path));
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e.into());
+    }
+    // the rename itself is only durable once the directory is flushed
+    #[cfg(unix)]
+    std::fs::File::open(match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    })?
+    .sync_all()?;
     Ok(())
 }
+
+/// Returns a path for a temporary file in the directory of `path`, unique for this process
+fn temp_sibling(path: &Path) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let count = TEMP_FILES.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        file_name,
+        std::process::id(),
+        count
+    ))
+}
+
+/// Writes the data to a new file and flushes it to disk, with the permissions of `target` if it
+/// exists
+fn write_synced(path: &Path, data: &[u8], target: &Path) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+    file.write_all(data)?;
+    if let Ok(metadata) = std::fs::metadata(target) {
+        file.set_permissions(metadata.permissions())?;
+    }
+    file.sync_all()
+}
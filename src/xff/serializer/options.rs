@@ -0,0 +1,79 @@
+#[cfg(feature = "compression")]
+use crate::xff::compression::Compression;
+
+/// Options for writing a XFF byte stream with `write_with_options` and `to_bytes_with_options`
+///
+/// `WriteOptions::default()` writes exactly the same bytes as `write` and `to_bytes`.
+///
+/// # Example
+/// ```rust
+/// use nabu::serde::{from_bytes, to_bytes_with_options, WriteOptions};
+/// use nabu::XffValue;
+///
+/// let options = WriteOptions::new().checksum(true);
+/// let bytes = to_bytes_with_options(XffValue::from("hello mom"), &options).unwrap();
+/// assert_eq!(from_bytes(&bytes).unwrap(), XffValue::from("hello mom"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WriteOptions {
+    checksum: bool,
+    /// Inverted, so that the default writes atomically
+    in_place: bool,
+    #[cfg(feature = "compression")]
+    compression: Compression,
+}
+
+impl WriteOptions {
+    /// Creates the default options
+    pub fn new() -> Self {
+        WriteOptions::default()
+    }
+
+    /// Appends an integrity trailer of CRC-32 checksums, verified whenever the byte stream is
+    /// read again
+    ///
+    /// A byte flipped anywhere in the byte stream is reported as `NabuError::ChecksumMismatch`
+    /// with the position of the block of 4096 bytes containing it, instead of being read as a
+    /// different value.
+    pub fn checksum(mut self, checksum: bool) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    /// Returns `true` if an integrity trailer is appended
+    pub fn has_checksum(&self) -> bool {
+        self.checksum
+    }
+
+    /// Writes files atomically, enabled by default
+    ///
+    /// The bytes are written to a temporary file next to the target, flushed to disk and then
+    /// renamed over the target, so that a crash never leaves a truncated file behind.
+    /// Disabling it overwrites the file in place, which keeps its identity, e.g. hard links, but
+    /// can destroy the previous content if the write is interrupted.
+    pub fn atomic(mut self, atomic: bool) -> Self {
+        self.in_place = !atomic;
+        self
+    }
+
+    /// Returns `true` if files are written atomically
+    pub fn is_atomic(&self) -> bool {
+        !self.in_place
+    }
+
+    /// Compresses the values selected by `compression`, `read` and `from_bytes` decompress them
+    /// transparently
+    ///
+    /// Values are only stored compressed if that makes them smaller.
+    #[cfg(feature = "compression")]
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Returns what is compressed
+    #[cfg(feature = "compression")]
+    pub fn get_compression(&self) -> Compression {
+        self.compression
+    }
+}
@@ -1,100 +1,61 @@
 use crate::{
     error::{NabuError, Result},
-    xff::value::XffValue,
+    xff::{
+        deserializer::v1::decode_xff_v1_char,
+        serializer::writer::{serialize_stream, Encoding},
+        value::{num::encode_number_text, Number, XffValue},
+    },
 };
 
 pub fn serialize_xff_v1(data: Vec<XffValue>) -> Result<Vec<u8>> {
-    let mut out: Vec<u8> = Default::default();
-    // Version 1
-    out.push(1);
     // only one value is permissable
-    out.extend(serialize_xff_v1_value(&data[0])?);
-    // EM
-    out.push(25);
-    Ok(out)
+    serialize_stream(&data[0], Encoding::V1)
 }
 
-fn serialize_xff_v1_value(data: &XffValue) -> Result<Vec<u8>> {
-    let mut out: Vec<u8> = Default::default();
-    match data {
-        XffValue::String(s) => {
-            // first create the string
-            let tmp_str: Vec<u8> = s.chars().map(|c| c as u8).collect();
-            // now byte structure and push
-            out.push(1);
-            out.extend(encode_length(tmp_str.len()));
-            out.extend(tmp_str);
-            out.push(24);
-        }
-        XffValue::Number(n) => {
-            // first create the string from the number
-            let tmp_num: Vec<u8> = n.as_string().chars().map(|c| c as u8).collect();
-            // now byte structure and push
-            out.push(2);
-            out.extend(encode_length(tmp_num.len()));
-            out.extend(tmp_num);
-            out.push(24);
-        }
-        XffValue::Array(a) => {
-            // create the array
-            let mut array_bytes: Vec<u8> = Default::default();
-            for value in &a.values {
-                array_bytes.extend(serialize_xff_v1_value(value)?);
-                // RS separator
-                array_bytes.push(30);
-                
-            }
-            // byte structure and push
-            out.push(3);
-            out.extend(encode_length(array_bytes.len()));
-            out.extend(array_bytes);
-            out.push(24);
-        }
-        XffValue::Object(o) => {
-            // create the object
-            let mut object_bytes: Vec<u8> = Default::default();
-            for (key, value) in o.map.iter() {
-                // GS
-                object_bytes.push(29);
-                // key
-                object_bytes.extend(serialize_xff_v1_value(&XffValue::from(key.as_str()))?);
-                // US
-                object_bytes.push(31);
-                // value
-                object_bytes.extend(serialize_xff_v1_value(value)?);
-                // Trailing GS
-                object_bytes.push(29);
-                // RS separator
-                object_bytes.push(30);
-            }
-            // byte structure and push
-            out.push(4);
-            out.extend(encode_length(object_bytes.len()));
-            out.extend(object_bytes);
-            out.push(24);
-        }
-        XffValue::Data(d) => {
-            out.push(5);
-            out.extend(encode_length(d.len));
-            out.extend(d.data.clone());
-            out.push(24);
-        }
-        XffValue::Boolean(b) => {
-            if *b {
-                out.push(16);
-            } else {
-                out.push(17);
-            }
-        }
-        XffValue::Null => {
-            out.push(0);
-        }
-        _ => Err(NabuError::InvalidXFFVersion(data.clone(), 1))?,
+/// Encodes a number into its v1 text, following the number grammar of the specification
+///
+/// # Errors
+/// Returns `NabuError::InvalidXFFValueForVersion` for `NaN` and infinite floats
+pub(crate) fn encode_xff_v1_number(data: &XffValue, n: &Number) -> Result<Vec<u8>> {
+    encode_number_text(n)
+        .map(String::into_bytes)
+        .ok_or_else(|| NabuError::InvalidXFFValueForVersion(data.clone(), 1))
+}
+
+/// Encodes a string into v1 bytes, one byte per character
+///
+/// # Errors
+/// Returns `NabuError::UnrepresentableCharacter` for any character not permissible in a v1
+/// `String`, instead of silently truncating it
+pub(crate) fn encode_xff_v1_string(s: &str) -> Result<Vec<u8>> {
+    s.chars().map(encode_xff_v1_char).collect()
+}
+
+/// Returns the length in bytes of the v1 encoded string, checking every character
+pub(crate) fn xff_v1_string_len(s: &str) -> Result<usize> {
+    let mut len: usize = 0;
+    for c in s.chars() {
+        encode_xff_v1_char(c)?;
+        len += 1;
     }
-    Ok(out)
+    Ok(len)
+}
+
+fn encode_xff_v1_char(c: char) -> Result<u8> {
+    match u8::try_from(c) {
+        Ok(byte) if decode_xff_v1_char(byte) == Some(c) => Ok(byte),
+        _ => Err(NabuError::UnrepresentableCharacter(c, 1)),
+    }
+}
+
+/// Returns the amount of bytes `encode_length` produces for the supplied length
+pub(crate) fn encoded_length_len(len: usize) -> usize {
+    let significant_bytes = (usize::BITS - len.leading_zeros()).div_ceil(8) as usize;
+    // the length byte and at least one byte of length
+    1 + significant_bytes.max(1)
 }
 
-fn encode_length(len: usize) -> Vec<u8> {
+pub(crate) fn encode_length(len: usize) -> Vec<u8> {
     if len <= 255 {
         let mut out: Vec<u8> = u8::from(1).to_le_bytes().to_vec();
         out.push(len.to_le_bytes().to_vec()[0]);
@@ -124,7 +85,7 @@ fn encode_length(len: usize) -> Vec<u8> {
         let mut out: Vec<u8> = u8::from(6).to_le_bytes().to_vec();
         out.extend(len.to_le_bytes().to_vec()[0..6].to_vec());
         return out;
-    } else if len <= 72_057_594_037_927_35 {
+    } else if len <= 72_057_594_037_927_935 {
         let mut out: Vec<u8> = u8::from(7).to_le_bytes().to_vec();
         out.extend(len.to_le_bytes().to_vec()[0..7].to_vec());
         return out;
@@ -0,0 +1,80 @@
+#[cfg(feature = "compression")]
+use crate::xff::compression::{compress_value, Compression};
+#[cfg(feature = "compression")]
+use crate::xff::serializer::writer::ValueWriter;
+use crate::{
+    error::{NabuError, Result},
+    xff::{
+        serializer::writer::{serialize_stream, Encoding},
+        value::{Number, XffValue},
+    },
+};
+
+pub fn serialize_xff_v2(data: Vec<XffValue>) -> Result<Vec<u8>> {
+    // only one value is permissable
+    serialize_stream(&data[0], Encoding::V2)
+}
+
+/// Serializes a complete v2 byte stream, compressing the parts selected by `compression`
+#[cfg(feature = "compression")]
+pub fn serialize_xff_v2_compressed(data: &XffValue, compression: Compression) -> Result<Vec<u8>> {
+    match compression {
+        Compression::None => serialize_stream(data, Encoding::V2),
+        Compression::Data => {
+            let mut writer = ValueWriter::new(Encoding::V2).compress_data();
+            let mut out: Vec<u8> = Vec::with_capacity(writer.value_len(data)? + 2);
+            writer.write_stream(data, &mut out)?;
+            Ok(out)
+        }
+        Compression::File => {
+            let mut writer = ValueWriter::new(Encoding::V2);
+            let mut encoded: Vec<u8> = Vec::with_capacity(writer.value_len(data)?);
+            writer.write_value(data, &mut encoded)?;
+            // Version 2
+            let mut out: Vec<u8> = vec![2];
+            out.extend(compress_value(&encoded).unwrap_or(encoded));
+            // EM
+            out.push(25);
+            Ok(out)
+        }
+    }
+}
+
+/// Encodes a number into its binary v2 form: one byte for the type of number, followed by the
+/// number itself in little-endian byte order, or the decimal text for decimals
+///
+/// # Errors
+/// Returns `NabuError::InvalidXFFValueForVersion` for `NaN` and infinite floats
+pub(crate) fn encode_xff_v2_number(data: &XffValue, n: &Number) -> Result<Vec<u8>> {
+    let mut out: Vec<u8> = Vec::with_capacity(9);
+    match n {
+        Number::Unsigned(u) => {
+            out.push(1);
+            out.extend((*u as u64).to_le_bytes());
+        }
+        Number::Integer(i) => {
+            out.push(2);
+            out.extend((*i as i64).to_le_bytes());
+        }
+        Number::Float(f) => {
+            if !f.is_finite() {
+                return Err(NabuError::InvalidXFFValueForVersion(data.clone(), 2));
+            }
+            out.push(3);
+            out.extend(f.to_le_bytes());
+        }
+        Number::BigUnsigned(u) => {
+            out.push(4);
+            out.extend(u.to_le_bytes());
+        }
+        Number::BigInteger(i) => {
+            out.push(5);
+            out.extend(i.to_le_bytes());
+        }
+        Number::Decimal(d) => {
+            out.push(6);
+            out.extend(d.as_str().as_bytes());
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,410 @@
+use std::{borrow::Cow, io::Write};
+
+#[cfg(feature = "compression")]
+use crate::xff::compression::compress_value;
+use crate::{
+    error::{NabuError, Result},
+    xff::deserializer::MAX_DEPTH,
+    xff::serializer::{
+        canonical::encode_canonical_number,
+        v1::{
+            encode_length, encode_xff_v1_number, encode_xff_v1_string, encoded_length_len,
+            xff_v1_string_len,
+        },
+        v2::encode_xff_v2_number,
+    },
+    xff::value::{Data, XffValue},
+    XFF_VERSION,
+};
+
+/// Incremental XFF writer over any `std::io::Write`
+///
+/// Writes the most recent version of the XFF specification, use `with_version` to write v1.
+///
+/// Values are written directly into the wrapped writer, without first building the complete byte
+/// stream in memory. The length attributes of arrays and objects are computed from the value tree
+/// up front.
+///
+/// Every call to `write` produces one complete XFF byte stream, version byte and `EM` included.
+/// Many small writes are made, so wrapping unbuffered writers like a `File` in a `BufWriter` is
+/// recommended.
+///
+/// # Example
+/// ```rust
+/// use nabu::serde::{XffWriter, from_bytes};
+/// use nabu::XffValue;
+///
+/// let data = XffValue::from(vec![XffValue::from("hello mom"), XffValue::from(true)]);
+///
+/// let mut writer = XffWriter::new(Vec::new());
+/// assert!(writer.write(&data).is_ok());
+/// let bytes: Vec<u8> = writer.into_inner();
+///
+/// assert_eq!(from_bytes(&bytes).unwrap(), data);
+/// ```
+#[derive(Debug)]
+pub struct XffWriter<W: Write> {
+    writer: W,
+    version: u8,
+}
+
+impl<W: Write> XffWriter<W> {
+    /// Creates a new `XffWriter` writing into the supplied writer
+    ///
+    /// # Arguments
+    /// * `writer` - The writer to write the XFF byte stream into
+    pub fn new(writer: W) -> Self {
+        XffWriter::with_version(writer, XFF_VERSION)
+    }
+
+    /// Creates a new `XffWriter` writing the supplied XFF version into the supplied writer
+    ///
+    /// Only versions 1 and 2 can be written incrementally, writing any other version errors.
+    ///
+    /// # Arguments
+    /// * `writer` - The writer to write the XFF byte stream into
+    /// * `version` - The XFF version to write
+    pub fn with_version(writer: W, version: u8) -> Self {
+        XffWriter { writer, version }
+    }
+
+    /// Writes the value as a complete XFF byte stream and flushes the writer
+    ///
+    /// # Arguments
+    /// * `data` - The XffValue to write
+    ///
+    /// # Errors
+    /// Returns IO errors of the wrapped writer
+    /// Returns `NabuError::UnknownXFFVersion` if the version of the writer cannot be written
+    /// incrementally
+    /// Also returns `NabuError::InvalidXFFVersion` if the value cannot be written in the version
+    /// Returns `NabuError::NestingTooDeep` for values nested deeper than `MAX_DEPTH`, before
+    /// anything is written
+    pub fn write(&mut self, data: &XffValue) -> Result<()> {
+        match self.version {
+            1 => write_stream(data, Encoding::V1, &mut self.writer)?,
+            2 => write_stream(data, Encoding::V2, &mut self.writer)?,
+            _ => return Err(NabuError::UnknownXFFVersion(self.version)),
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Returns a reference to the wrapped writer
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Consumes the `XffWriter` and returns the wrapped writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+// -----------------------------------------------------------
+//                     Shared value writer
+// -----------------------------------------------------------
+
+/// The encodings every byte stream is written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Encoding {
+    V1,
+    V2,
+    /// The canonical v2 byte stream of `serialize_xff_canonical`
+    Canonical,
+}
+
+impl Encoding {
+    fn version(self) -> u8 {
+        match self {
+            Encoding::V1 => 1,
+            Encoding::V2 | Encoding::Canonical => 2,
+        }
+    }
+}
+
+/// Serializes a complete byte stream of the encoding, version byte and `EM` included
+pub(crate) fn serialize_stream(data: &XffValue, encoding: Encoding) -> Result<Vec<u8>> {
+    let mut writer = ValueWriter::new(encoding);
+    let mut out: Vec<u8> = Vec::with_capacity(writer.value_len(data)? + 2);
+    writer.write_stream(data, &mut out)?;
+    Ok(out)
+}
+
+/// Writes a complete byte stream of the encoding, version byte and `EM` included, into any
+/// writer
+///
+/// No intermediate byte copies of arrays or objects are created, their lengths are computed from
+/// the value tree before any of the value is written.
+pub(crate) fn write_stream<W: Write>(
+    data: &XffValue,
+    encoding: Encoding,
+    out: &mut W,
+) -> Result<()> {
+    let mut writer = ValueWriter::new(encoding);
+    writer.value_len(data)?;
+    writer.write_stream(data, out)
+}
+
+/// Writes values in one encoding, in two passes
+///
+/// The first pass, `value_len`, computes the content length of every array and object bottom-up,
+/// each child is measured exactly once. The second pass, `write_value`, writes the value and
+/// takes the lengths in the same order they were computed.
+///
+/// Values nested deeper than `MAX_DEPTH` are rejected by `value_len`, before anything is written,
+/// so that everything written can be read back with the default `DecodeOptions`.
+pub(crate) struct ValueWriter {
+    encoding: Encoding,
+    /// The nesting depth of the array or object `value_len` is measuring
+    depth: usize,
+    /// The content lengths of the arrays and objects, in the order they are written
+    lens: Vec<usize>,
+    /// The next length `write_value` takes
+    next: usize,
+    /// If every `Data` value is compressed on its own, if that makes it smaller
+    #[cfg(feature = "compression")]
+    compress_data: bool,
+    /// The compressed encodings of the `Data` values, `None` if compressing does not make them
+    /// smaller
+    #[cfg(feature = "compression")]
+    compressed: Vec<Option<Vec<u8>>>,
+    /// The next compressed encoding `write_value` takes
+    #[cfg(feature = "compression")]
+    next_compressed: usize,
+}
+
+impl ValueWriter {
+    pub(crate) fn new(encoding: Encoding) -> Self {
+        ValueWriter {
+            encoding,
+            depth: 0,
+            lens: Vec::new(),
+            next: 0,
+            #[cfg(feature = "compression")]
+            compress_data: false,
+            #[cfg(feature = "compression")]
+            compressed: Vec::new(),
+            #[cfg(feature = "compression")]
+            next_compressed: 0,
+        }
+    }
+
+    /// Compresses every `Data` value on its own, if that makes it smaller
+    ///
+    /// The values are compressed while they are measured, so that the lengths of the arrays and
+    /// objects containing them account for their compressed encoding.
+    #[cfg(feature = "compression")]
+    pub(crate) fn compress_data(mut self) -> Self {
+        self.compress_data = true;
+        self
+    }
+
+    pub(crate) fn write_stream<W: Write>(&mut self, data: &XffValue, out: &mut W) -> Result<()> {
+        out.write_all(&[self.encoding.version()])?;
+        self.write_value(data, out)?;
+        // EM
+        out.write_all(&[25])?;
+        Ok(())
+    }
+
+    /// Returns the amount of bytes the value takes up once serialized, from its type byte up to
+    /// and including its `EV`, and records the content lengths of the arrays and objects in it
+    ///
+    /// Returns `NabuError::NestingTooDeep` for values nested deeper than `MAX_DEPTH`.
+    pub(crate) fn value_len(&mut self, data: &XffValue) -> Result<usize> {
+        let content_len = match data {
+            XffValue::Boolean(_) | XffValue::Null => return Ok(1),
+            XffValue::String(s) => self.string_len(s)?,
+            XffValue::Number(_) => self.number(data)?.len(),
+            XffValue::Data(d) => {
+                #[cfg(feature = "compression")]
+                if self.compress_data {
+                    let mut encoded: Vec<u8> =
+                        Vec::with_capacity(1 + encoded_length_len(d.len) + d.len + 1);
+                    write_data(&mut encoded, d)?;
+                    let compressed = compress_value(&encoded);
+                    let len = compressed.as_ref().map_or(encoded.len(), Vec::len);
+                    self.compressed.push(compressed);
+                    return Ok(len);
+                }
+                d.len
+            }
+            XffValue::Array(a) => {
+                self.enter()?;
+                let slot = self.reserve();
+                let mut len: usize = 0;
+                for value in a.values.iter() {
+                    len += self.value_len(value)?;
+                }
+                // RS separators
+                len += self.separators(a.values.len());
+                self.lens[slot] = len;
+                self.depth -= 1;
+                len
+            }
+            XffValue::Object(o) => {
+                self.enter()?;
+                let slot = self.reserve();
+                let mut len: usize = 0;
+                for (key, value) in o.map.iter() {
+                    let key_len = self.string_len(key)?;
+                    let key_len = 1 + encoded_length_len(key_len) + key_len + 1;
+                    // GS, key, US, value and GS
+                    len += 1 + key_len + 1 + self.value_len(value)? + 1;
+                }
+                // RS separators
+                len += self.separators(o.map.len());
+                self.lens[slot] = len;
+                self.depth -= 1;
+                len
+            }
+            _ => return Err(self.unsupported(data)),
+        };
+        // type byte, length, content, EV
+        Ok(1 + encoded_length_len(content_len) + content_len + 1)
+    }
+
+    /// Writes the value, `value_len` has to have measured it before
+    pub(crate) fn write_value<W: Write>(&mut self, data: &XffValue, out: &mut W) -> Result<()> {
+        match data {
+            XffValue::String(s) => write_content(out, 1, &self.string(s)?)?,
+            XffValue::Number(_) => write_content(out, 2, &self.number(data)?)?,
+            XffValue::Array(a) => {
+                out.write_all(&[3])?;
+                out.write_all(&encode_length(self.take_len()))?;
+                for (i, value) in a.values.iter().enumerate() {
+                    self.write_value(value, out)?;
+                    self.write_separator(out, i + 1 == a.values.len())?;
+                }
+                out.write_all(&[24])?;
+            }
+            XffValue::Object(o) => {
+                out.write_all(&[4])?;
+                out.write_all(&encode_length(self.take_len()))?;
+                for (i, (key, value)) in o.map.iter().enumerate() {
+                    // GS
+                    out.write_all(&[29])?;
+                    // key
+                    write_content(out, 1, &self.string(key)?)?;
+                    // US
+                    out.write_all(&[31])?;
+                    // value
+                    self.write_value(value, out)?;
+                    // Trailing GS
+                    out.write_all(&[29])?;
+                    self.write_separator(out, i + 1 == o.map.len())?;
+                }
+                out.write_all(&[24])?;
+            }
+            XffValue::Data(d) => {
+                #[cfg(feature = "compression")]
+                if self.compress_data {
+                    self.next_compressed += 1;
+                    if let Some(compressed) = &self.compressed[self.next_compressed - 1] {
+                        out.write_all(compressed)?;
+                        return Ok(());
+                    }
+                }
+                write_data(out, d)?;
+            }
+            XffValue::Boolean(b) => {
+                if *b {
+                    out.write_all(&[16])?;
+                } else {
+                    out.write_all(&[17])?;
+                }
+            }
+            XffValue::Null => {
+                out.write_all(&[0])?;
+            }
+            _ => return Err(self.unsupported(data)),
+        }
+        Ok(())
+    }
+
+    fn enter(&mut self) -> Result<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(NabuError::NestingTooDeep(MAX_DEPTH));
+        }
+        Ok(())
+    }
+
+    fn reserve(&mut self) -> usize {
+        self.lens.push(0);
+        self.lens.len() - 1
+    }
+
+    fn take_len(&mut self) -> usize {
+        self.next += 1;
+        self.lens[self.next - 1]
+    }
+
+    /// Returns the amount of `RS` separators between and after the elements
+    fn separators(&self, elements: usize) -> usize {
+        match self.encoding {
+            Encoding::V1 | Encoding::V2 => elements,
+            // only between the elements
+            Encoding::Canonical => elements.saturating_sub(1),
+        }
+    }
+
+    /// Writes the `RS` separator after an element, there is none after the last element of a
+    /// canonical byte stream
+    fn write_separator<W: Write>(&self, out: &mut W, last: bool) -> Result<()> {
+        if !(last && self.encoding == Encoding::Canonical) {
+            out.write_all(&[30])?;
+        }
+        Ok(())
+    }
+
+    /// Returns the length of the encoded string, checking every character
+    fn string_len(&self, s: &str) -> Result<usize> {
+        match self.encoding {
+            Encoding::V1 => xff_v1_string_len(s),
+            // UTF-8, the length is in bytes
+            Encoding::V2 | Encoding::Canonical => Ok(s.len()),
+        }
+    }
+
+    fn string<'a>(&self, s: &'a str) -> Result<Cow<'a, [u8]>> {
+        match self.encoding {
+            Encoding::V1 => encode_xff_v1_string(s).map(Cow::Owned),
+            Encoding::V2 | Encoding::Canonical => Ok(Cow::Borrowed(s.as_bytes())),
+        }
+    }
+
+    fn number(&self, data: &XffValue) -> Result<Vec<u8>> {
+        let XffValue::Number(n) = data else {
+            return Err(self.unsupported(data));
+        };
+        match self.encoding {
+            Encoding::V1 => encode_xff_v1_number(data, n),
+            Encoding::V2 => encode_xff_v2_number(data, n),
+            Encoding::Canonical => encode_canonical_number(data, n),
+        }
+    }
+
+    fn unsupported(&self, data: &XffValue) -> NabuError {
+        NabuError::InvalidXFFVersion(data.clone(), self.encoding.version())
+    }
+}
+
+/// Writes the type byte, the length, the content and `EV`
+fn write_content<W: Write>(out: &mut W, type_byte: u8, content: &[u8]) -> Result<()> {
+    out.write_all(&[type_byte])?;
+    out.write_all(&encode_length(content.len()))?;
+    out.write_all(content)?;
+    out.write_all(&[24])?;
+    Ok(())
+}
+
+/// Writes a `Data` value, with the length it states
+fn write_data<W: Write>(out: &mut W, d: &Data) -> Result<()> {
+    out.write_all(&[5])?;
+    out.write_all(&encode_length(d.len))?;
+    out.write_all(&d.data)?;
+    out.write_all(&[24])?;
+    Ok(())
+}
@@ -251,6 +251,25 @@ impl Array {
         self.values.get(index)
     }
 
+    /// Returns a mutable reference to the value at the supplied index, to edit it in place
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::{XffValue, Array};
+    ///
+    /// let mut array = Array::from(vec![
+    ///     XffValue::from("hi mom!"),
+    ///     XffValue::from(42.69),
+    /// ]);
+    ///
+    /// *array.get_mut(1).unwrap() = XffValue::from(-42);
+    /// assert_eq!(array.get(1), Some(&XffValue::from(-42)));
+    /// assert_eq!(array.get_mut(2), None);
+    /// ```
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut XffValue> {
+        self.values.get_mut(index)
+    }
+
     /// Returns `true` if the `Array` contains the supplied value
     ///
     /// # Example
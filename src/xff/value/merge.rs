@@ -0,0 +1,152 @@
+use crate::{
+    error::NabuError,
+    xff::value::{pointer_child, Object, XffValue},
+};
+
+/// How `Object::merge` and `XffValue::merge` resolve a conflict.\
+/// Two values conflict if they are stored at the same path, are not equal and are not both
+/// objects. Objects on both sides are always merged key by key, no matter the strategy.
+///
+/// # Example
+/// ```rust
+/// use nabu::{MergeStrategy, Object, XffValue};
+///
+/// let mut defaults = Object::from(vec![
+///     ("port", XffValue::from(8080)),
+///     ("hosts", XffValue::from(vec![XffValue::from("localhost")])),
+/// ]);
+/// let overrides = Object::from(vec![
+///     ("port", XffValue::from(80)),
+///     ("hosts", XffValue::from(vec![XffValue::from("example.com")])),
+/// ]);
+///
+/// defaults.merge(overrides, MergeStrategy::ConcatArrays).unwrap();
+/// assert_eq!(defaults.get("port"), Some(&XffValue::from(80)));
+/// assert_eq!(
+///     defaults.get("hosts"),
+///     Some(&XffValue::from(vec![XffValue::from("localhost"), XffValue::from("example.com")]))
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeStrategy {
+    /// The value of the merged in side replaces the existing one
+    #[default]
+    RightWins,
+    /// The existing value is kept, the merged in side only adds missing keys
+    LeftWins,
+    /// Arrays on both sides are concatenated, the merged in elements appended to the existing
+    /// ones; any other conflict is resolved as `RightWins`
+    ConcatArrays,
+    /// Any conflict is an error, and nothing is merged
+    ErrorOnConflict,
+}
+
+impl Object {
+    /// Merges the supplied object into this one, recursing into objects present on both sides.\
+    /// Keys only present in `other` are added, conflicting values resolved by the `strategy`.
+    ///
+    /// # Errors
+    /// Returns `NabuError::MergeConflict` with the path of the first conflict if the strategy is
+    /// `MergeStrategy::ErrorOnConflict`; the object is left unchanged in that case.
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::{MergeStrategy, Object, XffValue};
+    ///
+    /// let mut config = Object::from(vec![
+    ///     ("server", XffValue::from(vec![("port", XffValue::from(8080)), ("tls", XffValue::from(false))])),
+    /// ]);
+    /// let site = Object::from(vec![
+    ///     ("server", XffValue::from(vec![("tls", XffValue::from(true))])),
+    ///     ("name", XffValue::from("nabu")),
+    /// ]);
+    ///
+    /// let mut strict = config.clone();
+    /// assert!(strict.merge(site.clone(), MergeStrategy::ErrorOnConflict).is_err());
+    /// assert_eq!(strict, config);
+    ///
+    /// config.merge(site, MergeStrategy::RightWins).unwrap();
+    /// assert_eq!(config["name"], XffValue::from("nabu"));
+    /// let config = XffValue::from(config);
+    /// assert_eq!(config.pointer("/server/port"), Some(&XffValue::from(8080)));
+    /// assert_eq!(config.pointer("/server/tls"), Some(&XffValue::from(true)));
+    /// ```
+    pub fn merge(&mut self, other: Object, strategy: MergeStrategy) -> Result<(), NabuError> {
+        if strategy == MergeStrategy::ErrorOnConflict {
+            if let Some(path) = object_conflict(self, &other, "") {
+                return Err(NabuError::MergeConflict(path));
+            }
+        }
+        merge_objects(self, other, strategy);
+        Ok(())
+    }
+}
+
+impl XffValue {
+    /// Merges the supplied value into this one.\
+    /// Two objects are merged as by `Object::merge`, any other pair of unequal values is a
+    /// conflict at the root, resolved by the `strategy`.
+    ///
+    /// # Errors
+    /// Returns `NabuError::MergeConflict` with the path of the first conflict if the strategy is
+    /// `MergeStrategy::ErrorOnConflict`; the value is left unchanged in that case.
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::{MergeStrategy, XffValue};
+    ///
+    /// let mut value = XffValue::from(vec![XffValue::from(1)]);
+    /// value.merge(XffValue::from(vec![XffValue::from(2)]), MergeStrategy::ConcatArrays).unwrap();
+    /// assert_eq!(value, XffValue::from(vec![XffValue::from(1), XffValue::from(2)]));
+    ///
+    /// value.merge(XffValue::from("hi mom!"), MergeStrategy::LeftWins).unwrap();
+    /// assert!(value.is_array());
+    /// ```
+    pub fn merge(&mut self, other: XffValue, strategy: MergeStrategy) -> Result<(), NabuError> {
+        if strategy == MergeStrategy::ErrorOnConflict {
+            if let Some(path) = value_conflict(self, &other, String::new()) {
+                return Err(NabuError::MergeConflict(path));
+            }
+        }
+        merge_values(self, other, strategy);
+        Ok(())
+    }
+}
+
+/// Returns the path of the first conflict, if any
+fn object_conflict(left: &Object, right: &Object, path: &str) -> Option<String> {
+    right.iter().find_map(|(key, value)| {
+        left.get(key)
+            .and_then(|existing| value_conflict(existing, value, pointer_child(path, key)))
+    })
+}
+
+fn value_conflict(left: &XffValue, right: &XffValue, path: String) -> Option<String> {
+    match (left, right) {
+        (XffValue::Object(left), XffValue::Object(right)) => object_conflict(left, right, &path),
+        _ if left == right => None,
+        _ => Some(path),
+    }
+}
+
+fn merge_objects(left: &mut Object, right: Object, strategy: MergeStrategy) {
+    for (key, value) in right.map {
+        match left.get_mut(&key) {
+            Some(existing) => merge_values(existing, value, strategy),
+            None => left.insert(key, value),
+        }
+    }
+}
+
+fn merge_values(left: &mut XffValue, right: XffValue, strategy: MergeStrategy) {
+    match (left, right) {
+        (XffValue::Object(left), XffValue::Object(right)) => merge_objects(left, right, strategy),
+        (XffValue::Array(left), XffValue::Array(right))
+            if strategy == MergeStrategy::ConcatArrays =>
+        {
+            left.values.extend(right.values)
+        }
+        (_, _) if strategy == MergeStrategy::LeftWins => {}
+        (left, right) => *left = right,
+    }
+}
@@ -1,16 +1,26 @@
 use std::collections::{BTreeMap, HashMap};
 
+use crate::error::NabuError;
+use crate::xff::hash::{Sha256, DIGEST_LEN};
+use crate::xff::serializer::canonical::write_xff_canonical;
+
 pub use array::Array;
 pub use cmd_char::CommandCharacter;
 pub use data::Data;
-pub use num::Number;
+pub use merge::MergeStrategy;
+pub use num::{Decimal, Number};
 pub use object::Object;
+pub use query::Query;
+pub use value_ref::{XffStr, XffValueRef};
 
 pub mod array;
 pub mod cmd_char;
 pub mod data;
+pub mod merge;
 pub mod num;
 pub mod object;
+pub mod query;
+pub mod value_ref;
 
 #[derive(Debug, Clone, PartialEq)]
 /// An enum for the different types of XFF values.
@@ -26,6 +36,8 @@ pub mod object;
 /// E.g. `is_string()`, `is_number()`, etc.
 ///
 /// All variants have also `into_` functions implemented to retrieve the wrapped data inside.
+/// `as_` and `as_*_mut` functions return references to the wrapped data instead, to read or edit
+/// it in place without cloning.
 /// E.g. `into_string()`, `into_array()`, etc.
 ///
 /// For more information please refer to the readme, or the documentation of the functiuon or type.
@@ -120,9 +132,9 @@ impl XffValue {
     /// assert_eq!(num_value.into_string(), Some("42.69".to_string()));
     /// assert_eq!(data_value.into_string(), None);
     /// ```
-    pub fn into_string(&self) -> Option<String> {
+    pub fn into_string(self) -> Option<String> {
         match self {
-            XffValue::String(s) => Some(s.clone()),
+            XffValue::String(s) => Some(s),
             XffValue::Number(n) => Some(n.as_string()),
             _ => None,
         }
@@ -141,9 +153,9 @@ impl XffValue {
     /// assert_eq!(string_value.into_number(), None);
     /// assert_eq!(num_value.into_number(), Some(Number::from(42.69)));
     /// ```
-    pub fn into_number(&self) -> Option<Number> {
+    pub fn into_number(self) -> Option<Number> {
         match self {
-            XffValue::Number(n) => Some(n.clone()),
+            XffValue::Number(n) => Some(n),
             _ => None,
         }
     }
@@ -161,9 +173,9 @@ impl XffValue {
     /// assert_eq!(num_value.into_array(), None);
     /// assert_eq!(vec_value.into_array(), XffValue::from(vec![XffValue::from("hello mom!"), XffValue::from(42.69)]).into_array());
     /// ```
-    pub fn into_array(&self) -> Option<Array> {
+    pub fn into_array(self) -> Option<Array> {
         match self {
-            XffValue::Array(a) => Some(a.clone()),
+            XffValue::Array(a) => Some(a),
             _ => None,
         }
     }
@@ -188,9 +200,9 @@ impl XffValue {
     /// assert_eq!(map_value.into_object(), XffValue::from(map).into_object());
     ///
     /// ```
-    pub fn into_object(&self) -> Option<Object> {
+    pub fn into_object(self) -> Option<Object> {
         match self {
-            XffValue::Object(o) => Some(o.clone()),
+            XffValue::Object(o) => Some(o),
             _ => None,
         }
     }
@@ -208,9 +220,9 @@ impl XffValue {
     /// assert_eq!(num_value.into_data(), None);
     /// assert_eq!(data_value.into_data(), XffValue::from(vec![1, 2, 3]).into_data());
     /// ```
-    pub fn into_data(&self) -> Option<Data> {
+    pub fn into_data(self) -> Option<Data> {
         match self {
-            XffValue::Data(d) => Some(d.clone()),
+            XffValue::Data(d) => Some(d),
             _ => None,
         }
     }
@@ -230,9 +242,9 @@ impl XffValue {
     /// assert_eq!(bool_value_true.into_boolean(), Some(true));
     /// assert_eq!(bool_value_false.into_boolean(), Some(false));
     /// ```
-    pub fn into_boolean(&self) -> Option<bool> {
+    pub fn into_boolean(self) -> Option<bool> {
         match self {
-            XffValue::Boolean(b) => Some(*b),
+            XffValue::Boolean(b) => Some(b),
             _ => None,
         }
     }
@@ -250,13 +262,245 @@ impl XffValue {
     /// assert_eq!(num_value.into_null(), Some(()));
     /// assert_eq!(null_value.into_null(), None);
     /// ```
-    pub fn into_null(&self) -> Option<()> {
+    pub fn into_null(self) -> Option<()> {
         match self {
             XffValue::Null => None,
             _ => Some(()),
         }
     }
 
+    /// Returns a reference to the string if it is a `XffValue::String`
+    /// Returns `None` for all other variants
+    ///
+    /// Unlike `into_string`, numbers are not converted.
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::XffValue;
+    ///
+    /// let string_value = XffValue::from("hello mom!");
+    /// let num_value = XffValue::from(42.69);
+    ///
+    /// assert_eq!(string_value.as_str(), Some("hello mom!"));
+    /// assert_eq!(num_value.as_str(), None);
+    /// ```
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            XffValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the string if it is a `XffValue::String`
+    /// Returns `None` for all other variants
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::XffValue;
+    ///
+    /// let mut string_value = XffValue::from("hello");
+    /// string_value.as_string_mut().unwrap().push_str(" mom!");
+    ///
+    /// assert_eq!(string_value.as_str(), Some("hello mom!"));
+    /// ```
+    pub fn as_string_mut(&mut self) -> Option<&mut String> {
+        match self {
+            XffValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns a reference to the number if it is a `XffValue::Number`
+    /// Returns `None` for all other variants
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::{XffValue, Number};
+    ///
+    /// let num_value = XffValue::from(42.69);
+    ///
+    /// assert_eq!(num_value.as_number(), Some(&Number::from(42.69)));
+    /// assert_eq!(XffValue::Null.as_number(), None);
+    /// ```
+    pub fn as_number(&self) -> Option<&Number> {
+        match self {
+            XffValue::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the number if it is a `XffValue::Number`
+    /// Returns `None` for all other variants
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::{XffValue, Number};
+    ///
+    /// let mut num_value = XffValue::from(42.69);
+    /// *num_value.as_number_mut().unwrap() = Number::from(42);
+    ///
+    /// assert_eq!(num_value, XffValue::from(42));
+    /// ```
+    pub fn as_number_mut(&mut self) -> Option<&mut Number> {
+        match self {
+            XffValue::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Returns a reference to the array if it is a `XffValue::Array`
+    /// Returns `None` for all other variants
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::XffValue;
+    ///
+    /// let vec_value = XffValue::from(vec![XffValue::from("hello mom!"), XffValue::from(42.69)]);
+    ///
+    /// assert_eq!(vec_value.as_array().unwrap().len(), 2);
+    /// assert_eq!(vec_value.as_array().unwrap()[0], XffValue::from("hello mom!"));
+    /// assert_eq!(XffValue::Null.as_array(), None);
+    /// ```
+    pub fn as_array(&self) -> Option<&Array> {
+        match self {
+            XffValue::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the array if it is a `XffValue::Array`
+    /// Returns `None` for all other variants
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::XffValue;
+    ///
+    /// let mut vec_value = XffValue::from(vec![XffValue::from("hello mom!")]);
+    /// vec_value.as_array_mut().unwrap().push(42.69);
+    ///
+    /// assert_eq!(vec_value, XffValue::from(vec![XffValue::from("hello mom!"), XffValue::from(42.69)]));
+    /// ```
+    pub fn as_array_mut(&mut self) -> Option<&mut Array> {
+        match self {
+            XffValue::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// Returns a reference to the object if it is a `XffValue::Object`
+    /// Returns `None` for all other variants
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::XffValue;
+    ///
+    /// let object_value = XffValue::from(vec![("key", XffValue::from(42.69))]);
+    ///
+    /// assert_eq!(object_value.as_object().unwrap()["key"], XffValue::from(42.69));
+    /// assert_eq!(XffValue::Null.as_object(), None);
+    /// ```
+    pub fn as_object(&self) -> Option<&Object> {
+        match self {
+            XffValue::Object(o) => Some(o),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the object if it is a `XffValue::Object`
+    /// Returns `None` for all other variants
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::XffValue;
+    ///
+    /// let mut object_value = XffValue::from(vec![("key", XffValue::from(42.69))]);
+    /// object_value.as_object_mut().unwrap().insert("key", "hello mom!");
+    ///
+    /// assert_eq!(object_value.as_object().unwrap()["key"], XffValue::from("hello mom!"));
+    /// ```
+    pub fn as_object_mut(&mut self) -> Option<&mut Object> {
+        match self {
+            XffValue::Object(o) => Some(o),
+            _ => None,
+        }
+    }
+
+    /// Returns a reference to the data if it is a `XffValue::Data`
+    /// Returns `None` for all other variants
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::{XffValue, Data};
+    ///
+    /// let data_value = XffValue::from(vec![1, 2, 3]);
+    ///
+    /// assert_eq!(data_value.as_data(), Some(&Data::from(vec![1, 2, 3])));
+    /// assert_eq!(XffValue::Null.as_data(), None);
+    /// ```
+    pub fn as_data(&self) -> Option<&Data> {
+        match self {
+            XffValue::Data(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the data if it is a `XffValue::Data`
+    /// Returns `None` for all other variants
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::{XffValue, Data};
+    ///
+    /// let mut data_value = XffValue::from(vec![1, 2, 3]);
+    /// data_value.as_data_mut().unwrap().clear();
+    ///
+    /// assert_eq!(data_value.as_data(), Some(&Data::from(Vec::new())));
+    /// ```
+    pub fn as_data_mut(&mut self) -> Option<&mut Data> {
+        match self {
+            XffValue::Data(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Returns the boolean if it is a `XffValue::Boolean`, without consuming the value
+    /// Returns `None` for all other variants
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::XffValue;
+    ///
+    /// let object_value = XffValue::from(vec![("flag", XffValue::from(true))]);
+    ///
+    /// assert_eq!(object_value.as_object().unwrap()["flag"].as_boolean(), Some(true));
+    /// assert_eq!(XffValue::Null.as_boolean(), None);
+    /// ```
+    pub fn as_boolean(&self) -> Option<bool> {
+        match self {
+            XffValue::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the boolean if it is a `XffValue::Boolean`
+    /// Returns `None` for all other variants
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::XffValue;
+    ///
+    /// let mut bool_value = XffValue::from(true);
+    /// *bool_value.as_boolean_mut().unwrap() = false;
+    ///
+    /// assert!(bool_value.is_false());
+    /// ```
+    pub fn as_boolean_mut(&mut self) -> Option<&mut bool> {
+        match self {
+            XffValue::Boolean(b) => Some(b),
+            _ => None,
+        }
+    }
+
     /// Checks if the value is a string, returns `true` if it is.
     /// Returns `false` for all other variants.
     ///
@@ -413,6 +657,152 @@ impl XffValue {
     pub fn is_null(&self) -> bool {
         matches!(self, XffValue::Null)
     }
+
+    /// Returns the value at the JSON Pointer (RFC 6901), without cloning it
+    ///
+    /// The pointer is made up of reference tokens, each prefixed by `/`. A token is a key for an
+    /// `Object` and an index, without leading zeros, for an `Array`. `~1` in a token stands for
+    /// `/` and `~0` for `~`. The empty pointer `""` refers to the value itself.
+    ///
+    /// Returns `None` if the pointer is malformed or any token does not exist
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::XffValue;
+    ///
+    /// let user = XffValue::from(vec![("name", XffValue::from("Alice")), ("a/b", XffValue::from(true))]);
+    /// let data = XffValue::from(vec![("users", XffValue::from(vec![user]))]);
+    ///
+    /// assert_eq!(data.pointer("/users/0/name"), Some(&XffValue::from("Alice")));
+    /// assert_eq!(data.pointer("/users/0/a~1b"), Some(&XffValue::from(true)));
+    /// assert_eq!(data.pointer("/users/1/name"), None);
+    /// assert_eq!(data.pointer(""), Some(&data));
+    /// ```
+    pub fn pointer(&self, pointer: &str) -> Option<&XffValue> {
+        let mut current = self;
+        for token in pointer_tokens(pointer)? {
+            current = match current {
+                XffValue::Object(object) => object.get(&token?)?,
+                XffValue::Array(array) => array.get(pointer_index(&token?)?)?,
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+
+    /// Returns the value at the JSON Pointer (RFC 6901) mutably, to edit it in place
+    ///
+    /// Follows the same rules as `pointer`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::XffValue;
+    ///
+    /// let mut data = XffValue::from(vec![("users", XffValue::from(vec![XffValue::from("Alice")]))]);
+    ///
+    /// *data.pointer_mut("/users/0").unwrap() = XffValue::from("Bob");
+    /// assert_eq!(data.pointer("/users/0"), Some(&XffValue::from("Bob")));
+    /// ```
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut XffValue> {
+        let mut current = self;
+        for token in pointer_tokens(pointer)? {
+            current = match current {
+                XffValue::Object(object) => object.get_mut(&token?)?,
+                XffValue::Array(array) => array.get_mut(pointer_index(&token?)?)?,
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+
+    /// Returns all values selected by the query, without cloning them
+    ///
+    /// See `Query` for the syntax of queries.
+    ///
+    /// # Errors
+    /// Returns `NabuError::InvalidQuery` if the query is not valid
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::XffValue;
+    ///
+    /// let data = XffValue::from(vec![("ids", XffValue::from(vec![XffValue::from(1), XffValue::from(2)]))]);
+    ///
+    /// assert_eq!(data.query("$.ids[*]").unwrap(), vec![&XffValue::from(1), &XffValue::from(2)]);
+    /// assert!(data.query("ids").is_err());
+    /// ```
+    pub fn query(&self, query: &str) -> Result<Vec<&XffValue>, NabuError> {
+        Ok(Query::parse(query)?.select(self))
+    }
+
+    /// Returns the SHA-256 digest of the canonical byte stream of the value
+    ///
+    /// Values equal by content have the same hash on every machine and in every version of
+    /// nabu, numbers are compared by value no matter their variant. See `serialize_canonical`
+    /// for the canonical byte stream, it is hashed without being held in memory.
+    ///
+    /// # Errors
+    /// Returns `NabuError::InvalidXFFValueForVersion` for `NaN` and infinite floats, and
+    /// `NabuError::InvalidXFFVersion` for the deprecated `CommandCharacter` and `ArrayCmdChar`
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::XffValue;
+    ///
+    /// let a = XffValue::from(vec![("id", XffValue::from(1_u8)), ("name", XffValue::from("Alice"))]);
+    /// let b = XffValue::from(vec![("name", XffValue::from("Alice")), ("id", XffValue::from(1_i64))]);
+    /// let c = XffValue::from(vec![("name", XffValue::from("Bob")), ("id", XffValue::from(1))]);
+    ///
+    /// assert_eq!(a.content_hash().unwrap(), b.content_hash().unwrap());
+    /// assert_ne!(a.content_hash().unwrap(), c.content_hash().unwrap());
+    /// assert!(XffValue::from(f64::NAN).content_hash().is_err());
+    /// ```
+    pub fn content_hash(&self) -> Result<[u8; DIGEST_LEN], NabuError> {
+        let mut hasher = Sha256::new();
+        write_xff_canonical(self, &mut hasher)?;
+        Ok(hasher.finalize())
+    }
+}
+
+/// Splits a JSON Pointer into its unescaped reference tokens
+///
+/// Returns `None` if the pointer is neither empty nor starts with `/`, tokens containing a `~`
+/// not followed by `0` or `1` are `None` as well
+pub(crate) fn pointer_tokens(pointer: &str) -> Option<impl Iterator<Item = Option<String>> + '_> {
+    if !pointer.is_empty() && !pointer.starts_with('/') {
+        return None;
+    }
+    Some(pointer.split('/').skip(1).map(|token| {
+        let mut out = String::with_capacity(token.len());
+        let mut chars = token.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '~' => match chars.next() {
+                    Some('0') => out.push('~'),
+                    Some('1') => out.push('/'),
+                    _ => return None,
+                },
+                c => out.push(c),
+            }
+        }
+        Some(out)
+    }))
+}
+
+/// Appends the escaped reference token to the JSON Pointer
+pub(crate) fn pointer_child(pointer: &str, token: &str) -> String {
+    format!("{}/{}", pointer, token.replace('~', "~0").replace('/', "~1"))
+}
+
+/// Parses an array index of a JSON Pointer, leading zeros and signs are not permitted
+pub(crate) fn pointer_index(token: &str) -> Option<usize> {
+    if token.is_empty()
+        || !token.bytes().all(|b| b.is_ascii_digit())
+        || (token.len() > 1 && token.starts_with('0'))
+    {
+        return None;
+    }
+    token.parse().ok()
 }
 
 // -----------------------------------------------------------
@@ -513,21 +903,13 @@ where
         match c.1 {
             0 => {
                 let string = c.0.into();
-                let check_usize = &string.parse::<usize>();
-                if check_usize.is_ok() {
-                    XffValue::Number(Number::from(check_usize.as_ref().unwrap()))
-                } else {
-                    let check_isize = &string.parse::<isize>();
-                    if check_isize.is_ok() {
-                        XffValue::Number(Number::from(check_isize.as_ref().unwrap()))
-                    } else {
-                        let check_float = &string.parse::<f64>();
-                        if check_float.is_ok() {
-                            XffValue::Number(Number::from(check_float.as_ref().unwrap()))
-                        } else {
-                            XffValue::String(string)
-                        }
-                    }
+                // v0 predates the number grammar, any float Rust can parse is accepted
+                match string.parse::<Number>() {
+                    Ok(num) => XffValue::Number(num),
+                    Err(_) => match string.parse::<f64>() {
+                        Ok(f) => XffValue::Number(Number::from(f)),
+                        Err(_) => XffValue::String(string),
+                    },
                 }
             }
             1 => XffValue::String(c.0.into()),
@@ -578,6 +960,24 @@ impl From<i64> for XffValue {
     }
 }
 
+impl From<u128> for XffValue {
+    fn from(c: u128) -> Self {
+        XffValue::Number(Number::from(c))
+    }
+}
+
+impl From<i128> for XffValue {
+    fn from(c: i128) -> Self {
+        XffValue::Number(Number::from(c))
+    }
+}
+
+impl From<Decimal> for XffValue {
+    fn from(c: Decimal) -> Self {
+        XffValue::Number(Number::from(c))
+    }
+}
+
 impl From<f32> for XffValue {
     fn from(c: f32) -> Self {
         XffValue::Number(Number::from(c))
@@ -1,8 +1,17 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use crate::error::NabuError;
+
 #[derive(Debug, Clone, PartialEq)]
 /// A numeric value.
 ///
 /// `Number::form()` is implemented for all numeric types
 ///
+/// `u128` and `i128` are stored in `Unsigned` and `Integer` if they fit, and in `BigUnsigned` and
+/// `BigInteger` otherwise.
+/// Numbers that fit no other variant without losing precision are stored as `Decimal`.
+///
 /// # Example
 /// ```rust
 /// use nabu::Number;
@@ -28,6 +37,206 @@ pub enum Number {
     Integer(isize),
     /// A float
     Float(f64),
+    /// An unsigned integer too large for `Unsigned`
+    BigUnsigned(u128),
+    /// An integer too small or too large for `Integer`
+    BigInteger(i128),
+    /// A decimal number of arbitrary precision, kept as its decimal text
+    Decimal(Decimal),
+}
+
+/// A decimal number of arbitrary precision, kept as its decimal text
+///
+/// Valid decimal text follows the number grammar of the xff specification: an optional leading
+/// `-`, followed by decimal digits without a superfluous leading zero, with at most one `.` or `,`
+/// decimal separator between them.
+/// The text is kept exactly as it was parsed, so it is written back byte-for-byte.
+///
+/// # Example
+/// ```rust
+/// use nabu::Decimal;
+///
+/// let decimal: Decimal = "0.1000000000000000055".parse().unwrap();
+/// assert_eq!(decimal.as_str(), "0.1000000000000000055");
+/// assert_eq!(decimal.to_f64(), 0.1);
+///
+/// assert!("1e10".parse::<Decimal>().is_err());
+/// assert!("1.2.3".parse::<Decimal>().is_err());
+/// assert!("007".parse::<Decimal>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Decimal {
+    text: String,
+}
+
+impl Decimal {
+    /// Returns the decimal text
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the decimal text, consuming the decimal
+    pub fn into_string(self) -> String {
+        self.text
+    }
+
+    /// Returns the closest float to the decimal
+    /// This loses precision, and is infinite for decimals outside of the range of `f64`
+    pub fn to_f64(&self) -> f64 {
+        self.text
+            .replace(',', ".")
+            .parse::<f64>()
+            .expect("valid decimal text is a valid float")
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = NabuError;
+
+    /// Parses decimal text
+    ///
+    /// # Errors
+    /// Returns `NabuError::InvalidNumber` with the position of the first invalid byte if the text
+    /// is not valid decimal text
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        check_number_text(s.as_bytes())
+            .map(|_| Decimal {
+                text: s.to_string(),
+            })
+            .map_err(|pos| NabuError::InvalidNumber(pos, s.to_string()))
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+// -----------------------------------------------------------
+//                     Number grammar
+// -----------------------------------------------------------
+
+/// Checks the text against the number grammar of the xff specification
+///
+/// A number is an optional `-`, followed by decimal digits with no superfluous leading zero,
+/// optionally followed by a single `.` or `,` decimal separator and more decimal digits.
+/// Anything else, like `+`, exponents, `NaN` or `inf`, is invalid.
+///
+/// # Errors
+/// Returns the position of the first byte violating the grammar, the length of the text if it
+/// ends too early
+pub(crate) fn check_number_text(text: &[u8]) -> Result<(), usize> {
+    let digits_from = |start: usize| {
+        start
+            + text[start.min(text.len())..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .count()
+    };
+    let int_start = usize::from(text.first() == Some(&b'-'));
+    let int_end = digits_from(int_start);
+    if int_end == int_start {
+        return Err(int_start);
+    }
+    if text[int_start] == b'0' && int_end - int_start > 1 {
+        return Err(int_start + 1);
+    }
+    if int_end == text.len() {
+        return Ok(());
+    }
+    if !matches!(text[int_end], b'.' | b',') {
+        return Err(int_end);
+    }
+    let frac_end = digits_from(int_end + 1);
+    if frac_end == int_end + 1 || frac_end != text.len() {
+        return Err(frac_end);
+    }
+    Ok(())
+}
+
+/// Parses number text following the number grammar of the xff specification without losing
+/// precision
+///
+/// # Errors
+/// Returns the position of the first byte violating the grammar
+pub(crate) fn parse_number_text(text: &str) -> Result<Number, usize> {
+    check_number_text(text.as_bytes())?;
+    if let Ok(u) = text.parse::<usize>() {
+        Ok(Number::Unsigned(u))
+    } else if let Ok(i) = text.parse::<isize>() {
+        Ok(Number::Integer(i))
+    } else if let Ok(u) = text.parse::<u128>() {
+        Ok(Number::BigUnsigned(u))
+    } else if let Ok(i) = text.parse::<i128>() {
+        Ok(Number::BigInteger(i))
+    } else {
+        match text.parse::<f64>() {
+            // only if the float is written back unchanged, never for a `,` separator
+            Ok(f) if f.to_string() == text => Ok(Number::Float(f)),
+            _ => Ok(Number::Decimal(Decimal {
+                text: text.to_string(),
+            })),
+        }
+    }
+}
+
+/// Encodes the number as text following the number grammar of the xff specification
+///
+/// Returns `None` for `NaN` and infinite floats, they have no valid text form
+pub(crate) fn encode_number_text(num: &Number) -> Option<String> {
+    let text = num.as_string();
+    check_number_text(text.as_bytes()).ok().map(|_| text)
+}
+
+/// Encodes the number as its canonical text, the same for every number of the same value no
+/// matter its variant
+///
+/// The canonical text is the text of `encode_number_text` with `.` as the decimal separator, and
+/// without trailing zeros of the fraction, a fraction of only zeros, or the sign of zero.
+///
+/// Returns `None` for `NaN` and infinite floats, they have no valid text form
+pub(crate) fn canonical_number_text(num: &Number) -> Option<String> {
+    let mut text = encode_number_text(num)?.replace(',', ".");
+    if text.contains('.') {
+        let len = text.trim_end_matches('0').trim_end_matches('.').len();
+        text.truncate(len);
+    }
+    if text == "-0" {
+        text = String::from("0");
+    }
+    Some(text)
+}
+
+/// Orders numbers by their value, no matter their variant
+///
+/// Returns `None` if either number is `NaN`
+pub(crate) fn compare_numbers(left: &Number, right: &Number) -> Option<Ordering> {
+    match (as_i128(left), as_i128(right)) {
+        (Some(l), Some(r)) => Some(l.cmp(&r)),
+        _ => as_f64(left).partial_cmp(&as_f64(right)),
+    }
+}
+
+fn as_i128(num: &Number) -> Option<i128> {
+    match num {
+        Number::Unsigned(u) => i128::try_from(*u).ok(),
+        Number::Integer(i) => i128::try_from(*i).ok(),
+        Number::BigUnsigned(u) => i128::try_from(*u).ok(),
+        Number::BigInteger(i) => Some(*i),
+        Number::Float(_) | Number::Decimal(_) => None,
+    }
+}
+
+fn as_f64(num: &Number) -> f64 {
+    match num {
+        Number::Unsigned(u) => *u as f64,
+        Number::Integer(i) => *i as f64,
+        Number::BigUnsigned(u) => *u as f64,
+        Number::BigInteger(i) => *i as f64,
+        Number::Float(f) => *f,
+        Number::Decimal(d) => d.to_f64(),
+    }
 }
 
 // -----------------------------------------------------------
@@ -95,6 +304,66 @@ impl Number {
         }
     }
 
+    /// Returns the value as an unsigned integer if it is a `Number::BigUnsigned`
+    /// Returns `None` for all other variants
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::Number;
+    ///
+    /// let num_big = Number::from(u128::MAX);
+    /// let num_u = Number::from(42_u128);
+    ///
+    /// assert_eq!(num_big.into_u128(), Some(u128::MAX));
+    /// assert_eq!(num_u.into_u128(), None);
+    /// ```
+    pub fn into_u128(self) -> Option<u128> {
+        match self {
+            Number::BigUnsigned(u) => Some(u),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as an integer if it is a `Number::BigInteger`
+    /// Returns `None` for all other variants
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::Number;
+    ///
+    /// let num_big = Number::from(i128::MIN);
+    /// let num_i = Number::from(-42_i128);
+    ///
+    /// assert_eq!(num_big.into_i128(), Some(i128::MIN));
+    /// assert_eq!(num_i.into_i128(), None);
+    /// ```
+    pub fn into_i128(self) -> Option<i128> {
+        match self {
+            Number::BigInteger(i) => Some(i),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a decimal if it is a `Number::Decimal`
+    /// Returns `None` for all other variants
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::Number;
+    ///
+    /// let num_d: Number = "0.1000000000000000055".parse().unwrap();
+    /// let num_f: Number = "0.1".parse().unwrap();
+    ///
+    /// assert_eq!(num_d.into_decimal().unwrap().as_str(), "0.1000000000000000055");
+    /// assert_eq!(num_f.into_decimal(), None);
+    /// ```
+    pub fn into_decimal(self) -> Option<Decimal> {
+        match self {
+            Number::Decimal(d) => Some(d),
+            _ => None,
+        }
+    }
+
     /// Returns `true` if the number is an unsigned integer
     /// Returns `false` for all other variants
     ///
@@ -137,6 +406,48 @@ impl Number {
         matches!(self, Number::Float(_))
     }
 
+    /// Returns `true` if the number is a big unsigned integer
+    /// Returns `false` for all other variants
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::Number;
+    ///
+    /// let num_big = Number::from(u128::MAX);
+    /// assert!(num_big.is_big_unsigned());
+    /// ```
+    pub fn is_big_unsigned(&self) -> bool {
+        matches!(self, Number::BigUnsigned(_))
+    }
+
+    /// Returns `true` if the number is a big integer
+    /// Returns `false` for all other variants
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::Number;
+    ///
+    /// let num_big = Number::from(i128::MIN);
+    /// assert!(num_big.is_big_integer());
+    /// ```
+    pub fn is_big_integer(&self) -> bool {
+        matches!(self, Number::BigInteger(_))
+    }
+
+    /// Returns `true` if the number is a decimal
+    /// Returns `false` for all other variants
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::Number;
+    ///
+    /// let num_d: Number = "123456789012345678901234567890123456789012".parse().unwrap();
+    /// assert!(num_d.is_decimal());
+    /// ```
+    pub fn is_decimal(&self) -> bool {
+        matches!(self, Number::Decimal(_))
+    }
+
     /// Returns the number formatted as an ASCII string
     ///
     /// # Example
@@ -157,10 +468,58 @@ impl Number {
             Number::Unsigned(u) => format!("{}", u),
             Number::Integer(i) => format!("{}", i),
             Number::Float(f) => format!("{}", f),
+            Number::BigUnsigned(u) => format!("{}", u),
+            Number::BigInteger(i) => format!("{}", i),
+            Number::Decimal(d) => d.to_string(),
         }
     }
 }
 
+// -----------------------------------------------------------
+//                     FromStr implementation
+// -----------------------------------------------------------
+
+impl FromStr for Number {
+    type Err = NabuError;
+
+    /// Parses number text without losing precision
+    ///
+    /// The text has to follow the number grammar of the xff specification: an optional `-`,
+    /// followed by decimal digits without a superfluous leading zero, with at most one `.` or `,`
+    /// decimal separator between them.
+    ///
+    /// Integers are stored in the smallest variant that fits them.
+    /// Text that reads back as a float and is written out unchanged is stored as `Float`, any
+    /// other decimal text as `Decimal`.
+    ///
+    /// # Errors
+    /// Returns `NabuError::InvalidNumber` with the position of the first invalid byte if the text
+    /// is not a valid number
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::Number;
+    ///
+    /// assert_eq!("42".parse::<Number>().unwrap(), Number::Unsigned(42));
+    /// assert_eq!("42.69".parse::<Number>().unwrap(), Number::Float(42.69));
+    /// assert_eq!(
+    ///     "123456789012345678901234567890".parse::<Number>().unwrap(),
+    ///     Number::BigUnsigned(123456789012345678901234567890)
+    /// );
+    ///
+    /// let decimal = "0.1000000000000000055".parse::<Number>().unwrap();
+    /// assert!(decimal.is_decimal());
+    /// assert_eq!(decimal.as_string(), "0.1000000000000000055");
+    ///
+    /// assert!("1e5".parse::<Number>().is_err());
+    /// assert!("007".parse::<Number>().is_err());
+    /// assert!("NaN".parse::<Number>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_number_text(s).map_err(|pos| NabuError::InvalidNumber(pos, s.to_string()))
+    }
+}
+
 // -----------------------------------------------------------
 //                     From implementations
 // -----------------------------------------------------------
@@ -249,6 +608,30 @@ impl From<&f64> for Number {
     }
 }
 
+impl From<u128> for Number {
+    fn from(c: u128) -> Self {
+        match usize::try_from(c) {
+            Ok(u) => Number::Unsigned(u),
+            Err(_) => Number::BigUnsigned(c),
+        }
+    }
+}
+
+impl From<i128> for Number {
+    fn from(c: i128) -> Self {
+        match isize::try_from(c) {
+            Ok(i) => Number::Integer(i),
+            Err(_) => Number::BigInteger(c),
+        }
+    }
+}
+
+impl From<Decimal> for Number {
+    fn from(c: Decimal) -> Self {
+        Number::Decimal(c)
+    }
+}
+
 impl From<f32> for Number {
     fn from(c: f32) -> Self {
         Number::Float(c as f64)
@@ -265,6 +648,9 @@ impl std::fmt::Display for Number {
             Number::Unsigned(u) => write!(f, "{}", u),
             Number::Integer(i) => write!(f, "{}", i),
             Number::Float(fl) => write!(f, "{}", fl),
+            Number::BigUnsigned(u) => write!(f, "{}", u),
+            Number::BigInteger(i) => write!(f, "{}", i),
+            Number::Decimal(d) => write!(f, "{}", d),
         }
     }
 }
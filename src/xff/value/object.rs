@@ -246,6 +246,24 @@ impl Object {
         self.map.get(key)
     }
 
+    /// Returns a mutable reference to the value of the supplied key, to edit it in place
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::{XffValue, Object};
+    ///
+    /// let mut xff_obj_value = Object::from(vec![
+    ///     ("keyA".to_string(), XffValue::from(vec![XffValue::from("hi mom!")])),
+    /// ]);
+    ///
+    /// xff_obj_value.get_mut("keyA").unwrap().as_array_mut().unwrap().push(42.69);
+    /// assert_eq!(xff_obj_value.get("keyA"), Some(&XffValue::from(vec![XffValue::from("hi mom!"), XffValue::from(42.69)])));
+    /// assert_eq!(xff_obj_value.get_mut("keyB"), None);
+    /// ```
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut XffValue> {
+        self.map.get_mut(key)
+    }
+
     /// Returns the number of key-value pairs in the object, also known as its length.
     ///
     /// # Example
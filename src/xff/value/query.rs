@@ -0,0 +1,542 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use crate::{
+    error::NabuError,
+    xff::value::{num::compare_numbers, Number, XffValue},
+};
+
+/// A parsed query selecting any number of values nested inside of a `XffValue`
+///
+/// Queries are written in a subset of JSONPath and always start with `$`, the value the query is
+/// run on. It is followed by any number of segments, each selecting values inside of the values
+/// selected so far:
+///
+/// * `.key` or `["key"]` - The value of the key in an object, `['key']` works as well
+/// * `[3]` - The element at the index of an array, negative indices count from the end
+/// * `[1:3]` - The elements of an array in the slice, as `[start:end:step]` with every part optional
+/// * `.*` or `[*]` - All elements of an array or all values of an object
+/// * `[?(@.age >= 18)]` - All elements of an array or values of an object matching the filter
+/// * `..key` - The key in the value itself and in all values nested inside of it, `..*` and
+///   `..[...]` work as well
+///
+/// Filters compare a value relative to the candidate `@` with a literal or another relative value,
+/// using one of `==`, `!=`, `<`, `<=`, `>` or `>=`. Literals are numbers, strings in quotes, `true`,
+/// `false` and `null`. Only numbers and strings are ordered, numbers compare by their value no
+/// matter their variant. A filter without a comparison, like `[?(@.email)]`, matches if the value
+/// exists.
+///
+/// Keys containing whitespace or any of `.[]()=!<>` need to be quoted.
+///
+/// # Example
+/// ```rust
+/// use nabu::{Query, XffValue};
+///
+/// let users = XffValue::from(vec![
+///     XffValue::from(vec![("name", XffValue::from("Alice")), ("age", XffValue::from(31))]),
+///     XffValue::from(vec![("name", XffValue::from("Bob")), ("age", XffValue::from(17))]),
+///     XffValue::from(vec![("name", XffValue::from("Carol")), ("age", XffValue::from(45))]),
+/// ]);
+/// let data = XffValue::from(vec![("users", users)]);
+///
+/// let query: Query = "$.users[?(@.age >= 18)].name".parse().unwrap();
+/// assert_eq!(query.select(&data), vec![&XffValue::from("Alice"), &XffValue::from("Carol")]);
+///
+/// let names = data.query("$.users[-2:].name").unwrap();
+/// assert_eq!(names, vec![&XffValue::from("Bob"), &XffValue::from("Carol")]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    /// Applies the selector to the value
+    Child(Selector),
+    /// Applies the selector to the value and every value nested inside of it
+    Descendant(Selector),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Key(String),
+    Index(i64),
+    Slice(Option<i64>, Option<i64>, i64),
+    Wildcard,
+    Filter(Filter),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+    left: Operand,
+    comparison: Option<(Comparison, Operand)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    /// A path relative to the candidate, only made up of keys and indices
+    Current(Vec<Selector>),
+    Literal(XffValue),
+}
+
+impl Query {
+    /// Parses a query
+    ///
+    /// # Errors
+    /// Returns `NabuError::InvalidQuery` with the position of the first invalid character if the
+    /// query is not valid
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::Query;
+    ///
+    /// assert!(Query::parse("$.users[0].name").is_ok());
+    /// assert!(Query::parse("users[0].name").is_err());
+    /// ```
+    pub fn parse(query: &str) -> Result<Query, NabuError> {
+        let mut parser = Parser {
+            query,
+            chars: query.chars().collect(),
+            pos: 0,
+        };
+        parser.expect('$')?;
+        let mut segments: Vec<Segment> = Vec::new();
+        while parser.peek().is_some() {
+            segments.push(parser.segment()?);
+        }
+        Ok(Query { segments })
+    }
+
+    /// Returns all values selected by the query, in the order they appear in the value
+    ///
+    /// Values of objects are visited in the order of their keys.
+    ///
+    /// # Arguments
+    /// * `value` - The value to run the query on, `$` in the query
+    pub fn select<'a>(&self, value: &'a XffValue) -> Vec<&'a XffValue> {
+        let mut current: Vec<&'a XffValue> = vec![value];
+        for segment in &self.segments {
+            let mut next: Vec<&'a XffValue> = Vec::new();
+            for value in current {
+                match segment {
+                    Segment::Child(selector) => selector.select(value, &mut next),
+                    Segment::Descendant(selector) => select_descendants(selector, value, &mut next),
+                }
+            }
+            current = next;
+        }
+        current
+    }
+}
+
+impl FromStr for Query {
+    type Err = NabuError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Query::parse(s)
+    }
+}
+
+fn select_descendants<'a>(selector: &Selector, value: &'a XffValue, out: &mut Vec<&'a XffValue>) {
+    selector.select(value, out);
+    match value {
+        XffValue::Array(array) => {
+            for element in array.iter() {
+                select_descendants(selector, element, out);
+            }
+        }
+        XffValue::Object(object) => {
+            for (_, element) in object.iter() {
+                select_descendants(selector, element, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+impl Selector {
+    fn select<'a>(&self, value: &'a XffValue, out: &mut Vec<&'a XffValue>) {
+        match (self, value) {
+            (Selector::Key(key), XffValue::Object(object)) => out.extend(object.get(key)),
+            (Selector::Index(index), XffValue::Array(array)) => {
+                let index = if *index < 0 {
+                    array.len() as i64 + index
+                } else {
+                    *index
+                };
+                if let Ok(index) = usize::try_from(index) {
+                    out.extend(array.get(index));
+                }
+            }
+            (Selector::Slice(start, end, step), XffValue::Array(array)) => {
+                for index in slice_indices(array.len() as i64, *start, *end, *step) {
+                    out.push(&array[index]);
+                }
+            }
+            (Selector::Wildcard, XffValue::Array(array)) => out.extend(array.iter()),
+            (Selector::Wildcard, XffValue::Object(object)) => {
+                out.extend(object.iter().map(|(_, v)| v))
+            }
+            (Selector::Filter(filter), XffValue::Array(array)) => {
+                out.extend(array.iter().filter(|v| filter.matches(v)))
+            }
+            (Selector::Filter(filter), XffValue::Object(object)) => {
+                out.extend(object.iter().map(|(_, v)| v).filter(|v| filter.matches(v)))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns the indices of a slice with the semantics of python, `step` is never 0
+fn slice_indices(len: i64, start: Option<i64>, end: Option<i64>, step: i64) -> Vec<usize> {
+    let normalize = |i: i64| if i < 0 { i + len } else { i };
+    let mut out: Vec<usize> = Vec::new();
+    if step > 0 {
+        let start = start.map_or(0, normalize).clamp(0, len);
+        let end = end.map_or(len, normalize).clamp(0, len);
+        let mut i = start;
+        while i < end {
+            out.push(i as usize);
+            i = match i.checked_add(step) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+    } else {
+        let start = start.map_or(len - 1, normalize).clamp(-1, len - 1);
+        let end = end.map_or(-1, |e| normalize(e).clamp(-1, len - 1));
+        let mut i = start;
+        while i > end {
+            out.push(i as usize);
+            i = match i.checked_add(step) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+    }
+    out
+}
+
+impl Filter {
+    fn matches(&self, candidate: &XffValue) -> bool {
+        let left = match self.left.resolve(candidate) {
+            Some(left) => left,
+            None => return false,
+        };
+        let (comparison, right) = match &self.comparison {
+            Some((comparison, right)) => (comparison, right),
+            None => return true,
+        };
+        let right = match right.resolve(candidate) {
+            Some(right) => right,
+            None => return false,
+        };
+        let ordering = compare(left, right);
+        match comparison {
+            Comparison::Equal => ordering == Some(Ordering::Equal) || left == right,
+            Comparison::NotEqual => ordering != Some(Ordering::Equal) && left != right,
+            Comparison::Less => ordering == Some(Ordering::Less),
+            Comparison::LessEqual => {
+                matches!(ordering, Some(Ordering::Less | Ordering::Equal))
+            }
+            Comparison::Greater => ordering == Some(Ordering::Greater),
+            Comparison::GreaterEqual => {
+                matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+            }
+        }
+    }
+}
+
+impl Operand {
+    fn resolve<'a>(&'a self, candidate: &'a XffValue) -> Option<&'a XffValue> {
+        match self {
+            Operand::Literal(value) => Some(value),
+            Operand::Current(path) => {
+                let mut current = candidate;
+                for selector in path {
+                    let mut out: Vec<&XffValue> = Vec::new();
+                    selector.select(current, &mut out);
+                    current = out.pop()?;
+                }
+                Some(current)
+            }
+        }
+    }
+}
+
+/// Orders numbers by their value and strings lexicographically, all other values are unordered
+fn compare(left: &XffValue, right: &XffValue) -> Option<Ordering> {
+    match (left, right) {
+        (XffValue::Number(l), XffValue::Number(r)) => compare_numbers(l, r),
+        (XffValue::String(l), XffValue::String(r)) => Some(l.cmp(r)),
+        _ => None,
+    }
+}
+
+// -----------------------------------------------------------
+//                          Parser
+// -----------------------------------------------------------
+
+struct Parser<'a> {
+    query: &'a str,
+    chars: Vec<char>,
+    /// Position of the next character
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self) -> NabuError {
+        NabuError::InvalidQuery(self.pos, self.query.to_string())
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let out = self.peek();
+        if out.is_some() {
+            self.pos += 1;
+        }
+        out
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), NabuError> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn segment(&mut self) -> Result<Segment, NabuError> {
+        match self.next() {
+            Some('.') => {
+                if self.eat('.') {
+                    if self.eat('[') {
+                        Ok(Segment::Descendant(self.bracket()?))
+                    } else {
+                        Ok(Segment::Descendant(self.dot_selector()?))
+                    }
+                } else {
+                    Ok(Segment::Child(self.dot_selector()?))
+                }
+            }
+            Some('[') => Ok(Segment::Child(self.bracket()?)),
+            _ => {
+                self.pos -= 1;
+                Err(self.error())
+            }
+        }
+    }
+
+    /// Parses the selector after a `.`
+    fn dot_selector(&mut self) -> Result<Selector, NabuError> {
+        if self.eat('*') {
+            return Ok(Selector::Wildcard);
+        }
+        Ok(Selector::Key(self.name()?))
+    }
+
+    fn name(&mut self) -> Result<String, NabuError> {
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if ".[]()=!<>".contains(c) || c.is_whitespace() {
+                break;
+            }
+            name.push(c);
+            self.pos += 1;
+        }
+        if name.is_empty() {
+            return Err(self.error());
+        }
+        Ok(name)
+    }
+
+    /// Parses the selector after a `[` up to and including the closing `]`
+    fn bracket(&mut self) -> Result<Selector, NabuError> {
+        let selector = match self.peek() {
+            Some('*') => {
+                self.pos += 1;
+                Selector::Wildcard
+            }
+            Some('\'' | '"') => Selector::Key(self.quoted()?),
+            Some('?') => {
+                self.pos += 1;
+                Selector::Filter(self.filter()?)
+            }
+            _ => {
+                let start = self.optional_integer()?;
+                if self.eat(':') {
+                    let end = self.optional_integer()?;
+                    let step = if self.eat(':') {
+                        self.optional_integer()?.unwrap_or(1)
+                    } else {
+                        1
+                    };
+                    if step == 0 {
+                        return Err(self.error());
+                    }
+                    Selector::Slice(start, end, step)
+                } else {
+                    Selector::Index(start.ok_or(self.error())?)
+                }
+            }
+        };
+        self.expect(']')?;
+        Ok(selector)
+    }
+
+    /// Parses a string in single or double quotes, `\` escapes the next character
+    fn quoted(&mut self) -> Result<String, NabuError> {
+        let quote = self.next().ok_or(self.error())?;
+        let mut out = String::new();
+        loop {
+            match self.next() {
+                Some('\\') => out.push(self.next().ok_or(self.error())?),
+                Some(c) if c == quote => return Ok(out),
+                Some(c) => out.push(c),
+                None => return Err(self.error()),
+            }
+        }
+    }
+
+    fn optional_integer(&mut self) -> Result<Option<i64>, NabuError> {
+        let start = self.pos;
+        let mut text = String::new();
+        if self.eat('-') {
+            text.push('-');
+        }
+        while let Some(c) = self.peek().filter(char::is_ascii_digit) {
+            text.push(c);
+            self.pos += 1;
+        }
+        if text.is_empty() {
+            return Ok(None);
+        }
+        text.parse().map(Some).map_err(|_| {
+            self.pos = start;
+            self.error()
+        })
+    }
+
+    /// Parses a filter after the `?`, up to and including the closing `)`
+    fn filter(&mut self) -> Result<Filter, NabuError> {
+        self.expect('(')?;
+        self.skip_whitespace();
+        let left = self.operand()?;
+        self.skip_whitespace();
+        let comparison = if self.peek() == Some(')') {
+            None
+        } else {
+            let comparison = self.comparison()?;
+            self.skip_whitespace();
+            let right = self.operand()?;
+            self.skip_whitespace();
+            Some((comparison, right))
+        };
+        self.expect(')')?;
+        Ok(Filter { left, comparison })
+    }
+
+    fn comparison(&mut self) -> Result<Comparison, NabuError> {
+        let comparison = match (self.next(), self.peek()) {
+            (Some('='), Some('=')) => Comparison::Equal,
+            (Some('!'), Some('=')) => Comparison::NotEqual,
+            (Some('<'), Some('=')) => Comparison::LessEqual,
+            (Some('>'), Some('=')) => Comparison::GreaterEqual,
+            (Some('<'), _) => return Ok(Comparison::Less),
+            (Some('>'), _) => return Ok(Comparison::Greater),
+            _ => {
+                self.pos -= 1;
+                return Err(self.error());
+            }
+        };
+        self.pos += 1;
+        Ok(comparison)
+    }
+
+    fn operand(&mut self) -> Result<Operand, NabuError> {
+        match self.peek() {
+            Some('@') => {
+                self.pos += 1;
+                let mut path: Vec<Selector> = Vec::new();
+                loop {
+                    if self.eat('.') {
+                        path.push(Selector::Key(self.name()?));
+                    } else if self.eat('[') {
+                        let selector = match self.peek() {
+                            Some('\'' | '"') => Selector::Key(self.quoted()?),
+                            _ => Selector::Index(self.optional_integer()?.ok_or(self.error())?),
+                        };
+                        self.expect(']')?;
+                        path.push(selector);
+                    } else {
+                        return Ok(Operand::Current(path));
+                    }
+                }
+            }
+            Some('\'' | '"') => Ok(Operand::Literal(XffValue::from(self.quoted()?))),
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let start = self.pos;
+                let mut text = String::new();
+                while let Some(c) = self
+                    .peek()
+                    .filter(|c| *c == '-' || *c == '.' || c.is_ascii_digit())
+                {
+                    text.push(c);
+                    self.pos += 1;
+                }
+                let num = Number::from_str(&text).map_err(|_| {
+                    self.pos = start;
+                    self.error()
+                })?;
+                Ok(Operand::Literal(XffValue::Number(num)))
+            }
+            _ => {
+                let start = self.pos;
+                let mut word = String::new();
+                while let Some(c) = self.peek().filter(char::is_ascii_alphabetic) {
+                    word.push(c);
+                    self.pos += 1;
+                }
+                match word.as_str() {
+                    "true" => Ok(Operand::Literal(XffValue::Boolean(true))),
+                    "false" => Ok(Operand::Literal(XffValue::Boolean(false))),
+                    "null" => Ok(Operand::Literal(XffValue::Null)),
+                    _ => {
+                        self.pos = start;
+                        Err(self.error())
+                    }
+                }
+            }
+        }
+    }
+}
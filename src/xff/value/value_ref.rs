@@ -0,0 +1,229 @@
+use std::borrow::Cow;
+
+use crate::{
+    error::NabuError,
+    xff::{
+        deserializer::v1::decode_xff_v1_char,
+        value::{Array, Data, Number, Object, XffValue},
+    },
+};
+
+/// A value borrowed from a XFF byte stream
+///
+/// Returned by `nabu::serde::from_bytes_ref`, `Data` is a slice of the byte stream and `String`s
+/// are only decoded once they are accessed.
+/// Use `to_owned` to convert it into a `XffValue`.
+///
+/// # Example
+/// ```rust
+/// use nabu::serde::{from_bytes_ref, to_bytes};
+/// use nabu::{XffValue, XffValueRef};
+///
+/// let data = XffValue::from(vec![XffValue::from("hello mom"), XffValue::from(vec![0_u8, 1, 2])]);
+/// let bytes = to_bytes(data.clone()).unwrap();
+///
+/// let value_ref: XffValueRef = from_bytes_ref(&bytes).unwrap();
+/// let array = value_ref.as_array().unwrap();
+/// assert_eq!(array[0].as_string().unwrap().to_str().unwrap(), "hello mom");
+/// assert_eq!(array[1].as_data(), Some(&[0_u8, 1, 2][..]));
+///
+/// assert_eq!(value_ref.to_owned().unwrap(), data);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum XffValueRef<'a> {
+    /// A string, decoded lazily
+    String(XffStr<'a>),
+    /// A number
+    Number(Number),
+    /// An array
+    Array(Vec<XffValueRef<'a>>),
+    /// An object, as key-value pairs in the order of the byte stream
+    Object(Vec<(XffStr<'a>, XffValueRef<'a>)>),
+    /// Arbitrary data, borrowed from the byte stream
+    Data(&'a [u8]),
+    /// A boolean
+    Boolean(bool),
+    /// A null value
+    Null,
+}
+
+impl<'a> XffValueRef<'a> {
+    /// Returns the string if it is a `XffValueRef::String`
+    /// Returns `None` for all other variants
+    pub fn as_string(&self) -> Option<&XffStr<'a>> {
+        match self {
+            XffValueRef::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the number if it is a `XffValueRef::Number`
+    /// Returns `None` for all other variants
+    pub fn as_number(&self) -> Option<&Number> {
+        match self {
+            XffValueRef::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements if it is a `XffValueRef::Array`
+    /// Returns `None` for all other variants
+    pub fn as_array(&self) -> Option<&[XffValueRef<'a>]> {
+        match self {
+            XffValueRef::Array(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// Returns the key-value pairs if it is a `XffValueRef::Object`
+    /// Returns `None` for all other variants
+    pub fn as_object(&self) -> Option<&[(XffStr<'a>, XffValueRef<'a>)]> {
+        match self {
+            XffValueRef::Object(o) => Some(o),
+            _ => None,
+        }
+    }
+
+    /// Returns the borrowed data if it is a `XffValueRef::Data`
+    /// Returns `None` for all other variants
+    pub fn as_data(&self) -> Option<&'a [u8]> {
+        match self {
+            XffValueRef::Data(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Returns the boolean if it is a `XffValueRef::Boolean`
+    /// Returns `None` for all other variants
+    pub fn as_boolean(&self) -> Option<bool> {
+        match self {
+            XffValueRef::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the value is a `XffValueRef::Null`
+    pub fn is_null(&self) -> bool {
+        matches!(self, XffValueRef::Null)
+    }
+
+    /// Returns the value of the key if it is a `XffValueRef::Object` containing the key
+    /// Returns `None` for all other variants
+    ///
+    /// Keys that cannot be decoded never match.
+    ///
+    /// # Example
+    /// ```rust
+    /// use nabu::serde::{from_bytes_ref, to_bytes};
+    /// use nabu::{Number, Object, XffValue};
+    ///
+    /// let mut object = Object::new();
+    /// object.insert("key", 42);
+    /// let bytes = to_bytes(XffValue::from(object)).unwrap();
+    ///
+    /// let value_ref = from_bytes_ref(&bytes).unwrap();
+    /// assert_eq!(value_ref.get("key").unwrap().as_number(), Some(&Number::from(42)));
+    /// assert!(value_ref.get("missing").is_none());
+    /// ```
+    pub fn get(&self, key: &str) -> Option<&XffValueRef<'a>> {
+        self.as_object()?
+            .iter()
+            .find(|(k, _)| k.to_str().is_ok_and(|k| k == key))
+            .map(|(_, v)| v)
+    }
+
+    /// Converts the borrowed value into an owned `XffValue`, decoding all strings and copying all
+    /// data
+    ///
+    /// # Errors
+    /// Returns the errors of `XffStr::to_str` for any string or key that cannot be decoded
+    pub fn to_owned(&self) -> Result<XffValue, NabuError> {
+        Ok(match self {
+            XffValueRef::String(s) => XffValue::String(s.to_str()?.into_owned()),
+            XffValueRef::Number(n) => XffValue::Number(n.clone()),
+            XffValueRef::Array(a) => XffValue::Array(Array::from(
+                a.iter()
+                    .map(XffValueRef::to_owned)
+                    .collect::<Result<Vec<XffValue>, NabuError>>()?,
+            )),
+            XffValueRef::Object(o) => {
+                let mut object = Object::new();
+                for (key, value) in o {
+                    object.insert(key.to_str()?.into_owned(), value.to_owned()?);
+                }
+                XffValue::Object(object)
+            }
+            XffValueRef::Data(d) => XffValue::Data(Data::from(d.to_vec())),
+            XffValueRef::Boolean(b) => XffValue::Boolean(*b),
+            XffValueRef::Null => XffValue::Null,
+        })
+    }
+}
+
+/// A string borrowed from a XFF byte stream, decoded only when accessed
+#[derive(Debug, Clone)]
+pub struct XffStr<'a> {
+    bytes: &'a [u8],
+    version: u8,
+    /// Position of the first byte of the string in the byte stream
+    byte_pos: usize,
+}
+
+impl<'a> XffStr<'a> {
+    pub(crate) fn new(bytes: &'a [u8], version: u8, byte_pos: usize) -> Self {
+        XffStr {
+            bytes,
+            version,
+            byte_pos,
+        }
+    }
+
+    /// Returns the encoded bytes of the string, as stored in the byte stream
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Decodes the string
+    ///
+    /// v2 strings, and v1 strings made up of only ASCII characters, are borrowed from the byte
+    /// stream, all other v1 strings are decoded into an owned `String`.
+    ///
+    /// # Errors
+    /// Returns `NabuError::InvalidUTF8String` for v2 strings that are not valid UTF-8 and
+    /// `NabuError::InvalidASCIIString` for v1 strings containing bytes not permissible in a v1
+    /// `String`
+    pub fn to_str(&self) -> Result<Cow<'a, str>, NabuError> {
+        if self.version == 1 {
+            if let Some(pos) = self
+                .bytes
+                .iter()
+                .position(|b| decode_xff_v1_char(*b).is_none())
+            {
+                return Err(NabuError::InvalidASCIIString(
+                    self.bytes[pos],
+                    self.byte_pos + pos,
+                    1,
+                ));
+            }
+            if self.bytes.is_ascii() {
+                Ok(Cow::Borrowed(
+                    std::str::from_utf8(self.bytes).expect("ASCII is valid UTF-8"),
+                ))
+            } else {
+                Ok(Cow::Owned(
+                    self.bytes.iter().map(|b| char::from(*b)).collect(),
+                ))
+            }
+        } else {
+            std::str::from_utf8(self.bytes)
+                .map(Cow::Borrowed)
+                .map_err(|e| NabuError::InvalidUTF8String(self.byte_pos + e.valid_up_to()))
+        }
+    }
+}
+
+impl PartialEq for XffStr<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes && self.version == other.version
+    }
+}
@@ -0,0 +1,45 @@
+#[cfg(test)]
+mod atomic_writes {
+    use std::fs;
+
+    use nabu::*;
+
+    #[test]
+    fn replace_files() {
+        let dir = "xff-example-data/atomic";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir(dir).unwrap();
+        let path = format!("{dir}/data.xff");
+        serde::write(&path, XffValue::from("old")).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        }
+        serde::write(&path, XffValue::from("new")).unwrap();
+        assert_eq!(serde::read(&path).unwrap(), XffValue::from("new"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        // no temporary file is left behind
+        let files: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(files, vec!["data.xff"]);
+
+        let in_place = serde::WriteOptions::new().atomic(false);
+        assert!(!in_place.is_atomic());
+        assert!(serde::WriteOptions::default().is_atomic());
+        serde::write_with_options(&path, XffValue::from("in place"), &in_place).unwrap();
+        assert_eq!(serde::read(&path).unwrap(), XffValue::from("in place"));
+
+        // a failed write leaves neither the target nor a temporary file
+        assert!(serde::write(format!("{dir}/missing/data.xff"), XffValue::Null).is_err());
+        assert_eq!(fs::read_dir(dir).unwrap().count(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
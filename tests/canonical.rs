@@ -0,0 +1,269 @@
+#[cfg(test)]
+mod canonical {
+    use std::collections::HashMap;
+
+    use nabu::serde::{from_bytes, serialize_canonical, to_bytes, to_bytes_legacy};
+    use nabu::*;
+
+    fn hex(hash: [u8; 32]) -> String {
+        hash.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn number(text: &str) -> XffValue {
+        XffValue::Number(Number::Decimal(text.parse().unwrap()))
+    }
+
+    fn document() -> XffValue {
+        let mut object = Object::new();
+        object.insert("name", "hello mom");
+        object.insert("emoji", "hello 🌍");
+        object.insert("data", vec![7_u8; 300]);
+        object.insert(
+            "numbers",
+            vec![
+                XffValue::from(42),
+                XffValue::from(-42),
+                XffValue::from(4.2),
+                XffValue::from(u128::MAX),
+            ],
+        );
+        object.insert(
+            "nested",
+            vec![XffValue::from(vec![XffValue::Null, XffValue::from(true)])],
+        );
+        object.insert("empty", XffValue::from(Object::new()));
+        XffValue::from(object)
+    }
+
+    #[test]
+    fn hash_vectors() {
+        // digests of the canonical byte streams as computed by an independent SHA-256, the
+        // lengths of the byte streams cover the edges of the padding
+        let cases = [
+            (
+                0,
+                "ace4be564fc8f55262a079ded61669123c931b90bbf6834249bf534df8a67273",
+            ),
+            (
+                49,
+                "fb8d586fa4e855257b50d7521f16fdc64be514682e5148d8cdca7dabfbfb1a12",
+            ),
+            (
+                50,
+                "d9ad5f095f140c179b27585a68a71a4814d58b73da96fb435a29442f61586a71",
+            ),
+            (
+                58,
+                "38a39d00cedfd67a1e475f93db13a784814b7dee6f27b94643bde84110ffd49b",
+            ),
+            (
+                200,
+                "7199a20b703564030ff608fd052de4cf37da5443ac610580ecad83db0c3015b9",
+            ),
+            (
+                10_000,
+                "63c02ba6db0e8fd078fa8a99782b475cff7a6254c59547643e1df6d9fbf1811b",
+            ),
+        ];
+        for (len, digest) in cases {
+            let data: Vec<u8> = (0..len).map(|i| (i % 256) as u8).collect();
+            assert_eq!(hex(XffValue::from(data).content_hash().unwrap()), digest);
+        }
+        assert_eq!(
+            hex(XffValue::Null.content_hash().unwrap()),
+            "88c11a25f99c684ef7fd98b91c9d2df5609e1f86f3eab12c848dd11e5a92025a"
+        );
+    }
+
+    #[test]
+    fn structure() {
+        // no trailing RS
+        let value = XffValue::from(vec![XffValue::Null, XffValue::Null]);
+        assert_eq!(
+            serialize_canonical(&value).unwrap(),
+            vec![2, 3, 1, 3, 0, 30, 0, 24, 25]
+        );
+        let value = XffValue::from(vec![("a", XffValue::from(true))]);
+        assert_eq!(
+            serialize_canonical(&value).unwrap(),
+            vec![2, 4, 1, 9, 29, 1, 1, 1, b'a', 24, 31, 16, 29, 24, 25]
+        );
+        let value = XffValue::from(Vec::<XffValue>::new());
+        assert_eq!(
+            serialize_canonical(&value).unwrap(),
+            vec![2, 3, 1, 0, 24, 25]
+        );
+        // minimal length prefixes
+        let bytes = serialize_canonical(&XffValue::from("a".repeat(300))).unwrap();
+        assert_eq!(bytes[..5], [2, 1, 2, 44, 1]);
+        let bytes = serialize_canonical(&XffValue::from("a".repeat(255))).unwrap();
+        assert_eq!(bytes[..4], [2, 1, 1, 255]);
+    }
+
+    #[test]
+    fn numbers() {
+        let equal = [
+            vec![
+                XffValue::from(5_u8),
+                XffValue::from(5_i64),
+                XffValue::from(5.0),
+                number("5.000"),
+                number("5,0"),
+            ],
+            vec![
+                XffValue::from(0),
+                XffValue::from(-0.0),
+                number("-0.0"),
+                number("0,00"),
+            ],
+            vec![XffValue::from(-0.5), number("-0,50")],
+            vec![
+                XffValue::from(1e20),
+                XffValue::from(100_000_000_000_000_000_000_u128),
+            ],
+            vec![XffValue::from(i128::MIN), number(&i128::MIN.to_string())],
+        ];
+        for values in equal.iter() {
+            let bytes = serialize_canonical(&values[0]).unwrap();
+            for value in values.iter() {
+                assert_eq!(serialize_canonical(value).unwrap(), bytes, "{:?}", value);
+                assert_eq!(
+                    value.content_hash().unwrap(),
+                    values[0].content_hash().unwrap()
+                );
+            }
+        }
+        // the first binary type holding the value exactly
+        let bytes = serialize_canonical(&number("-0,50")).unwrap();
+        assert_eq!(
+            bytes,
+            [&[2, 2, 1, 9, 3][..], &(-0.5_f64).to_le_bytes(), &[24, 25]].concat()
+        );
+        let bytes = serialize_canonical(&XffValue::from(-1)).unwrap();
+        assert_eq!(
+            bytes,
+            [&[2, 2, 1, 9, 2][..], &(-1_i64).to_le_bytes(), &[24, 25]].concat()
+        );
+        let bytes = serialize_canonical(&number("0.1000000000000000055")).unwrap();
+        assert_eq!(
+            bytes,
+            [&[2, 2, 1, 22, 6][..], b"0.1000000000000000055", &[24, 25]].concat()
+        );
+
+        // different values are never equal
+        let different = [
+            XffValue::from(0.1),
+            number("0.1000000000000000055"),
+            XffValue::from(1),
+            XffValue::from(-1),
+            XffValue::from("1"),
+            XffValue::from(vec![1_u8]),
+        ];
+        for (i, a) in different.iter().enumerate() {
+            for b in different.iter().skip(i + 1) {
+                assert_ne!(
+                    a.content_hash().unwrap(),
+                    b.content_hash().unwrap(),
+                    "{:?} {:?}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn versions() {
+        let value = document();
+        let hash = value.content_hash().unwrap();
+        let v2 = from_bytes(&to_bytes(value.clone()).unwrap()).unwrap();
+        assert_eq!(v2.content_hash().unwrap(), hash);
+
+        // v1 can not hold the emoji, and reads numbers back as a different variant
+        let mut value = document();
+        value.as_object_mut().unwrap().remove("emoji");
+        let v1 = from_bytes(&to_bytes_legacy(vec![value.clone()], 1).unwrap()).unwrap();
+        assert_eq!(v1.content_hash().unwrap(), value.content_hash().unwrap());
+        assert_ne!(v1.content_hash().unwrap(), hash);
+
+        // the order objects are built in does not matter
+        let mut map = HashMap::new();
+        for i in 0..100 {
+            map.insert(format!("key {i}"), XffValue::from(i));
+        }
+        let mut reversed = Object::new();
+        for i in (0..100).rev() {
+            reversed.insert(format!("key {i}"), XffValue::from(i));
+        }
+        assert_eq!(
+            XffValue::from(map).content_hash().unwrap(),
+            XffValue::from(reversed).content_hash().unwrap()
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let value = document();
+        let decoded = from_bytes(&serialize_canonical(&value).unwrap()).unwrap();
+        assert_eq!(
+            decoded.content_hash().unwrap(),
+            value.content_hash().unwrap()
+        );
+        assert_eq!(
+            serialize_canonical(&decoded).unwrap(),
+            serialize_canonical(&value).unwrap()
+        );
+        // numbers are decoded with their value, as the first variant holding it
+        let numbers = decoded.as_object().unwrap().get("numbers").unwrap();
+        assert_eq!(
+            numbers.as_array().unwrap().values,
+            vec![
+                XffValue::from(42_usize),
+                XffValue::from(-42_i64),
+                XffValue::from(4.2),
+                XffValue::from(u128::MAX),
+            ]
+        );
+        let values = [
+            (XffValue::from(1_u8), XffValue::from(1_usize)),
+            (XffValue::from(-1_i8), XffValue::from(-1_i64)),
+            (XffValue::from(5.0), XffValue::from(5_usize)),
+            (XffValue::from(i128::MIN), XffValue::from(i128::MIN)),
+            (number("-0,50"), XffValue::from(-0.5)),
+            (
+                number("0.1000000000000000055"),
+                number("0.1000000000000000055"),
+            ),
+        ];
+        for (value, expected) in values {
+            let decoded = from_bytes(&serialize_canonical(&value).unwrap()).unwrap();
+            assert_eq!(decoded, expected, "{:?}", value);
+            assert_eq!(
+                decoded.content_hash().unwrap(),
+                value.content_hash().unwrap()
+            );
+        }
+        assert_eq!(
+            decoded.as_object().unwrap().get("data"),
+            value.as_object().unwrap().get("data")
+        );
+    }
+
+    #[test]
+    fn errors() {
+        for float in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let value = XffValue::from(vec![XffValue::from(float)]);
+            assert!(matches!(
+                serialize_canonical(&value),
+                Err(NabuError::InvalidXFFValueForVersion(_, 2))
+            ));
+            assert!(value.content_hash().is_err());
+        }
+        let value = XffValue::CommandCharacter(CommandCharacter::Null);
+        assert!(matches!(
+            serialize_canonical(&value),
+            Err(NabuError::InvalidXFFVersion(_, 2))
+        ));
+        assert!(value.content_hash().is_err());
+    }
+}
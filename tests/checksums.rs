@@ -0,0 +1,109 @@
+#[cfg(test)]
+mod checksums {
+    use std::fs;
+
+    use tyche::prelude::*;
+
+    use nabu::*;
+
+    #[test]
+    fn trailer() {
+        let mut object = Object::new();
+        object.insert("name", "archive");
+        let blob: Vec<u8> = (0..10_000).map(|_| random_u8().unwrap()).collect();
+        object.insert("blob", blob);
+        let value = XffValue::from(object);
+        let plain = serde::to_bytes(value.clone()).unwrap();
+        let options = serde::WriteOptions::new().checksum(true);
+        let bytes = serde::to_bytes_with_options(value.clone(), &options).unwrap();
+        // ETB announcing the trailer, then 3 blocks of 4096 bytes, the length of the checksummed
+        // bytes and ETB
+        assert_eq!(bytes[..2], [2, 23]);
+        assert_eq!(&bytes[2..plain.len() + 1], &plain[1..]);
+        assert_eq!(bytes.len(), plain.len() + 1 + 3 * 4 + 8 + 1);
+        assert_eq!(bytes.last(), Some(&23));
+        assert_eq!(serde::from_bytes(&bytes).unwrap(), value);
+        assert_eq!(
+            serde::from_bytes_ref(&bytes).unwrap().to_owned().unwrap(),
+            value
+        );
+        assert_eq!(
+            serde::to_bytes_with_options(value.clone(), &serde::WriteOptions::default()).unwrap(),
+            plain
+        );
+
+        // a flipped bit inside of the data is found in its block
+        for (pos, block) in [(2, 0), (5_000, 4096), (plain.len(), 8192)] {
+            let mut corrupted = bytes.clone();
+            corrupted[pos] ^= 0b0001_0000;
+            match serde::from_bytes(&corrupted) {
+                Err(NabuError::ChecksumMismatch(p)) => assert_eq!(p, block),
+                other => panic!("{pos}: {other:?}"),
+            }
+            assert!(matches!(
+                serde::from_bytes_ref(&corrupted),
+                Err(NabuError::ChecksumMismatch(_))
+            ));
+        }
+        // a corrupted checksum or trailer
+        let mut corrupted = bytes.clone();
+        corrupted[plain.len() + 1] ^= 1;
+        assert!(matches!(
+            serde::from_bytes(&corrupted),
+            Err(NabuError::ChecksumMismatch(0))
+        ));
+        // without its announcement, the trailer is not skipped
+        let mut corrupted = bytes.clone();
+        corrupted[1] = 0;
+        assert!(serde::from_bytes(&corrupted).is_err());
+        let mut corrupted = bytes.clone();
+        let len_pos = bytes.len() - 9;
+        corrupted[len_pos] ^= 1;
+        match serde::from_bytes(&corrupted) {
+            Err(NabuError::ChecksumMismatch(p)) => assert_eq!(p, len_pos),
+            other => panic!("{other:?}"),
+        }
+        assert!(matches!(
+            serde::from_bytes(&[2, 23, 0, 25]),
+            Err(NabuError::ChecksumMismatch(4))
+        ));
+
+        // streamed, the trailer is verified after the EM, and the next byte stream follows it
+        let mut stream = bytes.clone();
+        stream.extend(&plain);
+        stream.extend(&bytes);
+        let mut reader = serde::XffReader::new(stream.as_slice());
+        for _ in 0..3 {
+            assert_eq!(reader.read().unwrap(), value);
+        }
+        assert!(matches!(reader.read(), Err(NabuError::EmpthyXFF)));
+        let blob = bytes.windows(4).position(|w| w == [5, 2, 16, 39]).unwrap() + 4;
+        for (pos, block) in [
+            (blob, 0),
+            (5_000, 4096),
+            (plain.len() + 1, 0),
+            (bytes.len() - 9, len_pos),
+        ] {
+            let mut corrupted = bytes.clone();
+            corrupted[pos] ^= 0b0001_0000;
+            match serde::XffReader::new(corrupted.as_slice()).read() {
+                Err(NabuError::ChecksumMismatch(p)) => assert_eq!(p, block),
+                other => panic!("{pos}: {other:?}"),
+            }
+        }
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(matches!(
+            serde::XffReader::new(truncated).read(),
+            Err(NabuError::TruncatedXFF(_))
+        ));
+
+        let path = "xff-example-data/v2_checksum.xff";
+        serde::write_with_options(path, value.clone(), &options).unwrap();
+        assert_eq!(serde::read(path).unwrap(), value);
+        let mut document = serde::XffDocument::new(fs::File::open(path).unwrap()).unwrap();
+        let root = document.root();
+        let name = document.get(&root, "name").unwrap().unwrap();
+        assert_eq!(document.read(&name).unwrap(), XffValue::from("archive"));
+        fs::remove_file(path).unwrap();
+    }
+}
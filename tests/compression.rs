@@ -0,0 +1,271 @@
+#[cfg(test)]
+#[cfg(feature = "compression")]
+mod compression {
+    use std::io::Cursor;
+
+    use nabu::serde::{Compression, WriteOptions, XffDocument, XffReader};
+    use nabu::*;
+
+    fn album() -> XffValue {
+        let mut object = Object::new();
+        object.insert("name", "album");
+        object.insert("cover", "abcd".repeat(2_000).into_bytes());
+        object.insert("tiny", vec![1_u8, 2, 3]);
+        object.insert(
+            "pages",
+            vec![
+                XffValue::from("one ".repeat(500)),
+                XffValue::from(vec![0_u8; 70_000]),
+                XffValue::from(42),
+            ],
+        );
+        XffValue::from(object)
+    }
+
+    fn compressed(compression: Compression) -> Vec<u8> {
+        let options = WriteOptions::new().compression(compression);
+        serde::to_bytes_with_options(album(), &options).unwrap()
+    }
+
+    #[test]
+    fn round_trips() {
+        let plain = serde::to_bytes(album()).unwrap();
+        assert_eq!(compressed(Compression::None), plain);
+        for compression in [Compression::Data, Compression::File] {
+            let bytes = compressed(compression);
+            assert!(bytes.len() < plain.len() / 10, "{compression:?}");
+            assert_eq!(serde::from_bytes(&bytes).unwrap(), album());
+            assert_eq!(XffReader::new(bytes.as_slice()).read().unwrap(), album());
+        }
+        // incompressible values are stored as they are
+        let options = WriteOptions::new().compression(Compression::File);
+        assert_eq!(
+            serde::to_bytes_with_options(XffValue::from("hi"), &options).unwrap(),
+            serde::to_bytes(XffValue::from("hi")).unwrap()
+        );
+        // together with a checksum
+        let options = options.checksum(true);
+        let bytes = serde::to_bytes_with_options(album(), &options).unwrap();
+        assert_eq!(bytes.last(), Some(&23));
+        assert_eq!(serde::from_bytes(&bytes).unwrap(), album());
+
+        let path = "xff-example-data/v2_compressed.xff";
+        serde::write_with_options(path, album(), &options).unwrap();
+        assert_eq!(serde::read(path).unwrap(), album());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_round_trips() {
+        use ::serde::{Deserialize, Serialize};
+        use nabu::serde::DecodeOptions;
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Kind {
+            Photo,
+            Scan(String),
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Album {
+            name: String,
+            #[serde(with = "serde_bytes")]
+            cover: Vec<u8>,
+            pages: Vec<String>,
+            kind: Kind,
+        }
+
+        let album = Album {
+            name: String::from("album"),
+            cover: "abcd".repeat(2_000).into_bytes(),
+            pages: vec!["one ".repeat(500); 3],
+            kind: Kind::Photo,
+        };
+        for compression in [Compression::Data, Compression::File] {
+            let options = WriteOptions::new().compression(compression);
+            let bytes = serde::to_bytes_with_options(to_xff(&album).unwrap(), &options).unwrap();
+            let plain = to_xff_bytes(&album).unwrap();
+            assert!(bytes.len() < plain.len() / 2, "{compression:?}");
+            assert_eq!(from_xff_bytes::<Album>(&bytes).unwrap(), album);
+
+            // the decompressed bytes count towards the limits
+            let limited = DecodeOptions::new().max_total_bytes(7_000);
+            assert!(matches!(
+                from_xff_bytes_with_options::<Album>(&bytes, &limited),
+                Err(NabuError::AllocationLimitExceeded(..))
+            ));
+        }
+        // an enum compressed as a whole
+        let kind = Kind::Scan("page ".repeat(200));
+        let options = WriteOptions::new().compression(Compression::File);
+        let bytes = serde::to_bytes_with_options(to_xff(&kind).unwrap(), &options).unwrap();
+        assert_eq!(bytes[1], 6);
+        assert_eq!(from_xff_bytes::<Kind>(&bytes).unwrap(), kind);
+    }
+
+    #[test]
+    fn documents() {
+        let mut document = XffDocument::new(Cursor::new(compressed(Compression::Data))).unwrap();
+        let root = document.root();
+        assert!(root.is_object());
+        let name = document.get(&root, "name").unwrap().unwrap();
+        assert_eq!(document.read(&name).unwrap(), XffValue::from("album"));
+        let cover = document.get(&root, "cover").unwrap().unwrap();
+        assert!(cover.is_compressed());
+        assert!(!cover.is_data());
+        assert_eq!(
+            document.read(&cover).unwrap(),
+            XffValue::from("abcd".repeat(2_000).into_bytes())
+        );
+        // too short to be worth it
+        let tiny = document.get(&root, "tiny").unwrap().unwrap();
+        assert!(tiny.is_data());
+
+        let mut document = XffDocument::new(Cursor::new(compressed(Compression::File))).unwrap();
+        let root = document.root();
+        assert!(root.is_compressed());
+        assert_eq!(document.read(&root).unwrap(), album());
+
+        // borrowing is impossible, the bytes are not in the input
+        assert!(matches!(
+            serde::from_bytes_ref(&compressed(Compression::File)),
+            Err(NabuError::BorrowedCompressedValue(1))
+        ));
+    }
+
+    #[test]
+    fn invalid_compressed_values() {
+        let bytes = compressed(Compression::File);
+        assert_eq!(bytes[1], 6);
+        let content_pos = 3 + usize::from(bytes[2]);
+
+        let mut unknown = bytes.clone();
+        unknown[content_pos] = 200;
+        match serde::from_bytes(&unknown) {
+            Err(NabuError::UnknownCompressionMethod(200, pos)) => assert_eq!(pos, content_pos),
+            other => panic!("{other:?}"),
+        }
+        assert!(matches!(
+            XffReader::new(unknown.as_slice()).read(),
+            Err(NabuError::UnknownCompressionMethod(200, _))
+        ));
+
+        // a wrong decompressed length
+        let mut wrong_len = bytes.clone();
+        wrong_len[content_pos + 2] ^= 1;
+        assert!(matches!(
+            serde::from_bytes(&wrong_len),
+            Err(NabuError::InvalidCompressedValue(_))
+        ));
+        // every truncation of the compressed bytes, always an error and never a panic
+        let end = bytes.len() - 2;
+        for cut in content_pos..end {
+            let mut truncated = bytes[..cut].to_vec();
+            truncated.extend_from_slice(&bytes[end..]);
+            assert!(serde::from_bytes(&truncated).is_err(), "{cut}");
+        }
+
+        let cmp = |content: &[u8]| {
+            let mut out = vec![2, 6, 1, content.len() as u8];
+            out.extend_from_slice(content);
+            out.extend([24, 25]);
+            serde::from_bytes(&out)
+        };
+        // a match reaching before the start
+        assert!(matches!(
+            cmp(&[1, 1, 8, 0x14, 0, 5, 0]),
+            Err(NabuError::InvalidCompressedValue(4))
+        ));
+        // a compressed value inside of a compressed value
+        assert!(matches!(
+            cmp(&[1, 1, 4, 0x40, 6, 1, 0, 24]),
+            Err(NabuError::InvalidXFFByte(6, 1, 2))
+        ));
+        // two values
+        assert!(matches!(
+            cmp(&[1, 1, 2, 0x20, 0, 0]),
+            Err(NabuError::InvalidCompressedValue(4))
+        ));
+        // a valid null
+        assert_eq!(cmp(&[1, 1, 1, 0x10, 0]).unwrap(), XffValue::Null);
+        assert!(matches!(
+            cmp(&[]),
+            Err(NabuError::InvalidCompressedValue(4))
+        ));
+    }
+
+    /// Returns the LZ4 block inside of a compressed byte stream, and the encoding it decompresses to
+    fn lz4_block(value: XffValue) -> Option<(Vec<u8>, Vec<u8>)> {
+        let plain = serde::to_bytes(value.clone()).unwrap();
+        let options = WriteOptions::new().compression(Compression::File);
+        let bytes = serde::to_bytes_with_options(value, &options).unwrap();
+        if bytes[1] != 6 {
+            return None;
+        }
+        let content = &bytes[3 + usize::from(bytes[2])..bytes.len() - 2];
+        assert_eq!(content[0], 1);
+        let block = content[2 + usize::from(content[1])..].to_vec();
+        Some((block, plain[1..plain.len() - 1].to_vec()))
+    }
+
+    #[test]
+    fn lz4_interop() {
+        let mut values = vec![
+            album(),
+            XffValue::from(vec![0_u8; 100_000]),
+            XffValue::from(vec![XffValue::from("hello"); 50]),
+        ];
+        // every length around the end of block rules
+        for len in 0..64 {
+            values.push(XffValue::from("ab".repeat(len)));
+            values.push(XffValue::from(vec![7_u8; len]));
+        }
+        let mut compressed = 0;
+        for value in values {
+            let Some((block, encoded)) = lz4_block(value.clone()) else {
+                continue;
+            };
+            compressed += 1;
+            // the reference implementation rejects blocks breaking the end of block rules
+            let decompressed = lz4::block::decompress(&block, Some(encoded.len() as i32)).unwrap();
+            assert_eq!(decompressed, encoded, "{value:?}");
+
+            // and blocks compressed by it are read
+            let block = lz4::block::compress(&encoded, None, false).unwrap();
+            let mut content = vec![1, 8];
+            content.extend((encoded.len() as u64).to_le_bytes());
+            content.extend(block);
+            let mut bytes = vec![2, 6, 8];
+            bytes.extend((content.len() as u64).to_le_bytes());
+            bytes.extend(content);
+            bytes.extend([24, 25]);
+            assert_eq!(serde::from_bytes(&bytes).unwrap(), value);
+        }
+        assert!(compressed > 100, "{compressed}");
+    }
+
+    #[test]
+    fn compressed_keys() {
+        // `{"a": null}`, with the key compressed into the literals of a single sequence
+        let key = [6, 1, 9, 1, 1, 5, 0x50, 1, 1, 1, b'a', 24];
+        let mut bytes = vec![2, 4, 1, 17, 29];
+        bytes.extend_from_slice(&key);
+        bytes.extend([24, 31, 0, 29, 24, 25]);
+        let rejected = |result: Result<XffValue, NabuError>| match result {
+            Err(NabuError::InvalidObject(6, 6)) => {}
+            other => panic!("{other:?}"),
+        };
+        rejected(serde::from_bytes(&bytes));
+        rejected(XffReader::new(bytes.as_slice()).read());
+        rejected(serde::from_bytes_ref(&bytes).and_then(|value| value.to_owned()));
+        #[cfg(feature = "serde")]
+        rejected(from_xff_bytes::<XffValue>(&bytes));
+
+        // the same object with a plain key is read
+        let plain = [2, 4, 1, 9, 29, 1, 1, 1, b'a', 24, 31, 0, 29, 24, 25];
+        let expected = XffValue::from(vec![("a", XffValue::Null)]);
+        assert_eq!(serde::from_bytes(&plain).unwrap(), expected);
+        assert_eq!(XffReader::new(plain.as_slice()).read().unwrap(), expected);
+    }
+}
@@ -0,0 +1,252 @@
+#[cfg(test)]
+#[cfg(feature = "derive")]
+mod derive {
+    use std::collections::BTreeMap;
+
+    use nabu::*;
+
+    #[derive(Debug, Clone, PartialEq, IntoXff, FromXff)]
+    struct Person {
+        #[xff(rename = "full_name")]
+        name: String,
+        age: u8,
+        height: f64,
+        nickname: Option<String>,
+        picture: Vec<u8>,
+        tags: Vec<String>,
+        scores: Vec<Option<i32>>,
+        pet: Pet,
+        address: Address,
+        extra: XffValue,
+    }
+
+    #[derive(Debug, Clone, PartialEq, IntoXff, FromXff)]
+    struct Address {
+        street: String,
+        number: i32,
+    }
+
+    #[derive(Debug, Clone, PartialEq, IntoXff, FromXff)]
+    enum Pet {
+        None,
+        Dog(String),
+        #[xff(rename = "cat")]
+        Cat {
+            name: String,
+            #[xff(rename = "lives_left")]
+            lives: u8,
+        },
+        Fish(String, u8),
+    }
+
+    #[derive(Debug, Clone, PartialEq, IntoXff, FromXff)]
+    struct Meters(f64);
+
+    #[derive(Debug, Clone, PartialEq, IntoXff, FromXff)]
+    struct Point(i64, i64, Option<i64>);
+
+    #[derive(Debug, Clone, PartialEq, IntoXff, FromXff)]
+    struct Marker;
+
+    #[derive(Debug, Clone, PartialEq, IntoXff, FromXff)]
+    struct Tagged<T> {
+        tag: String,
+        inner: T,
+    }
+
+    fn make_person() -> Person {
+        Person {
+            name: "Jörg".to_string(),
+            age: 42,
+            height: 1.69,
+            nickname: None,
+            picture: vec![0, 1, 2, 3, 255],
+            tags: vec!["hello".to_string(), "mom".to_string()],
+            scores: vec![Some(-1), None, Some(3)],
+            pet: Pet::Cat {
+                name: "Garfield".to_string(),
+                lives: 9,
+            },
+            address: Address {
+                street: "Main Street".to_string(),
+                number: -1,
+            },
+            extra: XffValue::from(vec![XffValue::from(true), XffValue::Null]),
+        }
+    }
+
+    #[test]
+    fn struct_round_trip() {
+        let person = make_person();
+        let value = XffValue::from(person.clone());
+        assert!(value.is_object());
+
+        let object = value.clone().into_object().unwrap();
+        assert!(!object.contains_key("name"));
+        assert_eq!(object["full_name"], XffValue::from("Jörg"));
+        assert_eq!(object["age"], XffValue::from(42_u8));
+        assert_eq!(object["height"], XffValue::from(1.69));
+        assert_eq!(object["nickname"], XffValue::Null);
+        assert!(object["picture"].is_data());
+        assert_eq!(object["picture"], XffValue::from(vec![0_u8, 1, 2, 3, 255]));
+        assert_eq!(
+            object["tags"],
+            XffValue::from(vec![XffValue::from("hello"), XffValue::from("mom")])
+        );
+        assert_eq!(
+            object["scores"],
+            XffValue::from(vec![XffValue::from(-1), XffValue::Null, XffValue::from(3)])
+        );
+        assert_eq!(
+            object["pet"],
+            XffValue::from(BTreeMap::from([(
+                "cat",
+                XffValue::from(BTreeMap::from([
+                    ("name", XffValue::from("Garfield")),
+                    ("lives_left", XffValue::from(9_u8)),
+                ]))
+            )]))
+        );
+        assert_eq!(object["extra"], person.extra);
+
+        assert_eq!(Person::try_from(value).unwrap(), person);
+    }
+
+    #[test]
+    fn through_bytes() {
+        let person = make_person();
+        let bytes = nabu::serde::to_bytes(XffValue::from(person.clone())).unwrap();
+        let back = Person::try_from(nabu::serde::from_bytes(&bytes).unwrap()).unwrap();
+        assert_eq!(back, person);
+    }
+
+    #[test]
+    fn optional_fields() {
+        let mut object = XffValue::from(make_person()).into_object().unwrap();
+        object.remove("nickname");
+        assert_eq!(
+            Person::try_from(XffValue::from(object.clone())).unwrap().nickname,
+            None
+        );
+        object.insert("nickname", "Jöggi");
+        assert_eq!(
+            Person::try_from(XffValue::from(object.clone())).unwrap().nickname,
+            Some("Jöggi".to_string())
+        );
+        // unknown keys are ignored
+        object.insert("unknown", 42);
+        assert!(Person::try_from(XffValue::from(object.clone())).is_ok());
+
+        // missing required keys
+        object.remove("age");
+        let err = Person::try_from(XffValue::from(object)).unwrap_err();
+        assert!(matches!(err, NabuError::ConversionError(_)));
+        assert!(err.to_string().contains("Person.age"));
+    }
+
+    #[test]
+    fn enums() {
+        let pets = vec![
+            Pet::None,
+            Pet::Dog("Odie".to_string()),
+            Pet::Cat {
+                name: "Garfield".to_string(),
+                lives: 9,
+            },
+            Pet::Fish("Nemo".to_string(), 1),
+        ];
+        let values: Vec<XffValue> = pets.iter().cloned().map(XffValue::from).collect();
+        assert_eq!(
+            values[0],
+            XffValue::from(BTreeMap::from([("None", XffValue::Null)]))
+        );
+        assert_eq!(
+            values[1],
+            XffValue::from(BTreeMap::from([("Dog", XffValue::from("Odie"))]))
+        );
+        assert_eq!(
+            values[3],
+            XffValue::from(BTreeMap::from([(
+                "Fish",
+                XffValue::from(vec![XffValue::from("Nemo"), XffValue::from(1_u8)])
+            )]))
+        );
+        for (value, pet) in values.into_iter().zip(pets) {
+            assert_eq!(Pet::try_from(value).unwrap(), pet);
+        }
+
+        // unit variants are also accepted by name
+        assert_eq!(Pet::try_from(XffValue::from("None")).unwrap(), Pet::None);
+        assert!(Pet::try_from(XffValue::from("Horse")).is_err());
+        assert!(Pet::try_from(XffValue::from("Dog")).is_err());
+        assert!(Pet::try_from(XffValue::from(42)).is_err());
+        // the renamed variant is only known by its new name
+        assert!(Pet::try_from(XffValue::from(BTreeMap::from([(
+            "Cat",
+            XffValue::from(BTreeMap::from([
+                ("name", XffValue::from("Garfield")),
+                ("lives_left", XffValue::from(9_u8)),
+            ]))
+        )])))
+        .is_err());
+    }
+
+    #[test]
+    fn tuple_and_unit_structs() {
+        assert_eq!(XffValue::from(Meters(4.2)), XffValue::from(4.2));
+        assert_eq!(Meters::try_from(XffValue::from(4.2)).unwrap(), Meters(4.2));
+        // floats accept any number
+        assert_eq!(Meters::try_from(XffValue::from(4)).unwrap(), Meters(4.0));
+
+        let point = Point(1, -2, None);
+        let value = XffValue::from(point.clone());
+        assert_eq!(
+            value,
+            XffValue::from(vec![XffValue::from(1), XffValue::from(-2), XffValue::Null])
+        );
+        assert_eq!(Point::try_from(value).unwrap(), point);
+        assert!(Point::try_from(XffValue::from(vec![XffValue::from(1)])).is_err());
+
+        assert_eq!(XffValue::from(Marker), XffValue::Null);
+        assert_eq!(Marker::try_from(XffValue::Null).unwrap(), Marker);
+        assert!(Marker::try_from(XffValue::from(false)).is_err());
+    }
+
+    #[test]
+    fn generics() {
+        let tagged = Tagged {
+            tag: "home".to_string(),
+            inner: Address {
+                street: "Main Street".to_string(),
+                number: 1,
+            },
+        };
+        let value = XffValue::from(tagged.clone());
+        assert!(value.as_object().unwrap()["inner"].is_object());
+        assert_eq!(Tagged::try_from(value).unwrap(), tagged);
+    }
+
+    #[test]
+    fn mismatched_types() {
+        assert!(Address::try_from(XffValue::from("Main Street")).is_err());
+
+        let mut object = Object::new();
+        object.insert("street", "Main Street");
+        object.insert("number", "one");
+        let err = Address::try_from(XffValue::from(object.clone())).unwrap_err();
+        assert!(err.to_string().contains("Address.number"));
+
+        // numbers outside of the range of the field
+        object.insert("number", usize::MAX);
+        assert!(Address::try_from(XffValue::from(object.clone())).is_err());
+        object.insert("number", 42.0);
+        assert!(Address::try_from(XffValue::from(object.clone())).is_err());
+        object.insert("number", i128::MAX);
+        assert!(Address::try_from(XffValue::from(object.clone())).is_err());
+        object.insert("number", -42);
+        assert_eq!(
+            Address::try_from(XffValue::from(object)).unwrap().number,
+            -42
+        );
+    }
+}
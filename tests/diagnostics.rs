@@ -0,0 +1,239 @@
+#[cfg(test)]
+mod diagnostics {
+    use std::error::Error;
+    use std::fs;
+
+    use nabu::serde::{from_bytes_diagnosed, DecodeOptions};
+    use nabu::*;
+
+    fn users() -> XffValue {
+        let mut users = Vec::new();
+        for name in ["anna", "bert", "carl", "hello mom"] {
+            let mut user = Object::new();
+            user.insert("name", name);
+            user.insert("first name", name);
+            users.push(XffValue::from(user));
+        }
+        XffValue::from(vec![("users", XffValue::from(users))])
+    }
+
+    /// Replaces the first byte of `needle` in `bytes` with `byte`, returning its position
+    fn corrupt(bytes: &mut [u8], needle: &[u8], byte: u8) -> usize {
+        let pos = bytes
+            .windows(needle.len())
+            .position(|window| window == needle)
+            .unwrap();
+        bytes[pos] = byte;
+        pos
+    }
+
+    fn diagnose(bytes: &[u8]) -> Diagnostic {
+        from_bytes_diagnosed(bytes, &DecodeOptions::default()).unwrap_err()
+    }
+
+    #[test]
+    fn io_error_source() {
+        fn read() -> Result<XffValue, Box<dyn Error>> {
+            Ok(serde::read_exact("xff-example-data/does-not-exist.xff")?)
+        }
+        let err = read().unwrap_err();
+        let err = err.downcast_ref::<NabuError>().unwrap();
+        assert_eq!(err.code(), 100);
+        assert!(err.position().is_none());
+        let source = err.source().unwrap();
+        assert!(source.downcast_ref::<std::io::Error>().is_some());
+
+        assert!(NabuError::EmpthyXFF.source().is_none());
+    }
+
+    #[test]
+    fn paths() {
+        // v2
+        let mut bytes = serde::to_bytes(users()).unwrap();
+        let pos = corrupt(&mut bytes, b"hello mom", 255);
+        let diagnostic = diagnose(&bytes);
+        assert!(matches!(diagnostic.error(), NabuError::InvalidUTF8String(p) if *p == pos));
+        assert_eq!(diagnostic.code(), 400);
+        assert_eq!(diagnostic.position(), Some(pos));
+        assert_eq!(diagnostic.path(), "root.users[3][\"first name\"]");
+
+        // v1
+        let mut bytes = serde::to_bytes_legacy(vec![users()], 1).unwrap();
+        corrupt(&mut bytes, b"carl", 0);
+        let diagnostic = diagnose(&bytes);
+        assert_eq!(diagnostic.code(), 500);
+        assert_eq!(diagnostic.path(), "root.users[2][\"first name\"]");
+
+        // v0
+        let values = vec![
+            XffValue::from("anna"),
+            XffValue::from("bert"),
+            XffValue::from("carl"),
+        ];
+        let mut bytes = serde::to_bytes_legacy(values, 0).unwrap();
+        corrupt(&mut bytes, b"carl", 0);
+        assert_eq!(diagnose(&bytes).path(), "root[2]");
+
+        // errors outside of any value
+        let diagnostic = diagnose(&[7, 25]);
+        assert!(matches!(
+            diagnostic.error(),
+            NabuError::UnknownXFFVersion(7)
+        ));
+        assert_eq!(diagnostic.code(), 703);
+        assert_eq!(diagnostic.path(), "root");
+        assert!(diagnostic.excerpt().is_none());
+    }
+
+    #[test]
+    fn positions() {
+        // every decoder reports the position of the offending byte itself
+        fn errors(bytes: &[u8]) -> Vec<NabuError> {
+            vec![
+                serde::from_bytes(bytes).unwrap_err(),
+                serde::XffReader::new(bytes).read().unwrap_err(),
+                serde::from_bytes_ref(bytes)
+                    .and_then(|value| value.to_owned())
+                    .unwrap_err(),
+            ]
+        }
+
+        // v1 string
+        let mut bytes = serde::to_bytes_legacy(vec![users()], 1).unwrap();
+        let pos = corrupt(&mut bytes, b"rl", 0);
+        for err in errors(&bytes) {
+            assert!(
+                matches!(err, NabuError::InvalidASCIIString(0, p, 1) if p == pos),
+                "{:?}",
+                err
+            );
+        }
+
+        // v2 string
+        let mut bytes = serde::to_bytes(users()).unwrap();
+        let pos = corrupt(&mut bytes, b" mom", 255);
+        for err in errors(&bytes) {
+            assert!(
+                matches!(err, NabuError::InvalidUTF8String(p) if p == pos),
+                "{:?}",
+                err
+            );
+        }
+
+        // v2 number, the number type and the content
+        let value = XffValue::from(vec![("number", XffValue::from(42))]);
+        let mut bytes = serde::to_bytes(value).unwrap();
+        let pos = corrupt(&mut bytes, &[2, 42], 9);
+        for err in errors(&bytes) {
+            assert!(
+                matches!(err, NabuError::InvalidXFFByte(9, p, 2) if p == pos),
+                "{:?}",
+                err
+            );
+        }
+        let bytes = [2, 2, 1, 3, 6, b'4', b'x', 24, 25];
+        for err in errors(&bytes) {
+            assert!(matches!(err, NabuError::InvalidNumber(4, _)), "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn excerpt() {
+        let mut bytes = serde::to_bytes(users()).unwrap();
+        let pos = corrupt(&mut bytes, b"hello mom", 255);
+        let diagnostic = diagnose(&bytes);
+        let excerpt: Vec<&str> = diagnostic.excerpt().unwrap().split(' ').collect();
+        assert_eq!(excerpt.len(), 17);
+        assert_eq!(excerpt[8], "[ff]");
+        assert_eq!(
+            excerpt[9..],
+            ["65", "6c", "6c", "6f", "20", "6d", "6f", "6d"]
+        );
+        assert_eq!(excerpt[7], format!("{:02x}", bytes[pos - 1]));
+
+        // at the very start and end of the bytes
+        assert_eq!(diagnose(&[2, 99, 25]).excerpt(), Some("02 63 [19]"));
+        assert_eq!(diagnose(&[2, 16]).excerpt(), Some("02 10 [end]"));
+    }
+
+    #[test]
+    fn display() {
+        let diagnostic = diagnose(&[2, 99, 25]);
+        assert_eq!(
+            diagnostic.to_string(),
+            "Invalid XFF byte: 99 for Xff Version 2 at byte position 2 (error 502, at root, bytes: 02 63 [19])"
+        );
+        assert!(diagnostic.source().unwrap().is::<NabuError>());
+        let err: NabuError = diagnostic.into();
+        assert!(matches!(err, NabuError::InvalidXFFByte(99, 2, 2)));
+
+        assert_eq!(
+            NabuError::InvalidArray(12, 7).to_string(),
+            "Invalid array structure byte: 7 at byte position 12. Expected an array separator"
+        );
+    }
+
+    #[test]
+    fn codes() {
+        let cases = [
+            (NabuError::SerdeError(String::new()), 101),
+            (NabuError::MissingETX(0), 200),
+            (NabuError::MissingEV(0), 305),
+            (NabuError::BorrowedCompressedValue(0), 403),
+            (NabuError::InvalidASCIICommandCharacter(0, 0), 600),
+            (NabuError::ChecksumMismatch(0), 706),
+            (NabuError::WrongPassphrase, 709),
+            (NabuError::AllocationLimitExceeded(0, 0), 803),
+            (NabuError::ConversionError(String::new()), 900),
+            (NabuError::InvalidSchema(String::new()), 904),
+        ];
+        for (err, code) in cases {
+            assert_eq!(err.code(), code);
+        }
+        // every decode error of the corpus has a code, a path and an excerpt if it is within the bytes
+        for entry in fs::read_dir("xff-example-data/malformed").unwrap() {
+            let bytes = fs::read(entry.unwrap().path()).unwrap();
+            let diagnostic = diagnose(&bytes);
+            assert!((200..900).contains(&diagnostic.code()));
+            assert!(diagnostic.path().starts_with("root"));
+            if matches!(diagnostic.position(), Some(pos) if pos <= bytes.len()) {
+                assert!(diagnostic.excerpt().is_some(), "{diagnostic}");
+            }
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let bytes = serde::to_bytes(users()).unwrap();
+        let options = DecodeOptions::new().max_depth(2);
+        let diagnostic = from_bytes_diagnosed(&bytes, &options).unwrap_err();
+        assert_eq!(diagnostic.code(), 800);
+        assert_eq!(diagnostic.path(), "root.users[0]");
+        assert_eq!(
+            from_bytes_diagnosed(&bytes, &DecodeOptions::default()).unwrap(),
+            users()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn compressed() {
+        use nabu::serde::{Compression, WriteOptions};
+
+        let value = XffValue::from(vec![("data", XffValue::from(vec![7_u8; 1_000]))]);
+        let options = WriteOptions::new().compression(Compression::Data);
+        let bytes = serde::to_bytes_with_options(value, &options).unwrap();
+        // the decompressed bytes exceed the limit
+        let options = DecodeOptions::new().max_total_bytes(500);
+        let diagnostic = from_bytes_diagnosed(&bytes, &options).unwrap_err();
+        assert_eq!(diagnostic.code(), 803);
+        assert_eq!(diagnostic.path(), "root.data");
+        assert!(diagnostic.excerpt().is_some());
+        // the value decoded from them exceeds the limit, its position is in the decompressed bytes
+        let options = DecodeOptions::new().max_total_bytes(1_500);
+        let diagnostic = from_bytes_diagnosed(&bytes, &options).unwrap_err();
+        assert_eq!(diagnostic.code(), 803);
+        assert_eq!(diagnostic.path(), "root.data");
+        assert!(diagnostic.excerpt().is_none());
+    }
+}
@@ -0,0 +1,154 @@
+#[cfg(test)]
+mod diff {
+    use nabu::diff::{diff, Operation, Patch};
+    use nabu::*;
+
+    fn config(version: usize, hosts: Vec<&str>, logo: Vec<u8>) -> XffValue {
+        let mut server = Object::new();
+        server.insert("port", 8080);
+        server.insert("hosts", hosts.into_iter().map(XffValue::from).collect::<Vec<XffValue>>());
+        let mut object = Object::new();
+        object.insert("version", version);
+        object.insert("server", server);
+        object.insert("logo", logo);
+        XffValue::from(object)
+    }
+
+    fn assert_round_trip(old: &XffValue, new: &XffValue) -> Patch {
+        let patch = diff(old, new);
+        let mut patched = old.clone();
+        patch.apply(&mut patched).unwrap();
+        assert_eq!(&patched, new);
+        // through a stored patch
+        let bytes = serde::to_bytes(XffValue::from(patch.clone())).unwrap();
+        let stored = Patch::try_from(serde::from_bytes(&bytes).unwrap()).unwrap();
+        assert_eq!(stored, patch);
+        patch
+    }
+
+    #[test]
+    fn equal_values() {
+        let value = config(1, vec!["a", "b"], vec![1, 2, 3]);
+        let patch = assert_round_trip(&value, &value);
+        assert!(patch.is_empty());
+        assert_eq!(XffValue::from(patch), XffValue::from(Vec::<XffValue>::new()));
+    }
+
+    #[test]
+    fn objects() {
+        let old = config(1, vec!["a"], vec![1, 2, 3]);
+        let mut new = config(2, vec!["a"], vec![1, 2, 4]);
+        let object = new.as_object_mut().unwrap();
+        object.remove("logo");
+        object.insert("name/~", "nabu");
+        let patch = assert_round_trip(&old, &new);
+        assert_eq!(
+            patch.operations,
+            vec![
+                Operation::Remove { path: "/logo".to_string(), value: XffValue::from(vec![1_u8, 2, 3]) },
+                Operation::Replace { path: "/version".to_string(), old: XffValue::from(1_usize), new: XffValue::from(2_usize) },
+                Operation::Add { path: "/name~1~0".to_string(), value: XffValue::from("nabu") },
+            ]
+        );
+    }
+
+    #[test]
+    fn data_blobs() {
+        let old = config(1, vec![], vec![0; 100]);
+        let new = config(1, vec![], vec![1; 100]);
+        let patch = assert_round_trip(&old, &new);
+        assert_eq!(patch.len(), 1);
+        assert_eq!(patch.operations[0].path(), "/logo");
+    }
+
+    #[test]
+    fn arrays() {
+        let cases: Vec<(Vec<&str>, Vec<&str>)> = vec![
+            (vec!["a", "b", "c"], vec!["a", "x", "b", "c"]),
+            (vec!["a", "b", "c"], vec!["b", "c"]),
+            (vec!["a", "b", "c"], vec!["c", "b", "a"]),
+            (vec!["a", "b", "c"], vec![]),
+            (vec![], vec!["a", "b"]),
+            (vec!["a", "b", "c", "d"], vec!["a", "x", "y", "d", "e"]),
+            (vec!["x", "a", "x", "b", "x"], vec!["a", "b"]),
+        ];
+        for (old, new) in cases {
+            assert_round_trip(&config(1, old, vec![]), &config(1, new, vec![]));
+        }
+
+        let old = config(1, vec!["a", "b", "c"], vec![]);
+        let new = config(1, vec!["a", "x", "b", "c"], vec![]);
+        assert_eq!(
+            diff(&old, &new).operations,
+            vec![Operation::Add { path: "/server/hosts/1".to_string(), value: XffValue::from("x") }]
+        );
+        let new = config(1, vec!["a", "c"], vec![]);
+        assert_eq!(
+            diff(&old, &new).operations,
+            vec![Operation::Remove { path: "/server/hosts/1".to_string(), value: XffValue::from("b") }]
+        );
+    }
+
+    #[test]
+    fn nested_array_edits() {
+        let old = XffValue::from(vec![
+            config(1, vec!["a"], vec![]),
+            config(2, vec!["b"], vec![]),
+        ]);
+        let new = XffValue::from(vec![
+            config(1, vec!["a"], vec![]),
+            config(2, vec!["b", "c"], vec![]),
+            config(3, vec![], vec![]),
+        ]);
+        let patch = assert_round_trip(&old, &new);
+        assert_eq!(
+            patch.to_string(),
+            "+ /1/server/hosts/1: c\n+ /2: ".to_string() + &config(3, vec![], vec![]).to_string()
+        );
+
+        // long arrays fall back to comparing element by element
+        let old = XffValue::from((0..2_000).map(XffValue::from).collect::<Vec<XffValue>>());
+        let new = XffValue::from((0..2_000).map(|i| XffValue::from(i * 2)).collect::<Vec<XffValue>>());
+        assert_round_trip(&old, &new);
+    }
+
+    #[test]
+    fn type_changes_and_root() {
+        let old = XffValue::from(vec![XffValue::from(1)]);
+        let new = XffValue::from("hello mom");
+        let patch = assert_round_trip(&old, &new);
+        assert_eq!(
+            patch.operations,
+            vec![Operation::Replace { path: String::new(), old, new }]
+        );
+    }
+
+    #[test]
+    fn invalid_patches() {
+        let old = config(1, vec!["a"], vec![]);
+        let new = config(2, vec!["a", "b"], vec![]);
+        let patch = diff(&old, &new);
+
+        // applied to a different value, nothing is changed
+        let mut other = config(3, vec!["a"], vec![]);
+        assert!(matches!(patch.apply(&mut other), Err(NabuError::InvalidPatch(_))));
+        assert_eq!(other, config(3, vec!["a"], vec![]));
+        // applied twice
+        let mut value = old.clone();
+        patch.apply(&mut value).unwrap();
+        assert!(patch.apply(&mut value).is_err());
+
+        let missing = Patch {
+            operations: vec![Operation::Add { path: "/missing/key".to_string(), value: XffValue::Null }],
+        };
+        assert!(missing.apply(&mut value).is_err());
+        let out_of_bounds = Patch {
+            operations: vec![Operation::Add { path: "/server/hosts/5".to_string(), value: XffValue::Null }],
+        };
+        assert!(out_of_bounds.apply(&mut value).is_err());
+
+        assert!(Patch::try_from(XffValue::from("add")).is_err());
+        assert!(Patch::try_from(XffValue::from(vec![XffValue::from(vec![("op", XffValue::from("move")), ("path", XffValue::from(""))])])).is_err());
+        assert!(Patch::try_from(XffValue::from(vec![XffValue::from(vec![("op", XffValue::from("add")), ("path", XffValue::from("/a"))])])).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+#[cfg(test)]
+#[cfg(feature = "encryption")]
+mod encryption {
+    use std::fs;
+
+    use nabu::serde::{read, read_encrypted, write, write_encrypted};
+    use nabu::*;
+
+    const PASSPHRASE: &str = "correct horse battery staple";
+
+    fn credentials() -> XffValue {
+        let mut object = Object::new();
+        object.insert("user", "alice");
+        object.insert("password", "hunter2");
+        object.insert("key", vec![0xAB_u8; 64]);
+        XffValue::from(object)
+    }
+
+    #[test]
+    fn round_trips() {
+        let path = "xff-example-data/encrypted_round_trip.xff";
+        write_encrypted(path, credentials(), PASSPHRASE).unwrap();
+        let bytes = fs::read(path).unwrap();
+        assert!(bytes.starts_with(b"XFFCRYPT"));
+        assert!(!bytes.windows(7).any(|w| w == b"hunter2"));
+        assert_eq!(read_encrypted(path, PASSPHRASE).unwrap(), credentials());
+        assert_eq!(
+            read_encrypted(path, PASSPHRASE.as_bytes()).unwrap(),
+            credentials()
+        );
+        // a new salt and nonce every time
+        write_encrypted(path, credentials(), PASSPHRASE).unwrap();
+        assert_ne!(fs::read(path).unwrap(), bytes);
+
+        assert!(matches!(read(path), Err(NabuError::EncryptedXFF)));
+        assert!(matches!(
+            serde::from_bytes(&bytes),
+            Err(NabuError::EncryptedXFF)
+        ));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn wrong_passphrases_and_corruption() {
+        let path = "xff-example-data/encrypted_corrupted.xff";
+        write_encrypted(path, credentials(), PASSPHRASE).unwrap();
+        let bytes = fs::read(path).unwrap();
+        assert!(matches!(
+            read_encrypted(path, "correct horse battery stapler"),
+            Err(NabuError::WrongPassphrase)
+        ));
+        assert!(matches!(
+            read_encrypted(path, ""),
+            Err(NabuError::WrongPassphrase)
+        ));
+
+        // the encrypted bytes, the nonce and the authentication tag
+        for pos in [bytes.len() - 40, bytes.len() - 1, 60] {
+            let mut corrupted = bytes.clone();
+            corrupted[pos] ^= 1;
+            fs::write(path, &corrupted).unwrap();
+            assert!(
+                matches!(
+                    read_encrypted(path, PASSPHRASE),
+                    Err(NabuError::CorruptedEncryptedXFF)
+                ),
+                "{pos}"
+            );
+        }
+        fs::write(path, &bytes[..70]).unwrap();
+        assert!(matches!(
+            read_encrypted(path, PASSPHRASE),
+            Err(NabuError::CorruptedEncryptedXFF)
+        ));
+        let mut unknown = bytes.clone();
+        unknown[8] = 200;
+        fs::write(path, &unknown).unwrap();
+        assert!(matches!(
+            read_encrypted(path, PASSPHRASE),
+            Err(NabuError::UnknownEncryptionScheme(200))
+        ));
+
+        // not encrypted at all
+        write(path, credentials()).unwrap();
+        assert!(matches!(
+            read_encrypted(path, PASSPHRASE),
+            Err(NabuError::CorruptedEncryptedXFF)
+        ));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn excessive_costs() {
+        let path = "xff-example-data/encrypted_costs.xff";
+        write_encrypted(path, credentials(), PASSPHRASE).unwrap();
+        let bytes = fs::read(path).unwrap();
+        // memory cost 4 GiB, time cost 64, parallelism 16, each refused before deriving a key,
+        // deriving one would report a wrong passphrase
+        for (pos, cost) in [(9, 4 * 1024 * 1024_u32), (13, 64), (17, 16)] {
+            let mut costly = bytes.clone();
+            costly[pos..pos + 4].copy_from_slice(&cost.to_le_bytes());
+            fs::write(path, &costly).unwrap();
+            assert!(
+                matches!(
+                    read_encrypted(path, PASSPHRASE),
+                    Err(NabuError::CorruptedEncryptedXFF)
+                ),
+                "{pos}"
+            );
+        }
+        // the highest memory cost accepted
+        let mut accepted = bytes.clone();
+        accepted[9..13].copy_from_slice(&(64 * 1024_u32).to_le_bytes());
+        fs::write(path, &accepted).unwrap();
+        assert!(matches!(
+            read_encrypted(path, PASSPHRASE),
+            Err(NabuError::WrongPassphrase)
+        ));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "key_value_store")]
+    fn nabudb() {
+        use nabu::key_value_store::{new_encrypted_nabudb, new_nabudb};
+
+        let path = "xff-example-data/nabuDB_encrypted.xff";
+        let _ = fs::remove_file(path);
+        let mut db = new_encrypted_nabudb(path, PASSPHRASE).unwrap();
+        db.insert("password".to_string(), XffValue::from("hunter2"));
+        db.save().unwrap();
+        assert!(!format!("{db:?}").contains("horse"));
+
+        let read = new_encrypted_nabudb(path, PASSPHRASE).unwrap();
+        assert_eq!(read.get("password"), Some(&XffValue::from("hunter2")));
+        assert!(matches!(
+            new_encrypted_nabudb(path, "wrong"),
+            Err(NabuError::WrongPassphrase)
+        ));
+        assert!(matches!(new_nabudb(path), Err(NabuError::EncryptedXFF)));
+        fs::remove_file(path).unwrap();
+    }
+}
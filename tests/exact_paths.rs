@@ -0,0 +1,87 @@
+#[cfg(test)]
+mod exact_paths {
+    use std::fs;
+
+    use nabu::*;
+
+    #[test]
+    fn extensions() {
+        let dir = "xff-example-data/exact";
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir(dir).unwrap();
+        let files = || {
+            let mut files: Vec<_> = fs::read_dir(dir)
+                .unwrap()
+                .map(|e| e.unwrap().file_name().into_string().unwrap())
+                .collect();
+            files.sort();
+            files
+        };
+
+        serde::write(format!("{dir}/data.v2.backup"), XffValue::from(1)).unwrap();
+        assert_eq!(files(), vec!["data.v2.xff"]);
+        serde::write_exact(format!("{dir}/data.v2.backup"), XffValue::from(2)).unwrap();
+        serde::write_exact(format!("{dir}/data"), XffValue::from(3)).unwrap();
+        let legacy = vec![XffValue::from("four")];
+        serde::write_legacy_exact(format!("{dir}/data.v0"), legacy, 0).unwrap();
+        let options = serde::WriteOptions::new();
+        serde::write_with_options_exact(format!("{dir}/data.bin"), XffValue::from(5), &options)
+            .unwrap();
+        assert_eq!(
+            files(),
+            vec![
+                "data",
+                "data.bin",
+                "data.v0",
+                "data.v2.backup",
+                "data.v2.xff"
+            ]
+        );
+
+        let read = |name: &str| serde::read_exact(format!("{dir}/{name}")).unwrap();
+        assert_eq!(read("data.v2.backup"), XffValue::from(2));
+        assert_eq!(read("data"), XffValue::from(3));
+        assert_eq!(
+            read("data.v0"),
+            XffValue::from(vec![XffValue::from("four")])
+        );
+        assert_eq!(read("data.bin"), XffValue::from(5));
+        // the forcing variants still end up at `data.v2.xff`
+        assert_eq!(
+            serde::read(format!("{dir}/data.v2.backup")).unwrap(),
+            XffValue::from(1)
+        );
+
+        serde::remove_file_exact(format!("{dir}/data.v2.backup")).unwrap();
+        serde::remove_file(format!("{dir}/data.v2.backup")).unwrap();
+        assert_eq!(files(), vec!["data", "data.bin", "data.v0"]);
+        assert!(serde::read_exact(format!("{dir}/data.v2.backup")).is_err());
+
+        #[cfg(feature = "key_value_store")]
+        {
+            let path = format!("{dir}/store.db");
+            let mut db = key_value_store::new_nabudb_exact(&path).unwrap();
+            db.insert("key".to_string(), XffValue::from("value"));
+            db.save().unwrap();
+            let read = key_value_store::new_nabudb_exact(&path).unwrap();
+            assert_eq!(read.get("key"), Some(&XffValue::from("value")));
+            fs::remove_file(path).unwrap();
+        }
+        #[cfg(feature = "logging_wizard")]
+        {
+            use nabu::logging_wizard::{Log, LogData, LoggingWizard};
+
+            let path = format!("{dir}/wizard.log");
+            let mut wizard = LoggingWizard::new_exact(&path);
+            let mut log = Log::new();
+            log.add_log_data(LogData::new("name", XffValue::from("value"), None));
+            wizard.add_log(log);
+            wizard.save().unwrap();
+            let read = LoggingWizard::from_file_exact(&path).unwrap();
+            assert_eq!(read.logs.len(), 1);
+            fs::remove_file(path).unwrap();
+        }
+        assert_eq!(files(), vec!["data", "data.bin", "data.v0"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
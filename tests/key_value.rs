@@ -219,6 +219,32 @@ mod legacy_core {
         // cleanup
         let _ = std::fs::remove_file(path);
     }
+
+    #[test]
+    fn paths() {
+        use nabu::features::key_value::core::{
+            read_core, read_core_exact, write_core, write_core_exact,
+        };
+
+        let path = Path::new("xff-example-data/kv_core_paths.v0");
+        let forced = path.with_extension("xff");
+        let mut data = new_core_store();
+        data.insert("key0".to_string(), XffValue::from("value0"));
+
+        // the path is changed to end in `.xff`
+        write_core(path, data.clone()).unwrap();
+        assert!(!path.exists());
+        assert_eq!(read_core(path).unwrap(), data);
+        assert_eq!(read_core_exact(&forced).unwrap(), data);
+
+        write_core_exact(path, data.clone()).unwrap();
+        assert!(path.exists());
+        assert_eq!(read_core_exact(path).unwrap(), data);
+
+        // cleanup
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(forced).unwrap();
+    }
 }
 
 #[cfg(test)]
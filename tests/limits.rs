@@ -0,0 +1,293 @@
+#[cfg(test)]
+mod limits {
+    use std::fs;
+    use std::io::Cursor;
+
+    use nabu::serde::{
+        from_bytes_ref_with_options, from_bytes_with_options, recover_bytes_with_options,
+        DecodeOptions, XffDocument, XffReader, MAX_DEPTH,
+    };
+    use nabu::*;
+
+    fn document() -> XffValue {
+        let mut object = Object::new();
+        object.insert("name", "hello mom");
+        object.insert("data", vec![0_u8; 1_000]);
+        object.insert(
+            "array",
+            vec![XffValue::from(vec![XffValue::from("one"), XffValue::Null])],
+        );
+        XffValue::from(object)
+    }
+
+    fn all_versions() -> Vec<Vec<u8>> {
+        vec![
+            serde::to_bytes_legacy(vec![document()], 1).unwrap(),
+            serde::to_bytes(document()).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn defaults() {
+        let options = DecodeOptions::default();
+        assert_eq!(options, DecodeOptions::new());
+        assert_eq!(options.get_max_depth(), Some(MAX_DEPTH));
+        assert_eq!(options.get_max_value_len(), None);
+        assert_eq!(options.get_max_keys(), None);
+        assert_eq!(options.get_max_total_bytes(), None);
+        for bytes in all_versions() {
+            assert_eq!(
+                from_bytes_with_options(&bytes, &options).unwrap(),
+                document()
+            );
+        }
+        // loose limits change nothing
+        let options = DecodeOptions::new()
+            .max_depth(3)
+            .max_value_len(2_000)
+            .max_keys(3)
+            .max_total_bytes(1_000_000);
+        for bytes in all_versions() {
+            assert_eq!(
+                from_bytes_with_options(&bytes, &options).unwrap(),
+                document()
+            );
+        }
+    }
+
+    #[test]
+    fn depth() {
+        let options = DecodeOptions::new().max_depth(2);
+        for bytes in all_versions() {
+            assert!(matches!(
+                from_bytes_with_options(&bytes, &options),
+                Err(NabuError::DepthLimitExceeded(2, _))
+            ));
+        }
+        // far too deep for the stack, rejected long before
+        for version in [1, 2] {
+            let mut bytes = vec![version];
+            for _ in 0..1_000_000 {
+                bytes.extend([3, 1, 0]);
+            }
+            let options = DecodeOptions::new().max_depth(64);
+            match from_bytes_with_options(&bytes, &options) {
+                Err(NabuError::DepthLimitExceeded(64, pos)) => assert_eq!(pos, 1 + 64 * 3),
+                other => panic!("{other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn depth_above_default() {
+        // NUL, nested in 200 arrays
+        let mut value = vec![0_u8];
+        for _ in 0..200 {
+            let len = value.len();
+            let mut array = if len <= 255 {
+                vec![3, 1, len as u8]
+            } else {
+                vec![3, 2, len as u8, (len >> 8) as u8]
+            };
+            array.extend(value);
+            // EV
+            array.push(24);
+            value = array;
+        }
+        let mut bytes = vec![2];
+        bytes.extend(value);
+        bytes.push(25);
+        assert!(matches!(
+            serde::from_bytes(&bytes),
+            Err(NabuError::DepthLimitExceeded(MAX_DEPTH, _))
+        ));
+        // the decoders recurse once for every level, unoptimized builds need a larger stack
+        let decoded = std::thread::Builder::new()
+            .stack_size(64 << 20)
+            .spawn(move || {
+                let options = DecodeOptions::new().max_depth(1_000);
+                from_bytes_with_options(&bytes, &options)
+            })
+            .unwrap()
+            .join()
+            .unwrap()
+            .unwrap();
+        let mut depth = 0;
+        let mut value = &decoded;
+        while let XffValue::Array(array) = value {
+            depth += 1;
+            value = &array[0];
+        }
+        assert_eq!(depth, 200);
+    }
+
+    #[test]
+    fn writers_stop_at_default_depth() {
+        let mut value = XffValue::Null;
+        for _ in 0..MAX_DEPTH {
+            value = XffValue::from(vec![value]);
+        }
+        let bytes = serde::to_bytes(value.clone()).unwrap();
+        assert_eq!(serde::from_bytes(&bytes).unwrap(), value);
+
+        let mut object = Object::new();
+        object.insert("deep", value);
+        let value = XffValue::from(object);
+        assert!(matches!(
+            serde::to_bytes(value.clone()),
+            Err(NabuError::NestingTooDeep(MAX_DEPTH))
+        ));
+        assert!(matches!(
+            serde::to_bytes_legacy(vec![value.clone()], 1),
+            Err(NabuError::NestingTooDeep(MAX_DEPTH))
+        ));
+        assert!(matches!(
+            serde::serialize_canonical(&value),
+            Err(NabuError::NestingTooDeep(MAX_DEPTH))
+        ));
+        // nothing is written
+        let mut writer = serde::XffWriter::new(Vec::new());
+        assert!(matches!(
+            writer.write(&value),
+            Err(NabuError::NestingTooDeep(MAX_DEPTH))
+        ));
+        assert!(writer.into_inner().is_empty());
+    }
+
+    #[test]
+    fn value_length() {
+        let options = DecodeOptions::new().max_value_len(999);
+        for bytes in all_versions() {
+            assert!(matches!(
+                from_bytes_with_options(&bytes, &options),
+                Err(NabuError::ValueLengthLimitExceeded(_, _))
+            ));
+        }
+        // a declared length of u64::MAX, rejected before anything is read
+        let mut bytes = vec![1, 5, 8];
+        bytes.extend([255; 8]);
+        bytes.extend([24, 25]);
+        assert!(matches!(
+            from_bytes_with_options(&bytes, &DecodeOptions::new().max_value_len(1 << 20)),
+            Err(NabuError::ValueLengthLimitExceeded(usize::MAX, 2))
+        ));
+        // v0 data
+        let bytes = serde::to_bytes_legacy(vec![XffValue::from(vec![1_u8; 100])], 0).unwrap();
+        assert!(matches!(
+            from_bytes_with_options(&bytes, &DecodeOptions::new().max_value_len(99)),
+            Err(NabuError::ValueLengthLimitExceeded(100, 1))
+        ));
+    }
+
+    #[test]
+    fn keys() {
+        let options = DecodeOptions::new().max_keys(2);
+        for bytes in all_versions() {
+            assert!(matches!(
+                from_bytes_with_options(&bytes, &options),
+                Err(NabuError::KeyLimitExceeded(2, _))
+            ));
+        }
+    }
+
+    #[test]
+    fn total_bytes() {
+        let strings = XffValue::from(vec![XffValue::from("a".repeat(100)); 100]);
+        for bytes in [
+            serde::to_bytes_legacy(vec![strings.clone()], 1).unwrap(),
+            serde::to_bytes(strings.clone()).unwrap(),
+            serde::to_bytes_legacy(vec![XffValue::from("a".repeat(100)); 100], 0).unwrap(),
+        ] {
+            assert!(matches!(
+                from_bytes_with_options(&bytes, &DecodeOptions::new().max_total_bytes(5_000)),
+                Err(NabuError::AllocationLimitExceeded(5_000, _))
+            ));
+            assert!(from_bytes_with_options(
+                &bytes,
+                &DecodeOptions::new().max_total_bytes(100_000)
+            )
+            .is_ok());
+        }
+    }
+
+    #[test]
+    fn read_with_options() {
+        let path = "xff-example-data/limits.bin";
+        serde::write_exact(path, document()).unwrap();
+        let file_len = fs::metadata(path).unwrap().len() as usize;
+
+        let options = DecodeOptions::new();
+        assert_eq!(
+            serde::read_with_options_exact(path, &options).unwrap(),
+            document()
+        );
+        // the file itself counts towards the limit
+        let options = options.max_total_bytes(file_len - 1);
+        assert!(matches!(
+            serde::read_with_options_exact(path, &options),
+            Err(NabuError::AllocationLimitExceeded(max, 0)) if max == file_len - 1
+        ));
+        let options = options.max_total_bytes(file_len + 100);
+        assert!(matches!(
+            serde::read_with_options_exact(path, &options),
+            Err(NabuError::AllocationLimitExceeded(max, _)) if max == file_len + 100
+        ));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn entry_points() {
+        let options = DecodeOptions::new().max_depth(2);
+        let too_deep = |result: Result<XffValue, NabuError>| {
+            matches!(result, Err(NabuError::DepthLimitExceeded(2, _)))
+        };
+        for bytes in all_versions() {
+            let mut reader = XffReader::with_options(Cursor::new(&bytes), &options);
+            assert!(too_deep(reader.read()));
+            assert!(too_deep(
+                from_bytes_ref_with_options(&bytes, &options).and_then(|value| value.to_owned())
+            ));
+            let recovered = recover_bytes_with_options(&bytes, &options);
+            assert!(recovered.map_or(true, |recovered| !recovered.is_complete()));
+        }
+        let bytes = serde::to_bytes(document()).unwrap();
+        let mut document = XffDocument::with_options(Cursor::new(&bytes), &options).unwrap();
+        let root = document.root();
+        assert!(too_deep(document.read(&root)));
+        let array = document.get(&root, "array").unwrap().unwrap();
+        assert!(document.read(&array).is_ok());
+
+        #[cfg(feature = "serde")]
+        {
+            let nested = to_xff_bytes(&vec![vec![vec![1_u8]]]).unwrap();
+            assert!(matches!(
+                from_xff_bytes_with_options::<Vec<Vec<Vec<u8>>>>(&nested, &options),
+                Err(NabuError::DepthLimitExceeded(2, _))
+            ));
+            let nested = to_xff_bytes(&vec![vec![1_u8]]).unwrap();
+            assert!(from_xff_bytes_with_options::<Vec<Vec<u8>>>(&nested, &options).is_ok());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn compressed() {
+        use nabu::serde::{Compression, WriteOptions};
+
+        let value = XffValue::from(vec![0_u8; 100_000]);
+        let options = WriteOptions::new().compression(Compression::File);
+        let bytes = serde::to_bytes_with_options(value.clone(), &options).unwrap();
+        assert!(bytes.len() < 1_000);
+        // the decompressed length is checked before decompressing
+        assert!(matches!(
+            from_bytes_with_options(&bytes, &DecodeOptions::new().max_value_len(10_000)),
+            Err(NabuError::ValueLengthLimitExceeded(_, _))
+        ));
+        assert!(matches!(
+            from_bytes_with_options(&bytes, &DecodeOptions::new().max_total_bytes(10_000)),
+            Err(NabuError::AllocationLimitExceeded(10_000, _))
+        ));
+        let options = DecodeOptions::new().max_total_bytes(300_000);
+        assert_eq!(from_bytes_with_options(&bytes, &options).unwrap(), value);
+    }
+}
@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod malformed {
+    use std::fs;
+    use std::io::Cursor;
+    use std::panic::catch_unwind;
+
+    use tyche::prelude::*;
+
+    use nabu::serde::{XffDocument, XffReader, MAX_DEPTH};
+    use nabu::*;
+
+    /// Decodes the bytes with every decoder, returning how many of them succeeded
+    fn decode_all(bytes: &[u8]) -> usize {
+        let results = [
+            serde::from_bytes(bytes).is_ok(),
+            XffReader::new(bytes).read().is_ok(),
+            serde::from_bytes_ref(bytes)
+                .and_then(|value| value.to_owned())
+                .is_ok(),
+            XffDocument::new(Cursor::new(bytes))
+                .and_then(|mut document| {
+                    let root = document.root();
+                    document.read(&root)
+                })
+                .is_ok(),
+        ];
+        results.iter().filter(|ok| **ok).count()
+    }
+
+    fn seeds() -> Vec<Vec<u8>> {
+        let value = XffValue::from(vec![
+            ("name", XffValue::from("hello mom")),
+            ("number", XffValue::from(-42.69)),
+            ("data", XffValue::from(vec![0_u8, 24, 25, 29, 30, 31])),
+            (
+                "array",
+                XffValue::from(vec![
+                    XffValue::from(true),
+                    XffValue::from(false),
+                    XffValue::Null,
+                    XffValue::from(Vec::<XffValue>::new()),
+                    XffValue::from(Object::new()),
+                    XffValue::from(vec![XffValue::from(1), XffValue::from("nested")]),
+                ]),
+            ),
+        ]);
+        let v0 = vec![
+            XffValue::from("hello mom"),
+            XffValue::from(42),
+            XffValue::from(vec![1_u8, 2, 3]),
+            XffValue::from(CommandCharacter::LineFeed),
+            XffValue::from(CommandCharacter::Escape),
+        ];
+        vec![
+            serde::to_bytes_legacy(v0, 0).unwrap(),
+            serde::to_bytes_legacy(vec![value.clone()], 1).unwrap(),
+            serde::to_bytes(value).unwrap(),
+        ]
+    }
+
+    fn mutate(bytes: &mut Vec<u8>) {
+        // structural bytes of all versions are the most likely to reach new code paths
+        const STRUCTURAL: [u8; 17] = [0, 1, 2, 3, 4, 5, 6, 8, 16, 17, 24, 25, 27, 29, 30, 31, 255];
+        if bytes.is_empty() {
+            bytes.push(random_u8().unwrap());
+            return;
+        }
+        let pos = random_from_range(0, bytes.len() - 1).unwrap();
+        match random_from_range(0, 5).unwrap() {
+            0 => bytes[pos] ^= 1 << random_from_range(0, 7).unwrap(),
+            1 => bytes[pos] = random_u8().unwrap(),
+            2 => bytes[pos] = STRUCTURAL[random_from_range(0, STRUCTURAL.len() - 1).unwrap()],
+            3 => bytes.truncate(pos),
+            4 => {
+                let _ = bytes.remove(pos);
+            }
+            _ => bytes.insert(pos, random_u8().unwrap()),
+        }
+    }
+
+    #[test]
+    fn corpus() {
+        let mut amount = 0;
+        for entry in fs::read_dir("xff-example-data/malformed").unwrap() {
+            let path = entry.unwrap().path();
+            let bytes = fs::read(&path).unwrap();
+            assert!(serde::read_exact(&path).is_err(), "{path:?}");
+            let ok = catch_unwind(|| decode_all(&bytes));
+            assert_eq!(ok.ok(), Some(0), "{path:?}");
+            amount += 1;
+        }
+        assert!(amount >= 30);
+    }
+
+    #[test]
+    fn deep_nesting() {
+        // nesting up to the limit is decoded by every decoder
+        let mut value = XffValue::Null;
+        for _ in 0..MAX_DEPTH {
+            value = XffValue::from(vec![value]);
+        }
+        for bytes in [
+            serde::to_bytes_legacy(vec![value.clone()], 1).unwrap(),
+            serde::to_bytes(value.clone()).unwrap(),
+        ] {
+            assert_eq!(decode_all(&bytes), 4);
+            assert!(serde::recover_bytes(&bytes).unwrap().is_complete());
+            #[cfg(feature = "serde")]
+            assert_eq!(from_xff_bytes::<XffValue>(&bytes).unwrap(), value);
+        }
+
+        // anything deeper is rejected, far before the stack runs out
+        for depth in [MAX_DEPTH + 1, 100_000] {
+            for version in [1, 2] {
+                let mut bytes = vec![version];
+                for _ in 0..depth {
+                    // ARY
+                    bytes.extend([3, 0]);
+                }
+                bytes.extend(vec![24; depth]);
+                bytes.push(25);
+                assert_eq!(decode_all(&bytes), 0);
+                assert!(matches!(
+                    serde::from_bytes(&bytes),
+                    Err(NabuError::DepthLimitExceeded(MAX_DEPTH, _))
+                ));
+                assert!(!serde::recover_bytes(&bytes).unwrap().is_complete());
+                #[cfg(feature = "serde")]
+                assert!(from_xff_bytes::<XffValue>(&bytes).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn mutations() {
+        for seed in seeds() {
+            assert_eq!(decode_all(&seed), if seed[0] == 0 { 2 } else { 4 });
+            for _ in 0..5_000 {
+                let mut bytes = seed.clone();
+                for _ in 0..random_from_range(1, 4).unwrap() {
+                    mutate(&mut bytes);
+                }
+                let result = catch_unwind(|| decode_all(&bytes));
+                assert!(result.is_ok(), "panicked on {bytes:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+#[cfg(test)]
+mod merge {
+    use nabu::*;
+
+    fn defaults() -> Object {
+        let mut server = Object::new();
+        server.insert("port", 8080);
+        server.insert("hosts", hosts(vec!["localhost"]));
+        server.insert("tls", false);
+        let mut object = Object::new();
+        object.insert("server", server);
+        object.insert("name", "nabu");
+        object
+    }
+
+    fn overrides() -> Object {
+        let mut server = Object::new();
+        server.insert("port", 80);
+        server.insert("hosts", hosts(vec!["example.com"]));
+        server.insert("timeout", 30);
+        let mut object = Object::new();
+        object.insert("server", server);
+        object.insert("name", "nabu");
+        object.insert("debug", true);
+        object
+    }
+
+    fn hosts(hosts: Vec<&str>) -> XffValue {
+        XffValue::from(
+            hosts
+                .into_iter()
+                .map(XffValue::from)
+                .collect::<Vec<XffValue>>(),
+        )
+    }
+
+    fn at<V: Clone + Into<XffValue>>(value: &V, pointer: &str) -> XffValue {
+        value.clone().into().pointer(pointer).cloned().unwrap()
+    }
+
+    #[test]
+    fn right_wins() {
+        let mut object = defaults();
+        object.merge(overrides(), MergeStrategy::RightWins).unwrap();
+        assert_eq!(at(&object, "/server/port"), XffValue::from(80));
+        assert_eq!(at(&object, "/server/hosts"), hosts(vec!["example.com"]));
+        assert_eq!(at(&object, "/server/tls"), XffValue::from(false));
+        assert_eq!(at(&object, "/server/timeout"), XffValue::from(30));
+        assert_eq!(at(&object, "/debug"), XffValue::from(true));
+        assert_eq!(object.len(), 3);
+        assert_eq!(MergeStrategy::default(), MergeStrategy::RightWins);
+    }
+
+    #[test]
+    fn left_wins() {
+        let mut object = defaults();
+        object.merge(overrides(), MergeStrategy::LeftWins).unwrap();
+        assert_eq!(at(&object, "/server/port"), XffValue::from(8080));
+        assert_eq!(at(&object, "/server/hosts"), hosts(vec!["localhost"]));
+        // missing keys are still added
+        assert_eq!(at(&object, "/server/timeout"), XffValue::from(30));
+        assert_eq!(at(&object, "/debug"), XffValue::from(true));
+    }
+
+    #[test]
+    fn concat_arrays() {
+        let mut object = defaults();
+        object
+            .merge(overrides(), MergeStrategy::ConcatArrays)
+            .unwrap();
+        assert_eq!(
+            at(&object, "/server/hosts"),
+            hosts(vec!["localhost", "example.com"])
+        );
+        assert_eq!(at(&object, "/server/port"), XffValue::from(80));
+
+        // an array replacing a different type is not concatenated
+        let mut value = XffValue::from(vec![("hosts", XffValue::from("localhost"))]);
+        value
+            .merge(
+                XffValue::from(vec![("hosts", hosts(vec!["a"]))]),
+                MergeStrategy::ConcatArrays,
+            )
+            .unwrap();
+        assert_eq!(at(&value, "/hosts"), hosts(vec!["a"]));
+    }
+
+    #[test]
+    fn error_on_conflict() {
+        let mut object = defaults();
+        match object.merge(overrides(), MergeStrategy::ErrorOnConflict) {
+            Err(NabuError::MergeConflict(path)) => assert_eq!(path, "/server/hosts"),
+            other => panic!("{other:?}"),
+        }
+        assert_eq!(object, defaults());
+
+        // equal values and new keys are no conflict
+        let mut additions = Object::new();
+        additions.insert("name", "nabu");
+        additions.insert("server", vec![("timeout", 30)]);
+        object
+            .merge(additions, MergeStrategy::ErrorOnConflict)
+            .unwrap();
+        assert_eq!(at(&object, "/server/timeout"), XffValue::from(30));
+
+        let mut escaped = Object::from(vec![("a/b", XffValue::from(1))]);
+        let err = escaped
+            .merge(
+                Object::from(vec![("a/b", XffValue::from(2))]),
+                MergeStrategy::ErrorOnConflict,
+            )
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Merge conflict at '/a~1b'");
+    }
+
+    #[test]
+    fn values() {
+        let mut value = XffValue::from(defaults());
+        value
+            .merge(XffValue::from(overrides()), MergeStrategy::RightWins)
+            .unwrap();
+        let mut object = defaults();
+        object.merge(overrides(), MergeStrategy::RightWins).unwrap();
+        assert_eq!(value, XffValue::from(object));
+
+        let mut value = XffValue::from(42);
+        value
+            .merge(XffValue::from("hi mom!"), MergeStrategy::LeftWins)
+            .unwrap();
+        assert_eq!(value, XffValue::from(42));
+        value
+            .merge(XffValue::from("hi mom!"), MergeStrategy::ConcatArrays)
+            .unwrap();
+        assert_eq!(value, XffValue::from("hi mom!"));
+        match value.merge(XffValue::Null, MergeStrategy::ErrorOnConflict) {
+            Err(NabuError::MergeConflict(path)) => assert!(path.is_empty()),
+            other => panic!("{other:?}"),
+        }
+    }
+}
@@ -0,0 +1,175 @@
+#[cfg(test)]
+mod query {
+    use nabu::*;
+
+    fn user(name: &str, age: isize, email: Option<&str>) -> XffValue {
+        let mut object = Object::new();
+        object.insert("name", name);
+        object.insert("age", age);
+        if let Some(email) = email {
+            object.insert("email", email);
+        }
+        XffValue::from(object)
+    }
+
+    fn make_data() -> XffValue {
+        let mut object = Object::new();
+        object.insert(
+            "users",
+            vec![
+                user("Alice", 31, Some("alice@example.com")),
+                user("Bob", 17, None),
+                user("Carol", 45, Some("carol@example.com")),
+                user("Dave", 18, None),
+            ],
+        );
+        object.insert("~tilde/slash", 42.69);
+        object.insert("nested", vec![("name", XffValue::from("inner"))]);
+        XffValue::from(object)
+    }
+
+    #[test]
+    fn pointer() {
+        let data = make_data();
+        assert_eq!(data.pointer(""), Some(&data));
+        assert_eq!(data.pointer("/users/2/name"), Some(&XffValue::from("Carol")));
+        assert_eq!(data.pointer("/~0tilde~1slash"), Some(&XffValue::from(42.69)));
+        assert_eq!(data.pointer("/nested/name"), Some(&XffValue::from("inner")));
+        // missing keys and indices
+        assert_eq!(data.pointer("/users/4/name"), None);
+        assert_eq!(data.pointer("/missing"), None);
+        assert_eq!(data.pointer("/users/0/name/deeper"), None);
+        // malformed pointers
+        assert_eq!(data.pointer("users/0"), None);
+        assert_eq!(data.pointer("/users/01"), None);
+        assert_eq!(data.pointer("/users/-"), None);
+        assert_eq!(data.pointer("/users/+1"), None);
+        assert_eq!(data.pointer("/~2tilde~1slash"), None);
+    }
+
+    #[test]
+    fn pointer_mut() {
+        let mut data = make_data();
+        *data.pointer_mut("/users/1/age").unwrap() = XffValue::from(18);
+        assert_eq!(data.pointer("/users/1/age"), Some(&XffValue::from(18)));
+        if let Some(XffValue::Array(users)) = data.pointer_mut("/users") {
+            users.push(user("Eve", 22, None));
+        }
+        assert_eq!(data.pointer("/users/4/name"), Some(&XffValue::from("Eve")));
+        assert!(data.pointer_mut("/users/5").is_none());
+    }
+
+    #[test]
+    fn keys_indices_and_wildcards() {
+        let data = make_data();
+        assert_eq!(data.query("$").unwrap(), vec![&data]);
+        assert_eq!(data.query("$.users[0].name").unwrap(), vec![&XffValue::from("Alice")]);
+        assert_eq!(data.query("$['users'][-1][\"name\"]").unwrap(), vec![&XffValue::from("Dave")]);
+        assert_eq!(data.query("$['~tilde/slash']").unwrap(), vec![&XffValue::from(42.69)]);
+        assert_eq!(
+            data.query("$.users[*].name").unwrap(),
+            vec![
+                &XffValue::from("Alice"),
+                &XffValue::from("Bob"),
+                &XffValue::from("Carol"),
+                &XffValue::from("Dave"),
+            ]
+        );
+        // missing values select nothing
+        assert!(data.query("$.users[4]").unwrap().is_empty());
+        assert!(data.query("$.users[-5]").unwrap().is_empty());
+        assert!(data.query("$.missing.name").unwrap().is_empty());
+        assert!(data.query("$.users.name").unwrap().is_empty());
+        // values of objects in the order of their keys
+        assert_eq!(data.query("$.users[1].*").unwrap(), vec![&XffValue::from(17), &XffValue::from("Bob")]);
+        // descendants
+        assert_eq!(
+            data.query("$..name").unwrap(),
+            vec![
+                &XffValue::from("inner"),
+                &XffValue::from("Alice"),
+                &XffValue::from("Bob"),
+                &XffValue::from("Carol"),
+                &XffValue::from("Dave"),
+            ]
+        );
+        assert_eq!(data.query("$..email").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn slices() {
+        let data = XffValue::from((0..10).map(XffValue::from).collect::<Vec<XffValue>>());
+        let select = |query: &str| -> Vec<XffValue> {
+            data.query(query).unwrap().into_iter().cloned().collect()
+        };
+        let numbers = |range: Vec<i32>| -> Vec<XffValue> { range.into_iter().map(XffValue::from).collect() };
+        assert_eq!(select("$[1:4]"), numbers(vec![1, 2, 3]));
+        assert_eq!(select("$[:2]"), numbers(vec![0, 1]));
+        assert_eq!(select("$[8:]"), numbers(vec![8, 9]));
+        assert_eq!(select("$[-3:]"), numbers(vec![7, 8, 9]));
+        assert_eq!(select("$[::3]"), numbers(vec![0, 3, 6, 9]));
+        assert_eq!(select("$[::-4]"), numbers(vec![9, 5, 1]));
+        assert_eq!(select("$[5:2:-1]"), numbers(vec![5, 4, 3]));
+        assert_eq!(select("$[4:100]"), numbers(vec![4, 5, 6, 7, 8, 9]));
+        assert!(select("$[7:2]").is_empty());
+        assert!(data.query("$[::0]").is_err());
+    }
+
+    #[test]
+    fn filters() {
+        let data = make_data();
+        let names = |query: &str| -> Vec<String> {
+            data.query(query)
+                .unwrap()
+                .into_iter()
+                .map(|v| v.as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(names("$.users[?(@.age >= 18)].name"), vec!["Alice", "Carol", "Dave"]);
+        assert_eq!(names("$.users[?(@.age > 18)].name"), vec!["Alice", "Carol"]);
+        assert_eq!(names("$.users[?(@.age < 18)].name"), vec!["Bob"]);
+        assert_eq!(names("$.users[?(@.age <= 18)].name"), vec!["Bob", "Dave"]);
+        assert_eq!(names("$.users[?(@.age == 45)].name"), vec!["Carol"]);
+        // numbers compare by value no matter their variant
+        assert_eq!(names("$.users[?(@.age == 45.0)].name"), vec!["Carol"]);
+        assert_eq!(names("$.users[?(@.name != 'Bob')].name"), vec!["Alice", "Carol", "Dave"]);
+        assert_eq!(names("$.users[?(@.name > \"Bob\")].name"), vec!["Carol", "Dave"]);
+        assert_eq!(names("$.users[?(@.email)].name"), vec!["Alice", "Carol"]);
+        assert_eq!(names("$.users[?(@['name'] == 'Dave')].name"), vec!["Dave"]);
+        // unordered values never match an ordering
+        assert!(names("$.users[?(@.name > 18)].name").is_empty());
+
+        let flags = XffValue::from(vec![
+            XffValue::from(vec![("on", XffValue::from(true))]),
+            XffValue::from(vec![("on", XffValue::Null)]),
+        ]);
+        assert_eq!(flags.query("$[?(@.on == true)]").unwrap(), vec![&flags.as_array().unwrap()[0]]);
+        assert_eq!(flags.query("$[?(@.on == null)]").unwrap().len(), 1);
+        let pairs = XffValue::from(vec![XffValue::from(vec![XffValue::from(1), XffValue::from(1)])]);
+        assert_eq!(pairs.query("$[?(@[0] == @[1])]").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn invalid_queries() {
+        let positions = [
+            ("users", 0),
+            ("$users", 1),
+            ("$.", 2),
+            ("$[", 2),
+            ("$[0", 3),
+            ("$['users", 8),
+            ("$[?(@.age >> 1)]", 11),
+            ("$[?(@.age > maybe)]", 12),
+            ("$[?(@.age > 1.2.3)]", 12),
+        ];
+        for (query, pos) in positions {
+            match Query::parse(query) {
+                Err(NabuError::InvalidQuery(p, q)) => {
+                    assert_eq!(p, pos, "{query}");
+                    assert_eq!(q, query);
+                }
+                other => panic!("{query}: {other:?}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,354 @@
+#[cfg(test)]
+mod recover {
+    use std::fs;
+    use std::panic::catch_unwind;
+
+    use tyche::prelude::*;
+
+    use nabu::serde::{recover_bytes, Recovered};
+    use nabu::*;
+
+    fn users(amount: usize) -> XffValue {
+        let mut users = Vec::new();
+        for i in 0..amount {
+            let mut user = Object::new();
+            user.insert("name", format!("user {i}"));
+            user.insert("data", vec![30_u8, 24, 29, 25, i as u8]);
+            user.insert("tags", vec![XffValue::from("a"), XffValue::from("b")]);
+            users.push(XffValue::from(user));
+        }
+        XffValue::from(vec![("users", XffValue::from(users))])
+    }
+
+    fn v1(value: XffValue) -> Vec<u8> {
+        serde::to_bytes_legacy(vec![value], 1).unwrap()
+    }
+
+    fn v2(value: XffValue) -> Vec<u8> {
+        serde::to_bytes(value).unwrap()
+    }
+
+    /// The keys and values of a `NabuDB`, in the order it writes them
+    fn key_values(amount: usize) -> Vec<XffValue> {
+        let mut values = Vec::new();
+        for i in 0..amount {
+            values.push(XffValue::from(format!("key {i}")));
+            values.push(XffValue::from(format!("value {i}")));
+        }
+        values
+    }
+
+    fn v0(values: Vec<XffValue>) -> Vec<u8> {
+        serde::to_bytes_legacy(values, 0).unwrap()
+    }
+
+    fn find(bytes: &[u8], needle: &[u8]) -> usize {
+        bytes
+            .windows(needle.len())
+            .position(|window| window == needle)
+            .unwrap()
+    }
+
+    fn user_names(recovered: &Recovered) -> Vec<String> {
+        recovered
+            .value()
+            .as_object()
+            .unwrap()
+            .get("users")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            // users recovered only partially may be missing their name, or a flipped type byte
+            // may have turned it into another readable value
+            .filter_map(|user| user.as_object()?.get("name")?.as_str())
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn undamaged() {
+        let recovered = recover_bytes(&v1(users(10))).unwrap();
+        assert!(recovered.is_complete());
+        assert_eq!(recovered.into_value(), users(10));
+
+        let recovered = recover_bytes(&v2(users(10))).unwrap();
+        assert!(recovered.is_complete());
+        assert_eq!(recovered.value(), &users(10));
+        let recovered = recover_bytes(&v0(key_values(10))).unwrap();
+        assert!(recovered.is_complete());
+        assert_eq!(recovered.into_value(), XffValue::from(key_values(10)));
+
+        assert!(matches!(recover_bytes(&[1]), Err(NabuError::MissingEM(2))));
+    }
+
+    #[test]
+    fn damaged_value() {
+        let mut bytes = v1(users(10));
+        let pos = find(&bytes, b"user 3");
+        bytes[pos] = 0;
+        let recovered = recover_bytes(&bytes).unwrap();
+        let skipped = recovered.skipped();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].path(), "root.users[3].name");
+        assert!(skipped[0].range().contains(&pos));
+        assert!(matches!(
+            skipped[0].error(),
+            NabuError::InvalidASCIIString(0, _, 1)
+        ));
+        // only the damaged key is lost
+        let user = recovered.value().as_object().unwrap().get("users").unwrap();
+        let user = user
+            .as_array()
+            .unwrap()
+            .get(3)
+            .unwrap()
+            .as_object()
+            .unwrap();
+        assert_eq!(user.len(), 2);
+        assert!(user.get("name").is_none());
+        assert_eq!(user.get("tags").unwrap().as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn damaged_length() {
+        let mut bytes = v1(users(10));
+        // the length of the length of the tags array of user 5
+        let pos = find(&bytes, b"user 5");
+        let pos = pos + find(&bytes[pos..], &[3]) + 1;
+        bytes[pos] = 200;
+        let recovered = recover_bytes(&bytes).unwrap();
+        assert_eq!(recovered.skipped().len(), 1);
+        assert_eq!(recovered.skipped()[0].path(), "root.users[5].tags");
+        assert!(matches!(
+            recovered.skipped()[0].error(),
+            NabuError::InvalidXFFValueLength(200)
+        ));
+        assert_eq!(user_names(&recovered).len(), 10);
+
+        // the length of an array is not trusted, its elements still are
+        let mut bytes = v1(users(10));
+        let pos = find(&bytes, &[3, 1]);
+        bytes[pos + 2] = bytes[pos + 2].wrapping_add(1);
+        let recovered = recover_bytes(&bytes).unwrap();
+        assert!(recovered.is_complete());
+        assert_eq!(recovered.value(), &users(10));
+    }
+
+    #[test]
+    fn damaged_separator() {
+        let mut bytes = v1(users(10));
+        let pos = find(&bytes, b"user 4");
+        // EV of user 3, the RS in front of user 4 and its OBJ
+        let pos = bytes[..pos]
+            .windows(3)
+            .rposition(|w| w == [24, 30, 4])
+            .unwrap()
+            + 1;
+        bytes[pos] = 99;
+        let recovered = recover_bytes(&bytes).unwrap();
+        let skipped = recovered.skipped();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].path(), "root.users");
+        assert_eq!(skipped[0].range().start, pos);
+        assert!(matches!(skipped[0].error(), NabuError::InvalidArray(p, 99) if *p == pos));
+        let names = user_names(&recovered);
+        assert_eq!(names.len(), 9);
+        assert!(!names.contains(&"user 4".to_string()));
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = v1(users(100));
+        let pos = find(&bytes, b"user 60");
+        let recovered = recover_bytes(&bytes[..pos]).unwrap();
+        assert!(!recovered.is_complete());
+        assert_eq!(recovered.skipped()[0].path(), "root.users[60].name");
+        let names = user_names(&recovered);
+        assert_eq!(names.len(), 60);
+        assert_eq!(names[59], "user 59");
+        // the data of user 60 precedes its name, and is recovered
+        let users = recovered.value().as_object().unwrap().get("users").unwrap();
+        assert_eq!(users.as_array().unwrap().len(), 61);
+    }
+
+    #[test]
+    fn single_damaged_byte() {
+        let bytes = v1(users(200));
+        // "users" EV US ARY, followed by the length of the array
+        let ary_pos = find(&bytes, b"users") + 7;
+        let content_pos = ary_pos + 2 + bytes[ary_pos + 1] as usize;
+        // EV of the array, trailing GS, EV of the root object and EM
+        let content_end = bytes.len() - 4;
+        for _ in 0..1_000 {
+            let mut damaged = bytes.clone();
+            let pos = random_from_range(content_pos, content_end - 1).unwrap();
+            damaged[pos] ^= 1 << random_from_range(0, 7).unwrap();
+            let recovered = recover_bytes(&damaged).unwrap();
+            for skipped in recovered.skipped() {
+                assert!(
+                    skipped.path().starts_with("root.users"),
+                    "{}",
+                    skipped.path()
+                );
+            }
+            let names = user_names(&recovered);
+            assert!(names.len() >= 198, "{pos}: {}", names.len());
+        }
+    }
+
+    #[test]
+    fn v2_damaged_value() {
+        let mut bytes = v2(users(10));
+        let pos = find(&bytes, b"user 3");
+        bytes[pos] = 255;
+        let recovered = recover_bytes(&bytes).unwrap();
+        let skipped = recovered.skipped();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].path(), "root.users[3].name");
+        assert!(matches!(skipped[0].error(), NabuError::InvalidUTF8String(p) if *p == pos));
+        let names = user_names(&recovered);
+        assert_eq!(names.len(), 9);
+        assert!(!names.contains(&"user 3".to_string()));
+    }
+
+    #[test]
+    fn v2_checksum() {
+        let options = serde::WriteOptions::new().checksum(true);
+        let bytes = serde::to_bytes_with_options(users(10), &options).unwrap();
+        let recovered = recover_bytes(&bytes).unwrap();
+        assert!(recovered.is_complete());
+        assert_eq!(recovered.into_value(), users(10));
+
+        // still readable, but not what was written
+        let mut damaged = bytes.clone();
+        let pos = find(&damaged, b"user 3");
+        damaged[pos] = b'x';
+        let recovered = recover_bytes(&damaged).unwrap();
+        let skipped = recovered.skipped();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].path(), "root");
+        assert!(matches!(skipped[0].error(), NabuError::ChecksumMismatch(_)));
+        // the trailer follows EM
+        assert_eq!(damaged[skipped[0].range().start - 1], 25);
+        assert_eq!(skipped[0].range().end, damaged.len());
+        assert_eq!(user_names(&recovered)[3], "xser 3");
+
+        damaged[pos] = 255;
+        let recovered = recover_bytes(&damaged).unwrap();
+        let skipped = recovered.skipped();
+        assert_eq!(skipped.len(), 2);
+        assert_eq!(skipped[0].path(), "root.users[3].name");
+        assert!(matches!(skipped[1].error(), NabuError::ChecksumMismatch(_)));
+        assert_eq!(user_names(&recovered).len(), 9);
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn v2_compressed() {
+        let pages: Vec<XffValue> = (0..3)
+            .map(|i| XffValue::from(format!("page {i} ").repeat(1_000).into_bytes()))
+            .collect();
+        let value = XffValue::from(vec![("pages", XffValue::from(pages.clone()))]);
+        let options = serde::WriteOptions::new().compression(serde::Compression::Data);
+        let mut bytes = serde::to_bytes_with_options(value.clone(), &options).unwrap();
+        assert!(recover_bytes(&bytes).unwrap().is_complete());
+
+        // the three pages take up most of the bytes, the middle one is damaged as a whole
+        let pos = bytes.len() / 2;
+        bytes[pos] ^= 0xff;
+        let recovered = recover_bytes(&bytes).unwrap();
+        assert_eq!(recovered.skipped().len(), 1);
+        assert_eq!(recovered.skipped()[0].path(), "root.pages[1]");
+        let recovered = recovered.value().as_object().unwrap().get("pages").unwrap();
+        assert_eq!(
+            recovered,
+            &XffValue::from(vec![pages[0].clone(), pages[2].clone()])
+        );
+    }
+
+    #[test]
+    fn v0_damaged_value() {
+        let mut bytes = v0(key_values(10));
+        let pos = find(&bytes, b"value 3");
+        bytes[pos + 1] = 0;
+        let recovered = recover_bytes(&bytes).unwrap();
+        let skipped = recovered.skipped();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].path(), "root[7]");
+        assert_eq!(skipped[0].range(), pos - 1..pos + 8);
+        assert!(matches!(
+            skipped[0].error(),
+            NabuError::InvalidASCIIString(0, p, 0) if *p == pos + 1
+        ));
+        // the damaged value is kept as null, every key stays paired with its value
+        let mut expected = key_values(10);
+        expected[7] = XffValue::Null;
+        assert_eq!(recovered.into_value(), XffValue::from(expected));
+
+        // a damaged type byte
+        let mut bytes = v0(key_values(10));
+        bytes[pos - 1] = 99;
+        let recovered = recover_bytes(&bytes).unwrap();
+        assert_eq!(recovered.skipped().len(), 1);
+        assert_eq!(recovered.skipped()[0].path(), "root[7]");
+        assert!(matches!(
+            recovered.skipped()[0].error(),
+            NabuError::InvalidXFFByte(99, _, 0)
+        ));
+        assert_eq!(recovered.value().as_array().unwrap().len(), 20);
+    }
+
+    #[test]
+    fn v0_truncated() {
+        let bytes = v0(key_values(10));
+        let pos = find(&bytes, b"key 6");
+        let recovered = recover_bytes(&bytes[..pos]).unwrap();
+        assert_eq!(recovered.skipped().len(), 1);
+        assert_eq!(recovered.skipped()[0].path(), "root[12]");
+        assert_eq!(recovered.skipped()[0].range(), pos - 1..pos);
+        assert_eq!(recovered.into_value(), XffValue::from(key_values(6)));
+    }
+
+    #[test]
+    fn never_panics() {
+        for seed in [v0(key_values(5)), v1(users(5)), v2(users(5))] {
+            never_panics_on(seed);
+        }
+        for entry in fs::read_dir("xff-example-data/malformed").unwrap() {
+            let bytes = fs::read(entry.unwrap().path()).unwrap();
+            assert!(catch_unwind(|| recover_bytes(&bytes).is_ok()).is_ok());
+        }
+    }
+
+    fn never_panics_on(seed: Vec<u8>) {
+        for _ in 0..5_000 {
+            let mut bytes = seed.clone();
+            for _ in 0..random_from_range(1, 8).unwrap() {
+                let pos = random_from_range(0, bytes.len() - 1).unwrap();
+                match random_from_range(0, 2).unwrap() {
+                    0 => bytes[pos] = random_u8().unwrap(),
+                    1 => {
+                        let _ = bytes.remove(pos);
+                    }
+                    _ => bytes.insert(pos, random_u8().unwrap()),
+                }
+            }
+            let result = catch_unwind(|| recover_bytes(&bytes).map(|r| r.skipped().len()));
+            assert!(result.is_ok(), "panicked on {bytes:?}");
+        }
+    }
+
+    #[test]
+    fn files() {
+        let path = "xff-example-data/recover-test.bin";
+        let mut bytes = v1(users(3));
+        let pos = find(&bytes, b"user 1");
+        bytes[pos] = 1;
+        fs::write(path, &bytes).unwrap();
+        let recovered = serde::recover_exact(path).unwrap();
+        assert_eq!(recovered.skipped()[0].path(), "root.users[1].name");
+        assert!(matches!(serde::recover(path), Err(NabuError::IoError(_))));
+        fs::remove_file(path).unwrap();
+    }
+}
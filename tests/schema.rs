@@ -0,0 +1,272 @@
+#[cfg(test)]
+mod schema {
+    use nabu::schema::{Pattern, Schema, ValueType, Violation};
+    use nabu::*;
+
+    fn of_type(name: &str) -> XffValue {
+        XffValue::from(vec![("types", XffValue::from(name))])
+    }
+
+    fn strings(strings: Vec<&str>) -> XffValue {
+        XffValue::from(
+            strings
+                .into_iter()
+                .map(XffValue::from)
+                .collect::<Vec<XffValue>>(),
+        )
+    }
+
+    fn user_schema() -> XffValue {
+        let mut name = Object::new();
+        name.insert("types", "string");
+        name.insert("pattern", "^[A-Z][a-z]+$");
+        let mut age = Object::new();
+        age.insert("types", strings(vec!["number", "null"]));
+        age.insert("minimum", 0);
+        age.insert("maximum", 150.5);
+        let mut avatar = Object::new();
+        avatar.insert("types", "data");
+        avatar.insert("max_length", 4_usize);
+        let mut tags = Object::new();
+        tags.insert("types", "array");
+        tags.insert("max_length", 2_usize);
+        tags.insert("items", of_type("string"));
+        let mut properties = Object::new();
+        properties.insert("name", name);
+        properties.insert("age", age);
+        properties.insert("avatar", avatar);
+        properties.insert("tags", tags);
+        properties.insert("admin", of_type("boolean"));
+        let mut user = Object::new();
+        user.insert("types", "object");
+        user.insert("required", strings(vec!["name", "age"]));
+        user.insert("properties", properties);
+        user.insert("deny_unknown_keys", true);
+
+        let mut users = Object::new();
+        users.insert("types", "array");
+        users.insert("items", user);
+        XffValue::from(vec![
+            ("types", XffValue::from("object")),
+            ("properties", XffValue::from(vec![("users", users)])),
+        ])
+    }
+
+    fn user(name: &str, age: XffValue) -> Object {
+        let mut object = Object::new();
+        object.insert("name", name);
+        object.insert("age", age);
+        object
+    }
+
+    fn paths(violations: &[Violation]) -> Vec<&str> {
+        violations.iter().map(|v| v.path.as_str()).collect()
+    }
+
+    #[test]
+    fn valid_documents() {
+        let schema = Schema::try_from(user_schema()).unwrap();
+        let mut alice = user("Alice", XffValue::from(31));
+        alice.insert("tags", strings(vec!["a", "b"]));
+        alice.insert("avatar", vec![1_u8, 2, 3, 4]);
+        alice.insert("admin", true);
+        let bob = user("Bob", XffValue::Null);
+        let document = XffValue::from(vec![(
+            "users",
+            XffValue::from(vec![XffValue::from(alice), XffValue::from(bob)]),
+        )]);
+        assert_eq!(schema.validate(&document), vec![]);
+        // through a stored document
+        let read = serde::from_bytes(&serde::to_bytes(document).unwrap()).unwrap();
+        assert!(schema.is_valid(&read));
+
+        assert!(Schema::new().is_valid(&XffValue::Null));
+        assert!(schema.is_valid(&XffValue::from(Object::new())));
+    }
+
+    #[test]
+    fn every_violation_with_its_path() {
+        let schema = Schema::try_from(user_schema()).unwrap();
+        let mut alice = user("alice", XffValue::from(-1));
+        alice.insert(
+            "tags",
+            vec![XffValue::from("a"), XffValue::from(2), XffValue::from("c")],
+        );
+        alice.insert("avatar", vec![0_u8; 5]);
+        alice.insert("admin", "yes");
+        alice.insert("password", "hunter2");
+        let mut bob = Object::new();
+        bob.insert("age", 151);
+        let document = XffValue::from(vec![(
+            "users",
+            XffValue::from(vec![XffValue::from(alice), XffValue::from(bob)]),
+        )]);
+
+        let violations = schema.validate(&document);
+        assert_eq!(
+            paths(&violations),
+            vec![
+                "/users/0/admin",
+                "/users/0/age",
+                "/users/0/avatar",
+                "/users/0/name",
+                "/users/0/password",
+                "/users/0/tags",
+                "/users/0/tags/1",
+                "/users/1/name",
+                "/users/1/age",
+            ]
+        );
+        let messages: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            messages[0],
+            "'/users/0/admin': expected boolean, found string"
+        );
+        assert_eq!(messages[1], "'/users/0/age': -1 is less than 0");
+        assert_eq!(messages[2], "'/users/0/avatar': length 5 is more than 4");
+        assert_eq!(
+            messages[3],
+            "'/users/0/name': does not match the pattern ^[A-Z][a-z]+$"
+        );
+        assert_eq!(messages[4], "'/users/0/password': unknown key");
+        assert_eq!(
+            messages[6],
+            "'/users/0/tags/1': expected string, found number"
+        );
+        assert_eq!(messages[7], "'/users/1/name': missing required key");
+        assert_eq!(messages[8], "'/users/1/age': 151 is more than 150.5");
+
+        let violations = schema.validate(&XffValue::from(vec![XffValue::Null]));
+        assert_eq!(
+            violations[0].to_string(),
+            "'': expected object, found array"
+        );
+    }
+
+    #[test]
+    fn number_ranges() {
+        let schema = Schema {
+            minimum: Some(Number::from(-0.5)),
+            maximum: Some(Number::from(u128::MAX)),
+            ..Schema::default()
+        };
+        assert!(schema.is_valid(&XffValue::from(-0.5)));
+        assert!(schema.is_valid(&XffValue::from(u128::MAX)));
+        assert!(schema.is_valid(&XffValue::from(0)));
+        assert!(!schema.is_valid(&XffValue::from(-1)));
+        assert!(!schema.is_valid(&XffValue::from(f64::NAN)));
+        // other types are unconstrained
+        assert!(schema.is_valid(&XffValue::from("-1")));
+    }
+
+    #[test]
+    fn patterns() {
+        let cases = [
+            ("abc", "xxabcxx", true),
+            ("^abc$", "xabc", false),
+            ("^a.c$", "a-c", true),
+            ("^colou?r$", "color", true),
+            ("^colou?r$", "colouur", false),
+            ("^(ab)+$", "ababab", true),
+            ("^(ab)+$", "", false),
+            ("^(cat|dog)s?$", "dogs", true),
+            ("^\\d{3}-\\d{2,}$", "123-45678", true),
+            ("^\\d{3}-\\d{2,}$", "12-45", false),
+            ("^\\w{2,3}$", "a_1", true),
+            ("^\\w{2,3}$", "abcd", false),
+            ("^[^\\s]+$", "no-space", true),
+            ("^[^\\s]+$", "a space", false),
+            ("^[a-f0-9-]+$", "dead-beef", true),
+            ("^[a-f0-9-]+$", "DEAD", false),
+            ("^\\.\\*$", ".*", true),
+            (
+                "^(a*)*b$",
+                "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaac",
+                false,
+            ),
+            ("^ü+$", "üü", true),
+            ("a|^b", "cb", false),
+            ("", "anything", true),
+        ];
+        for (pattern, text, expected) in cases {
+            assert_eq!(
+                Pattern::parse(pattern).unwrap().is_match(text),
+                expected,
+                "{pattern} {text}"
+            );
+        }
+        assert!(Pattern::parse("^.*$")
+            .unwrap()
+            .is_match(&"x".repeat(1_000_000)));
+
+        for invalid in [
+            "(a",
+            "a)",
+            "[a",
+            "[z-a]",
+            "*a",
+            "a{2",
+            "a{3,2}",
+            "\\q",
+            "^*",
+            "(a{1000}){1000}",
+            "a{10001}",
+            "(){18446744073709551615}",
+            "(){1,18446744073709551616}",
+            &"(".repeat(200_000),
+            &format!("{}a{}", "(".repeat(65), ")".repeat(65)),
+        ] {
+            assert!(
+                matches!(Pattern::parse(invalid), Err(NabuError::InvalidSchema(_))),
+                "{invalid}"
+            );
+        }
+        // repeating nothing compiles to nothing
+        for empty in ["(){10000}", "((()){10000}){10000}", "(()*)+"] {
+            assert!(Pattern::parse(empty).unwrap().is_match("x"), "{empty}");
+        }
+        let nested = format!("^{}a{}$", "(".repeat(64), ")".repeat(64));
+        assert!(Pattern::parse(&nested).unwrap().is_match("a"));
+    }
+
+    #[test]
+    fn stored_schemas() {
+        let schema = Schema::try_from(user_schema()).unwrap();
+        assert_eq!(schema.types, vec![ValueType::Object]);
+        let stored = XffValue::from(schema.clone());
+        assert_eq!(stored, user_schema());
+        let read = serde::from_bytes(&serde::to_bytes(stored).unwrap()).unwrap();
+        assert_eq!(Schema::try_from(read).unwrap(), schema);
+
+        let errors = [
+            (XffValue::from("string"), "''"),
+            (of_type("text"), "'/types'"),
+            (XffValue::from(vec![("typo", XffValue::Null)]), "'/typo'"),
+            (
+                XffValue::from(vec![("min_length", XffValue::from(-1))]),
+                "'/min_length'",
+            ),
+            (
+                XffValue::from(vec![("items", of_type("strin"))]),
+                "'/items/types'",
+            ),
+            (
+                XffValue::from(vec![(
+                    "properties",
+                    XffValue::from(vec![("a/b", XffValue::from(1))]),
+                )]),
+                "'/properties/a~1b'",
+            ),
+            (
+                XffValue::from(vec![("pattern", XffValue::from("("))]),
+                "pattern '('",
+            ),
+        ];
+        for (value, expected) in errors {
+            match Schema::try_from(value) {
+                Err(NabuError::InvalidSchema(msg)) => assert!(msg.starts_with(expected), "{msg}"),
+                other => panic!("{expected}: {other:?}"),
+            }
+        }
+    }
+}
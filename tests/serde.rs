@@ -0,0 +1,227 @@
+#[cfg(test)]
+#[cfg(feature = "serde")]
+mod serde_integration {
+    use std::collections::BTreeMap;
+
+    use ::serde::{Deserialize, Serialize};
+
+    use nabu::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Person {
+        name: String,
+        age: u8,
+        height: f64,
+        nickname: Option<String>,
+        #[serde(with = "serde_bytes")]
+        picture: Vec<u8>,
+        tags: Vec<String>,
+        pet: Pet,
+        address: Address,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Address {
+        street: String,
+        number: i32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Pet {
+        None,
+        Dog(String),
+        Cat { name: String, lives: u8 },
+        Fish(String, u8),
+    }
+
+    fn make_person() -> Person {
+        Person {
+            name: "Jörg".to_string(),
+            age: 42,
+            height: 1.69,
+            nickname: None,
+            picture: vec![0, 1, 2, 3, 255],
+            tags: vec!["hello".to_string(), "mom".to_string()],
+            pet: Pet::Cat {
+                name: "Garfield".to_string(),
+                lives: 9,
+            },
+            address: Address {
+                street: "Main Street".to_string(),
+                number: -1,
+            },
+        }
+    }
+
+    #[test]
+    fn struct_to_xff() {
+        let person = make_person();
+        let value = to_xff(&person);
+        assert!(value.is_ok());
+        let value = value.unwrap();
+        assert!(value.is_object());
+
+        let object = value.clone().into_object().unwrap();
+        assert_eq!(object["name"], XffValue::from("Jörg"));
+        assert_eq!(object["age"], XffValue::from(42_usize));
+        assert_eq!(object["nickname"], XffValue::Null);
+        assert_eq!(object["picture"], XffValue::from(vec![0_u8, 1, 2, 3, 255]));
+        assert!(object["picture"].is_data());
+        assert_eq!(
+            object["tags"],
+            XffValue::from(vec![XffValue::from("hello"), XffValue::from("mom")])
+        );
+        assert_eq!(
+            object["pet"],
+            XffValue::from(BTreeMap::from([(
+                "Cat",
+                XffValue::from(BTreeMap::from([
+                    ("name", XffValue::from("Garfield")),
+                    ("lives", XffValue::from(9_usize)),
+                ]))
+            )]))
+        );
+
+        let back: Person = from_xff(value).unwrap();
+        assert_eq!(back, person);
+    }
+
+    #[test]
+    fn enums() {
+        let pets = vec![
+            Pet::None,
+            Pet::Dog("Odie".to_string()),
+            Pet::Cat {
+                name: "Garfield".to_string(),
+                lives: 9,
+            },
+            Pet::Fish("Nemo".to_string(), 1),
+        ];
+        let value = to_xff(&pets).unwrap();
+        let array = value.clone().into_array().unwrap();
+        assert_eq!(array[0], XffValue::from("None"));
+        assert_eq!(
+            array[1],
+            XffValue::from(BTreeMap::from([("Dog", XffValue::from("Odie"))]))
+        );
+        assert_eq!(from_xff::<Vec<Pet>>(value).unwrap(), pets);
+
+        let bytes = to_xff_bytes(&pets).unwrap();
+        assert_eq!(from_xff_bytes::<Vec<Pet>>(&bytes).unwrap(), pets);
+        let v1_bytes = nabu::serde::to_bytes_legacy(vec![to_xff(&pets).unwrap()], 1).unwrap();
+        assert_eq!(from_xff_bytes::<Vec<Pet>>(&v1_bytes).unwrap(), pets);
+
+        assert!(from_xff::<Pet>(XffValue::from("Horse")).is_err());
+        assert!(from_xff::<Pet>(XffValue::from(42)).is_err());
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let person = make_person();
+        let bytes = to_xff_bytes(&person);
+        assert!(bytes.is_ok());
+        let bytes = bytes.unwrap();
+        // identical to encoding the XffValue
+        assert_eq!(bytes, nabu::serde::to_bytes(to_xff(&person).unwrap()).unwrap());
+        assert_eq!(nabu::serde::from_bytes(&bytes).unwrap(), to_xff(&person).unwrap());
+
+        let back: Person = from_xff_bytes(&bytes).unwrap();
+        assert_eq!(back, person);
+
+        // v1 byte streams are decoded directly as well
+        let v1_bytes = nabu::serde::to_bytes_legacy(vec![to_xff(&person).unwrap()], 1).unwrap();
+        let back: Person = from_xff_bytes(&v1_bytes).unwrap();
+        assert_eq!(back, person);
+
+        // truncated and invalid byte streams
+        assert!(from_xff_bytes::<Person>(&bytes[..bytes.len() - 1]).is_err());
+        assert!(from_xff_bytes::<Person>(&bytes[..bytes.len() / 2]).is_err());
+        assert!(from_xff_bytes::<Person>(&[]).is_err());
+        assert!(from_xff_bytes::<Person>(&[42, 0, 25]).is_err());
+        // wrong type
+        assert!(from_xff_bytes::<Vec<String>>(&bytes).is_err());
+    }
+
+    #[test]
+    fn borrowed_bytes() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Borrowed<'a> {
+            text: &'a str,
+            #[serde(with = "serde_bytes")]
+            data: &'a [u8],
+        }
+        let borrowed = Borrowed {
+            text: "hello mom 👋",
+            data: &[1, 2, 3],
+        };
+        let bytes = to_xff_bytes(&borrowed).unwrap();
+        let back: Borrowed = from_xff_bytes(&bytes).unwrap();
+        assert_eq!(back, borrowed);
+    }
+
+    #[test]
+    fn maps_and_options() {
+        let map: BTreeMap<u32, Option<f64>> = BTreeMap::from([(1, Some(42.69)), (2, None)]);
+        let value = to_xff(&map).unwrap();
+        let object = value.clone().into_object().unwrap();
+        assert_eq!(object["1"], XffValue::from(42.69));
+        assert_eq!(object["2"], XffValue::Null);
+        assert_eq!(from_xff::<BTreeMap<u32, Option<f64>>>(value).unwrap(), map);
+
+        let bytes = to_xff_bytes(&map).unwrap();
+        assert_eq!(from_xff_bytes::<BTreeMap<u32, Option<f64>>>(&bytes).unwrap(), map);
+
+        // keys have to be strings
+        let bad_map: BTreeMap<Vec<u8>, bool> = BTreeMap::from([(vec![1], true)]);
+        assert!(to_xff(&bad_map).is_err());
+    }
+
+    #[test]
+    fn xff_value_is_serde() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Dynamic {
+            id: u64,
+            payload: XffValue,
+        }
+        let dynamic = Dynamic {
+            id: 1,
+            payload: XffValue::from(vec![
+                XffValue::from("hello mom"),
+                XffValue::from(-42),
+                XffValue::from(vec![0_u8, 1, 2]),
+                XffValue::Null,
+            ]),
+        };
+        let value = to_xff(&dynamic).unwrap();
+        assert_eq!(value.clone().into_object().unwrap()["payload"], dynamic.payload);
+        assert_eq!(from_xff::<Dynamic>(value).unwrap(), dynamic);
+
+        let bytes = to_xff_bytes(&dynamic).unwrap();
+        assert_eq!(from_xff_bytes::<Dynamic>(&bytes).unwrap(), dynamic);
+    }
+
+    #[test]
+    fn unit_and_newtypes() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Meters(f64);
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Marker;
+
+        assert_eq!(to_xff(&()).unwrap(), XffValue::Null);
+        assert_eq!(to_xff(&Marker).unwrap(), XffValue::Null);
+        assert_eq!(to_xff(&Meters(4.2)).unwrap(), XffValue::from(4.2));
+        assert_eq!(from_xff::<Meters>(XffValue::from(4.2)).unwrap(), Meters(4.2));
+        assert_eq!(from_xff::<Marker>(XffValue::Null).unwrap(), Marker);
+        assert_eq!(from_xff::<Option<u8>>(XffValue::Null).unwrap(), None);
+
+        // numbers outside of the range of the type
+        assert!(from_xff::<u8>(XffValue::from(256)).is_err());
+        assert!(from_xff::<u8>(XffValue::from(-1)).is_err());
+        // 128 bit integers are stored losslessly
+        assert_eq!(to_xff(&u128::MAX).unwrap(), XffValue::from(u128::MAX));
+        assert_eq!(from_xff::<u128>(XffValue::from(u128::MAX)).unwrap(), u128::MAX);
+        assert_eq!(from_xff::<i128>(XffValue::from(i128::MIN)).unwrap(), i128::MIN);
+        let bytes = to_xff_bytes(&i128::MIN).unwrap();
+        assert_eq!(from_xff_bytes::<i128>(&bytes).unwrap(), i128::MIN);
+    }
+}
@@ -140,6 +140,15 @@ mod v0 {
         XffValue::String(out)
     }
 
+    #[test]
+    fn strings_with_command_characters() {
+        // command characters 8 through 13 need no escaping inside of a string
+        let data = vec![XffValue::from("hello\tmom\r\n")];
+        let bytes = serde::to_bytes_legacy(data.clone(), 0).unwrap();
+        assert_eq!(serde::from_bytes(&bytes).unwrap(), XffValue::from(data));
+        assert!(serde::from_bytes(&[0, 2, b'a', 0, 3, 25]).is_err());
+    }
+
     #[test]
     #[ignore]
     fn serializer_deserializer_bare_bones() {
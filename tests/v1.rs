@@ -14,7 +14,7 @@ mod v1 {
         let real_data2 = XffValue::from(fs::read("Cargo.toml").unwrap());
         let real_data3 = XffValue::from(fs::read("README.md").unwrap());
         let data = XffValue::from(vec![real_data.clone(), real_data2.clone(), real_data3.clone()]);
-        let write = serde::write(path, data);
+        let write = serde::write_legacy(path, vec![data], 1);
         assert!(write.is_ok());
         let read = serde::read(path);
         assert!(read.is_ok());
@@ -37,7 +37,7 @@ mod v1 {
                     ("array".to_string(), XffValue::from(Array::from(vec![XffValue::from(n)]))),
                     ("key0".to_string(), XffValue::from(42.69)),
                 ]));
-                let write = serde::write(path, data);
+                let write = serde::write_legacy(path, vec![data], 1);
                 assert!(write.is_ok());
             } else {
                 // read the file and append
@@ -48,7 +48,7 @@ mod v1 {
                 ary.push(XffValue::from(n));
                 data.insert("array".to_string(), XffValue::from(ary));
                 data.insert(format!("key{}", n), XffValue::from(42.69));
-                let write = serde::write(path, XffValue::from(data));
+                let write = serde::write_legacy(path, vec![XffValue::from(data)], 1);
                 assert!(write.is_ok());
             }
         }
@@ -58,7 +58,7 @@ mod v1 {
         assert!(read.is_ok());
         let read = read.unwrap().into_object().unwrap();
         assert_eq!(read.len(), 101);
-        assert_eq!(read["array"].into_array().unwrap().len(), 100);
+        assert_eq!(read["array"].as_array().unwrap().len(), 100);
         assert_eq!(read["key0"], XffValue::from(42.69));
         assert_eq!(read["key42"], XffValue::from(42.69));
         assert_eq!(read["key69"], XffValue::from(42.69));
@@ -75,7 +75,7 @@ mod v1 {
             if n == 0 {
                 // create a new file
                 let data = XffValue::from(vec![XffValue::from(format!("Value {}", n))]);
-                let write = serde::write(path, data);
+                let write = serde::write_legacy(path, vec![data], 1);
                 assert!(write.is_ok());
             } else {
                 // read the file and append
@@ -83,7 +83,7 @@ mod v1 {
                 assert!(read.is_ok());
                 let mut data = read.unwrap().into_array().unwrap();
                 data.push(XffValue::from(format!("Value {}", n)));
-                let write = serde::write(path, XffValue::from(data));
+                let write = serde::write_legacy(path, vec![XffValue::from(data)], 1);
                 assert!(write.is_ok());
             }
         }
@@ -124,7 +124,7 @@ mod v1 {
             lorem_long,
         ]);
         assert!(xff_val.is_array());
-        let write = serde::write("xff-example-data/v1_primitive_values.xff", xff_val.clone());
+        let write = serde::write_legacy("xff-example-data/v1_primitive_values.xff", vec![xff_val.clone()], 1);
         assert!(write.is_ok());
 
         let read = serde::read("xff-example-data/v1_primitive_values.xff");
@@ -158,7 +158,7 @@ mod v1 {
             str_with_single_quote,
         ]);
         assert!(xff_val.is_array());
-        let write = serde::write("xff-example-data/v1_escape_chars.xff", xff_val.clone());
+        let write = serde::write_legacy("xff-example-data/v1_escape_chars.xff", vec![xff_val.clone()], 1);
         assert!(write.is_ok());
 
         let read = serde::read("xff-example-data/v1_escape_chars.xff");
@@ -176,7 +176,7 @@ mod v1 {
 
         let xff_val = XffValue::from(vec![small_data, medium_data, large_data]);
         assert!(xff_val.is_array());
-        let write = serde::write("xff-example-data/v1_data.xff", xff_val.clone());
+        let write = serde::write_legacy("xff-example-data/v1_data.xff", vec![xff_val.clone()], 1);
         assert!(write.is_ok());
 
         let read = serde::read("xff-example-data/v1_data.xff");
@@ -186,6 +186,255 @@ mod v1 {
         assert_eq!(read, xff_val);
     }
 
+    #[test]
+    fn in_memory_bytes() {
+        let xff_val = XffValue::from(BTreeMap::from([
+            ("key0", XffValue::from("value0")),
+            ("key1", XffValue::from(-42)),
+            ("key2", XffValue::from(make_random_data_with_length(10_000))),
+            ("key3", XffValue::from(vec![XffValue::from(true), XffValue::Null])),
+        ]));
+        let bytes = serde::to_bytes_legacy(vec![xff_val.clone()], 1);
+        assert!(bytes.is_ok());
+        let bytes = bytes.unwrap();
+
+        // identical to the file content
+        let write = serde::write_legacy("xff-example-data/v1_in_memory_bytes.xff", vec![xff_val.clone()], 1);
+        assert!(write.is_ok());
+        assert_eq!(fs::read("xff-example-data/v1_in_memory_bytes.xff").unwrap(), bytes);
+        std::fs::remove_file("xff-example-data/v1_in_memory_bytes.xff").unwrap();
+
+        let read = serde::from_bytes(&bytes);
+        assert!(read.is_ok());
+        assert_eq!(read.unwrap(), xff_val);
+
+        let v0_bytes = fs::read("xff-example-data/v0.xff").unwrap();
+        assert_eq!(serde::from_bytes(&v0_bytes).unwrap(), serde::read("xff-example-data/v0.xff").unwrap());
+
+        assert!(serde::from_bytes(&[]).is_err());
+        assert!(serde::from_bytes(&[42, 0, 25]).is_err());
+    }
+
+    #[test]
+    fn empty_containers() {
+        let xff_val = XffValue::from(vec![
+            XffValue::from(Vec::<XffValue>::new()),
+            XffValue::from(Object::new()),
+            XffValue::from(vec![("empty", XffValue::from(Vec::<XffValue>::new()))]),
+        ]);
+        let bytes = serde::to_bytes_legacy(vec![xff_val.clone()], 1).unwrap();
+        assert_eq!(serde::from_bytes(&bytes).unwrap(), xff_val);
+        assert_eq!(serde::XffReader::new(bytes.as_slice()).read().unwrap(), xff_val);
+        // Version, ARY, length, EV, EM
+        assert_eq!(
+            serde::from_bytes(&[1, 3, 1, 0, 24, 25]).unwrap(),
+            XffValue::from(Vec::<XffValue>::new())
+        );
+        assert_eq!(serde::from_bytes(&[1, 4, 1, 0, 24, 25]).unwrap(), XffValue::from(Object::new()));
+    }
+
+    #[test]
+    fn borrowed_values() {
+        let xff_val = XffValue::from(BTreeMap::from([
+            ("ascii", XffValue::from("hello mom")),
+            ("latin", XffValue::from("Grüße")),
+            ("number", XffValue::from(-42)),
+            ("data", XffValue::from(make_random_data_with_length(1_000))),
+            ("array", XffValue::from(vec![XffValue::from(true), XffValue::Null])),
+        ]));
+        let bytes = serde::to_bytes_legacy(vec![xff_val.clone()], 1).unwrap();
+
+        let value_ref = serde::from_bytes_ref(&bytes).unwrap();
+        assert_eq!(value_ref.to_owned().unwrap(), xff_val);
+        // ASCII strings are borrowed, all others decoded
+        let ascii = value_ref.get("ascii").unwrap().as_string().unwrap().to_str().unwrap();
+        assert!(matches!(ascii, std::borrow::Cow::Borrowed("hello mom")));
+        let latin = value_ref.get("latin").unwrap().as_string().unwrap().to_str().unwrap();
+        assert!(matches!(latin, std::borrow::Cow::Owned(_)));
+        assert_eq!(latin, "Grüße");
+        // data is a slice of the byte stream
+        let data = value_ref.get("data").unwrap().as_data().unwrap();
+        let start = data.as_ptr() as usize - bytes.as_ptr() as usize;
+        assert_eq!(&bytes[start..start + data.len()], data);
+
+        // invalid characters are only found once decoded
+        let bytes = vec![1, 1, 1, 2, b'a', 0, 24, 25];
+        let value_ref = serde::from_bytes_ref(&bytes).unwrap();
+        assert!(value_ref.as_string().unwrap().to_str().is_err());
+        assert!(value_ref.to_owned().is_err());
+
+        assert!(serde::from_bytes_ref(&bytes[..bytes.len() - 1]).is_err());
+        assert!(serde::from_bytes_ref(&[]).is_err());
+        assert!(serde::from_bytes_ref(&[1]).is_err());
+        let v0_bytes = fs::read("xff-example-data/v0.xff").unwrap();
+        assert!(serde::from_bytes_ref(&v0_bytes).is_err());
+    }
+
+    #[test]
+    fn document() {
+        let xff_val = XffValue::from(BTreeMap::from([
+            ("data", XffValue::from(make_random_data_with_length(20_000))),
+            ("Grüße", XffValue::from(vec![XffValue::from(-42), XffValue::Null, XffValue::from("hello mom")])),
+        ]));
+        let bytes = serde::to_bytes_legacy(vec![xff_val.clone()], 1).unwrap();
+
+        let mut document = serde::XffDocument::new(std::io::Cursor::new(bytes)).unwrap();
+        assert_eq!(document.version(), 1);
+        let root = document.root();
+        let array = document.get(&root, "Grüße").unwrap().unwrap();
+        let null = document.index(&array, 1).unwrap().unwrap();
+        assert!(null.is_null());
+        let string = document.index(&array, 2).unwrap().unwrap();
+        assert_eq!(string.content_len(), 9);
+        assert_eq!(document.read(&string).unwrap(), XffValue::from("hello mom"));
+        assert_eq!(document.read(&root).unwrap(), xff_val);
+        // keys not representable in v1 are never contained
+        assert!(document.get(&root, "👋🏽").unwrap().is_none());
+    }
+
+    #[test]
+    fn lossless_numbers() {
+        let texts = [
+            "123456789012345678901234567890",
+            "-123456789012345678901234567890",
+            "340282366920938463463374607431768211456",
+            "0.1000000000000000055",
+            "1.50",
+            "-1,5",
+            "42.69",
+        ];
+        for text in texts {
+            // Version, NUM, length, number, EV, EM
+            let mut bytes = vec![1, 2, 1, text.len() as u8];
+            bytes.extend(text.as_bytes());
+            bytes.extend([24, 25]);
+
+            let read = serde::from_bytes(&bytes).unwrap();
+            assert_eq!(read.as_number().unwrap().as_string(), text);
+            assert_eq!(serde::to_bytes_legacy(vec![read], 1).unwrap(), bytes);
+            let read = serde::XffReader::new(bytes.as_slice()).read().unwrap();
+            assert_eq!(serde::to_bytes_legacy(vec![read], 1).unwrap(), bytes);
+        }
+
+        let read = serde::from_bytes(&[1, 2, 1, 3, b'1', b'.', b'5', 24, 25]).unwrap();
+        assert_eq!(read, XffValue::from(1.5));
+        let read = serde::from_bytes(&[1, 2, 1, 4, b'-', b'1', b'2', b'8', 24, 25]).unwrap();
+        assert_eq!(read, XffValue::from(-128));
+        let big = "123456789012345678901234567890";
+        let mut bytes = vec![1, 2, 1, big.len() as u8];
+        bytes.extend(big.as_bytes());
+        bytes.extend([24, 25]);
+        assert_eq!(
+            serde::from_bytes(&bytes).unwrap(),
+            XffValue::from(123456789012345678901234567890_u128)
+        );
+    }
+
+    #[test]
+    fn number_grammar() {
+        // Version, NUM, length, number, EV, EM
+        let number_bytes = |text: &str| {
+            let mut bytes = vec![1, 2, 1, text.len() as u8];
+            bytes.extend(text.as_bytes());
+            bytes.extend([24, 25]);
+            bytes
+        };
+
+        assert_eq!(
+            serde::from_bytes(&number_bytes("0")).unwrap(),
+            XffValue::from(0_usize)
+        );
+        assert_eq!(
+            serde::from_bytes(&number_bytes("-0.5")).unwrap(),
+            XffValue::from(-0.5)
+        );
+        // `,` is a decimal separator as well
+        let read = serde::from_bytes(&number_bytes("42,69")).unwrap();
+        assert_eq!(read.into_number().unwrap().into_decimal().unwrap().to_f64(), 42.69);
+
+        // text, position of the first invalid byte
+        let invalid = [
+            ("", 0),
+            ("-", 1),
+            ("+1", 0),
+            ("007", 1),
+            ("-01", 2),
+            ("1e5", 1),
+            ("1.5e5", 3),
+            ("1.", 2),
+            (".5", 0),
+            ("1.2.3", 3),
+            ("1,2.3", 3),
+            ("--1", 1),
+            ("NaN", 0),
+            ("inf", 0),
+            ("-inf", 1),
+            (" 1", 0),
+        ];
+        for (text, pos) in invalid {
+            let bytes = number_bytes(text);
+            // the number starts after version, NUM and the length
+            let expected = format!("Invalid number: {} at byte position {}", text, 4 + pos);
+            let read = serde::from_bytes(&bytes);
+            assert_eq!(read.unwrap_err().to_string(), expected, "{:?}", text);
+            let read = serde::XffReader::new(bytes.as_slice()).read();
+            assert_eq!(read.unwrap_err().to_string(), expected, "{:?}", text);
+            assert!(text.parse::<Number>().is_err());
+        }
+
+        // non-finite floats are not written
+        for num in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(serde::to_bytes_legacy(vec![XffValue::from(num)], 1).is_err());
+            assert!(serde::XffWriter::with_version(Vec::new(), 1)
+                .write(&XffValue::from(vec![XffValue::from(num)]))
+                .is_err());
+        }
+        let written = serde::to_bytes_legacy(vec![XffValue::from(-0.0)], 1).unwrap();
+        assert_eq!(written, number_bytes("-0"));
+    }
+
+    #[test]
+    fn streaming_reader_writer() {
+        let values = vec![
+            XffValue::from(BTreeMap::from([
+                ("key0", XffValue::from("value0")),
+                ("key1", XffValue::from(-42)),
+                ("key2", XffValue::from(vec![XffValue::from(42.69), XffValue::Null])),
+            ])),
+            XffValue::from(vec![make_random_string(), XffValue::from(false)]),
+            XffValue::from(Array::new()),
+            XffValue::from(Object::new()),
+            XffValue::from(make_random_data_with_length(100_000)),
+        ];
+
+        // several streams written into the same writer
+        let mut writer = serde::XffWriter::with_version(Vec::new(), 1);
+        for value in &values {
+            assert!(writer.write(value).is_ok());
+        }
+        let bytes = writer.into_inner();
+
+        let mut expected: Vec<u8> = Vec::new();
+        for value in &values {
+            expected.extend(serde::to_bytes_legacy(vec![value.clone()], 1).unwrap());
+        }
+        assert_eq!(bytes, expected);
+
+        let mut reader = serde::XffReader::new(bytes.as_slice());
+        for value in &values {
+            let read = reader.read();
+            assert!(read.is_ok());
+            assert_eq!(&read.unwrap(), value);
+        }
+        assert!(reader.read().is_err());
+
+        let mut reader = serde::XffReader::new(&bytes[..bytes.len() - 10]);
+        for _ in 0..values.len() - 1 {
+            assert!(reader.read().is_ok());
+        }
+        assert!(reader.read().is_err());
+    }
+
     #[test]
     fn object() {
         let map_small = XffValue::from(BTreeMap::from([
@@ -226,7 +475,7 @@ mod v1 {
             ("key1", map_medium),
         ]));
         assert!(xff_val.is_object());
-        let write = serde::write("xff-example-data/v1_object.xff", xff_val.clone());
+        let write = serde::write_legacy("xff-example-data/v1_object.xff", vec![xff_val.clone()], 1);
         assert!(write.is_ok());
     }
 
@@ -243,16 +492,16 @@ mod v1 {
         let xff_array = XffValue::from(vec![XffValue::from("hello"), XffValue::from(42.69)]);
         let xff_object = XffValue::from(BTreeMap::from([("key", XffValue::from(42.69))]));
 
-        let write_string = serde::write("xff-example-data/v1_string.xff", xff_string.clone());
-        let write_number_f = serde::write("xff-example-data/v1_number_f.xff", xff_number_f.clone());
-        let write_number_i = serde::write("xff-example-data/v1_number_i.xff", xff_number_i.clone());
-        let write_number_u = serde::write("xff-example-data/v1_number_u.xff", xff_number_u.clone());
-        let write_boolean_t = serde::write("xff-example-data/v1_boolean_t.xff", xff_boolean_t.clone());
-        let write_boolean_f = serde::write("xff-example-data/v1_boolean_f.xff", xff_boolean_f.clone());
-        let write_null = serde::write("xff-example-data/v1_null.xff", xff_null.clone());
-        let write_data = serde::write("xff-example-data/v1_data.xff", xff_data.clone());
-        let write_array = serde::write("xff-example-data/v1_array.xff", xff_array.clone());
-        let write_object = serde::write("xff-example-data/v1_object.xff", xff_object.clone());
+        let write_string = serde::write_legacy("xff-example-data/v1_string.xff", vec![xff_string.clone()], 1);
+        let write_number_f = serde::write_legacy("xff-example-data/v1_number_f.xff", vec![xff_number_f.clone()], 1);
+        let write_number_i = serde::write_legacy("xff-example-data/v1_number_i.xff", vec![xff_number_i.clone()], 1);
+        let write_number_u = serde::write_legacy("xff-example-data/v1_number_u.xff", vec![xff_number_u.clone()], 1);
+        let write_boolean_t = serde::write_legacy("xff-example-data/v1_boolean_t.xff", vec![xff_boolean_t.clone()], 1);
+        let write_boolean_f = serde::write_legacy("xff-example-data/v1_boolean_f.xff", vec![xff_boolean_f.clone()], 1);
+        let write_null = serde::write_legacy("xff-example-data/v1_null.xff", vec![xff_null.clone()], 1);
+        let write_data = serde::write_legacy("xff-example-data/v1_data.xff", vec![xff_data.clone()], 1);
+        let write_array = serde::write_legacy("xff-example-data/v1_array.xff", vec![xff_array.clone()], 1);
+        let write_object = serde::write_legacy("xff-example-data/v1_object.xff", vec![xff_object.clone()], 1);
 
         assert!(write_string.is_ok());
         assert!(write_number_f.is_ok());
@@ -325,7 +574,7 @@ mod v1 {
             XffValue::Null,
         ];
 
-        let write = serde::write("xff-example-data/v1_complete_array.xff", XffValue::from(values.clone()));
+        let write = serde::write_legacy("xff-example-data/v1_complete_array.xff", vec![XffValue::from(values.clone())], 1);
         assert!(write.is_ok());
 
         let read = serde::read("xff-example-data/v1_complete_array.xff");
@@ -350,9 +599,10 @@ mod v1 {
                 data.push(make_random_value(7));
                 gen_len -= 1;
             }
-            let write = serde::write(
+            let write = serde::write_legacy(
                 "tests/v1_simulated_data_40-ignore.xff",
-                XffValue::from(data),
+                vec![XffValue::from(data)],
+                1,
             );
             assert!(write.is_ok());
         }
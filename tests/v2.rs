@@ -0,0 +1,731 @@
+#[cfg(test)]
+mod v2 {
+    use std::collections::BTreeMap;
+    use std::fs;
+
+    use tyche::prelude::*;
+
+    use nabu::*;
+
+    #[test]
+    fn actual_data() {
+        let path = "xff-example-data/v2_actual_data.xff";
+        let real_data = XffValue::from(fs::read("src/lib.rs").unwrap());
+        let real_data2 = XffValue::from(fs::read("Cargo.toml").unwrap());
+        let real_data3 = XffValue::from(fs::read_to_string("README.md").unwrap());
+        let data = XffValue::from(vec![real_data.clone(), real_data2.clone(), real_data3.clone()]);
+        let write = serde::write(path, data);
+        assert!(write.is_ok());
+        let read = serde::read(path);
+        assert!(read.is_ok());
+        let read = read.unwrap();
+        assert!(read.is_array());
+        let array = read.into_array().unwrap();
+        assert_eq!(array.len(), 3);
+        assert_eq!(array[0], real_data);
+        assert_eq!(array[1], real_data2);
+        assert_eq!(array[2], real_data3);
+    }
+
+    #[test]
+    fn read_write_loop_object() {
+        let path = "xff-example-data/v2_loop_complex.xff";
+        for n in 0..100 {
+            if n == 0 {
+                // create a new file
+                let data = XffValue::from(BTreeMap::from([
+                    ("array".to_string(), XffValue::from(Array::from(vec![XffValue::from(n)]))),
+                    ("schlüssel0".to_string(), XffValue::from(42.69)),
+                ]));
+                let write = serde::write(path, data);
+                assert!(write.is_ok());
+            } else {
+                // read the file and append
+                let read = serde::read(path);
+                assert!(read.is_ok());
+                let mut data = read.unwrap().into_object().unwrap();
+                let mut ary = data.remove("array").unwrap().into_array().unwrap();
+                ary.push(XffValue::from(n));
+                data.insert("array".to_string(), XffValue::from(ary));
+                data.insert(format!("schlüssel{}", n), XffValue::from(42.69));
+                let write = serde::write(path, XffValue::from(data));
+                assert!(write.is_ok());
+            }
+        }
+
+        // read the file and assert the result
+        let read = serde::read(path);
+        assert!(read.is_ok());
+        let read = read.unwrap().into_object().unwrap();
+        assert_eq!(read.len(), 101);
+        assert_eq!(read["array"].as_array().unwrap().len(), 100);
+        assert_eq!(read["array"].as_array().unwrap()[42], XffValue::from(42));
+        assert_eq!(read["schlüssel0"], XffValue::from(42.69));
+        assert_eq!(read["schlüssel42"], XffValue::from(42.69));
+        assert_eq!(read["schlüssel69"], XffValue::from(42.69));
+        assert_eq!(read["schlüssel99"], XffValue::from(42.69));
+
+        // remove the file
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn read_write_loop_array() {
+        let path = "xff-example-data/v2_loop.xff";
+        for n in 0..100 {
+            if n == 0 {
+                // create a new file
+                let data = XffValue::from(vec![XffValue::from(format!("Wert № {}", n))]);
+                let write = serde::write(path, data);
+                assert!(write.is_ok());
+            } else {
+                // read the file and append
+                let read = serde::read(path);
+                assert!(read.is_ok());
+                let mut data = read.unwrap().into_array().unwrap();
+                data.push(XffValue::from(format!("Wert № {}", n)));
+                let write = serde::write(path, XffValue::from(data));
+                assert!(write.is_ok());
+            }
+        }
+        // read the file and assert the result
+        let read = serde::read(path);
+        assert!(read.is_ok());
+        let read = read.unwrap().into_array().unwrap();
+        assert_eq!(read.len(), 100);
+        assert_eq!(read[0], XffValue::from("Wert № 0"));
+        assert_eq!(read[42], XffValue::from("Wert № 42"));
+        assert_eq!(read[69], XffValue::from("Wert № 69"));
+        assert_eq!(read[99], XffValue::from("Wert № 99"));
+
+        // clear the file
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn primitive_values() {
+        let string = XffValue::from("hello mom");
+        let u_num = XffValue::from(u8::MAX);
+        let i_num = XffValue::from(i8::MIN);
+        let f_num = XffValue::from(42.69_f32);
+        let t_bool = XffValue::from(true);
+        let f_bool = XffValue::from(false);
+        let non = XffValue::Null;
+        let lorem_short = XffValue::from("Lorem ipsum dolor sit amet, qui minim labore adipisicing minim sint cillum sint consectetur cupidatat.");
+        let lorem_greek = XffValue::from("Λορεμ ιπσθμ δολορ σιτ αμετ, εα μει αλιι ομνιθμ ινσολενς, ιδ σεα ερατ σcριπτα.");
+        let xff_val = XffValue::from(vec![
+            string,
+            u_num,
+            i_num,
+            f_num,
+            t_bool,
+            f_bool,
+            non,
+            lorem_short,
+            lorem_greek,
+        ]);
+        assert!(xff_val.is_array());
+        let write = serde::write("xff-example-data/v2_primitive_values.xff", xff_val.clone());
+        assert!(write.is_ok());
+
+        let read = serde::read("xff-example-data/v2_primitive_values.xff");
+        assert!(read.is_ok());
+        let read = read.unwrap().clone();
+        assert!(read.is_array());
+        assert_eq!(read, xff_val);
+    }
+
+    #[test]
+    fn escape_chars() {
+        let str_with_backspace = XffValue::from("hello\x08mom");
+        let str_with_horizontal_tab = XffValue::from("hello\x09mom");
+        let str_with_line_feed = XffValue::from("hello\x0Amom");
+        let str_with_vertical_tab = XffValue::from("hello\x0Bmom");
+        let str_with_carriage_return = XffValue::from("hello\x0Cmom");
+        let str_with_form_feed = XffValue::from("hello\x0Dmom");
+        let str_with_backslash = XffValue::from("hello\\mom");
+        let str_with_double_quote = XffValue::from("hello\"mom");
+        let str_with_single_quote = XffValue::from("hello'mom");
+        // command characters of xff itself are just string content in v2
+        let str_with_command_chars = XffValue::from("hello\x18\x19\x1D\x1E\x1Fmom");
+
+        let xff_val = XffValue::from(vec![
+            str_with_backspace,
+            str_with_horizontal_tab,
+            str_with_line_feed,
+            str_with_vertical_tab,
+            str_with_carriage_return,
+            str_with_form_feed,
+            str_with_backslash,
+            str_with_double_quote,
+            str_with_single_quote,
+            str_with_command_chars,
+        ]);
+        assert!(xff_val.is_array());
+        let write = serde::write("xff-example-data/v2_escape_chars.xff", xff_val.clone());
+        assert!(write.is_ok());
+
+        let read = serde::read("xff-example-data/v2_escape_chars.xff");
+        assert!(read.is_ok());
+        let read = read.unwrap().clone();
+        assert!(read.is_array());
+        assert_eq!(read, xff_val);
+    }
+
+    #[test]
+    fn data() {
+        let small_data = XffValue::from(make_random_data_with_length(100));
+        let medium_data = XffValue::from(make_random_data_with_length(10_000));
+        let large_data = XffValue::from(make_random_data_with_length(1_000_000));
+
+        let xff_val = XffValue::from(vec![small_data, medium_data, large_data]);
+        assert!(xff_val.is_array());
+        let write = serde::write("xff-example-data/v2_data.xff", xff_val.clone());
+        assert!(write.is_ok());
+
+        let read = serde::read("xff-example-data/v2_data.xff");
+        assert!(read.is_ok());
+        let read = read.unwrap().clone();
+        assert!(read.is_array());
+        assert_eq!(read, xff_val);
+    }
+
+    #[test]
+    fn binary_numbers() {
+        // Version, NUM, length 9, unsigned, 42, EV, EM
+        let bytes = serde::to_bytes(XffValue::from(42_usize)).unwrap();
+        assert_eq!(bytes, vec![2, 2, 1, 9, 1, 42, 0, 0, 0, 0, 0, 0, 0, 24, 25]);
+        // signed, -1
+        let bytes = serde::to_bytes(XffValue::from(-1)).unwrap();
+        assert_eq!(bytes, vec![2, 2, 1, 9, 2, 255, 255, 255, 255, 255, 255, 255, 255, 24, 25]);
+
+        // the type of the number survives the round trip
+        let numbers = vec![
+            XffValue::from(0),
+            XffValue::from(0_usize),
+            XffValue::from(0.0),
+            XffValue::from(42),
+            XffValue::from(i64::MIN),
+            XffValue::from(u64::MAX),
+            XffValue::from(f64::MAX),
+            XffValue::from(f64::MIN_POSITIVE),
+            XffValue::from(-0.1),
+        ];
+        for number in numbers {
+            let bytes = serde::to_bytes(number.clone()).unwrap();
+            assert_eq!(serde::from_bytes(&bytes).unwrap(), number);
+        }
+
+        // NaN and infinity cannot be stored
+        assert!(serde::to_bytes(XffValue::from(f64::NAN)).is_err());
+        assert!(serde::to_bytes(XffValue::from(f64::INFINITY)).is_err());
+        let mut nan = vec![2, 2, 1, 9, 3];
+        nan.extend(f64::NAN.to_le_bytes());
+        nan.extend([24, 25]);
+        assert!(serde::from_bytes(&nan).is_err());
+        assert!(serde::XffReader::new(nan.as_slice()).read().is_err());
+
+        // unknown number type
+        let bytes = vec![2, 2, 1, 9, 7, 0, 0, 0, 0, 0, 0, 0, 0, 24, 25];
+        assert!(serde::from_bytes(&bytes).is_err());
+        // wrong length
+        let bytes = vec![2, 2, 1, 2, 1, 42, 24, 25];
+        assert!(serde::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn big_numbers() {
+        // Version, NUM, length 17, unsigned 128 bit, EV, EM
+        let bytes = serde::to_bytes(XffValue::from(u128::MAX)).unwrap();
+        let mut expected = vec![2, 2, 1, 17, 4];
+        expected.extend(u128::MAX.to_le_bytes());
+        expected.extend([24, 25]);
+        assert_eq!(bytes, expected);
+        // Version, NUM, length 22, decimal, "0.1000000000000000055", EV, EM
+        let decimal = XffValue::from("0.1000000000000000055".parse::<Decimal>().unwrap());
+        let bytes = serde::to_bytes(decimal.clone()).unwrap();
+        let mut expected = vec![2, 2, 1, 22, 6];
+        expected.extend(b"0.1000000000000000055");
+        expected.extend([24, 25]);
+        assert_eq!(bytes, expected);
+        assert_eq!(serde::from_bytes(&bytes).unwrap(), decimal);
+
+        let numbers = vec![
+            XffValue::from(u128::MAX),
+            XffValue::from(i128::MIN),
+            XffValue::Number(Number::BigUnsigned(42)),
+            XffValue::Number(Number::BigInteger(-42)),
+            XffValue::from("123456789012345678901234567890123456789012345".parse::<Decimal>().unwrap()),
+            XffValue::from("-1,50".parse::<Decimal>().unwrap()),
+        ];
+        for number in numbers {
+            let bytes = serde::to_bytes(number.clone()).unwrap();
+            assert_eq!(serde::from_bytes(&bytes).unwrap(), number);
+            assert_eq!(
+                serde::XffReader::new(bytes.as_slice()).read().unwrap(),
+                number
+            );
+        }
+
+        // invalid decimal text
+        let mut bytes = vec![2, 2, 1, 5, 6];
+        bytes.extend(b"1e10");
+        bytes.extend([24, 25]);
+        assert!(serde::from_bytes(&bytes).is_err());
+        // wrong length of a 128 bit integer
+        let bytes = vec![2, 2, 1, 9, 4, 0, 0, 0, 0, 0, 0, 0, 0, 24, 25];
+        assert!(serde::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn extended_version_header() {
+        // version 256 announced by the extended version header
+        let bytes = vec![255, 0, 1, 0, 25];
+        let read = serde::from_bytes(&bytes);
+        assert!(read.is_err());
+        assert_eq!(read.unwrap_err().to_string(), "Unknown extended XFF version: 256");
+        let read = serde::XffReader::new(bytes.as_slice()).read();
+        assert!(read.is_err());
+        assert_eq!(read.unwrap_err().to_string(), "Unknown extended XFF version: 256");
+
+        assert!(serde::from_bytes(&[255, 0]).is_err());
+    }
+
+    #[test]
+    fn streaming_reader_writer() {
+        let values = vec![
+            XffValue::from(BTreeMap::from([
+                ("schlüssel0", XffValue::from("wert0")),
+                ("schlüssel1", XffValue::from(-42)),
+                ("schlüssel2", XffValue::from(vec![XffValue::from(42.69), XffValue::Null])),
+            ])),
+            XffValue::from(vec![make_random_string(), XffValue::from(false)]),
+            XffValue::from(Array::new()),
+            XffValue::from(Object::new()),
+            XffValue::from(make_random_data_with_length(100_000)),
+        ];
+
+        // several streams written into the same writer
+        let mut writer = serde::XffWriter::new(Vec::new());
+        for value in &values {
+            assert!(writer.write(value).is_ok());
+        }
+        let bytes = writer.into_inner();
+
+        let mut expected: Vec<u8> = Vec::new();
+        for value in &values {
+            expected.extend(serde::to_bytes(value.clone()).unwrap());
+        }
+        assert_eq!(bytes, expected);
+
+        let mut reader = serde::XffReader::new(bytes.as_slice());
+        for value in &values {
+            let read = reader.read();
+            assert!(read.is_ok());
+            assert_eq!(&read.unwrap(), value);
+        }
+        assert!(reader.read().is_err());
+
+        let mut reader = serde::XffReader::new(&bytes[..bytes.len() - 10]);
+        for _ in 0..values.len() - 1 {
+            assert!(reader.read().is_ok());
+        }
+        assert!(reader.read().is_err());
+
+        // v0 cannot be written incrementally
+        let mut writer = serde::XffWriter::with_version(Vec::new(), 0);
+        assert!(writer.write(&XffValue::Null).is_err());
+    }
+
+    #[test]
+    fn object() {
+        let map_small = XffValue::from(BTreeMap::from([
+            ("key0", XffValue::from("value0")),
+            ("key1", XffValue::from(-42)),
+            ("key2", XffValue::from(Data::from(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9]))),
+        ]));
+        let map_medium = XffValue::from(BTreeMap::from([
+            ("key0", XffValue::from("value0")),
+            ("key1", XffValue::from(-42)),
+            ("key2", XffValue::from(-420.69)),
+            (
+                "key3",
+                XffValue::from(Data::from(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9])),
+            ),
+            (
+                "κλειδί",
+                XffValue::from(BTreeMap::from([("鍵", XffValue::from(42.69))])),
+            ),
+            ("key5", XffValue::from(42.69)),
+            ("key6", XffValue::from(true)),
+            ("key7", XffValue::from(false)),
+            ("key8", XffValue::from(vec![XffValue::from("hello"), XffValue::from(42.69)])),
+            ("key9", XffValue::Null),
+            ("key10", XffValue::from(54642.69)),
+            ("key11", XffValue::from("lorem ipsum")),
+            ("key12", XffValue::from("Λορεμ ιπσθμ δολορ σιτ αμετ")),
+            ("key13", XffValue::from(Array::new())),
+            ("key14", XffValue::from(Object::new())),
+        ]));
+        let xff_val = XffValue::from(BTreeMap::from([
+            ("key0", map_small),
+            ("key1", map_medium),
+        ]));
+        assert!(xff_val.is_object());
+        let write = serde::write("xff-example-data/v2_object.xff", xff_val.clone());
+        assert!(write.is_ok());
+
+        let read = serde::read("xff-example-data/v2_object.xff");
+        assert!(read.is_ok());
+        assert_eq!(read.unwrap(), xff_val);
+    }
+
+    #[test]
+    fn singeltons() {
+        let xff_string = XffValue::from("hello mom 👋");
+        let xff_number_f = XffValue::from(42.69);
+        let xff_number_i = XffValue::from(-42);
+        let xff_number_u = XffValue::from(usize::MAX);
+        let xff_boolean_t = XffValue::from(true);
+        let xff_boolean_f = XffValue::from(false);
+        let xff_null = XffValue::Null;
+        let xff_data = XffValue::from(Data::from(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9]));
+        let xff_array = XffValue::from(vec![XffValue::from("hello"), XffValue::from(42.69)]);
+        let xff_object = XffValue::from(BTreeMap::from([("key", XffValue::from(42.69))]));
+
+        let write_string = serde::write("xff-example-data/v2_string.xff", xff_string.clone());
+        let write_number_f = serde::write("xff-example-data/v2_number_f.xff", xff_number_f.clone());
+        let write_number_i = serde::write("xff-example-data/v2_number_i.xff", xff_number_i.clone());
+        let write_number_u = serde::write("xff-example-data/v2_number_u.xff", xff_number_u.clone());
+        let write_boolean_t = serde::write("xff-example-data/v2_boolean_t.xff", xff_boolean_t.clone());
+        let write_boolean_f = serde::write("xff-example-data/v2_boolean_f.xff", xff_boolean_f.clone());
+        let write_null = serde::write("xff-example-data/v2_null.xff", xff_null.clone());
+        let write_data = serde::write("xff-example-data/v2_data_singelton.xff", xff_data.clone());
+        let write_array = serde::write("xff-example-data/v2_array.xff", xff_array.clone());
+        let write_object = serde::write("xff-example-data/v2_object_singelton.xff", xff_object.clone());
+
+        assert!(write_string.is_ok());
+        assert!(write_number_f.is_ok());
+        assert!(write_number_i.is_ok());
+        assert!(write_number_u.is_ok());
+        assert!(write_boolean_t.is_ok());
+        assert!(write_boolean_f.is_ok());
+        assert!(write_null.is_ok());
+        assert!(write_data.is_ok());
+        assert!(write_array.is_ok());
+        assert!(write_object.is_ok());
+
+        let read_string = serde::read("xff-example-data/v2_string.xff");
+        let read_number_f = serde::read("xff-example-data/v2_number_f.xff");
+        let read_number_i = serde::read("xff-example-data/v2_number_i.xff");
+        let read_number_u = serde::read("xff-example-data/v2_number_u.xff");
+        let read_boolean_t = serde::read("xff-example-data/v2_boolean_t.xff");
+        let read_boolean_f = serde::read("xff-example-data/v2_boolean_f.xff");
+        let read_null = serde::read("xff-example-data/v2_null.xff");
+        let read_data = serde::read("xff-example-data/v2_data_singelton.xff");
+        let read_array = serde::read("xff-example-data/v2_array.xff");
+        let read_object = serde::read("xff-example-data/v2_object_singelton.xff");
+
+        assert_eq!(read_string.unwrap(), xff_string);
+        assert_eq!(read_number_f.unwrap(), xff_number_f);
+        assert_eq!(read_number_i.unwrap(), xff_number_i);
+        assert_eq!(read_number_u.unwrap(), xff_number_u);
+        assert_eq!(read_boolean_t.unwrap(), xff_boolean_t);
+        assert_eq!(read_boolean_f.unwrap(), xff_boolean_f);
+        assert_eq!(read_null.unwrap(), xff_null);
+        assert_eq!(read_data.unwrap(), xff_data);
+        assert_eq!(read_array.unwrap(), xff_array);
+        assert_eq!(read_object.unwrap(), xff_object);
+    }
+
+    #[test]
+    fn complete_array() {
+        let values = vec![
+            XffValue::from("hello"),
+            XffValue::from("hallöchen"),
+            XffValue::from(42.69),
+            XffValue::from(true),
+            XffValue::from(vec![XffValue::from("hello"), XffValue::from(42.69)]),
+            XffValue::from(BTreeMap::from([("key", XffValue::from(42.69))])),
+            XffValue::from(u8::MAX),
+            XffValue::from(u16::MAX),
+            XffValue::from(u32::MAX),
+            XffValue::from(u64::MAX),
+            XffValue::from(i8::MIN),
+            XffValue::from(i16::MIN),
+            XffValue::from(i32::MIN),
+            XffValue::from(i64::MIN),
+            XffValue::from(42.69_f32),
+            XffValue::from(69.42_f64),
+            XffValue::from("hello".to_string()),
+            XffValue::from(vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20]),
+            XffValue::from(false),
+            XffValue::Null,
+        ];
+
+        let write = serde::write("xff-example-data/v2_complete_array.xff", XffValue::from(values.clone()));
+        assert!(write.is_ok());
+
+        let read = serde::read("xff-example-data/v2_complete_array.xff");
+        assert!(read.is_ok());
+        assert_eq!(read.unwrap().into_array().unwrap().into_vec(), values);
+    }
+
+    #[test]
+    fn random_values() {
+        for _ in 0..10 {
+            let value = make_random_value(7);
+            let bytes = serde::to_bytes(value.clone());
+            assert!(bytes.is_ok());
+            let bytes = bytes.unwrap();
+            assert_eq!(serde::from_bytes(&bytes).unwrap(), value);
+            assert_eq!(serde::XffReader::new(bytes.as_slice()).read().unwrap(), value);
+            assert_eq!(serde::from_bytes_ref(&bytes).unwrap().to_owned().unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn borrowed_values() {
+        let xff_val = XffValue::from(BTreeMap::from([
+            ("emoji", XffValue::from("hi mom 👋🏽")),
+            ("number", XffValue::from(u128::MAX)),
+            ("data", XffValue::from(vec![0_u8, 1, 2, 3])),
+            ("empty", XffValue::from(Vec::<XffValue>::new())),
+        ]));
+        let bytes = serde::to_bytes(xff_val.clone()).unwrap();
+
+        let value_ref = serde::from_bytes_ref(&bytes).unwrap();
+        assert_eq!(value_ref.to_owned().unwrap(), xff_val);
+        let emoji = value_ref.get("emoji").unwrap().as_string().unwrap();
+        assert!(matches!(emoji.to_str().unwrap(), std::borrow::Cow::Borrowed("hi mom 👋🏽")));
+        assert_eq!(emoji.as_bytes(), "hi mom 👋🏽".as_bytes());
+        assert_eq!(
+            value_ref.get("number").unwrap().as_number(),
+            Some(&Number::from(u128::MAX))
+        );
+        assert_eq!(value_ref.get("empty").unwrap().as_array(), Some(&[][..]));
+        // keys are kept in the order of the byte stream
+        let keys: Vec<_> = value_ref
+            .as_object()
+            .unwrap()
+            .iter()
+            .map(|(k, _)| k.to_str().unwrap())
+            .collect();
+        assert_eq!(keys, vec!["data", "emoji", "empty", "number"]);
+
+        // invalid UTF-8 is only found once decoded, with the position of the invalid byte
+        let bytes = vec![2, 1, 1, 2, b'a', 255, 24, 25];
+        let value_ref = serde::from_bytes_ref(&bytes).unwrap();
+        assert_eq!(
+            value_ref.to_owned().unwrap_err().to_string(),
+            "Invalid UTF-8 string at byte position 5"
+        );
+
+        assert!(serde::from_bytes_ref(&[255, 0, 1, 0, 25]).is_err());
+        assert!(serde::from_bytes_ref(&[2, 42, 25]).is_err());
+    }
+
+    #[test]
+    fn document() {
+        let images: Vec<XffValue> = (0..10_000)
+            .map(|i| XffValue::from(BTreeMap::from([("id", XffValue::from(i)), ("name", XffValue::from(format!("image {i} 👋🏽")))])))
+            .collect();
+        let xff_val = XffValue::from(BTreeMap::from([
+            ("data", XffValue::from(make_random_data_with_length(100_000))),
+            ("images", XffValue::from(images.clone())),
+            ("name", XffValue::from("album")),
+        ]));
+        let path = "xff-example-data/v2_document.xff";
+        serde::write(path, xff_val.clone()).unwrap();
+
+        let file = std::io::BufReader::new(fs::File::open(path).unwrap());
+        let mut document = serde::XffDocument::new(file).unwrap();
+        assert_eq!(document.version(), 2);
+        let root = document.root();
+        assert!(root.is_object());
+        let images_node = document.get(&root, "images").unwrap().unwrap();
+        assert!(images_node.is_array());
+        let image = document.index(&images_node, 9_042).unwrap().unwrap();
+        assert_eq!(document.read(&image).unwrap(), images[9_042]);
+        let name = document.get(&image, "name").unwrap().unwrap();
+        assert!(name.is_string());
+        assert_eq!(document.read(&name).unwrap(), XffValue::from("image 9042 👋🏽"));
+        assert_eq!(document.read(&root).unwrap(), xff_val);
+
+        assert!(document.index(&images_node, 10_000).unwrap().is_none());
+        assert!(document.get(&root, "missing").unwrap().is_none());
+        assert!(document.get(&images_node, "name").unwrap().is_none());
+        assert!(document.index(&root, 0).unwrap().is_none());
+        fs::remove_file(path).unwrap();
+
+        // the byte stream may start anywhere in the reader
+        let mut bytes = vec![42, 42];
+        bytes.append(&mut serde::to_bytes(XffValue::from(vec![XffValue::from(1), XffValue::from(true)])).unwrap());
+        let mut reader = std::io::Cursor::new(bytes);
+        reader.set_position(2);
+        let mut document = serde::XffDocument::new(reader).unwrap();
+        let root = document.root();
+        assert_eq!(root.position(), 1);
+        let element = document.index(&root, 1).unwrap().unwrap();
+        assert!(element.is_boolean());
+        assert_eq!(document.read(&element).unwrap(), XffValue::from(true));
+
+        // broken length prefixes are found while navigating
+        let bytes = serde::to_bytes(XffValue::from(vec![XffValue::from("a"), XffValue::from("b")])).unwrap();
+        let mut broken = bytes.clone();
+        // length of the first string
+        broken[6] = 2;
+        let mut document = serde::XffDocument::new(std::io::Cursor::new(broken)).unwrap();
+        let root = document.root();
+        assert!(document.index(&root, 1).is_err());
+        assert!(serde::XffDocument::new(std::io::Cursor::new(&bytes[..bytes.len() - 1])).is_err());
+        assert!(serde::XffDocument::new(std::io::Cursor::new(Vec::new())).is_err());
+        let v0_bytes = fs::read("xff-example-data/v0.xff").unwrap();
+        assert!(serde::XffDocument::new(std::io::Cursor::new(v0_bytes)).is_err());
+    }
+
+    #[test]
+    fn unicode_strings() {
+        let path = "xff-example-data/v2_unicode_strings.xff";
+        let xff_val = XffValue::from(BTreeMap::from([
+            ("latin", XffValue::from("hello mom")),
+            ("umlaute", XffValue::from("Grüße aus Köln")),
+            ("greek", XffValue::from("Γειά σου μαμά")),
+            ("cjk", XffValue::from("你好，妈妈")),
+            ("emoji", XffValue::from("hi mom 👋🏽")),
+            ("control", XffValue::from("tab\tnull\0escape\x1b")),
+            ("ключ", XffValue::from(vec![XffValue::from("€"), XffValue::from("𝄞")])),
+        ]));
+        let write = serde::write_legacy(path, vec![xff_val.clone()], 2);
+        assert!(write.is_ok());
+
+        let read = serde::read(path);
+        assert!(read.is_ok());
+        assert_eq!(read.unwrap(), xff_val);
+
+        let bytes = std::fs::read(path).unwrap();
+        assert_eq!(bytes[0], 2);
+        assert_eq!(serde::from_bytes(&bytes).unwrap(), xff_val);
+        assert_eq!(serde::XffReader::new(bytes.as_slice()).read().unwrap(), xff_val);
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        // Version, TXT, length 2, invalid continuation byte, EV, EM
+        let bytes = vec![2, 1, 1, 2, 0xC3, 0x28, 24, 25];
+        let read = serde::from_bytes(&bytes);
+        assert!(read.is_err());
+        // Valid UTF-8 "ü"
+        let bytes = vec![2, 1, 1, 2, 0xC3, 0xBC, 24, 25];
+        assert_eq!(serde::from_bytes(&bytes).unwrap(), XffValue::from("ü"));
+    }
+
+    #[test]
+    fn v1_rejects_unrepresentable_characters() {
+        let path = "xff-example-data/v1_unrepresentable.xff";
+        let write = serde::write_legacy(path, vec![XffValue::from("hi mom 👋")], 1);
+        assert!(write.is_err());
+        assert!(!std::path::Path::new(path).exists());
+
+        let key = XffValue::from(BTreeMap::from([("Γ", XffValue::Null)]));
+        assert!(serde::to_bytes_legacy(vec![key], 1).is_err());
+
+        // Latin-1 characters are still fine in v1
+        let latin = XffValue::from("Grüße");
+        assert_eq!(serde::from_bytes(&serde::to_bytes_legacy(vec![latin.clone()], 1).unwrap()).unwrap(), latin);
+    }
+
+    fn make_random_value(end: usize) -> XffValue {
+        let seed = random_from_range(1, end).unwrap();
+        match seed {
+            1 => make_random_string(),
+            2 => make_random_number(),
+            3 => make_random_boolean(),
+            4 => XffValue::Null,
+            5 => make_random_data(),
+            6 => make_random_array(),
+            7 => make_random_object(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn make_random_object() -> XffValue {
+        let mut out = BTreeMap::new();
+        let seed = random_from_range(0, 100).unwrap();
+        for _n in 0..seed {
+            out.insert(make_random_string().into_string().unwrap(), make_random_value(5));
+        }
+        XffValue::from(out)
+    }
+
+    fn make_random_boolean() -> XffValue {
+        random_bool().unwrap().into()
+    }
+
+    fn make_random_array() -> XffValue {
+        let seed = random_from_range(0, 100).unwrap();
+        let mut out: Vec<XffValue> = Default::default();
+        for _n in 0..seed {
+            out.push(make_random_value(5));
+        }
+        XffValue::from(out)
+    }
+
+    fn make_random_data_with_length(len: usize) -> Vec<u8> {
+        let mut out: Vec<u8> = Default::default();
+        for _ in 0..len {
+            out.push(random_u8().unwrap());
+        }
+        out
+    }
+
+    fn make_random_data() -> XffValue {
+        let seed = random_from_range(0, 1_000).unwrap();
+        XffValue::from(make_random_data_with_length(seed))
+    }
+
+    fn make_random_number() -> XffValue {
+        match random_from_range(0, 4).unwrap() {
+            0 => {
+                // negative
+                let seed = random_from_range(1, 3524654654).unwrap();
+                XffValue::from(-(seed as i64))
+            }
+            1 => {
+                // positive
+                let seed = random_from_range(1, 3524654654).unwrap();
+                XffValue::from(seed)
+            }
+            2 => {
+                // float
+                let seed1 = random_from_range(1, 3524654).unwrap();
+                let seed2 = random_from_range(1, 4564253).unwrap();
+                if random_from_range(0, 1).unwrap() == 0 {
+                    // negative
+                    XffValue::from(-(seed1 as f64 / seed2 as f64))
+                } else {
+                    // positive
+                    XffValue::from(seed1 as f64 / seed2 as f64)
+                }
+            }
+            3 => XffValue::from(0),
+            4 => XffValue::from(0.0),
+            _ => unreachable!(),
+        }
+    }
+
+    fn make_random_string() -> XffValue {
+        // latin, umlaute, greek, cjk and emoji; from 1 up to 4 bytes in UTF-8
+        let unicode = ['ä', 'ß', 'é', 'Ω', 'λ', 'Ж', '€', '你', '好', '👋', '𝄞'];
+        let seed = random_from_range(1, 255).unwrap();
+        let mut out: String = Default::default();
+        for _ in 0..seed {
+            if random_bool().unwrap() {
+                out.push(random_latin_char().unwrap());
+            } else {
+                out.push(unicode[random_from_range(0, unicode.len() - 1).unwrap()]);
+            }
+        }
+        XffValue::String(out)
+    }
+}